@@ -0,0 +1,191 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Datelike, Local, NaiveDateTime};
+use rusqlite::Connection;
+
+use crate::database::{backup_db, get_data_dir};
+
+// storage-20240607-183000.db, the local time the backup was taken
+const BACKUP_PREFIX: &str = "storage-";
+const BACKUP_SUFFIX: &str = ".db";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+// with milliseconds, eg. a migration and the restore it was run for fall in the same second
+const PRE_OP_PREFIX: &str = "pre-op-";
+const PRE_OP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S%3f";
+const PRE_OP_BACKUPS_KEPT: usize = 10;
+
+/*
+ * how many backups --rotate keeps, the newest of each day, week and month
+ * a backup can count for all three, eg. today's is the latest daily, weekly and monthly one
+ *
+ * [backup]
+ * daily = 7
+ * weekly = 4
+ * monthly = 12
+ * */
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            daily: 7,
+            weekly: 4,
+            monthly: 12,
+        }
+    }
+}
+
+pub fn get_backup_dir() -> PathBuf {
+    let mut backup_dir = get_data_dir();
+
+    backup_dir.push("backups");
+
+    backup_dir
+}
+
+// a consistent copy of the database, safe to take while other processes use it
+pub fn create_backup(db_conn: &Connection) -> Result<PathBuf, String> {
+    backup_as(
+        db_conn,
+        &format!(
+            "{BACKUP_PREFIX}{}{BACKUP_SUFFIX}",
+            Local::now().format(BACKUP_TIME_FORMAT)
+        ),
+    )
+}
+
+/*
+ * taken right before something hard to undo (migrations, restore, ...), named
+ * pre-op-20240607-183000123-<operation>.db. --rotate leaves these alone, only the
+ * newest PRE_OP_BACKUPS_KEPT are kept around
+ * */
+pub fn create_pre_op_backup(db_conn: &Connection, operation: &str) -> Result<PathBuf, String> {
+    let path = backup_as(
+        db_conn,
+        &format!(
+            "{PRE_OP_PREFIX}{}-{operation}{BACKUP_SUFFIX}",
+            Local::now().format(PRE_OP_TIME_FORMAT)
+        ),
+    )?;
+
+    let mut pre_op_backups: Vec<PathBuf> = fs::read_dir(get_backup_dir())
+        .map_err(|error| error.to_string())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(PRE_OP_PREFIX))
+        })
+        .collect();
+
+    // names start with the time, so sorting them puts the oldest first
+    pre_op_backups.sort();
+
+    let surplus = pre_op_backups.len().saturating_sub(PRE_OP_BACKUPS_KEPT);
+    for old_backup in &pre_op_backups[..surplus] {
+        let _ = fs::remove_file(old_backup);
+    }
+
+    Ok(path)
+}
+
+fn backup_as(db_conn: &Connection, name: &str) -> Result<PathBuf, String> {
+    let backup_dir = get_backup_dir();
+    fs::create_dir_all(&backup_dir).map_err(|error| error.to_string())?;
+
+    let path = backup_dir.join(name);
+
+    backup_db(db_conn, &path).map_err(|error| error.to_string())?;
+
+    Ok(path)
+}
+
+// backups in the directory with the time they were taken, oldest first
+pub fn get_backups(backup_dir: &Path) -> io::Result<Vec<(NaiveDateTime, PathBuf)>> {
+    let mut backups = vec![];
+
+    for entry in fs::read_dir(backup_dir)? {
+        let path = entry?.path();
+
+        // anything not named like a backup was put there by someone else, leave it be
+        let taken_at = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(BACKUP_PREFIX))
+            .and_then(|name| name.strip_suffix(BACKUP_SUFFIX))
+            .and_then(|time| NaiveDateTime::parse_from_str(time, BACKUP_TIME_FORMAT).ok());
+
+        if let Some(taken_at) = taken_at {
+            backups.push((taken_at, path));
+        }
+    }
+
+    backups.sort();
+
+    Ok(backups)
+}
+
+// year and no. of the day, week or month within it
+type Period = (i32, u32);
+type PeriodOf = fn(&NaiveDateTime) -> Period;
+
+// removes the backups the retention doesn't keep, returns the removed ones
+pub fn prune_backups(backup_dir: &Path, retention: Retention) -> io::Result<Vec<PathBuf>> {
+    let backups = get_backups(backup_dir)?;
+
+    let mut kept: HashSet<&PathBuf> = HashSet::new();
+
+    let periods: [(usize, PeriodOf); 3] = [
+        (retention.daily, |taken_at| {
+            (taken_at.year(), taken_at.ordinal())
+        }),
+        (retention.weekly, |taken_at| {
+            let week = taken_at.iso_week();
+            (week.year(), week.week())
+        }),
+        (retention.monthly, |taken_at| {
+            (taken_at.year(), taken_at.month())
+        }),
+    ];
+
+    for (keep, period_of) in periods {
+        let mut seen_periods = HashSet::new();
+
+        // newest first, so the first backup of a period is the one to keep
+        for (taken_at, path) in backups.iter().rev() {
+            let period = period_of(taken_at);
+
+            if seen_periods.contains(&period) {
+                continue;
+            }
+
+            if seen_periods.len() == keep {
+                break;
+            }
+
+            seen_periods.insert(period);
+            kept.insert(path);
+        }
+    }
+
+    let mut removed = vec![];
+
+    for (_, path) in &backups {
+        if !kept.contains(path) {
+            fs::remove_file(path)?;
+            removed.push(path.clone());
+        }
+    }
+
+    Ok(removed)
+}
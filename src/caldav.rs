@@ -0,0 +1,566 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+
+use crate::{
+    crypto::get_secret,
+    utils::{curl_config_string, run_curl, today},
+    Status, Task,
+};
+
+/*
+ * a caldav task list (eg. nextcloud, fastmail) with curl doing the https, tasks are VTODOs:
+ *
+ * todo         NEEDS-ACTION    a blocked task goes out as NEEDS-ACTION and stays blocked
+ * in_progress  IN-PROCESS      when it comes back unchanged
+ * done         COMPLETED       CANCELLED ones are left alone
+ *
+ * the date goes out as DUE (a date, no time). only SUMMARY, STATUS, DUE, COMPLETED and
+ * PERCENT-COMPLETE are written, anything else a phone app keeps on the item stays as is
+ *
+ * [caldav]
+ * url = "https://cloud.example.com/remote.php/dav/calendars/me/tasks/"
+ * username = "me"
+ *
+ * the password (better an app password) comes from DAILY_DOSE_CALDAV_PASSWORD or the keyring
+ * */
+const PASSWORD_ACCOUNT: &str = "caldav-password";
+const PASSWORD_ENV: &str = "DAILY_DOSE_CALDAV_PASSWORD";
+
+const ICS_DATE_FORMAT: &str = "%Y%m%d";
+const ICS_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+pub struct Account {
+    // the task list (calendar collection), ending in /
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+// an item of the task list, ics is its whole calendar object
+#[derive(Debug)]
+pub struct Todo {
+    pub href: String,
+    pub etag: String,
+    pub ics: String,
+}
+
+// the VTODO properties synced with a task, timestamps as stored (utc, rfc 3339)
+#[derive(Debug, Default)]
+pub struct TodoFields {
+    pub summary: String,
+    pub status: Option<String>,
+    pub due: Option<NaiveDate>,
+    pub completed_at: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Account {
+    pub fn new(url: &str, username: &str) -> Result<Account, String> {
+        let password = get_secret(
+            PASSWORD_ACCOUNT,
+            PASSWORD_ENV,
+            &format!("CalDAV password for {username}: "),
+            "caldav password",
+        )?;
+
+        Ok(Account {
+            url: format!("{}/", url.trim_end_matches('/')),
+            username: username.to_string(),
+            password,
+        })
+    }
+
+    // where a new item for the task goes, as a path like the hrefs the server lists
+    pub fn new_href(&self, task_id: &str) -> String {
+        format!("{}{task_id}.ics", url_path(&self.url))
+    }
+
+    fn href_url(&self, href: &str) -> String {
+        format!("{}{href}", url_origin(&self.url))
+    }
+
+    fn request(&self, config_lines: &[String]) -> Result<(String, String), String> {
+        let mut config_lines = config_lines.to_vec();
+        config_lines.push(format!(
+            "user = {}",
+            curl_config_string(&format!("{}:{}", self.username, self.password))
+        ));
+
+        run_curl(&config_lines)
+    }
+}
+
+/*
+ * every VTODO of the list with its etag, or just the etags (ics left empty). the server
+ * answers with a multistatus document of one response per item
+ * */
+pub fn get_todos(account: &Account, with_data: bool) -> Result<Vec<Todo>, String> {
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+<d:prop><d:getetag/>{}</d:prop>
+<c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter></c:filter>
+</c:calendar-query>"#,
+        if with_data { "<c:calendar-data/>" } else { "" }
+    );
+
+    let (response, status) = account.request(&[
+        format!("url = {}", curl_config_string(&account.url)),
+        "request = \"REPORT\"".to_string(),
+        "header = \"Depth: 1\"".to_string(),
+        "header = \"Content-Type: application/xml; charset=utf-8\"".to_string(),
+        format!("data-binary = {}", curl_config_string(&body)),
+    ])?;
+
+    if status != "207" {
+        return Err(format!(
+            "CalDAV server answered {status} listing {}",
+            account.url
+        ));
+    }
+
+    Ok(xml_elements(&response, "response")
+        .into_iter()
+        .filter_map(|item| {
+            let text = |name: &str| xml_elements(item, name).first().map(|text| xml_text(text));
+
+            Some(Todo {
+                href: text("href")?,
+                etag: text("getetag")?,
+                ics: text("calendar-data").unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+/*
+ * writes the item, only over the version of the etag when given, only when it doesn't exist
+ * yet otherwise. either way an item changed on the server in between isn't overwritten
+ * */
+pub fn put_todo(
+    account: &Account,
+    href: &str,
+    ics: &str,
+    etag: Option<&str>,
+) -> Result<(), String> {
+    let precondition = match etag {
+        Some(etag) => format!("If-Match: {etag}"),
+        None => "If-None-Match: *".to_string(),
+    };
+
+    let (_, status) = account.request(&[
+        format!("url = {}", curl_config_string(&account.href_url(href))),
+        "request = \"PUT\"".to_string(),
+        format!("header = {}", curl_config_string(&precondition)),
+        "header = \"Content-Type: text/calendar; charset=utf-8\"".to_string(),
+        format!("data-binary = {}", curl_config_string(ics)),
+    ])?;
+
+    match status.as_str() {
+        "200" | "201" | "204" => Ok(()),
+        "412" => Err(format!("{href} changed on the server in between")),
+        status => Err(format!("CalDAV server answered {status} writing {href}")),
+    }
+}
+
+pub fn delete_todo(account: &Account, href: &str, etag: &str) -> Result<(), String> {
+    let (_, status) = account.request(&[
+        format!("url = {}", curl_config_string(&account.href_url(href))),
+        "request = \"DELETE\"".to_string(),
+        format!(
+            "header = {}",
+            curl_config_string(&format!("If-Match: {etag}"))
+        ),
+    ])?;
+
+    match status.as_str() {
+        // gone already is as good
+        "200" | "204" | "404" => Ok(()),
+        "412" => Err(format!("{href} changed on the server in between")),
+        status => Err(format!("CalDAV server answered {status} deleting {href}")),
+    }
+}
+
+// the synced properties of the item's VTODO
+pub fn todo_fields(ics: &str) -> TodoFields {
+    let mut fields = TodoFields::default();
+
+    for line in vtodo_lines(&unfold(ics)) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, _params) = name.split_once(';').unwrap_or((name, ""));
+
+        match name.to_ascii_uppercase().as_str() {
+            "SUMMARY" => fields.summary = unescape_text(value).trim().to_string(),
+            "STATUS" => fields.status = Some(value.trim().to_ascii_uppercase()),
+            "DUE" => {
+                fields.due = value
+                    .get(..8)
+                    .and_then(|date| NaiveDate::parse_from_str(date, ICS_DATE_FORMAT).ok())
+            }
+            "COMPLETED" => fields.completed_at = parse_ics_time(value),
+            "LAST-MODIFIED" => fields.last_modified = parse_ics_time(value),
+            _ => {}
+        }
+    }
+
+    fields
+}
+
+/*
+ * the task an item stands for, current being the task it was synced with before. None for
+ * items to leave alone: cancelled ones and ones without a summary
+ * */
+pub fn todo_task(fields: &TodoFields, id: &str, current: Option<&Task>) -> Option<Task> {
+    if fields.summary.is_empty() {
+        return None;
+    }
+
+    let status = match (fields.status.as_deref(), current.map(|task| task.status)) {
+        (Some("CANCELLED"), _) => return None,
+        (Some("COMPLETED"), _) => Status::Done,
+        (Some("IN-PROCESS"), _) => Status::InProgress,
+        (_, Some(Status::Blocked)) => Status::Blocked,
+        _ => Status::Todo,
+    };
+
+    let completed_on = fields
+        .completed_at
+        .as_deref()
+        .and_then(|completed_at| DateTime::parse_from_rfc3339(completed_at).ok())
+        .map(|completed_at| completed_at.with_timezone(&Local).date_naive());
+
+    let date = fields
+        .due
+        .or(completed_on)
+        .map(|date| date.format("%F").to_string())
+        .or_else(|| current.map(|task| task.date.clone()))
+        .unwrap_or_else(|| today().format("%F").to_string());
+
+    Some(Task {
+        id: id.to_string(),
+        description: fields.summary.clone(),
+        status,
+        date,
+        updated_at: None,
+        deferred_until: None,
+        points: None,
+        block_reason: None,
+        position: 0,
+        short_id: None,
+        priority: None,
+        project: None,
+        due: None,
+        parent_id: None,
+        tags: vec![],
+    })
+}
+
+/*
+ * the item for the task, the synced properties replaced in the item's existing calendar
+ * object when there is one, or a new calendar object with the task id as UID
+ * */
+pub fn task_ics(task: &Task, existing_ics: Option<&str>) -> String {
+    let now = Utc::now().format(ICS_TIME_FORMAT).to_string();
+    let due = task
+        .deferred_until
+        .as_deref()
+        .unwrap_or(&task.date)
+        .replace('-', "");
+
+    let mut properties = vec![
+        format!("SUMMARY:{}", escape_text(&task.description)),
+        format!(
+            "STATUS:{}",
+            match task.status {
+                Status::Todo | Status::Blocked => "NEEDS-ACTION",
+                Status::InProgress => "IN-PROCESS",
+                Status::Done => "COMPLETED",
+            }
+        ),
+        format!("DUE;VALUE=DATE:{due}"),
+        format!("LAST-MODIFIED:{now}"),
+        format!("DTSTAMP:{now}"),
+    ];
+
+    if task.status == Status::Done {
+        let completed_at = task
+            .updated_at
+            .as_deref()
+            .and_then(|updated_at| DateTime::parse_from_rfc3339(updated_at).ok())
+            .map(|updated_at| updated_at.to_utc().format(ICS_TIME_FORMAT).to_string())
+            .unwrap_or_else(|| now.clone());
+
+        properties.push(format!("COMPLETED:{completed_at}"));
+        properties.push("PERCENT-COMPLETE:100".to_string());
+    }
+
+    let lines = match existing_ics {
+        Some(ics) => {
+            let replaced = |line: &str| {
+                let name = line.split([':', ';']).next().unwrap_or_default();
+                [
+                    "SUMMARY",
+                    "STATUS",
+                    "DUE",
+                    "LAST-MODIFIED",
+                    "DTSTAMP",
+                    "COMPLETED",
+                    "PERCENT-COMPLETE",
+                ]
+                .contains(&name.to_ascii_uppercase().as_str())
+            };
+
+            let mut lines = vec![];
+            // 1 in the VTODO itself, more in a component within, eg. a VALARM
+            let mut depth = 0;
+
+            for line in unfold(ics) {
+                let upper = line.to_ascii_uppercase();
+
+                if upper == "BEGIN:VTODO" && depth == 0 {
+                    depth = 1;
+                } else if upper == "END:VTODO" && depth == 1 {
+                    lines.append(&mut properties);
+                    depth = 0;
+                } else if depth > 0 && upper.starts_with("BEGIN:") {
+                    depth += 1;
+                } else if depth > 1 && upper.starts_with("END:") {
+                    depth -= 1;
+                } else if depth == 1 && replaced(&line) {
+                    continue;
+                }
+
+                lines.push(line);
+            }
+
+            lines
+        }
+        None => [
+            vec![
+                "BEGIN:VCALENDAR".to_string(),
+                "VERSION:2.0".to_string(),
+                "PRODID:-//daily-dose//EN".to_string(),
+                "BEGIN:VTODO".to_string(),
+                format!("UID:{}", task.id),
+            ],
+            properties,
+            vec!["END:VTODO".to_string(), "END:VCALENDAR".to_string()],
+        ]
+        .concat(),
+    };
+
+    lines
+        .iter()
+        .map(|line| format!("{}\r\n", fold(line)))
+        .collect()
+}
+
+// content lines with the continuation lines (starting with a space or tab) joined back
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for line in ics.split('\n').map(|line| line.trim_end_matches('\r')) {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+// the lines of the first VTODO, without nested components like VALARM
+fn vtodo_lines(lines: &[String]) -> Vec<&str> {
+    let mut vtodo = vec![];
+    let mut depth = 0;
+
+    for line in lines {
+        let upper = line.to_ascii_uppercase();
+
+        if upper == "BEGIN:VTODO" && depth == 0 {
+            depth = 1;
+        } else if upper == "END:VTODO" && depth == 1 {
+            break;
+        } else if depth > 0 && upper.starts_with("BEGIN:") {
+            depth += 1;
+        } else if depth > 1 && upper.starts_with("END:") {
+            depth -= 1;
+        } else if depth == 1 {
+            vtodo.push(line.as_str());
+        }
+    }
+
+    vtodo
+}
+
+// lines longer than 75 bytes continue on the next line, not splitting a character
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_len = 0;
+
+    for char in line.chars() {
+        if line_len + char.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_len = 1;
+        }
+        folded.push(char);
+        line_len += char.len_utf8();
+    }
+
+    folded
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+
+    while let Some(char) = chars.next() {
+        match (char, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => {
+                unescaped.push(' ');
+                chars.next();
+            }
+            ('\\', Some(escaped)) => {
+                unescaped.push(escaped);
+                chars.next();
+            }
+            (char, _) => unescaped.push(char),
+        }
+    }
+
+    unescaped
+}
+
+// 20240607T183000Z, or a floating local time without the Z
+fn parse_ics_time(value: &str) -> Option<String> {
+    let value = value.trim();
+
+    let time = match value.strip_suffix('Z') {
+        Some(utc) => NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .ok()?
+            .and_utc(),
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .ok()?
+            .and_local_timezone(Local)
+            .earliest()?
+            .to_utc(),
+    };
+
+    Some(time.to_rfc3339_opts(SecondsFormat::Micros, true))
+}
+
+/*
+ * the contents of every element of the name, whatever its namespace prefix (d:href, D:href
+ * and href are all href). enough for multistatus responses, which don't nest elements of
+ * the same name
+ * */
+fn xml_elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut elements = vec![];
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        let tag_end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[..tag_end];
+        let qualified_name = tag
+            .split(|char: char| char.is_whitespace() || char == '/')
+            .next()
+            .unwrap_or_default();
+        let local_name = qualified_name.rsplit(':').next().unwrap_or_default();
+
+        if tag.starts_with(['/', '?', '!']) || local_name != name {
+            continue;
+        }
+
+        if tag.ends_with('/') {
+            elements.push("");
+            continue;
+        }
+
+        let content = &rest[(tag_end + 1).min(rest.len())..];
+        let Some(end) = content.find(&format!("</{qualified_name}>")) else {
+            break;
+        };
+
+        elements.push(&content[..end]);
+        rest = &content[end..];
+    }
+
+    elements
+}
+
+// the text of an element, from a CDATA section or with the entities decoded
+fn xml_text(content: &str) -> String {
+    let content = content.trim();
+
+    if let Some(cdata) = content
+        .strip_prefix("<![CDATA[")
+        .and_then(|cdata| cdata.strip_suffix("]]>"))
+    {
+        return cdata.to_string();
+    }
+
+    let mut text = String::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+
+        let decoded = match &rest[1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+
+        match decoded {
+            Some(char) => {
+                text.push(char);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    text.push_str(rest);
+    text
+}
+
+// https://host:port of the url, hrefs from the server are paths on it
+fn url_origin(url: &str) -> &str {
+    let after_scheme = url.find("://").map(|index| index + 3).unwrap_or_default();
+
+    match url[after_scheme..].find('/') {
+        Some(index) => &url[..after_scheme + index],
+        None => url,
+    }
+}
+
+fn url_path(url: &str) -> &str {
+    &url[url_origin(url).len()..]
+}
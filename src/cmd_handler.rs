@@ -1,39 +1,435 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+    process, thread,
+    time::Duration,
+};
 
-use chrono::{Datelike, Local};
-use clap::{arg, builder, value_parser, Arg, ArgMatches, Command};
-use rusqlite::Connection;
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, TimeDelta, Utc};
+use clap::{arg, builder, value_parser, Arg, ArgGroup, ArgMatches, Command};
+use comfy_table::{ContentArrangement, Table};
+use regex::Regex;
+use rusqlite::{types::Value, Connection};
+use ulid::Ulid;
 
+#[cfg(feature = "summarize")]
+use crate::summarize::summarize_standup;
 use crate::{
+    backup::{create_backup, create_pre_op_backup, get_backup_dir, prune_backups, Retention},
+    caldav::{delete_todo, get_todos, put_todo, task_ics, todo_fields, todo_task, Account},
+    completions::{get_completions_path, render_completions, Shell},
+    config::{
+        get_config_path, get_last_data_dir_path, load_config, prepend_data_dir,
+        write_starter_config, Config,
+    },
+    crypto::{generate_key, load_key, Key},
     database::{
-        delete_task, get_tasks_by_date, insert_task, update_task_description, update_task_status,
+        abort_import, archive_tasks, attach_archive, clear_usage, count_backup_tasks,
+        count_tasks_by_status, create_sync_db, create_task_table, delete_external_task,
+        delete_task, delete_task_dep, delete_task_tags, delete_template, delete_view,
+        depends_on_task, dismiss_merge_conflict, finish_import, for_each_task_by_date,
+        get_app_state, get_archive_path, get_completed_tasks, get_data_dir, get_day_counts,
+        get_db_path, get_dependencies, get_dependents, get_estimated_tasks, get_external_id,
+        get_external_task_id, get_external_tasks, get_goal_progress, get_journal_entries,
+        get_journal_entry, get_known_data_dirs, get_last_change, get_merge_conflicts,
+        get_overdue_tasks, get_projects, get_recent_tasks, get_sprint_by_name, get_sprint_for_date,
+        get_sprints, get_storage_dir, get_stuck_tasks, get_subtasks, get_task_annotations,
+        get_task_attachments, get_task_by_id, get_task_deps, get_task_history, get_task_links,
+        get_tasks, get_tasks_by_date, get_tasks_completed_since, get_template_tasks, get_templates,
+        get_usage, get_view_filter, get_views, goal_exists, has_db, insert_goal,
+        insert_imported_tasks, insert_seed_tasks, insert_sprint, insert_task,
+        insert_task_annotation, insert_task_attachment, insert_task_dep, insert_task_links,
+        insert_task_tags, insert_template_tasks, is_conflict_error, is_task_table_migrated,
+        merge_db, migrate_task_table, move_data_dir, open_db_connection, open_profile_db,
+        populate_date_from_template, push_db, relocate_attachments, resolve_task_id, restore_db,
+        run_query, save_template, save_view, seal_plain_values, set_app_state, set_data_dir,
+        set_external_sync_state, set_journal_entry, set_task_goal, set_task_parent,
+        store_external_tasks, take_merge_conflict, update_task_blocked, update_task_deferred_until,
+        update_task_description, update_task_due, update_task_points, update_task_priority,
+        update_task_project, update_task_status, ExternalTask, GoalProgress, MergeCounts,
+        ProjectCount, SortKey, Storage, TaskFilter,
     },
+    gitlab::{comment_done, get_assigned_issues, get_gitlab_token, issue_task, DEFAULT_GITLAB_URL},
+    gtasks::{get_access_token, get_google_task_lists, get_google_tasks, google_task, Credentials},
+    html::render_period_html,
+    jira::render_jira_csv,
+    json::{parse_json, render_report_json, task_json, Json},
+    lock::acquire_db_lock,
+    markdown::plain_markdown,
+    pdf::render_period_pdf,
+    reminders::{read_reminders, reminder_task},
     render_tasks_table,
-    utils::{construct_timestamp, iso_format_timestamp},
-    Status, Task,
+    scheduler::{parse_job, Job},
+    seed::seed_tasks,
+    server::serve_metrics,
+    slack::render_slack_blocks,
+    template::Template,
+    update::{binary_asset_name, get_latest_release, install_release, CURRENT_VERSION},
+    utils::parse_filter_expression,
+    utils::parse_tag_arg,
+    utils::{
+        anonymize_text, export_checksum, porcelain_header, porcelain_task_line,
+        verify_export_checksum,
+    },
+    utils::{
+        construct_timestamp, day_start_timestamp, iso_format_timestamp, parse_date_arg,
+        parse_later_date_arg,
+    },
+    utils::{
+        description_similarity, edit_in_editor, extract_urls, find_secrets, open_with_default_app,
+        prompt_line,
+    },
+    utils::{display_options, format_local_timestamp, now_timestamp},
+    utils::{extract_tags, parse_porcelain_line, parse_porcelain_task, parse_quarter_arg},
+    utils::{finish_progress, print_progress},
+    utils::{format_duration, task_created_at, today},
+    utils::{
+        format_status, print_table, redact_description, render_focus_banner, render_task_card,
+    },
+    utils::{month_range, quarter_range, year_range, ListRange, Lookback, RedactStyle},
+    utils::{
+        print_overall_completion, render_tasks_chunk, render_tasks_table_with_notes, Completion,
+        TaskNotes,
+    },
+    xlsx::render_xlsx,
+    Goal, Priority, Sprint, Status, Task,
 };
 
+// commands writing to the database, these run under the advisory db lock
+pub const MUTATING_CMDS: &[&str] = &[
+    "add", "update", "mark", "unmark", "prio", "tag", "untag", "delete", "template", "snooze",
+    "sprint", "attach", "view", "focus", "log", "note", "block", "standup", "key", "import",
+    "restore", "merge", "sync", "caldav", "goal", "journal", "seed", "archive",
+];
+
+// subcommands writing to the database, under commands that also have reading ones
+const MUTATING_SUBCMDS: &[&str] = &["save", "apply", "delete", "create", "seal", "add", "remove"];
+
+// eg. template save writes, template list only reads
+pub fn is_mutating_cmd(cmd_matches: &ArgMatches) -> bool {
+    match cmd_matches.subcommand() {
+        Some(("template" | "view" | "sprint" | "key" | "goal" | "deps", sub_matches)) => {
+            sub_matches
+                .subcommand_name()
+                .is_some_and(|name| MUTATING_SUBCMDS.contains(&name))
+        }
+        // listing them only reads
+        Some(("conflicts", sub_matches)) => {
+            sub_matches.contains_id("take") || sub_matches.contains_id("dismiss")
+        }
+        Some(("usage", sub_matches)) => sub_matches.get_flag("reset"),
+        Some((name, _)) => MUTATING_CMDS.contains(&name),
+        None => false,
+    }
+}
+
+const FILTER_EXPRESSION_HELP: &str = "Filter expression, all terms have to match:
+  status:todo          status:todo,blocked matches either
+  priority:high        priority:high,medium matches either
+  tag:backend          attached, or #backend in the description
+  project:acme         project:acme,globex matches either
+  before:2024-06-01    tasks dated before (after:, on: work the same way)
+  re:JIRA-\\d+          description matching a regex
+  rollout \"two words\"  anything else searches the description
+Dates accept YYYY-MM-DD, today, tomorrow, yesterday or a weekday";
+
+fn print_update_error(task_id: &str, error: rusqlite::Error) {
+    if is_conflict_error(&error) {
+        println!(
+            "Conflict: task {task_id} was modified by someone else in the meantime, nothing was changed. Re-run the command to apply it on the latest version"
+        );
+    } else {
+        println!("Error updating task = {:?}", error);
+    }
+}
+
+/*
+ * the configured daily_template is added to a date the first time any command touches it
+ * past dates are left alone, so looking up history doesn't fill it with checklists
+ * what it says goes to stderr, ahead of the command's own (maybe porcelain or json) output
+ * */
+pub fn apply_daily_template(db_conn: &Connection, config: &Config, date: &NaiveDate) {
+    let Some(template) = config.get("daily_template") else {
+        return;
+    };
+
+    if *date < today() {
+        return;
+    }
+
+    let iso_timestamp = iso_format_timestamp(date);
+
+    match populate_date_from_template(db_conn, template, &iso_timestamp) {
+        Ok(Some(0)) => {}
+        Ok(Some(task_count)) => eprintln!(
+            "Added {} from daily template '{template}' to {iso_timestamp}",
+            task_count_text(task_count)
+        ),
+        Ok(None) => {
+            eprintln!("Error = no template named '{template}' for daily_template in config")
+        }
+        Err(error) => eprintln!("Error applying daily template = {:?}", error),
+    }
+}
+
+/*
+ * encrypt_descriptions = true in config seals descriptions and notes with the key from the
+ * OS keyring before they are stored. no key means no encryption, a missing key while it is
+ * turned on stops everything, rather than quietly storing clear text
+ * */
+pub fn load_encryption_key(config: &Config) -> Option<Key> {
+    if config.get_parsed("encrypt_descriptions") != Some(true) {
+        return None;
+    }
+
+    match load_key() {
+        Ok(key) => Some(key),
+        Err(error) => {
+            println!("Error loading encryption key = {error}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+ * the data directory from storage (DAILY_DOSE_STORAGE) and data_dir (DAILY_DOSE_DATA_DIR),
+ * the environment going over the config. a data_dir on its own means custom storage
+ * */
+pub fn resolve_data_dir(config: &Config, profile: Option<&str>) -> Result<PathBuf, String> {
+    let custom_dir = std::env::var_os("DAILY_DOSE_DATA_DIR")
+        .map(PathBuf::from)
+        .or_else(|| config.get("data_dir").map(PathBuf::from));
+
+    let storage = match std::env::var("DAILY_DOSE_STORAGE") {
+        Ok(storage) => Some(storage),
+        Err(_) => config.get("storage").map(|storage| storage.to_string()),
+    };
+
+    let storage = match storage {
+        Some(storage) => storage
+            .parse::<Storage>()
+            .map_err(|_| format!("unknown storage '{storage}', use xdg, home or custom"))?,
+        None if custom_dir.is_some() => Storage::Custom,
+        None => Storage::default(),
+    };
+
+    get_storage_dir(storage, custom_dir.as_deref(), profile)
+}
+
+/*
+ * when storage or data_dir changed the database is still where it was, so it's moved over
+ * along with backups and attachments. looks where it was last time, then the places the
+ * other storage settings use. returns the directory it was moved from
+ * */
+pub fn relocate_data_dir(profile: Option<&str>, data_dir: &Path) -> Option<PathBuf> {
+    if has_db(data_dir) {
+        return None;
+    }
+
+    let last_data_dir = fs::read_to_string(get_last_data_dir_path(profile))
+        .ok()
+        .map(|last_data_dir| PathBuf::from(last_data_dir.trim_end()));
+
+    let old_data_dir = last_data_dir
+        .into_iter()
+        .chain(get_known_data_dirs(profile))
+        .find(|old_data_dir| old_data_dir != data_dir && has_db(old_data_dir))?;
+
+    // nobody else may write while the files are on their way
+    let old_db_path = old_data_dir.join("storage.db");
+    let moved = acquire_db_lock(&old_db_path.to_string_lossy())
+        .and_then(|_lock| move_data_dir(&old_data_dir, data_dir));
+
+    match moved {
+        Ok(()) => {
+            // a leftover empty directory would only confuse, anything else in it is kept
+            let _ = fs::remove_dir(&old_data_dir);
+            println!(
+                "Moved data from {} to {}",
+                old_data_dir.display(),
+                data_dir.display()
+            );
+            Some(old_data_dir)
+        }
+        Err(error) => {
+            println!(
+                "Error moving data from {} to {} = {error}",
+                old_data_dir.display(),
+                data_dir.display()
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+// after the move the copied attachments point at the new place, and it's remembered
+pub fn finish_relocation(
+    db_conn: &Connection,
+    profile: Option<&str>,
+    data_dir: &Path,
+    old_data_dir: Option<&Path>,
+) {
+    if let Some(old_data_dir) = old_data_dir
+        && let Err(error) = relocate_attachments(db_conn, old_data_dir, data_dir)
+    {
+        println!("Error updating attachment paths = {:?}", error);
+    }
+
+    let last_data_dir_path = get_last_data_dir_path(profile);
+    let data_dir = data_dir.to_string_lossy();
+
+    if fs::read_to_string(&last_data_dir_path).is_ok_and(|last| last.trim_end() == data_dir) {
+        return;
+    }
+
+    let _ = last_data_dir_path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&last_data_dir_path, format!("{data_dir}\n")));
+}
+
+/*
+ * secret_check = true in config looks for tokens and keys in a description before it is
+ * stored, asking whether to go ahead when something turns up. without a terminal to ask
+ * on nothing is stored, --allow-secrets skips the check
+ * */
+fn confirm_secret_free(arg_matches: &ArgMatches, config: &Config, description: &str) -> bool {
+    if config.get_parsed("secret_check") != Some(true) || arg_matches.get_flag("allow-secrets") {
+        return true;
+    }
+
+    let secrets = find_secrets(description);
+
+    if secrets.is_empty() {
+        return true;
+    }
+
+    println!(
+        "Warning: the description looks like it contains {}",
+        secrets.join(", ")
+    );
+
+    match prompt_line("Store it anyway? [y/N] ") {
+        Ok(Some(answer)) if answer.eq_ignore_ascii_case("y") => true,
+        Ok(Some(_)) => false,
+        Ok(None) => {
+            println!("Not stored, pass --allow-secrets to store it anyway");
+            false
+        }
+        Err(error) => {
+            println!("Error reading answer = {error}");
+            false
+        }
+    }
+}
+
 pub fn construct_cmd_args() -> Command {
     Command::new("Daily Dose")
-        .version("1.0.0")
+        .version(CURRENT_VERSION)
         .about("Record your daily dose of pain")
+        .args([
+            arg!(--accessible "Labeled lines instead of tables, for screen readers").global(true),
+            arg!(--compact "One line per task instead of a table").global(true),
+            arg!(--porcelain "Stable tab separated output for scripts").global(true),
+            arg!(--full "Show long descriptions in full instead of cutting them").global(true),
+            arg!(--wide "Use the whole terminal width for tables").global(true),
+            arg!(--profile <NAME> "Use a separate config and database, eg. work or personal (or DAILY_DOSE_PROFILE)")
+                .value_parser(|name: &str| match name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+                    true => Ok(name.to_string()),
+                    false => Err("Use letters, digits, - and _ only".to_string()),
+                })
+                .required(false)
+                .global(true),
+            arg!(--"read-only" "Open the database read-only and refuse commands changing it (or DAILY_DOSE_READONLY=1)")
+                .global(true),
+            arg!(--redact [STYLE] "Mask descriptions for screen sharing, words (default) or full")
+                .value_parser(|style: &str| style.parse::<RedactStyle>())
+                .global(true),
+        ])
         .subcommands([
+            Command::new("init")
+                .about("Set up the config file and database, and show where everything lives")
+                .args([
+                    arg!(--path <DIR> "Keep the database in DIR instead of the data directory")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(false),
+                    arg!(--completions <SHELL> "Also install shell completions")
+                        .value_parser(value_parser!(Shell))
+                        .required(false),
+                ]),
             Command::new("list")
                 .about("List multiple standups based on timeline")
                 .args([
                     arg!(-m --month <MONTH_NO> "List standups for specified month (eg. 1, 2, 3)")
                         .value_parser(value_parser!(u32).range(1..=12))
                         .required(false),
+                    arg!(-y --year <YEAR_NO> "Year of --month, or the whole year on its own")
+                        .value_parser(value_parser!(i32).range(1978..=9999))
+                        .required(false),
                     arg!(-l --limit <LIMIT> "Limit no. of standups in result")
                         .value_parser(value_parser!(u32).range(1..))
                         .required(false),
                     arg!(-s --search <QUERY> "Search query(task) for searching")
                         .value_parser(builder::NonEmptyStringValueParser::new())
                         .required(false),
+                    arg!(--"search-regex" <PATTERN> "Search tasks matching a regex (eg. 'JIRA-\\d+')")
+                        .value_parser(|pattern: &str| {
+                            Regex::new(pattern).map(|_| pattern.to_string())
+                        })
+                        .conflicts_with("search")
+                        .required(false),
+                    arg!(--filter <EXPRESSION> "Filter expression, eg. 'status:todo before:2024-06-01 \"rollout\"'")
+                        .long_help(FILTER_EXPRESSION_HELP)
+                        .value_parser(|expression: &str| parse_filter_expression(expression))
+                        .required(false),
+                    arg!(--sort <KEY> "Order of tasks within a date")
+                        .value_parser(value_parser!(SortKey))
+                        .required(false),
+                    arg!(--priority <PRIORITY> "Only tasks of these priorities, comma separated (eg. high,medium)")
+                        .value_parser(value_parser!(Priority))
+                        .value_delimiter(',')
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                    arg!(--project <NAME> "Only tasks of this project, repeat for several")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                    arg!(--tag <TAG> "Only tasks with this tag, attached or as #tag in the description, repeat to need several")
+                        .value_parser(parse_tag_arg)
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                    arg!(--asc "List oldest date first, reading chronologically top to bottom")
+                        .visible_alias("reverse")
+                        .action(clap::ArgAction::SetTrue),
                     Arg::new("include-id")
                         .long("include-id")
                         .action(clap::ArgAction::SetTrue),
+                    arg!(--"fill-gaps" "Also list the days without any tasks")
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--all "List every date instead of a range")
+                        .conflicts_with_all(["month", "year", "fill-gaps"])
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--overdue "Only unfinished tasks past their due date, from any date")
+                        .conflicts_with_all(["month", "year", "fill-gaps"])
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--jsonl "One task per line as json, printed as they are read")
+                        .conflicts_with("fill-gaps")
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--archived "Also list tasks moved to archive.db by archive")
+                        .action(clap::ArgAction::SetTrue),
                 ]),
+            Command::new("todo")
+                .about("Todo tasks of the last days, shortcut_range in config or 14d")
+                .args(status_shortcut_args()),
+            Command::new("doing")
+                .about("In progress tasks of the last days, shortcut_range in config or 14d")
+                .args(status_shortcut_args()),
+            Command::new("blocked")
+                .about("Blocked tasks of the last days with what they are blocked on, shortcut_range in config or 14d")
+                .args(status_shortcut_args()),
             Command::new("show")
                 .about("Show tasks for any specific date")
                 .args([
@@ -49,6 +445,26 @@ pub fn construct_cmd_args() -> Command {
                     Arg::new("include-id")
                         .long("include-id")
                         .action(clap::ArgAction::SetTrue),
+                    arg!(--"include-overdue" "Also list unfinished tasks from earlier dates")
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--notes "Show the notes of each task beneath it, see note")
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--sort <KEY> "Order of tasks within a date")
+                        .value_parser(value_parser!(SortKey))
+                        .required(false),
+                    arg!(--status <STATUS> "Only tasks of these statuses, comma separated (eg. todo,in_progress)")
+                        .value_parser(value_parser!(Status))
+                        .value_delimiter(',')
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                    arg!(--project <NAME> "Only tasks of this project, repeat for several")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                    arg!(--id <TASK_ID> "Show everything about a single task instead, by its id or short id (eg. 0612-3)")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .conflicts_with_all(["day", "month", "year", "include-overdue", "notes", "sort", "status", "project"])
+                        .required(false),
                 ]),
             Command::new("add")
                 .about("Add a task to current or specific date's standup task list")
@@ -67,24 +483,146 @@ pub fn construct_cmd_args() -> Command {
                         .required(false),
                     arg!(--done "Mark the task as done by default else will be marked todo")
                         .action(clap::ArgAction::SetTrue),
+                    arg!(--points <POINTS> "Effort estimate in story points")
+                        .value_parser(value_parser!(u32))
+                        .required(false),
+                    arg!(--link <URL> "Link to attach, urls in the description are picked up as well")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                    arg!(--goal <NAME> "Goal the task counts towards, see goal add")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!(-p --priority <PRIORITY> "How urgent the task is, high ones come first within a date")
+                        .value_parser(value_parser!(Priority))
+                        .required(false),
+                    arg!(--project <NAME> "Project or client the task is for, see projects")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!(--due <DATE> "Date the task has to be done by (eg. 2024-06-01, friday)")
+                        .value_parser(parse_date_arg)
+                        .required(false),
+                    arg!(--parent <TASK> "Add it as a subtask of today's task by index, or any task by id (eg. 2, 0612-3)")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!(--"depends-on" <TASK> "Task it waits on, given like --parent, repeat for several, see deps")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                    arg!(-t --tag <TAG> "Tag to attach (eg. backend), repeat for several")
+                        .value_parser(parse_tag_arg)
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                    arg!(--"allow-secrets" "Store the description even if it looks like it holds a secret")
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--"allow-duplicate" "Add the task even if an open one looks the same")
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--card "Show the added task as a card"),
                 ]),
-            Command::new("update")
-                .about("Update a task based on task id")
+            Command::new("log")
+                .about("Log work that is already done, as a done task for today")
                 .args([
                     arg!([TASK] "Task description")
                         .value_parser(builder::NonEmptyStringValueParser::new())
                         .required(true),
-                    arg!(--id <TASK_ID> "Task ID to update on")
+                    arg!(--points <POINTS> "Effort estimate in story points")
+                        .value_parser(value_parser!(u32))
+                        .required(false),
+                    arg!(--project <NAME> "Project or client the task is for, see projects")
                         .value_parser(builder::NonEmptyStringValueParser::new())
-                        .required(true),
+                        .required(false),
+                    arg!(--"allow-secrets" "Store the description even if it looks like it holds a secret")
+                        .action(clap::ArgAction::SetTrue),
                 ]),
+            Command::new("update")
+                .about("Update today's specific task by index, or any task by id")
+                .args([
+                    // a u8 index, unless --id is given, then it is the description, see task_target
+                    arg!([TASK_INDEX] "Update current date's task based on task index, left out with --id")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!([TASK] "Task description")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!(--id <TASK_ID> "Task ID or short id (eg. 0612-3) to update on")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!(--points <POINTS> "Effort estimate in story points")
+                        .value_parser(value_parser!(u32))
+                        .required(false),
+                    arg!(--goal <NAME> "Goal the task counts towards, see goal add")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!(-p --priority <PRIORITY> "How urgent the task is, high ones come first within a date")
+                        .value_parser(value_parser!(Priority))
+                        .required(false),
+                    arg!(--project <NAME> "Project or client the task is for, see projects")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!(--due <DATE> "Date the task has to be done by (eg. 2024-06-01, friday)")
+                        .value_parser(parse_date_arg)
+                        .required(false),
+                    arg!(--"allow-secrets" "Store the description even if it looks like it holds a secret")
+                        .action(clap::ArgAction::SetTrue),
+                ])
+                .group(
+                    ArgGroup::new("changes")
+                        .args(["TASK_INDEX", "TASK", "points", "goal", "priority", "project", "due"])
+                        .required(true)
+                        .multiple(true),
+                ),
             Command::new("mark")
                 .about("Mark today's specific task as done")
-                .arg(
+                .args([
                     arg!([TASK_INDEX] "Mark current date's task based on task index")
                         .value_parser(value_parser!(u8).range(1..=100))
                         .required(true),
-                ),
+                    arg!(--card "Show the task as a card once marked"),
+                ]),
+            Command::new("prio")
+                .about("Change the priority of today's specific task")
+                .args([
+                    arg!([TASK_INDEX] "Current date's task based on task index")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(true),
+                    arg!([PRIORITY] "high, medium or low")
+                        .value_parser(value_parser!(Priority))
+                        .required(true),
+                ]),
+            Command::new("tag")
+                .about("Attach tags to today's specific task")
+                .args([
+                    arg!([TASK_INDEX] "Current date's task based on task index")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(true),
+                    arg!([TAGS]... "Tags to attach (eg. backend bug)")
+                        .value_parser(parse_tag_arg)
+                        .required(true),
+                ]),
+            Command::new("deps")
+                .about("Tasks waiting on other tasks, they stay blocked until those are done")
+                .arg(
+                    arg!(--all "Also list the dependencies of done tasks")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .subcommands([
+                    Command::new("add")
+                        .about("Make a task wait on another one")
+                        .args(dependency_args()),
+                    Command::new("remove")
+                        .about("Stop a task waiting on another one")
+                        .args(dependency_args()),
+                ]),
+            Command::new("untag")
+                .about("Remove tags attached to today's specific task")
+                .args([
+                    arg!([TASK_INDEX] "Current date's task based on task index")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(true),
+                    arg!([TAGS]... "Tags to remove (eg. bug)")
+                        .value_parser(parse_tag_arg)
+                        .required(true),
+                ]),
             Command::new("unmark")
                 .about("Unmark today's specific task as todo")
                 .arg(
@@ -92,77 +630,1139 @@ pub fn construct_cmd_args() -> Command {
                         .value_parser(value_parser!(u8).range(1..=100))
                         .required(true),
                 ),
+            Command::new("block")
+                .about("Mark today's specific task as blocked, asking what it is blocked on")
+                .args([
+                    arg!([TASK_INDEX] "Current date's task index")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(true),
+                    arg!(--reason <REASON> "What the task is blocked on")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                ]),
             Command::new("delete")
-                .about("Delete a task based on task id")
+                .about("Delete today's specific task by index, or any task by id")
+                .args([
+                    arg!([TASK_INDEX] "Delete current date's task based on task index")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(false),
+                    arg!(--id <TASK_ID> "Task ID or short id (eg. 0612-3) to delete")
+                        .value_parser(builder::NonEmptyStringValueParser::new()),
+                    arg!(--force "Delete without asking first"),
+                ])
+                .group(
+                    ArgGroup::new("target")
+                        .args(["TASK_INDEX", "id"])
+                        .required(true),
+                ),
+            Command::new("snooze")
+                .about("Hide today's specific task until a later date")
+                .args([
+                    arg!([TASK_INDEX] "Snooze current date's task based on task index")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(true),
+                    arg!(--until <DATE> "Date the task comes back on (eg. 2024-06-14, tomorrow, monday)")
+                        .value_parser(parse_later_date_arg)
+                        .required(true),
+                ]),
+            Command::new("open")
+                .about("Open a link of today's specific task in the browser, show --id numbers them")
+                .args([
+                    arg!([TASK_INDEX] "Open current date's task link based on task index")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(true),
+                    arg!(--link <LINK_NO> "Which of the task's links to open, defaults to the first")
+                        .value_parser(value_parser!(u8).range(1..))
+                        .required(false),
+                ]),
+            Command::new("attach")
+                .about("Attach a file to today's specific task, or list and open its attachments")
+                .args([
+                    arg!([TASK_INDEX] "Current date's task index")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(true),
+                    arg!([FILE] "File to attach, lists the attachments when left out")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(false),
+                    arg!(--copy "Keep a copy of the file in the data directory")
+                        .action(clap::ArgAction::SetTrue)
+                        .requires("FILE"),
+                    arg!(--open [ATTACHMENT_NO] "Open an attachment, defaults to the first")
+                        .value_parser(value_parser!(u8).range(1..))
+                        .conflicts_with("FILE"),
+                ]),
+            Command::new("note")
+                .visible_alias("annotate")
+                .about("Add a timestamped note to today's task by index or any task by id, or list its notes")
+                .args([
+                    // a u8 index, unless --id is given, then it is the note, see task_target
+                    arg!([TASK_INDEX] "Current date's task index, left out with --id")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!([NOTE] "Note to add, lists the notes when left out")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                    arg!(--id <TASK_ID> "Task ID or short id (eg. 0612-3) to note on")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .required(false),
+                ]),
+            Command::new("recent")
+                .about("List recently added or modified tasks with their last change")
+                .arg(
+                    arg!(-n --number <COUNT> "No. of tasks to list")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("20"),
+                ),
+            Command::new("focus")
+                .about("Pick the task you are working on now, marks it in progress and shows it big")
+                .args([
+                    arg!([TASK_INDEX] "Current date's task index, defaults to the task in progress or the first todo")
+                        .value_parser(value_parser!(u8).range(1..=100))
+                        .required(false),
+                    arg!(--prompt "Only print the focused task on one line, for shell prompts")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("TASK_INDEX"),
+                ]),
+            Command::new("standup")
+                .about("What got done since yesterday, what's planned today and current blockers")
+                .args(standup_args()),
+            Command::new("journal")
+                .about("Write the day's free-form journal entry in your editor, next to its tasks")
+                .arg(
+                    arg!(--date <DATE> "Day of the entry, defaults to today")
+                        .value_parser(parse_date_arg)
+                        .required(false),
+                ),
+            Command::new("next")
+                .about("List upcoming tasks, including snoozed ones coming back and ones falling due")
+                .arg(
+                    arg!(--days <DAYS> "How many days ahead to look")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("7"),
+                ),
+            Command::new("stats")
+                .about("Completed tasks, completion rate and load per month of a year")
+                .args([
+                    arg!(--compare <YEAR> "Two years side by side (eg. --compare 2023 2024)")
+                        .value_parser(value_parser!(i32).range(1978..=9999))
+                        .num_args(2)
+                        .required(false),
+                    arg!(--format <FORMAT> "Output format")
+                        .value_parser(value_parser!(StatsFormat))
+                        .default_value("text"),
+                ]),
+            Command::new("daemon")
+                .about("Run the jobs under [daemon] in config on their schedule, until stopped"),
+            Command::new("serve")
+                .about("Serve prometheus metrics of your tasks on /metrics")
+                .arg(
+                    arg!(--address <ADDRESS> "Address to listen on")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .default_value("127.0.0.1:9464"),
+                ),
+            Command::new("onthisday")
+                .about("What you were working on this day a month and a year ago")
+                .arg(
+                    arg!(--ago <LOOKBACK> "How far to look back (eg. 7d, 2w, 1m, 1y), defaults to onthisday from config or 1m and 1y")
+                        .value_parser(|value: &str| value.parse::<Lookback>())
+                        .action(clap::ArgAction::Append)
+                        .required(false),
+                ),
+            Command::new("template")
+                .about("Save a day's tasks as a reusable checklist and apply it to other dates")
+                .subcommand_required(true)
+                .subcommands([
+                    Command::new("save")
+                        .about("Save the tasks of today or a specific date, with their priorities and tags, as a template")
+                        .args([
+                            arg!([NAME] "Template name")
+                                .value_parser(builder::NonEmptyStringValueParser::new())
+                                .required(true),
+                            arg!(-d --day <DAY_NO> "Day to capture tasks from")
+                                .value_parser(value_parser!(u32).range(1..=31))
+                                .required(false),
+                            arg!(-m --month <MONTH_NO> "Month to capture tasks from")
+                                .value_parser(value_parser!(u32).range(1..=12))
+                                .required(false),
+                            arg!(-y --year <YEAR_NO> "Year to capture tasks from")
+                                .value_parser(value_parser!(u32).range(1978..))
+                                .required(false),
+                        ]),
+                    Command::new("apply")
+                        .about("Add the template's tasks to a date's task list")
+                        .args([
+                            arg!([NAME] "Template name")
+                                .value_parser(builder::NonEmptyStringValueParser::new())
+                                .required(true),
+                            arg!(--date <DATE> "Date to add tasks to (eg. 2024-06-14, tomorrow, friday)")
+                                .value_parser(parse_date_arg)
+                                .required(false),
+                        ]),
+                    Command::new("list").about("List saved templates"),
+                    Command::new("delete")
+                        .about("Delete a saved template")
+                        .arg(
+                            arg!([NAME] "Template name")
+                                .value_parser(builder::NonEmptyStringValueParser::new())
+                                .required(true),
+                        ),
+                ]),
+            Command::new("view")
+                .about("Save filters under a name and list tasks with them (eg. `view stuck`)")
+                .subcommand_required(true)
+                .allow_external_subcommands(true)
+                .subcommands([
+                    Command::new("save")
+                        .about("Save a filter expression as a named view")
+                        .args([
+                            arg!([NAME] "View name")
+                                .value_parser(builder::NonEmptyStringValueParser::new())
+                                .required(true),
+                            arg!(--filter <EXPRESSION> "Filter expression, same as `list --filter`")
+                                .long_help(FILTER_EXPRESSION_HELP)
+                                .value_parser(|expression: &str| {
+                                    parse_filter_expression(expression)
+                                        .map(|_| expression.to_string())
+                                })
+                                .required(true),
+                        ]),
+                    Command::new("list").about("List saved views"),
+                    Command::new("delete")
+                        .about("Delete a saved view")
+                        .arg(
+                            arg!([NAME] "View name")
+                                .value_parser(builder::NonEmptyStringValueParser::new())
+                                .required(true),
+                        ),
+                ]),
+            Command::new("sprint")
+                .about("Group tasks into sprints by date")
+                .subcommand_required(true)
+                .subcommands([
+                    Command::new("create")
+                        .about("Create a sprint, tasks dated within it belong to it")
+                        .args([
+                            arg!([NAME] "Sprint name")
+                                .value_parser(builder::NonEmptyStringValueParser::new())
+                                .required(true),
+                            arg!(--start <DATE> "First day of the sprint, defaults to today")
+                                .value_parser(parse_date_arg)
+                                .required(false),
+                            arg!(--end <DATE> "Last day of the sprint, defaults to two weeks after start")
+                                .value_parser(parse_date_arg)
+                                .required(false),
+                        ]),
+                    Command::new("current").about("Show the sprint running today and its tasks"),
+                    Command::new("list").about("List all sprints"),
+                ]),
+            Command::new("projects")
+                .about("List the projects given to tasks with how many of their tasks are still open"),
+            Command::new("goal")
+                .about("Quarterly goals and how far the tasks linked to them got")
+                .subcommand_required(true)
+                .subcommands([
+                    Command::new("add")
+                        .about("Add a goal, link tasks to it with add or update --goal")
+                        .args([
+                            arg!([NAME] "Goal name")
+                                .value_parser(builder::NonEmptyStringValueParser::new())
+                                .required(true),
+                            arg!(--quarter <QUARTER> "Quarter of the goal (eg. Q3, 2024-Q3), defaults to the current one")
+                                .value_parser(parse_quarter_arg)
+                                .required(false),
+                        ]),
+                    Command::new("progress")
+                        .about("Done and linked tasks per goal of the current quarter")
+                        .arg(
+                            arg!(--quarter <QUARTER> "Another quarter's goals (eg. Q2, 2024-Q2)")
+                                .value_parser(parse_quarter_arg)
+                                .required(false),
+                        ),
+                ]),
+            Command::new("key")
+                .about("Manage the key encrypting descriptions and notes (encrypt_descriptions in config)")
+                .subcommand_required(true)
+                .subcommands([
+                    Command::new("generate")
+                        .about("Create a random key and store it in the OS keyring"),
+                    Command::new("seal")
+                        .about("Encrypt descriptions and notes stored before encryption was turned on"),
+                ]),
+            Command::new("backup")
+                .about("Snapshot the database into the backups folder of the data directory")
+                .arg(arg!(--rotate "Also remove old backups, keeping the newest per day, week and month ([backup] daily, weekly, monthly in config)")),
+            Command::new("export")
+                .about("Write every task in the format import reads, with a checksum, as csv for jira, as a slack message or as an excel workbook")
+                .args([
+                    arg!(-o --output <FILE> "Write the export to FILE instead of printing it")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(false),
+                    arg!(--format <FORMAT> "Output format, jira-csv is for jira's csv importer, slack-blocks a block kit message of today's tasks, xlsx a workbook with a sheet per month")
+                        .value_parser(value_parser!(ExportFormat))
+                        .default_value("porcelain"),
+                    arg!(--since <LOOKBACK> "With --format jira-csv, slack-blocks or xlsx only tasks from eg. 7d or 2w ago on")
+                        .value_parser(|value: &str| value.parse::<Lookback>())
+                        .required(false),
+                    arg!(--verify "Read the written file back and compare it with the database")
+                        .requires("output"),
+                    arg!(--anonymize "Replace descriptions and block reasons with placeholder words, to share in bug reports"),
+                    arg!(--archived "Also export tasks moved to archive.db by archive"),
+                ]),
+            Command::new("restore")
+                .about("Replace the database with a backup, taking a backup of the current one first")
                 .arg(
-                    arg!(--id <TASK_ID> "Task ID to delete")
+                    arg!(<BACKUP> "Backup file, or its name in the backups folder")
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+            Command::new("query")
+                .about("Run a read-only SQL statement against the database, eg. \"SELECT date, count(*) FROM tasks GROUP BY date\"")
+                .long_about("Run a read-only SQL statement against the database, eg. \"SELECT date, count(*) FROM tasks GROUP BY date\"\n\nSELECT, WITH, VALUES and EXPLAIN statements are taken, nothing that changes the database. With encrypt_descriptions on, unseal(description) gives the descriptions in clear text")
+                .args([
+                    arg!(<SQL> "The statement, a single one")
+                        .value_parser(builder::NonEmptyStringValueParser::new()),
+                    arg!(--format <FORMAT> "Output format")
+                        .value_parser(value_parser!(QueryFormat))
+                        .default_value("table"),
+                ]),
+            Command::new("usage")
+                .about("Show how often each command was run, counted with usage_stats = true in config")
+                .arg(arg!(--reset "Forget the counts so far")),
+            Command::new("self-update")
+                .about("Replace this binary with the latest release from GitHub, once its checksum matches")
+                .long_about("Replace this binary with the latest release from GitHub, once its checksum matches\n\nThe checksum comes from the release's SHA256SUMS, next to the binary it covers. It only catches a broken or cut off download, it doesn't prove who published the release: no signature is checked")
+                .arg(arg!(--check "Only tell whether a newer release is out")),
+            Command::new("seed")
+                .about("Fill the database with made up tasks, to try things out or take screenshots (see --profile)")
+                .args([
+                    arg!(--days <DAYS> "Days back from today to add tasks on")
+                        .value_parser(value_parser!(u32).range(1..=3650))
+                        .default_value("60"),
+                    arg!(--"per-day" <TASKS> "Tasks added on each day")
+                        .value_parser(value_parser!(u32).range(1..=100))
+                        .default_value("5"),
+                    arg!(--seed <NUMBER> "Makes the same tasks every time, a random one is printed otherwise")
+                        .value_parser(value_parser!(u64))
+                        .required(false),
+                    arg!(--force "Add to a database that has tasks already, after a backup"),
+                ]),
+            Command::new("archive")
+                .about("Move done tasks of long ago to archive.db, list --archived and export --archived still include them")
+                .arg(
+                    arg!(--"older-than" <LOOKBACK> "How long ago a done task's date has to be, eg. 6m or 1y, archive_after in config or 90d by default")
+                        .value_parser(|value: &str| value.parse::<Lookback>())
+                        .required(false),
+                ),
+            Command::new("caldav")
+                .about("Sync tasks with a CalDAV task list both ways, eg. on Nextcloud or Fastmail")
+                .arg(
+                    arg!(--since <LOOKBACK> "Send tasks not synced before from eg. 7d or 2w ago on, 14d by default")
+                        .value_parser(|value: &str| value.parse::<Lookback>())
+                        .required(false),
+                ),
+            Command::new("merge")
+                .about("Merge another copy of the database task by task, eg. a synced conflicted copy")
+                .arg(
+                    arg!(<FILE> "The other copy, it is only read")
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+            Command::new("sync")
+                .about("Merge with a remote copy both ways, optionally keeping only recent tasks there")
+                .args([
+                    arg!(<REMOTE> "The remote copy, created when it doesn't exist yet")
+                        .value_parser(value_parser!(PathBuf)),
+                    arg!(--since <LOOKBACK> "Keep only tasks from eg. 90d, 12w or 3m ago on in the remote")
+                        .value_parser(|value: &str| value.parse::<Lookback>())
+                        .required(false),
+                ]),
+            Command::new("conflicts")
+                .about("Review the versions that lost out in a merge, and take one back")
+                .args([
+                    arg!(--take <CONFLICT_NO> "Bring back the version that lost")
+                        .value_parser(value_parser!(usize))
+                        .required(false),
+                    arg!(--dismiss <CONFLICT_NO> "Keep the current version and forget the other")
+                        .value_parser(value_parser!(usize))
+                        .required(false),
+                ])
+                .group(ArgGroup::new("resolution").args(["take", "dismiss"])),
+            Command::new("import")
+                .about("Import tasks from --porcelain output, an interrupted import can be resumed or rolled back")
+                .args([
+                    arg!([FILE] "File with the task lines printed by --porcelain")
+                        .value_parser(value_parser!(PathBuf)),
+                    arg!(--from <APP> "Import from another app instead of a file")
+                        .value_parser(value_parser!(ImportApp))
+                        .required(false),
+                    arg!(--list <NAME> "List to import with --from, eg. Work, all of them for gtasks when left out")
+                        .required(false)
+                        .requires("from"),
+                    arg!(--since [LOOKBACK] "With --from gtasks or gitlab only tasks changed eg. 7d ago on, or since the last import when left empty")
+                        .value_parser(|value: &str| value.parse::<Lookback>())
+                        .requires("from"),
+                    arg!(--resume "Carry on with an interrupted import"),
+                    arg!(--abort "Roll back an interrupted import, removing the tasks it added"),
+                ])
+                .group(
+                    ArgGroup::new("source")
+                        .args(["FILE", "from", "resume", "abort"])
+                        .required(true),
+                ),
+            Command::new("report")
+                .about("Summarize tasks over a period")
+                .args([
+                    arg!(--sprint [NAME] "Committed vs completed tasks of a sprint, defaults to the current one")
                         .value_parser(builder::NonEmptyStringValueParser::new()),
+                    arg!(--"cycle-time" "Time from adding to finishing tasks per week, with the slowest ones")
+                        .action(clap::ArgAction::SetTrue),
+                    arg!(--variance "Points vs the time tasks took, per week and #tag, flagging where points run low"),
+                    arg!(--aging [DAYS] "In progress or blocked tasks stuck for longer than DAYS, defaults to aging_days from config or 3")
+                        .value_parser(value_parser!(u32)),
+                    arg!(--week "Tasks of the current week, day by day"),
+                    arg!(--month "Tasks of the current month, day by day"),
+                    arg!(--profiles <NAMES> "Week or month report of each of these profiles, comma separated (eg. work,oncall)")
+                        .value_parser(builder::NonEmptyStringValueParser::new())
+                        .value_delimiter(',')
+                        .conflicts_with_all(["sprint", "cycle-time", "variance", "aging", "quarter", "year"])
+                        .required(false),
+                    arg!(--merge "Combine the --profiles into one report, grouped by profile")
+                        .requires("profiles"),
+                    arg!(--quarter <QUARTER> "Totals, busiest weeks and top #tags of a quarter (eg. Q2, 2024-Q2)")
+                        .value_parser(parse_quarter_arg),
+                    arg!(--year [YEAR] "Totals, busiest weeks and top #tags of a year, defaults to this one")
+                        .value_parser(value_parser!(i32).range(1978..=9999)),
+                    arg!(--format <FORMAT> "Output format, html and pdf are for the week and month reports only")
+                        .value_parser(value_parser!(ReportFormat))
+                        .default_value("text"),
+                    arg!(-o --output <FILE> "Write the report to FILE instead of printing it")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(false),
+                    arg!(--template <FILE> "Fill in a jinja template FILE with the report, its fields are the ones of --format json")
+                        .value_parser(value_parser!(PathBuf))
+                        .conflicts_with("format")
+                        .required(false),
+                ])
+                .group(
+                    ArgGroup::new("kind")
+                        .args([
+                            "sprint",
+                            "cycle-time",
+                            "variance",
+                            "aging",
+                            "week",
+                            "month",
+                            "quarter",
+                            "year",
+                        ])
+                        .required(true),
                 ),
         ])
 }
 
-pub fn handle_cmd_list(arg_matches: &ArgMatches, db_conn: &Connection) {
-    let mut now = Local::now().date_naive();
+// index is 1 based, as shown in the Idx column of today's table
+fn get_todays_task(db_conn: &Connection, task_index: u8) -> Task {
+    let start_date = iso_format_timestamp(&today());
+
+    let tasks = get_tasks_by_date(db_conn, &start_date, None).expect("Failed to fetch tasks");
+
+    match tasks
+        .into_iter()
+        .find(|task| task.position == task_index as u32)
+    {
+        Some(task) => task,
+        None => panic!("Error: Index outbound"),
+    }
+}
+
+// like get_todays_task, but says so and returns None when today has no such task
+fn find_todays_task(db_conn: &Connection, task_index: u8) -> Option<Task> {
+    let start_date = iso_format_timestamp(&today());
+
+    let tasks = match get_tasks_by_date(db_conn, &start_date, None) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {:?}", error);
+            return None;
+        }
+    };
+
+    let task = tasks
+        .into_iter()
+        .find(|task| task.position == task_index as u32);
+    if task.is_none() {
+        println!("No task {task_index} today");
+    }
+
+    task
+}
+
+fn group_tasks_by_date(tasks: Vec<Task>) -> HashMap<String, Vec<Task>> {
+    let mut date_tasks_map: HashMap<String, Vec<Task>> = HashMap::new();
+    for task in tasks {
+        date_tasks_map
+            .entry(task.effective_date().clone())
+            .or_default()
+            .push(task);
+    }
+
+    date_tasks_map
+}
+
+// renders tasks grouped under their date, newest date first unless ascending
+fn render_grouped_tasks(tasks: Vec<Task>, include_id: bool, ascending: bool) {
+    render_date_groups(
+        group_tasks_by_date(tasks),
+        include_id,
+        ascending,
+        &TaskNotes::new(),
+    );
+}
+
+fn render_date_groups(
+    date_tasks_map: HashMap<String, Vec<Task>>,
+    include_id: bool,
+    ascending: bool,
+    notes: &TaskNotes,
+) {
+    let mut task_grouped_by_date: Vec<(&String, &Vec<Task>)> = date_tasks_map.iter().collect();
+
+    // sorting by date
+    if ascending {
+        task_grouped_by_date.sort_by(|a, b| a.0.cmp(b.0));
+    } else {
+        task_grouped_by_date.sort_by(|a, b| b.0.cmp(a.0));
+    }
+
+    render_tasks_table_with_notes(&task_grouped_by_date, include_id, notes);
+}
+
+pub fn handle_cmd_list(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let today = today();
 
     let get_include_id_flag = arg_matches.get_flag("include-id");
 
-    if let Some(month_no) = arg_matches.get_one::<u32>("month") {
-        now = now.with_month(*month_no).expect("Invalid month");
+    let year = arg_matches
+        .get_one::<i32>("year")
+        .copied()
+        .unwrap_or(today.year());
+
+    // a whole month, or a whole year when only --year is given, the running one only up to today
+    let range = match arg_matches.get_one::<u32>("month") {
+        Some(month) => month_range(year, *month),
+        None if arg_matches.contains_id("year") => year_range(year),
+        None => Some(
+            config
+                .get_parsed::<ListRange>("list_range")
+                .unwrap_or_default()
+                .dates(today),
+        ),
+    };
+
+    let Some((first_date, last_date)) = range else {
+        println!("Invalid month or year");
+        return;
+    };
+
+    let start_date = iso_format_timestamp(&first_date);
+    let end_date = match (first_date..=last_date).contains(&today) {
+        true => iso_format_timestamp(&today),
+        false => iso_format_timestamp(&last_date),
+    };
+
+    let mut filter = match arg_matches.get_one::<TaskFilter>("filter") {
+        Some(filter) => filter.clone(),
+        None => TaskFilter::new(),
+    };
+
+    // an expression with its own dates replaces the default range, overdue tasks can be on any
+    let overdue = arg_matches.get_flag("overdue");
+    let default_range = !arg_matches.get_flag("all") && !overdue && !filter.is_date_bounded();
+    if default_range {
+        filter = filter.date_range(&start_date, &end_date);
     }
 
-    let start_date = iso_format_timestamp(&now.with_day(1).expect("Internal Error: Invalid day"));
-    let end_date = iso_format_timestamp(&now);
+    if overdue {
+        filter = filter.overdue(&iso_format_timestamp(&today));
+    }
 
-    match get_tasks_by_date(&db_conn, &start_date, Some(&end_date)) {
-        Ok(tasks) => {
-            let mut date_tasks_map: HashMap<String, Vec<Task>> = HashMap::new();
-            for task in tasks {
-                if date_tasks_map.contains_key(&task.date) {
-                    let task_list = date_tasks_map.get_mut(&task.date);
-
-                    match task_list {
-                        Some(list) => {
-                            list.push(task);
-                        }
-                        None => {
-                            date_tasks_map.insert(task.date.clone(), vec![task]);
-                        }
-                    }
-                } else {
-                    date_tasks_map.insert(task.date.clone(), vec![task]);
+    if let Some(query) = arg_matches.get_one::<String>("search") {
+        filter = filter.search(query);
+    }
+
+    if let Some(pattern) = arg_matches.get_one::<String>("search-regex") {
+        filter = filter.search_regex(pattern);
+    }
+
+    for priority in arg_matches
+        .get_many::<Priority>("priority")
+        .unwrap_or_default()
+    {
+        filter = filter.priority(*priority);
+    }
+
+    for project in arg_matches
+        .get_many::<String>("project")
+        .unwrap_or_default()
+    {
+        filter = filter.project(project);
+    }
+
+    for tag in arg_matches.get_many::<String>("tag").unwrap_or_default() {
+        filter = filter.tag(tag);
+    }
+
+    if let Some(sort) = arg_matches.get_one::<SortKey>("sort") {
+        filter = filter.sort(*sort);
+    }
+
+    match with_archive(arg_matches, db_conn) {
+        Some(true) => filter = filter.include_archived(),
+        Some(false) => {}
+        None => return,
+    }
+
+    let ascending = arg_matches.get_flag("asc");
+
+    // printed while read, only filling the gaps needs every date first
+    if !arg_matches.get_flag("fill-gaps") {
+        let result = match arg_matches.get_flag("jsonl") {
+            true => for_each_task_by_date(db_conn, &filter, !ascending, |task| {
+                println!("{}", task_json(&task))
+            }),
+            false => {
+                let mut stream = ListStream::new(get_include_id_flag);
+                let result =
+                    for_each_task_by_date(db_conn, &filter, !ascending, |task| stream.push(task));
+                stream.finish();
+                result
+            }
+        };
+
+        if let Err(error) = result {
+            println!("Error fetching tasks = {error}");
+        }
+        return;
+    }
+
+    let tasks = match get_tasks(db_conn, &filter) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let mut date_tasks_map = group_tasks_by_date(tasks);
+
+    // the gaps of the default window are all filled, a filter's only between the tasks found
+    let (first_date, last_date) = match default_range {
+        true => (Some(start_date), Some(end_date)),
+        false => (
+            date_tasks_map.keys().min().cloned(),
+            date_tasks_map.keys().max().cloned(),
+        ),
+    };
+
+    if let (Some(first_date), Some(last_date)) = (first_date, last_date)
+        && let (Ok(first_date), Ok(last_date)) = (
+            NaiveDate::parse_from_str(&first_date, "%F"),
+            NaiveDate::parse_from_str(&last_date, "%F"),
+        )
+    {
+        for date in first_date.iter_days().take_while(|date| *date <= last_date) {
+            date_tasks_map
+                .entry(iso_format_timestamp(&date))
+                .or_default();
+        }
+    }
+
+    render_date_groups(
+        date_tasks_map,
+        get_include_id_flag,
+        ascending,
+        &TaskNotes::new(),
+    );
+}
+
+// tasks per table when a listing is printed as it is read
+const LIST_CHUNK_TASKS: usize = 500;
+
+/*
+ * a listing printed while it is read, so a year of tasks doesn't wait for (or sit in memory
+ * for) the last one. rows come ordered by date, so a date is complete once the next starts,
+ * and a table goes out once it holds LIST_CHUNK_TASKS tasks, never splitting a date
+ * */
+struct ListStream {
+    include_id: bool,
+    dates: Vec<(String, Vec<Task>)>,
+    chunk_tasks: usize,
+    printed_dates: usize,
+    completion: Completion,
+}
+
+impl ListStream {
+    fn new(include_id: bool) -> Self {
+        ListStream {
+            include_id,
+            dates: vec![],
+            chunk_tasks: 0,
+            printed_dates: 0,
+            completion: Completion::default(),
+        }
+    }
+
+    fn push(&mut self, task: Task) {
+        self.completion.add(&task);
+        self.chunk_tasks += 1;
+
+        match self.dates.last_mut() {
+            Some((date, tasks)) if date == task.effective_date() => tasks.push(task),
+            _ => {
+                if self.chunk_tasks > LIST_CHUNK_TASKS {
+                    self.flush();
+                    self.chunk_tasks = 1;
                 }
+                self.dates.push((task.effective_date().clone(), vec![task]));
             }
+        }
+    }
+
+    fn flush(&mut self) {
+        let grouped = self
+            .dates
+            .iter()
+            .map(|(date, tasks)| (date, tasks))
+            .collect();
+        render_tasks_chunk(&grouped, self.include_id, &TaskNotes::new());
+
+        self.printed_dates += self.dates.len();
+        self.dates.clear();
+    }
+
+    fn finish(mut self) {
+        // nothing found still shows the empty table
+        if !self.dates.is_empty() || self.printed_dates == 0 {
+            self.flush();
+        }
+
+        if self.printed_dates > 1 {
+            print_overall_completion(&self.completion);
+        }
+    }
+}
+
+fn status_shortcut_args() -> Vec<Arg> {
+    vec![
+        arg!(--since <LOOKBACK> "How far back to look instead (eg. 7d, 2w, 1m)")
+            .value_parser(|value: &str| value.parse::<Lookback>())
+            .required(false),
+        Arg::new("include-id")
+            .long("include-id")
+            .action(clap::ArgAction::SetTrue),
+    ]
+}
+
+// todo, doing and blocked, list filtered to the status over a recent window
+const DEFAULT_SHORTCUT_RANGE: Lookback = Lookback::Days(14);
+
+pub fn handle_cmd_status_shortcut(
+    name: &str,
+    arg_matches: &ArgMatches,
+    db_conn: &Connection,
+    config: &Config,
+) {
+    let (status, label) = match name {
+        "todo" => (Status::Todo, "todo"),
+        "doing" => (Status::InProgress, "in progress"),
+        "blocked" => (Status::Blocked, "blocked"),
+        _ => unreachable!("only the status shortcuts get here"),
+    };
+
+    let lookback = match arg_matches.get_one::<Lookback>("since") {
+        Some(lookback) => *lookback,
+        None => config
+            .get_parsed("shortcut_range")
+            .unwrap_or(DEFAULT_SHORTCUT_RANGE),
+    };
 
-            let mut task_grouped_by_date: Vec<(&String, &Vec<Task>)> =
-                date_tasks_map.iter().collect();
+    let today = today();
+    let Some(start_date) = lookback.date_before(today) else {
+        println!("Error = the range goes back too far");
+        return;
+    };
 
-            // sorting by date
-            task_grouped_by_date.sort_by(|a, b| b.0.cmp(a.0));
+    let filter = TaskFilter::new()
+        .date_range(
+            &iso_format_timestamp(&start_date),
+            &iso_format_timestamp(&today),
+        )
+        .status(status);
 
-            render_tasks_table(&task_grouped_by_date, get_include_id_flag);
+    match get_tasks(db_conn, &filter) {
+        Ok(tasks) if tasks.is_empty() => {
+            println!("No {label} tasks since {}", lookback.describe())
         }
+        Ok(tasks) => render_grouped_tasks(tasks, arg_matches.get_flag("include-id"), false),
         Err(error) => println!("Error fetching tasks = {error}"),
     }
 }
 
-pub fn handle_cmd_show(arg_matches: &ArgMatches, db_conn: &Connection) {
+pub fn handle_cmd_show(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    if arg_matches.contains_id("id") {
+        if let Some(task_id) = task_id_arg(arg_matches, db_conn) {
+            match get_task_by_id(db_conn, &task_id) {
+                Ok(task) => print_task_detail(db_conn, &task),
+                Err(error) => println!("Error fetching task = {:?}", error),
+            }
+        }
+        return;
+    }
+
     let timestamp = construct_timestamp(arg_matches);
 
+    apply_daily_template(db_conn, config, &timestamp);
+
     let get_include_id_flag = arg_matches.get_flag("include-id");
 
     let start_date = iso_format_timestamp(&timestamp);
 
-    match get_tasks_by_date(db_conn, &start_date, None) {
-        Ok(tasks) => render_tasks_table(&vec![(&start_date, &tasks)], get_include_id_flag),
+    let mut filter = TaskFilter::new().date_range(&start_date, &start_date);
+
+    if let Some(sort) = arg_matches.get_one::<SortKey>("sort") {
+        filter = filter.sort(*sort);
+    }
+
+    let statuses: Vec<Status> = arg_matches
+        .get_many::<Status>("status")
+        .unwrap_or_default()
+        .copied()
+        .collect();
+    for status in &statuses {
+        filter = filter.status(*status);
+    }
+
+    let projects: Vec<&String> = arg_matches
+        .get_many::<String>("project")
+        .unwrap_or_default()
+        .collect();
+    for project in &projects {
+        filter = filter.project(project);
+    }
+
+    // case doesn't matter, like TaskFilter::project
+    let in_projects = |task: &Task| {
+        projects.is_empty()
+            || task.project.as_ref().is_some_and(|project| {
+                projects
+                    .iter()
+                    .any(|wanted| wanted.eq_ignore_ascii_case(project))
+            })
+    };
+
+    if config.get_parsed::<bool>("show_journal") == Some(true) {
+        match get_journal_entry(db_conn, &start_date) {
+            Ok(Some(note)) => println!("{}\n", redact_description(note.trim_end())),
+            Ok(None) => {}
+            Err(error) => println!("Error fetching journal = {:?}", error),
+        }
+    }
+
+    match get_tasks(db_conn, &filter) {
+        Ok(tasks) => {
+            let notes = match shown_notes(arg_matches, db_conn, &tasks) {
+                Ok(notes) => notes,
+                Err(error) => {
+                    println!("Error fetching notes = {:?}", error);
+                    return;
+                }
+            };
+
+            render_tasks_table_with_notes(
+                &vec![(&start_date, &tasks)],
+                get_include_id_flag,
+                &notes,
+            );
+            // the day's load is about all of its tasks, not the ones shown
+            if statuses.is_empty() && projects.is_empty() {
+                warn_overload(config, &timestamp, &tasks, true);
+            }
+        }
         Err(error) => println!("Error getting tasks for date = {error}"),
     }
+
+    if arg_matches.get_flag("include-overdue") {
+        // a future date can't make anything overdue yet, today is the latest cut off
+        let overdue_before = iso_format_timestamp(&timestamp.min(today()));
+
+        let overdue_tasks = get_overdue_tasks(db_conn, &overdue_before).map(|tasks| {
+            tasks
+                .into_iter()
+                .filter(|task| statuses.is_empty() || statuses.contains(&task.status))
+                .filter(in_projects)
+                .collect::<Vec<_>>()
+        });
+
+        match overdue_tasks {
+            Ok(tasks) if tasks.is_empty() => {}
+            Ok(tasks) => {
+                let notes = match shown_notes(arg_matches, db_conn, &tasks) {
+                    Ok(notes) => notes,
+                    Err(error) => {
+                        println!("Error fetching notes = {:?}", error);
+                        return;
+                    }
+                };
+
+                println!("Overdue");
+                render_date_groups(
+                    group_tasks_by_date(tasks),
+                    get_include_id_flag,
+                    false,
+                    &notes,
+                );
+            }
+            Err(error) => println!("Error getting overdue tasks = {error}"),
+        }
+    }
+}
+
+/*
+ * daily_capacity in config is how many points fit in a day. once the points of a date's
+ * tasks add up to more, add and show say so, and show suggests the open tasks to move out,
+ * lowest priority and latest added first, until the rest fits
+ * */
+fn warn_overload(config: &Config, date: &NaiveDate, tasks: &[Task], suggest: bool) {
+    let Some(capacity) = config.get_parsed::<u32>("daily_capacity") else {
+        return;
+    };
+
+    if display_options().porcelain {
+        return;
+    }
+
+    let planned: u32 = tasks.iter().filter_map(|task| task.points).sum();
+    if planned <= capacity {
+        return;
+    }
+
+    println!(
+        "Overloaded: {planned} points planned for {}, {capacity} fit in a day",
+        iso_format_timestamp(date)
+    );
+
+    if !suggest {
+        return;
+    }
+
+    let mut movable: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| task.status == Status::Todo && task.points.is_some_and(|points| points > 0))
+        .collect();
+    // low priority first, tasks without one count as medium
+    movable.sort_by_key(|task| {
+        let priority = match task.priority {
+            Some(Priority::Low) => 0,
+            None | Some(Priority::Medium) => 1,
+            Some(Priority::High) => 2,
+        };
+        (priority, std::cmp::Reverse(task.position))
+    });
+
+    let mut remaining = planned;
+    let mut suggested = vec![];
+    for task in movable {
+        if remaining <= capacity {
+            break;
+        }
+        remaining -= task.points.unwrap_or_default();
+        suggested.push(task);
+    }
+
+    if suggested.is_empty() {
+        return;
+    }
+
+    // snooze only takes today's tasks by index
+    let is_today = *date == today();
+
+    println!("Moving these would bring it down to {remaining} points:");
+    for task in suggested {
+        let points = match task.points.unwrap_or_default() {
+            1 => "1 point".to_string(),
+            points => format!("{points} points"),
+        };
+        match is_today {
+            true => println!(
+                "  {}  {} ({points}), eg. snooze {} --until tomorrow",
+                task.position,
+                redact_description(&task.description),
+                task.position
+            ),
+            false => println!(
+                "  {}  {} ({points})",
+                task.short_or_id(),
+                redact_description(&task.description)
+            ),
+        }
+    }
+}
+
+pub fn handle_cmd_journal(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let date = iso_format_timestamp(
+        &arg_matches
+            .get_one::<NaiveDate>("date")
+            .copied()
+            .unwrap_or_else(today),
+    );
+
+    let note = match get_journal_entry(db_conn, &date) {
+        Ok(note) => note.unwrap_or_default(),
+        Err(error) => {
+            println!("Error fetching journal = {:?}", error);
+            return;
+        }
+    };
+
+    let edited = match edit_in_editor(&note, &format!("journal-{date}.md")) {
+        Ok(edited) => edited,
+        Err(error) => {
+            println!("Error editing journal = {error}");
+            return;
+        }
+    };
+
+    if edited.trim() == note.trim() {
+        println!("Journal of {date} unchanged");
+        return;
+    }
+
+    match set_journal_entry(db_conn, &date, &edited) {
+        Ok(()) if edited.trim().is_empty() => println!("Journal of {date} removed"),
+        Ok(()) => println!("Journal of {date} saved"),
+        Err(error) => println!("Error saving journal = {:?}", error),
+    }
+}
+
+// card of a task with its links, attachments, notes and changes
+fn print_task_detail(db_conn: &Connection, task: &Task) {
+    let task_line = |task: &Task| {
+        format!(
+            "{}  {}  {}",
+            task.short_or_id(),
+            format_status(task.status),
+            redact_description(&task.description)
+        )
+    };
+
+    let sections = [
+        (
+            "Parent",
+            match &task.parent_id {
+                Some(parent_id) => match get_task_by_id(db_conn, parent_id) {
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(vec![]),
+                    parent => parent.map(|parent| vec![task_line(&parent)]),
+                },
+                None => Ok(vec![]),
+            },
+        ),
+        (
+            "Subtasks",
+            get_subtasks(db_conn, &task.id)
+                .map(|subtasks| subtasks.iter().map(task_line).collect()),
+        ),
+        (
+            "Waits on",
+            get_dependencies(db_conn, &task.id)
+                .map(|dependencies| dependencies.iter().map(task_line).collect()),
+        ),
+        (
+            "Needed by",
+            get_dependents(db_conn, &task.id)
+                .map(|dependents| dependents.iter().map(task_line).collect()),
+        ),
+        // numbered, for open --link
+        (
+            "Links",
+            get_task_links(db_conn, &task.id).map(|links| {
+                links
+                    .iter()
+                    .enumerate()
+                    .map(|(index, link)| format!("{}  {link}", index + 1))
+                    .collect()
+            }),
+        ),
+        ("Attachments", get_task_attachments(db_conn, &task.id)),
+        (
+            "Notes",
+            get_task_annotations(db_conn, &task.id).map(|notes| {
+                notes
+                    .into_iter()
+                    .map(|(created_at, note)| {
+                        format!(
+                            "{}  {}",
+                            format_local_timestamp(&created_at),
+                            plain_markdown(&note)
+                        )
+                    })
+                    .collect()
+            }),
+        ),
+        (
+            "History",
+            get_task_history(db_conn, &task.id).map(|changes| {
+                changes
+                    .into_iter()
+                    .map(|change| {
+                        let changed_at = format_local_timestamp(&change.changed_at);
+                        match change.field.as_str() {
+                            "created" => format!("{changed_at}  created"),
+                            field => format!(
+                                "{changed_at}  {field}: {} -> {}",
+                                change.old_value.unwrap_or_default(),
+                                change.new_value.unwrap_or_default()
+                            ),
+                        }
+                    })
+                    .collect()
+            }),
+        ),
+    ];
+
+    let sections: Vec<(&str, Vec<String>)> = sections
+        .into_iter()
+        .map(|(name, entries)| {
+            let entries = entries.unwrap_or_else(|error| vec![format!("Error fetching = {error}")]);
+            (name, entries)
+        })
+        .collect();
+
+    render_task_card(task, &sections);
+}
+
+// the notes show --notes puts under each task, none without it
+fn shown_notes(
+    arg_matches: &ArgMatches,
+    db_conn: &Connection,
+    tasks: &[Task],
+) -> Result<TaskNotes, rusqlite::Error> {
+    if !arg_matches.get_flag("notes") {
+        return Ok(TaskNotes::new());
+    }
+
+    tasks
+        .iter()
+        .map(|task| Ok((task.id.clone(), get_task_annotations(db_conn, &task.id)?)))
+        .collect()
 }
 
-pub fn handle_cmd_add(arg_matches: &ArgMatches, db_conn: &Connection) {
+pub fn handle_cmd_add(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
     let task_description = arg_matches
         .get_one::<String>("TASK")
         .expect("Task description is required for add");
 
+    if !confirm_secret_free(arg_matches, config, task_description) {
+        return;
+    }
+
     let task_status = if arg_matches.get_flag("done") {
         Status::Done
     } else {
@@ -171,69 +1771,4987 @@ pub fn handle_cmd_add(arg_matches: &ArgMatches, db_conn: &Connection) {
 
     let timestamp = construct_timestamp(arg_matches);
 
+    apply_daily_template(db_conn, config, &timestamp);
+
     let iso_timestamp = iso_format_timestamp(&timestamp);
 
-    if let Err(error) = insert_task(db_conn, task_description, task_status, &iso_timestamp) {
-        println!("Error inserting new task = {:?}", error);
+    let points = arg_matches.get_one::<u32>("points").copied();
+
+    let priority = arg_matches.get_one::<Priority>("priority").copied();
+
+    let project = arg_matches.get_one::<String>("project").map(String::as_str);
+
+    let due = arg_matches
+        .get_one::<NaiveDate>("due")
+        .map(iso_format_timestamp);
+
+    let goal = arg_matches.get_one::<String>("goal");
+    if let Some(goal) = goal
+        && !check_goal_exists(db_conn, goal)
+    {
+        return;
     }
-}
 
-pub fn handle_cmd_update(arg_matches: &ArgMatches, db_conn: &Connection) {
-    let task_description = arg_matches
-        .get_one::<String>("TASK")
-        .expect("Task description is required for add");
+    let parent = match arg_matches.get_one::<String>("parent") {
+        Some(reference) => match task_by_reference(db_conn, reference) {
+            Some(parent) => Some(parent),
+            None => return,
+        },
+        None => None,
+    };
 
-    let task_id = arg_matches
-        .get_one::<String>("id")
-        .expect("Task ID is required");
+    let mut dependencies = vec![];
+    for reference in arg_matches
+        .get_many::<String>("depends-on")
+        .unwrap_or_default()
+    {
+        match task_by_reference(db_conn, reference) {
+            Some(dependency) => dependencies.push(dependency),
+            None => return,
+        }
+    }
 
-    if let Err(error) = update_task_description(db_conn, task_id, task_description) {
-        println!("Error updating task = {:?}", error);
+    let mut links: Vec<String> = arg_matches
+        .get_many::<String>("link")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+    links.extend(extract_urls(task_description));
+
+    if !confirm_not_duplicate(
+        arg_matches,
+        db_conn,
+        config,
+        task_description,
+        &timestamp,
+        &links,
+    ) {
+        return;
     }
-}
 
-pub fn handle_cmd_delete(arg_matches: &ArgMatches, db_conn: &Connection) {
-    let task_id = arg_matches
-        .get_one::<String>("id")
-        .expect("Task ID is required");
+    let task_id = match insert_task(
+        db_conn,
+        task_description,
+        task_status,
+        &iso_timestamp,
+        points,
+        priority,
+        project,
+        due.as_deref(),
+    ) {
+        Ok(task_id) => task_id,
+        Err(error) => {
+            println!("Error inserting new task = {:?}", error);
+            return;
+        }
+    };
+
+    if let Err(error) = insert_task_links(db_conn, &task_id, &links) {
+        println!("Error saving task links = {:?}", error);
+    }
+
+    let tags: Vec<String> = arg_matches
+        .get_many::<String>("tag")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+    if let Err(error) = insert_task_tags(db_conn, &task_id, &tags) {
+        println!("Error saving task tags = {:?}", error);
+    }
+
+    if let Some(goal) = goal
+        && let Err(error) = set_task_goal(db_conn, &task_id, goal)
+    {
+        println!("Error linking the task to its goal = {:?}", error);
+    }
+
+    if let Some(parent) = parent
+        && let Err(error) = set_task_parent(db_conn, &task_id, &parent.id)
+    {
+        println!("Error making it a subtask = {:?}", error);
+    }
+
+    if !dependencies.is_empty() {
+        match get_task_by_id(db_conn, &task_id) {
+            Ok(mut task) => {
+                for dependency in &dependencies {
+                    add_dependency(db_conn, &task, dependency);
+                }
+                refresh_dependency_block(db_conn, &mut task);
+            }
+            Err(error) => println!("Error fetching task = {:?}", error),
+        }
+    }
+
+    if config.get_parsed::<u32>("daily_capacity").is_some() {
+        let filter = TaskFilter::new().date_range(&iso_timestamp, &iso_timestamp);
+        match get_tasks(db_conn, &filter) {
+            Ok(tasks) => warn_overload(config, &timestamp, &tasks, false),
+            Err(error) => println!("Error getting tasks for date = {error}"),
+        }
+    }
 
-    if let Err(error) = delete_task(db_conn, task_id) {
-        println!("Error deleting task = {:?}", error);
+    if arg_matches.get_flag("card") {
+        match get_task_by_id(db_conn, &task_id) {
+            Ok(task) => render_task_card(&task, &[]),
+            Err(error) => println!("Error fetching task = {:?}", error),
+        }
     }
 }
 
-pub fn handle_cmd_mark(arg_matches: &ArgMatches, db_conn: &Connection) {
-    let now = Local::now().date_naive();
+/*
+ * an add whose description is close to an open task on that date, or one still open from
+ * earlier, is most likely the same task written down again. it asks whether to carry the
+ * existing one over to the date (earlier ones only) or annotate it with the new description
+ * instead. without a terminal to ask on the task is added with a warning, --allow-duplicate
+ * or duplicate_check = false in config skip the check
+ * */
+const DUPLICATE_SIMILARITY: f64 = 0.8;
 
-    let task_index = arg_matches
-        .get_one::<u8>("TASK_INDEX")
-        .expect("Tasks Index is required");
+// false when the existing task was used instead, or the add was called off
+fn confirm_not_duplicate(
+    arg_matches: &ArgMatches,
+    db_conn: &Connection,
+    config: &Config,
+    description: &str,
+    timestamp: &NaiveDate,
+    links: &[String],
+) -> bool {
+    if config.get_parsed("duplicate_check") == Some(false)
+        || arg_matches.get_flag("allow-duplicate")
+    {
+        return true;
+    }
+
+    let date = iso_format_timestamp(timestamp);
+    let Some(next_date) = timestamp.succ_opt() else {
+        return true;
+    };
 
-    let start_date = iso_format_timestamp(&now);
+    let open_tasks = match get_overdue_tasks(db_conn, &iso_format_timestamp(&next_date)) {
+        Ok(open_tasks) => open_tasks,
+        Err(error) => {
+            println!("Error checking for duplicates = {:?}", error);
+            return true;
+        }
+    };
 
-    let tasks = get_tasks_by_date(db_conn, &start_date, None).expect("Failed to fetch tasks");
+    let Some(mut existing) = open_tasks
+        .into_iter()
+        .map(|task| (description_similarity(description, &task.description), task))
+        .filter(|(similarity, _)| *similarity >= DUPLICATE_SIMILARITY)
+        .max_by(|(first, _), (second, _)| first.total_cmp(second))
+        .map(|(_, task)| task)
+    else {
+        return true;
+    };
+
+    let existing_date = existing.effective_date().clone();
+    let earlier = existing_date < date;
+
+    println!(
+        "Warning: '{}' ({}, {existing_date}) is still open and looks like the same task",
+        redact_description(&existing.description),
+        existing.short_or_id()
+    );
+
+    let prompt = match earlier {
+        true => format!("[c]arry it over to {date}, [a]nnotate it, add [n]ew anyway or [q]uit? "),
+        false => "[a]nnotate it, add [n]ew anyway or [q]uit? ".to_string(),
+    };
+
+    let answer = match prompt_line(&prompt) {
+        Ok(Some(answer)) => answer.to_lowercase(),
+        Ok(None) => {
+            println!("Added anyway, pass --allow-duplicate to skip this check");
+            return true;
+        }
+        Err(error) => {
+            println!("Error reading answer = {error}");
+            return false;
+        }
+    };
+
+    match answer.as_str() {
+        "c" if earlier => match update_task_deferred_until(db_conn, &mut existing, &date) {
+            Ok(()) => println!(
+                "Carried '{}' over to {date}",
+                redact_description(&existing.description)
+            ),
+            Err(error) => print_update_error(&existing.id, error),
+        },
+        "a" => {
+            match insert_task_annotation(db_conn, &existing.id, description) {
+                Ok(()) => println!("Annotated '{}'", redact_description(&existing.description)),
+                Err(error) => {
+                    println!("Error saving annotation = {:?}", error);
+                    return false;
+                }
+            }
 
-    let selected_row = tasks
-        .get(*task_index as usize - 1)
-        .expect("Error: Index outbound");
+            if let Err(error) = insert_task_links(db_conn, &existing.id, links) {
+                println!("Error saving task links = {:?}", error);
+            }
+        }
+        "n" => return true,
+        _ => println!("Nothing added"),
+    }
 
-    update_task_status(db_conn, &selected_row.id, Status::Done).expect("Failed to update task");
+    false
 }
 
-pub fn handle_cmd_unmark(arg_matches: &ArgMatches, db_conn: &Connection) {
-    let now = Local::now().date_naive();
+// tells how to add the goal when there is none of the name
+fn check_goal_exists(db_conn: &Connection, goal: &str) -> bool {
+    match goal_exists(db_conn, goal) {
+        Ok(true) => true,
+        Ok(false) => {
+            println!("No goal named '{goal}', add it with goal add first");
+            false
+        }
+        Err(error) => {
+            println!("Error fetching goal = {:?}", error);
+            false
+        }
+    }
+}
 
-    let task_index = arg_matches
-        .get_one::<u8>("TASK_INDEX")
-        .expect("Tasks Index is required");
+pub fn handle_cmd_log(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let task_description = arg_matches
+        .get_one::<String>("TASK")
+        .expect("Task description is required for log");
+
+    if !confirm_secret_free(arg_matches, config, task_description) {
+        return;
+    }
 
-    let start_date = iso_format_timestamp(&now);
+    let iso_timestamp = iso_format_timestamp(&today());
 
-    let tasks = get_tasks_by_date(db_conn, &start_date, None).expect("Failed to fetch tasks");
+    let points = arg_matches.get_one::<u32>("points").copied();
 
-    let selected_row = tasks
-        .get(*task_index as usize - 1)
-        .expect("Error: Index outbound");
+    let task_id = match insert_task(
+        db_conn,
+        task_description,
+        Status::Done,
+        &iso_timestamp,
+        points,
+        None,
+        arg_matches.get_one::<String>("project").map(String::as_str),
+        None,
+    ) {
+        Ok(task_id) => task_id,
+        Err(error) => {
+            println!("Error inserting new task = {:?}", error);
+            return;
+        }
+    };
+
+    if let Err(error) = insert_task_links(db_conn, &task_id, &extract_urls(task_description)) {
+        println!("Error saving task links = {:?}", error);
+    }
+}
+
+pub fn handle_cmd_update(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let Some((task_id, task_description)) = task_target(arg_matches, db_conn, "TASK") else {
+        return;
+    };
+    let task_id = &task_id;
 
-    update_task_status(db_conn, &selected_row.id, Status::Todo).expect("Failed to update task");
+    if task_description.is_none()
+        && !arg_matches.contains_id("points")
+        && !arg_matches.contains_id("goal")
+        && !arg_matches.contains_id("priority")
+        && !arg_matches.contains_id("project")
+        && !arg_matches.contains_id("due")
+    {
+        println!(
+            "Nothing to update, pass a new description, --points, --goal, --priority, --project or --due"
+        );
+        return;
+    }
+
+    if let Some(task_description) = task_description
+        && !confirm_secret_free(arg_matches, config, task_description)
+    {
+        return;
+    }
+
+    let mut task = match get_task_by_id(db_conn, task_id) {
+        Ok(task) => task,
+        Err(error) => {
+            println!("Error fetching task = {:?}", error);
+            return;
+        }
+    };
+
+    if let Some(task_description) = task_description {
+        if let Err(error) = update_task_description(db_conn, &mut task, task_description) {
+            print_update_error(task_id, error);
+            return;
+        }
+
+        // links of the old description stay, they may still be of use
+        if let Err(error) = insert_task_links(db_conn, task_id, &extract_urls(task_description)) {
+            println!("Error saving task links = {:?}", error);
+        }
+    }
+
+    if let Some(points) = arg_matches.get_one::<u32>("points")
+        && let Err(error) = update_task_points(db_conn, &mut task, *points)
+    {
+        print_update_error(task_id, error);
+        return;
+    }
+
+    if let Some(priority) = arg_matches.get_one::<Priority>("priority")
+        && let Err(error) = update_task_priority(db_conn, &mut task, *priority)
+    {
+        print_update_error(task_id, error);
+        return;
+    }
+
+    if let Some(project) = arg_matches.get_one::<String>("project")
+        && let Err(error) = update_task_project(db_conn, &mut task, project)
+    {
+        print_update_error(task_id, error);
+        return;
+    }
+
+    if let Some(due) = arg_matches.get_one::<NaiveDate>("due")
+        && let Err(error) = update_task_due(db_conn, &mut task, &iso_format_timestamp(due))
+    {
+        print_update_error(task_id, error);
+        return;
+    }
+
+    if let Some(goal) = arg_matches.get_one::<String>("goal")
+        && check_goal_exists(db_conn, goal)
+        && let Err(error) = set_task_goal(db_conn, &task.id, goal)
+    {
+        println!("Error linking the task to its goal = {:?}", error);
+    }
+}
+
+/*
+ * update 3 "new description" goes by today's index like mark, update --id 0612-3 "new
+ * description" by id, note the same with its text. clap can't tell the two first
+ * positionals apart, so with --id the text is whatever came first
+ * */
+fn task_target<'a>(
+    arg_matches: &'a ArgMatches,
+    db_conn: &Connection,
+    text_arg: &str,
+) -> Option<(String, Option<&'a String>)> {
+    let first = arg_matches.get_one::<String>("TASK_INDEX");
+    let text = arg_matches.get_one::<String>(text_arg);
+
+    if arg_matches.contains_id("id") {
+        if text.is_some() {
+            println!("Pass either a task index or --id, not both");
+            return None;
+        }
+
+        return task_id_arg(arg_matches, db_conn).map(|task_id| (task_id, first));
+    }
+
+    match first.map(|index| index.parse::<u8>()) {
+        Some(Ok(task_index @ 1..=100)) => Some((get_todays_task(db_conn, task_index).id, text)),
+        Some(_) => {
+            println!("Task index must be a no. between 1 and 100");
+            None
+        }
+        None => {
+            println!("Pass the index of today's task, or --id for any other");
+            None
+        }
+    }
+}
+
+pub fn handle_cmd_delete(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let task = match arg_matches.get_one::<u8>("TASK_INDEX") {
+        Some(task_index) => match find_todays_task(db_conn, *task_index) {
+            Some(task) => task,
+            None => return,
+        },
+        None => {
+            let Some(task_id) = task_id_arg(arg_matches, db_conn) else {
+                return;
+            };
+
+            match get_task_by_id(db_conn, &task_id) {
+                Ok(task) => task,
+                Err(error) => {
+                    println!("Error fetching task = {:?}", error);
+                    return;
+                }
+            }
+        }
+    };
+
+    if !arg_matches.get_flag("force") && !confirm_delete(&task) {
+        return;
+    }
+
+    // gone with it, see delete_task
+    let dependents = get_dependents(db_conn, &task.id).unwrap_or_default();
+
+    match delete_task(db_conn, &task.id) {
+        Ok(()) => println!("Deleted '{}'", redact_description(&task.description)),
+        Err(error) => {
+            println!("Error deleting task = {:?}", error);
+            return;
+        }
+    }
+
+    for mut dependent in dependents {
+        refresh_dependency_block(db_conn, &mut dependent);
+    }
+}
+
+// without a terminal to ask on nothing is deleted, --force skips asking
+fn confirm_delete(task: &Task) -> bool {
+    let prompt = format!(
+        "Delete '{}' ({}, {})? [y/N] ",
+        redact_description(&task.description),
+        task.short_or_id(),
+        task.effective_date()
+    );
+
+    match prompt_line(&prompt) {
+        Ok(Some(answer)) if answer.eq_ignore_ascii_case("y") => true,
+        Ok(Some(_)) => {
+            println!("Nothing deleted");
+            false
+        }
+        Ok(None) => {
+            println!("Nothing deleted, pass --force to delete without asking");
+            false
+        }
+        Err(error) => {
+            println!("Error reading answer = {error}");
+            false
+        }
+    }
+}
+
+// --id as a task's id, given as either its id or its short id. None when there is no such task
+fn task_id_arg(arg_matches: &ArgMatches, db_conn: &Connection) -> Option<String> {
+    let reference = arg_matches
+        .get_one::<String>("id")
+        .expect("Task ID is required");
+
+    resolve_task_reference(db_conn, reference)
+}
+
+// add --parent and the like, a no. is today's task by index like mark, anything else an id or short id
+fn task_by_reference(db_conn: &Connection, reference: &str) -> Option<Task> {
+    if let Ok(task_index) = reference.parse::<u8>() {
+        return find_todays_task(db_conn, task_index);
+    }
+
+    let task_id = resolve_task_reference(db_conn, reference)?;
+
+    match get_task_by_id(db_conn, &task_id) {
+        Ok(task) => Some(task),
+        Err(error) => {
+            println!("Error fetching task = {:?}", error);
+            None
+        }
+    }
+}
+
+fn resolve_task_reference(db_conn: &Connection, reference: &str) -> Option<String> {
+    match resolve_task_id(db_conn, reference) {
+        Ok(task_id) => Some(task_id),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            println!("No task with id {reference}");
+            None
+        }
+        Err(error) => {
+            println!("Error fetching task = {:?}", error);
+            None
+        }
+    }
+}
+
+pub fn handle_cmd_mark(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+
+    let mut selected_row = get_todays_task(db_conn, *task_index);
+
+    let Some(subtasks) = confirm_subtasks_done(db_conn, &selected_row) else {
+        return;
+    };
+
+    if let Err(error) = update_task_status(db_conn, &mut selected_row, Status::Done) {
+        print_update_error(&selected_row.id, error);
+        return;
+    }
+
+    if config.get_parsed::<bool>("gitlab.comment_on_done") == Some(true) {
+        comment_on_gitlab_issue(db_conn, config, &selected_row.id);
+    }
+
+    for mut subtask in subtasks {
+        if let Err(error) = update_task_status(db_conn, &mut subtask, Status::Done) {
+            print_update_error(&subtask.id, error);
+            continue;
+        }
+
+        if config.get_parsed::<bool>("gitlab.comment_on_done") == Some(true) {
+            comment_on_gitlab_issue(db_conn, config, &subtask.id);
+        }
+
+        refresh_dependents(db_conn, &subtask.id);
+    }
+
+    refresh_dependents(db_conn, &selected_row.id);
+
+    if arg_matches.get_flag("card") {
+        render_task_card(&selected_row, &[]);
+    }
+}
+
+/*
+ * marking a task done while some of its subtasks aren't asks whether they are done too. the
+ * subtasks to mark along with it, None when the mark was called off. without a terminal to ask
+ * on only the task itself is marked
+ * */
+fn confirm_subtasks_done(db_conn: &Connection, task: &Task) -> Option<Vec<Task>> {
+    let unfinished: Vec<Task> = match get_subtasks(db_conn, &task.id) {
+        Ok(subtasks) => subtasks
+            .into_iter()
+            .filter(|subtask| subtask.status != Status::Done)
+            .collect(),
+        Err(error) => {
+            println!("Error fetching subtasks = {:?}", error);
+            return None;
+        }
+    };
+
+    if unfinished.is_empty() {
+        return Some(vec![]);
+    }
+
+    println!(
+        "'{}' has {} unfinished subtask{}:",
+        redact_description(&task.description),
+        unfinished.len(),
+        if unfinished.len() == 1 { "" } else { "s" }
+    );
+    for subtask in &unfinished {
+        println!(
+            "  {} ({}, {})",
+            redact_description(&subtask.description),
+            subtask.short_or_id(),
+            format_status(subtask.status)
+        );
+    }
+
+    match prompt_line("Mark them done [t]oo, [o]nly this one or [q]uit? ") {
+        Ok(Some(answer)) => match answer.to_lowercase().as_str() {
+            "t" => Some(unfinished),
+            "o" => Some(vec![]),
+            _ => {
+                println!("Nothing marked");
+                None
+            }
+        },
+        Ok(None) => {
+            println!("Marking only this one, its subtasks stay open");
+            Some(vec![])
+        }
+        Err(error) => {
+            println!("Error reading answer = {error}");
+            None
+        }
+    }
+}
+
+// the task is done either way, a failed comment is only reported
+fn comment_on_gitlab_issue(db_conn: &Connection, config: &Config, task_id: &str) {
+    let external_id = match get_external_id(db_conn, "gitlab", task_id) {
+        Ok(Some(external_id)) => external_id,
+        Ok(None) => return,
+        Err(error) => {
+            println!("Error fetching the gitlab issue = {:?}", error);
+            return;
+        }
+    };
+
+    let url = config.get("gitlab.url").unwrap_or(DEFAULT_GITLAB_URL);
+
+    if let Err(error) = get_gitlab_token().and_then(|token| comment_done(url, &token, &external_id))
+    {
+        println!("Error commenting on the gitlab issue = {error}");
+    }
+}
+
+pub fn handle_cmd_prio(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+    let priority = *arg_matches
+        .get_one::<Priority>("PRIORITY")
+        .expect("Priority is required");
+
+    let mut selected_row = get_todays_task(db_conn, *task_index);
+
+    match update_task_priority(db_conn, &mut selected_row, priority) {
+        Ok(()) => println!(
+            "'{}' is {priority} priority now",
+            redact_description(&selected_row.description)
+        ),
+        Err(error) => print_update_error(&selected_row.id, error),
+    }
+}
+
+pub fn handle_cmd_tag(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+    let tags: Vec<String> = arg_matches
+        .get_many::<String>("TAGS")
+        .expect("Tags are required")
+        .cloned()
+        .collect();
+
+    let selected_row = get_todays_task(db_conn, *task_index);
+
+    match insert_task_tags(db_conn, &selected_row.id, &tags) {
+        Ok(()) => println!(
+            "Tagged '{}' with #{}",
+            redact_description(&selected_row.description),
+            tags.join(" #")
+        ),
+        Err(error) => println!("Error saving task tags = {:?}", error),
+    }
+}
+
+pub fn handle_cmd_untag(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+    let tags: Vec<String> = arg_matches
+        .get_many::<String>("TAGS")
+        .expect("Tags are required")
+        .cloned()
+        .collect();
+
+    let selected_row = get_todays_task(db_conn, *task_index);
+
+    let removed = match delete_task_tags(db_conn, &selected_row.id, &tags) {
+        Ok(removed) => removed,
+        Err(error) => {
+            println!("Error removing task tags = {:?}", error);
+            return;
+        }
+    };
+
+    println!(
+        "Removed {removed} tag{} from '{}'",
+        if removed == 1 { "" } else { "s" },
+        redact_description(&selected_row.description)
+    );
+
+    // #tags written in the description are not attached, only editing it drops them
+    let described = extract_tags(&selected_row.description);
+    for tag in tags.iter().filter(|tag| described.contains(tag)) {
+        println!("#{tag} is part of the description, update the task to drop it");
+    }
+}
+
+fn dependency_args() -> Vec<Arg> {
+    vec![
+        arg!([TASK] "The waiting task, today's by index or any by id (eg. 2, 0612-3)")
+            .value_parser(builder::NonEmptyStringValueParser::new())
+            .required(true),
+        arg!([DEPENDS_ON] "The task it waits on, given the same way")
+            .value_parser(builder::NonEmptyStringValueParser::new())
+            .required(true),
+    ]
+}
+
+pub fn handle_cmd_deps(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let Some((sub_name, sub_matches)) = arg_matches.subcommand() else {
+        print_task_deps(db_conn, arg_matches.get_flag("all"));
+        return;
+    };
+
+    let Some(mut task) = task_by_reference(
+        db_conn,
+        sub_matches
+            .get_one::<String>("TASK")
+            .expect("Task is required"),
+    ) else {
+        return;
+    };
+    let Some(depends_on) = task_by_reference(
+        db_conn,
+        sub_matches
+            .get_one::<String>("DEPENDS_ON")
+            .expect("The task it waits on is required"),
+    ) else {
+        return;
+    };
+
+    let task_description = redact_description(&task.description);
+    let depends_on_description = redact_description(&depends_on.description);
+
+    match sub_name {
+        "add" => {
+            if !add_dependency(db_conn, &task, &depends_on) {
+                return;
+            }
+            println!("'{task_description}' waits on '{depends_on_description}' now");
+        }
+        "remove" => match delete_task_dep(db_conn, &task.id, &depends_on.id) {
+            Ok(0) => {
+                println!("'{task_description}' doesn't wait on '{depends_on_description}'");
+                return;
+            }
+            Ok(_) => println!("'{task_description}' no longer waits on '{depends_on_description}'"),
+            Err(error) => {
+                println!("Error removing dependency = {:?}", error);
+                return;
+            }
+        },
+        _ => unreachable!("deps only has add and remove"),
+    }
+
+    refresh_dependency_block(db_conn, &mut task);
+}
+
+// false when it wasn't added, dependencies going round in a circle could never be done
+fn add_dependency(db_conn: &Connection, task: &Task, depends_on: &Task) -> bool {
+    if task.id == depends_on.id {
+        println!("A task can't wait on itself");
+        return false;
+    }
+
+    match depends_on_task(db_conn, &depends_on.id, &task.id) {
+        Ok(false) => {}
+        Ok(true) => {
+            println!(
+                "'{}' already waits on '{}', they can't wait on each other",
+                redact_description(&depends_on.description),
+                redact_description(&task.description)
+            );
+            return false;
+        }
+        Err(error) => {
+            println!("Error fetching dependencies = {:?}", error);
+            return false;
+        }
+    }
+
+    match insert_task_dep(db_conn, &task.id, &depends_on.id) {
+        Ok(()) => true,
+        Err(error) => {
+            println!("Error saving dependency = {:?}", error);
+            false
+        }
+    }
+}
+
+/*
+ * a task with unfinished dependencies is blocked with a reason naming them, and goes back to
+ * todo once they are all done. only blocks set this way are lifted, a task blocked for a
+ * reason of its own stays blocked
+ * */
+const DEPENDENCY_REASON: &str = "depends on ";
+
+fn refresh_dependency_block(db_conn: &Connection, task: &mut Task) {
+    if task.status == Status::Done {
+        return;
+    }
+
+    let unfinished: Vec<Task> = match get_dependencies(db_conn, &task.id) {
+        Ok(dependencies) => dependencies
+            .into_iter()
+            .filter(|dependency| dependency.status != Status::Done)
+            .collect(),
+        Err(error) => {
+            println!("Error fetching dependencies = {:?}", error);
+            return;
+        }
+    };
+
+    let blocked_by_dependencies = task.status == Status::Blocked
+        && task
+            .block_reason
+            .as_deref()
+            .is_some_and(|reason| reason.starts_with(DEPENDENCY_REASON));
+
+    if unfinished.is_empty() {
+        if blocked_by_dependencies {
+            match update_task_status(db_conn, task, Status::Todo) {
+                Ok(()) => println!(
+                    "'{}' is unblocked, back to todo",
+                    redact_description(&task.description)
+                ),
+                Err(error) => print_update_error(&task.id, error),
+            }
+        }
+        return;
+    }
+
+    if task.status == Status::Blocked && !blocked_by_dependencies {
+        return;
+    }
+
+    let reason = format!(
+        "{DEPENDENCY_REASON}{}",
+        unfinished
+            .iter()
+            .map(|dependency| dependency.short_or_id())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    if task.block_reason.as_deref() == Some(reason.as_str()) {
+        return;
+    }
+
+    match update_task_blocked(db_conn, task, Some(&reason)) {
+        Ok(()) => println!(
+            "'{}' is blocked, it {reason}",
+            redact_description(&task.description)
+        ),
+        Err(error) => print_update_error(&task.id, error),
+    }
+}
+
+// after the task was done, opened again or deleted
+fn refresh_dependents(db_conn: &Connection, task_id: &str) {
+    match get_dependents(db_conn, task_id) {
+        Ok(dependents) => {
+            for mut dependent in dependents {
+                refresh_dependency_block(db_conn, &mut dependent);
+            }
+        }
+        Err(error) => println!("Error fetching dependent tasks = {:?}", error),
+    }
+}
+
+// every task waiting on others, with the ones it waits on under it
+fn print_task_deps(db_conn: &Connection, all: bool) {
+    let deps = match get_task_deps(db_conn) {
+        Ok(deps) => deps,
+        Err(error) => {
+            println!("Error fetching dependencies = {:?}", error);
+            return;
+        }
+    };
+
+    let mut waiting: Vec<(String, Vec<String>)> = vec![];
+    for (task_id, depends_on) in deps {
+        match waiting.iter_mut().find(|(id, _)| *id == task_id) {
+            Some((_, dependencies)) => dependencies.push(depends_on),
+            None => waiting.push((task_id, vec![depends_on])),
+        }
+    }
+
+    let task_line = |task: &Task| {
+        format!(
+            "{}  {}  {}",
+            task.short_or_id(),
+            format_status(task.status),
+            redact_description(&task.description)
+        )
+    };
+
+    let mut printed = false;
+    for (task_id, dependencies) in waiting {
+        // archived tasks are done, they have nothing left to wait on
+        let Ok(task) = get_task_by_id(db_conn, &task_id) else {
+            continue;
+        };
+        if task.status == Status::Done && !all {
+            continue;
+        }
+
+        println!("{}", task_line(&task));
+        for dependency_id in dependencies {
+            match get_task_by_id(db_conn, &dependency_id) {
+                Ok(dependency) => println!("  └ {}", task_line(&dependency)),
+                Err(_) => println!("  └ {dependency_id}  archived"),
+            }
+        }
+        printed = true;
+    }
+
+    if !printed {
+        println!("No tasks waiting on others, add one with deps add");
+    }
+}
+
+pub fn handle_cmd_unmark(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+
+    let mut selected_row = get_todays_task(db_conn, *task_index);
+
+    if let Err(error) = update_task_status(db_conn, &mut selected_row, Status::Todo) {
+        print_update_error(&selected_row.id, error);
+        return;
+    }
+
+    // open again, so the tasks waiting on it are too
+    refresh_dependency_block(db_conn, &mut selected_row);
+    refresh_dependents(db_conn, &selected_row.id);
+}
+
+pub fn handle_cmd_block(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+
+    let mut selected_row = get_todays_task(db_conn, *task_index);
+
+    // an empty answer blocks the task without a reason
+    let reason = match arg_matches.get_one::<String>("reason") {
+        Some(reason) => Some(reason.clone()),
+        None => match prompt_line("Blocked on: ") {
+            Ok(reason) => reason.filter(|reason| !reason.is_empty()),
+            Err(error) => {
+                println!("Error reading block reason = {error}");
+                return;
+            }
+        },
+    };
+
+    if let Err(error) = update_task_blocked(db_conn, &mut selected_row, reason.as_deref()) {
+        print_update_error(&selected_row.id, error);
+    }
+}
+
+pub fn handle_cmd_template(arg_matches: &ArgMatches, db_conn: &Connection) {
+    match arg_matches.subcommand() {
+        Some(("save", sub_matches)) => handle_cmd_template_save(sub_matches, db_conn),
+        Some(("apply", sub_matches)) => handle_cmd_template_apply(sub_matches, db_conn),
+        Some(("list", _)) => match get_templates(db_conn) {
+            Ok(templates) if templates.is_empty() => println!("No templates saved yet"),
+            Ok(templates) => {
+                for (name, task_count) in templates {
+                    println!("{name} ({task_count} tasks)");
+                }
+            }
+            Err(error) => println!("Error fetching templates = {:?}", error),
+        },
+        Some(("delete", sub_matches)) => {
+            let name = sub_matches
+                .get_one::<String>("NAME")
+                .expect("Template name is required");
+
+            match delete_template(db_conn, name) {
+                Ok(0) => println!("No template named '{name}'"),
+                Ok(_) => println!("Deleted template '{name}'"),
+                Err(error) => println!("Error deleting template = {:?}", error),
+            }
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+}
+
+fn handle_cmd_template_save(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let name = arg_matches
+        .get_one::<String>("NAME")
+        .expect("Template name is required");
+
+    let start_date = iso_format_timestamp(&construct_timestamp(arg_matches));
+
+    let tasks = match get_tasks_by_date(db_conn, &start_date, None) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {:?}", error);
+            return;
+        }
+    };
+
+    if tasks.is_empty() {
+        println!("No tasks on {start_date} to save as template");
+        return;
+    }
+
+    match save_template(db_conn, name, &tasks) {
+        Ok(()) => println!(
+            "Saved template '{name}' with {}",
+            task_count_text(tasks.len())
+        ),
+        Err(error) => println!("Error saving template = {:?}", error),
+    }
+}
+
+fn handle_cmd_template_apply(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let name = arg_matches
+        .get_one::<String>("NAME")
+        .expect("Template name is required");
+
+    let date = arg_matches
+        .get_one::<NaiveDate>("date")
+        .copied()
+        .unwrap_or_else(today);
+
+    let iso_timestamp = iso_format_timestamp(&date);
+
+    let template_tasks = match get_template_tasks(db_conn, name) {
+        Ok(template_tasks) if template_tasks.is_empty() => {
+            println!("No template named '{name}'");
+            return;
+        }
+        Ok(template_tasks) => template_tasks,
+        Err(error) => {
+            println!("Error fetching template = {:?}", error);
+            return;
+        }
+    };
+
+    if let Err(error) = insert_template_tasks(db_conn, &template_tasks, &iso_timestamp) {
+        println!(
+            "Error inserting template tasks, none were added = {:?}",
+            error
+        );
+        return;
+    }
+
+    println!(
+        "Added {} from '{name}' to {iso_timestamp}",
+        task_count_text(template_tasks.len())
+    );
+}
+
+// eg. 1 task, 3 tasks
+fn task_count_text(count: usize) -> String {
+    match count {
+        1 => "1 task".to_string(),
+        count => format!("{count} tasks"),
+    }
+}
+
+pub fn handle_cmd_snooze(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let now = today();
+
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+
+    let until = arg_matches
+        .get_one::<NaiveDate>("until")
+        .expect("Snooze date is required");
+
+    if *until <= now {
+        println!("Snooze date must be after today");
+        return;
+    }
+
+    let mut selected_row = get_todays_task(db_conn, *task_index);
+
+    let until = iso_format_timestamp(until);
+
+    match update_task_deferred_until(db_conn, &mut selected_row, &until) {
+        Ok(()) => println!(
+            "Snoozed '{}' until {until}",
+            redact_description(&selected_row.description)
+        ),
+        Err(error) => print_update_error(&selected_row.id, error),
+    }
+}
+
+pub fn handle_cmd_sprint(arg_matches: &ArgMatches, db_conn: &Connection) {
+    match arg_matches.subcommand() {
+        Some(("create", sub_matches)) => handle_cmd_sprint_create(sub_matches, db_conn),
+        Some(("current", _)) => {
+            let today = iso_format_timestamp(&today());
+
+            let sprint = match get_sprint_for_date(db_conn, &today) {
+                Ok(sprint) => sprint,
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    println!("No sprint running today");
+                    return;
+                }
+                Err(error) => {
+                    println!("Error fetching sprint = {:?}", error);
+                    return;
+                }
+            };
+
+            println!(
+                "Sprint '{}' ({} to {})",
+                sprint.name, sprint.start_date, sprint.end_date
+            );
+
+            match get_tasks_by_date(db_conn, &sprint.start_date, Some(&sprint.end_date)) {
+                Ok(tasks) => render_grouped_tasks(tasks, true, false),
+                Err(error) => println!("Error fetching tasks = {error}"),
+            }
+        }
+        Some(("list", _)) => match get_sprints(db_conn) {
+            Ok(sprints) if sprints.is_empty() => println!("No sprints created yet"),
+            Ok(sprints) => {
+                for sprint in sprints {
+                    println!(
+                        "{} ({} to {})",
+                        sprint.name, sprint.start_date, sprint.end_date
+                    );
+                }
+            }
+            Err(error) => println!("Error fetching sprints = {:?}", error),
+        },
+        _ => unreachable!("subcommand is required"),
+    }
+}
+
+fn handle_cmd_sprint_create(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let name = arg_matches
+        .get_one::<String>("NAME")
+        .expect("Sprint name is required");
+
+    let start_date = arg_matches
+        .get_one::<NaiveDate>("start")
+        .copied()
+        .unwrap_or_else(today);
+
+    // two week sprints unless told otherwise
+    let end_date = arg_matches
+        .get_one::<NaiveDate>("end")
+        .copied()
+        .unwrap_or(start_date + Days::new(13));
+
+    if end_date < start_date {
+        println!("Sprint can't end before it starts");
+        return;
+    }
+
+    let sprint = Sprint {
+        name: name.to_string(),
+        start_date: iso_format_timestamp(&start_date),
+        end_date: iso_format_timestamp(&end_date),
+    };
+
+    match insert_sprint(db_conn, &sprint) {
+        Ok(()) => println!(
+            "Created sprint '{}' ({} to {})",
+            sprint.name, sprint.start_date, sprint.end_date
+        ),
+        Err(error) => println!("Error creating sprint = {:?}", error),
+    }
+}
+
+pub fn handle_cmd_projects(db_conn: &Connection) {
+    let projects = match get_projects(db_conn) {
+        Ok(projects) => projects,
+        Err(error) => {
+            println!("Error fetching projects = {:?}", error);
+            return;
+        }
+    };
+
+    if projects.is_empty() {
+        println!("No projects yet, give a task one with add or update --project");
+        return;
+    }
+
+    let mut projects_table = Table::new();
+
+    projects_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_header(vec![" Project ", " Open ", " Tasks "]);
+
+    for ProjectCount { name, open, total } in projects {
+        projects_table.add_row(vec![name, open.to_string(), total.to_string()]);
+    }
+
+    print_table(&projects_table);
+}
+
+pub fn handle_cmd_goal(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let (year, quarter) = match arg_matches
+        .subcommand()
+        .and_then(|(_, sub_matches)| sub_matches.get_one::<(i32, u32)>("quarter"))
+    {
+        Some(quarter) => *quarter,
+        None => {
+            let today = today();
+            (today.year(), today.month0() / 3 + 1)
+        }
+    };
+    let quarter = format!("{year}-Q{quarter}");
+
+    match arg_matches.subcommand() {
+        Some(("add", sub_matches)) => {
+            let goal = Goal {
+                name: sub_matches
+                    .get_one::<String>("NAME")
+                    .expect("Goal name is required")
+                    .to_string(),
+                quarter,
+            };
+
+            match insert_goal(db_conn, &goal) {
+                Ok(()) => println!("Added goal '{}' for {}", goal.name, goal.quarter),
+                Err(error) => println!("Error adding goal = {:?}", error),
+            }
+        }
+        Some(("progress", _)) => {
+            let progress = match get_goal_progress(db_conn, &quarter) {
+                Ok(progress) => progress,
+                Err(error) => {
+                    println!("Error fetching goals = {:?}", error);
+                    return;
+                }
+            };
+
+            if progress.is_empty() {
+                println!("No goals for {quarter}");
+                return;
+            }
+
+            let mut goals_table = Table::new();
+
+            goals_table
+                .load_preset(comfy_table::presets::ASCII_FULL)
+                .set_header(vec![" Goal ", " Done ", " Tasks ", " Progress "]);
+
+            for GoalProgress { goal, done, total } in progress {
+                let percentage = match total {
+                    0 => "-".to_string(),
+                    total => format!("{}%", done * 100 / total),
+                };
+
+                goals_table.add_row(vec![
+                    goal.name,
+                    done.to_string(),
+                    total.to_string(),
+                    percentage,
+                ]);
+            }
+
+            println!("Goals of {quarter}");
+            print_table(&goals_table);
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ReportFormat {
+    Text,
+    // self contained page, for mails and wikis
+    Html,
+    // one printable page, needs an output file
+    Pdf,
+    // see json.rs for the schema
+    Json,
+}
+
+/*
+ * explicit setup: config file, database and optionally shell completions, then where they are
+ * running it again is harmless, an existing config is kept as it is
+ * */
+pub fn handle_cmd_init(arg_matches: &ArgMatches, profile: Option<&str>, config: &Config) {
+    let config_path = get_config_path(profile);
+    let path = arg_matches.get_one::<PathBuf>("path");
+
+    // relative to where init ran would point elsewhere for the next command
+    let data_dir = match path.map(std::path::absolute) {
+        Some(Ok(data_dir)) => data_dir,
+        Some(Err(error)) => {
+            println!("Error = {error}");
+            return;
+        }
+        None => match resolve_data_dir(config, profile) {
+            Ok(data_dir) => data_dir,
+            Err(error) => {
+                println!("Error = {error}");
+                return;
+            }
+        },
+    };
+
+    // data_dir is only looked at with custom storage, the database would be moved back
+    if path.is_some()
+        && let Some(storage) = config.get("storage")
+        && storage != Storage::Custom.to_string()
+    {
+        println!(
+            "Error = {} keeps the database in {storage} storage, set storage = \"custom\" there",
+            config_path.display()
+        );
+        return;
+    }
+
+    let config_result = match (config_path.exists(), path, config.get("data_dir")) {
+        (false, _, _) => write_starter_config(&config_path, path.map(|_| data_dir.as_path())),
+        (true, Some(_), None) => prepend_data_dir(&config_path, &data_dir),
+        (true, Some(_), Some(configured)) if Path::new(configured) != data_dir => {
+            println!(
+                "Error = {} keeps the database in {configured} already, change data_dir there",
+                config_path.display()
+            );
+            return;
+        }
+        (true, _, _) => Ok(()),
+    };
+
+    if let Err(error) = config_result {
+        println!("Error writing {} = {error}", config_path.display());
+        return;
+    }
+
+    let old_data_dir = relocate_data_dir(profile, &data_dir);
+    set_data_dir(data_dir.clone());
+
+    let mut db_conn = match open_db_connection(load_encryption_key(config), false) {
+        Ok(db_conn) => db_conn,
+        Err(error) => {
+            println!("Error creating database = {:?}", error);
+            return;
+        }
+    };
+
+    if let Err(error) = create_task_table(&db_conn).and_then(|()| migrate_task_table(&mut db_conn))
+    {
+        println!("Error creating database = {:?}", error);
+        return;
+    }
+
+    finish_relocation(&db_conn, profile, &data_dir, old_data_dir.as_deref());
+
+    println!("Config:      {}", config_path.display());
+    println!("Database:    {}", get_db_path());
+    println!("Backups:     {}", get_backup_dir().display());
+
+    if let Some(shell) = arg_matches.get_one::<Shell>("completions") {
+        // completions are for the name it was run by, eg. a `dd` symlink
+        let bin_name = std::env::args()
+            .next()
+            .and_then(|arg| {
+                Path::new(&arg)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "daily_dose".to_string());
+
+        let Some(completions_path) = get_completions_path(*shell, &bin_name) else {
+            println!("Error = no place for {shell:?} completions on this system");
+            return;
+        };
+
+        let script = render_completions(*shell, &construct_cmd_args(), &bin_name);
+
+        match completions_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&completions_path, script))
+        {
+            Ok(()) => println!("Completions: {}", completions_path.display()),
+            Err(error) => println!("Error writing {} = {error}", completions_path.display()),
+        }
+    }
+
+    if let Some(profile) = profile {
+        println!("Use this profile with --profile {profile} or DAILY_DOSE_PROFILE={profile}");
+    }
+}
+
+pub fn handle_cmd_key_generate() {
+    match generate_key() {
+        Ok(()) => println!(
+            "Encryption key stored in the keyring, set encrypt_descriptions = true in config to use it"
+        ),
+        Err(error) => println!("Error = {error}"),
+    }
+}
+
+pub fn handle_cmd_key(arg_matches: &ArgMatches, db_conn: &Connection, encrypting: bool) {
+    if arg_matches.subcommand_matches("seal").is_none() {
+        return;
+    }
+
+    if !encrypting {
+        println!("Encryption is off, set encrypt_descriptions = true in config first");
+        return;
+    }
+
+    if !backup_before(db_conn, "seal") {
+        return;
+    }
+
+    match seal_plain_values(db_conn) {
+        Ok(count) => println!("Encrypted {count} stored values"),
+        Err(error) => println!("Error encrypting stored values = {:?}", error),
+    }
+}
+
+pub fn handle_cmd_backup(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    match create_backup(db_conn) {
+        Ok(path) => println!("Backed up to {}", path.display()),
+        Err(error) => {
+            println!("Error backing up = {error}");
+            return;
+        }
+    }
+
+    if !arg_matches.get_flag("rotate") {
+        return;
+    }
+
+    let default_retention = Retention::default();
+    let retention = Retention {
+        daily: config
+            .get_parsed("backup.daily")
+            .unwrap_or(default_retention.daily),
+        weekly: config
+            .get_parsed("backup.weekly")
+            .unwrap_or(default_retention.weekly),
+        monthly: config
+            .get_parsed("backup.monthly")
+            .unwrap_or(default_retention.monthly),
+    };
+
+    match prune_backups(&get_backup_dir(), retention) {
+        Ok(removed) if removed.is_empty() => {}
+        Ok(removed) => println!("Removed {} old backup(s)", removed.len()),
+        Err(error) => println!("Error removing old backups = {error}"),
+    }
+}
+
+/*
+ * every task as a porcelain task line, raw descriptions even when redacting, after
+ * a checksum line (see export_checksum) that import checks before storing anything
+ * */
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    // what import reads, see porcelain_header
+    Porcelain,
+    // see jira.rs for the columns
+    JiraCsv,
+    // see slack.rs for the blocks
+    SlackBlocks,
+    // see xlsx.rs for the sheets
+    Xlsx,
+}
+
+pub fn handle_cmd_export(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let anonymize = arg_matches.get_flag("anonymize");
+
+    let format = *arg_matches
+        .get_one::<ExportFormat>("format")
+        .expect("Format has a default");
+
+    match format {
+        ExportFormat::Porcelain if arg_matches.contains_id("since") => {
+            println!("Error = --since only works with --format jira-csv, slack-blocks or xlsx");
+            return;
+        }
+        ExportFormat::Xlsx if !arg_matches.contains_id("output") => {
+            println!("Error = xlsx exports need an output file, pass -o <FILE>");
+            return;
+        }
+        _ if arg_matches.get_flag("verify") && !matches!(format, ExportFormat::Porcelain) => {
+            println!("Error = --verify only works with --format porcelain");
+            return;
+        }
+        ExportFormat::JiraCsv | ExportFormat::SlackBlocks | ExportFormat::Xlsx => {
+            export_for_app(arg_matches, db_conn, format, anonymize);
+            return;
+        }
+        ExportFormat::Porcelain => {}
+    }
+
+    let Some(archived) = with_archive(arg_matches, db_conn) else {
+        return;
+    };
+
+    let export_lines = match get_export_lines(db_conn, anonymize, archived) {
+        Ok(export_lines) => export_lines,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let task_lines: Vec<&str> = export_lines.iter().map(String::as_str).collect();
+
+    // the checksum goes first, a file cut off anywhere still has it
+    let export = format!(
+        "{}\n{}\n{}",
+        porcelain_header(),
+        export_checksum(&task_lines),
+        task_lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>()
+    );
+
+    let Some(path) = arg_matches.get_one::<PathBuf>("output") else {
+        print!("{export}");
+        return;
+    };
+
+    if let Err(error) = fs::write(path, export) {
+        println!("Error writing {} = {error}", path.display());
+        return;
+    }
+
+    println!("Exported {} tasks to {}", task_lines.len(), path.display());
+
+    if arg_matches.get_flag("verify") {
+        verify_export(db_conn, path, anonymize, archived);
+    }
+}
+
+/*
+ * issues to create in jira from ad-hoc tasks, --since 7d for the last week's, or a standup
+ * message for slack, of today unless --since, or a workbook to hand around. there is nothing
+ * to import back, ids are left out
+ * */
+fn export_for_app(
+    arg_matches: &ArgMatches,
+    db_conn: &Connection,
+    format: ExportFormat,
+    anonymize: bool,
+) {
+    let today = today();
+    let mut filter = TaskFilter::new();
+
+    match with_archive(arg_matches, db_conn) {
+        Some(true) => filter = filter.include_archived(),
+        Some(false) => {}
+        None => return,
+    }
+
+    let since_date = match arg_matches.get_one::<Lookback>("since") {
+        Some(since) => match since.date_before(today) {
+            Some(since_date) => Some(since_date),
+            None => {
+                println!("Error = --since goes back too far");
+                return;
+            }
+        },
+        None if matches!(format, ExportFormat::SlackBlocks) => Some(today),
+        None => None,
+    };
+
+    if let Some(since_date) = since_date {
+        let Some(day_before) = since_date.pred_opt() else {
+            println!("Error = --since goes back too far");
+            return;
+        };
+
+        // after is exclusive, the day before keeps tasks of the first day
+        filter = filter.after(&day_before.format("%F").to_string());
+    }
+
+    // a standup leaves out what is planned for later days
+    if let (ExportFormat::SlackBlocks, Some(tomorrow)) = (format, today.succ_opt()) {
+        filter = filter.before(&tomorrow.format("%F").to_string());
+    }
+
+    let tasks = match get_tasks(db_conn, &filter) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let tasks: Vec<Task> = match anonymize {
+        true => tasks
+            .into_iter()
+            .map(|task| Task {
+                description: anonymize_text(&task.description),
+                block_reason: task.block_reason.as_deref().map(anonymize_text),
+                ..task
+            })
+            .collect(),
+        false => tasks,
+    };
+
+    let export = match format {
+        ExportFormat::SlackBlocks => {
+            let dates = match since_date.filter(|since_date| *since_date < today) {
+                Some(since_date) => format!("{since_date} to {today}"),
+                None => today.to_string(),
+            };
+
+            render_slack_blocks(&tasks, &dates).into_bytes()
+        }
+        ExportFormat::Xlsx => match render_xlsx(&tasks) {
+            Ok(workbook) => workbook,
+            Err(error) => {
+                println!("Error creating workbook = {error}");
+                return;
+            }
+        },
+        _ => render_jira_csv(&tasks).into_bytes(),
+    };
+
+    let Some(path) = arg_matches.get_one::<PathBuf>("output") else {
+        print!("{}", String::from_utf8_lossy(&export));
+        return;
+    };
+
+    match fs::write(path, export) {
+        Ok(()) => println!("Exported {} tasks to {}", tasks.len(), path.display()),
+        Err(error) => println!("Error writing {} = {error}", path.display()),
+    }
+}
+
+// anonymize keeps ids, dates, statuses and points, the free text is replaced
+fn get_export_lines(
+    db_conn: &Connection,
+    anonymize: bool,
+    archived: bool,
+) -> Result<Vec<String>, rusqlite::Error> {
+    let filter = match archived {
+        true => TaskFilter::new().include_archived(),
+        false => TaskFilter::new(),
+    };
+
+    Ok(get_tasks(db_conn, &filter)?
+        .into_iter()
+        .map(|task| match anonymize {
+            true => {
+                let task = Task {
+                    block_reason: task.block_reason.as_deref().map(anonymize_text),
+                    ..task
+                };
+                porcelain_task_line(&task, &anonymize_text(&task.description))
+            }
+            false => porcelain_task_line(&task, &task.description),
+        })
+        .collect())
+}
+
+// reads the written export back, its checksum has to hold and its tasks match the database
+fn verify_export(db_conn: &Connection, path: &Path, anonymize: bool, archived: bool) {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) => {
+            println!("Error reading {} back = {error}", path.display());
+            return;
+        }
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
+
+    match verify_export_checksum(&lines) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            println!(
+                "Verification failed, {} has no checksum line",
+                path.display()
+            );
+            return;
+        }
+        Err(error) => {
+            println!("Verification failed, {error}");
+            return;
+        }
+    }
+
+    let mut db_lines = match get_export_lines(db_conn, anonymize, archived) {
+        Ok(db_lines) => db_lines,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let mut file_lines: Vec<String> = lines
+        .iter()
+        .filter(|line| line.starts_with("task\t"))
+        .map(|line| line.to_string())
+        .collect();
+
+    db_lines.sort();
+    file_lines.sort();
+
+    let missing = db_lines
+        .iter()
+        .filter(|line| file_lines.binary_search(line).is_err())
+        .count();
+    let unexpected = file_lines
+        .iter()
+        .filter(|line| db_lines.binary_search(line).is_err())
+        .count();
+
+    match (missing, unexpected) {
+        (0, 0) => println!("Verified {} tasks against the database", file_lines.len()),
+        (missing, unexpected) => println!(
+            "Verification failed, {missing} tasks of the database are missing or differ, {unexpected} tasks aren't in the database"
+        ),
+    }
+}
+
+/*
+ * takes a pre-op backup before something hard to undo and says how to get back to it
+ * false when the backup failed, the operation should not go ahead then
+ * */
+pub fn backup_before(db_conn: &Connection, operation: &str) -> bool {
+    match create_pre_op_backup(db_conn, operation) {
+        Ok(path) => {
+            println!(
+                "Backed up the database before {operation}, undo with `restore {}`",
+                path.display()
+            );
+            true
+        }
+        Err(error) => {
+            println!("Error backing up before {operation} = {error}, nothing was changed");
+            false
+        }
+    }
+}
+
+pub fn handle_cmd_restore(arg_matches: &ArgMatches, db_conn: &mut Connection) {
+    let backup = arg_matches
+        .get_one::<PathBuf>("BACKUP")
+        .expect("Backup is required");
+
+    // a bare file name is looked up in the backups folder
+    let path = match backup.exists() {
+        true => backup.clone(),
+        false => get_backup_dir().join(backup),
+    };
+
+    if !path.is_file() {
+        println!("Error = no backup at {}", path.display());
+        return;
+    }
+
+    // checked first, so a file that isn't a daily-dose database changes nothing
+    let task_count = match count_backup_tasks(&path) {
+        Ok(task_count) => task_count,
+        Err(error) => {
+            println!("Error reading {} = {:?}", path.display(), error);
+            return;
+        }
+    };
+
+    if !backup_before(db_conn, "restore") {
+        return;
+    }
+
+    match restore_db(db_conn, &path) {
+        Ok(()) => println!("Restored {task_count} tasks from {}", path.display()),
+        Err(error) => println!("Error restoring {} = {:?}", path.display(), error),
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum QueryFormat {
+    Table,
+    // see json.rs for the schema
+    Json,
+}
+
+pub fn handle_cmd_query(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let sql = arg_matches
+        .get_one::<String>("SQL")
+        .expect("SQL is required");
+
+    let (columns, rows) = match run_query(db_conn, sql) {
+        Ok(result) => result,
+        Err(error) => {
+            println!("Error running query = {error}");
+            return;
+        }
+    };
+
+    if matches!(
+        arg_matches.get_one::<QueryFormat>("format"),
+        Some(QueryFormat::Json)
+    ) {
+        let rows = rows
+            .into_iter()
+            .map(|row| Json::Array(row.into_iter().map(sql_value_json).collect()))
+            .collect();
+
+        print!(
+            "{}",
+            render_report_json(
+                "query",
+                vec![
+                    (
+                        "columns",
+                        Json::Array(columns.into_iter().map(Json::from).collect()),
+                    ),
+                    ("rows", Json::Array(rows)),
+                ],
+            )
+        );
+        return;
+    }
+
+    let mut query_table = Table::new();
+
+    query_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_width(100)
+        .set_header(columns.iter().map(|column| format!(" {column} ")));
+
+    let row_count = rows.len();
+    for row in rows {
+        query_table.add_row(row.into_iter().map(|value| match value {
+            Value::Null => String::new(),
+            Value::Integer(value) => value.to_string(),
+            Value::Real(value) => value.to_string(),
+            Value::Text(value) => value,
+            Value::Blob(value) => format!("<{} bytes>", value.len()),
+        }));
+    }
+
+    print_table(&query_table);
+
+    if !display_options().porcelain {
+        match row_count {
+            1 => println!("1 row"),
+            row_count => println!("{row_count} rows"),
+        }
+    }
+}
+
+fn sql_value_json(value: Value) -> Json {
+    match value {
+        Value::Null => Json::Null,
+        Value::Integer(value) => value.into(),
+        Value::Real(value) => value.into(),
+        Value::Text(value) => value.into(),
+        Value::Blob(value) => value
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+            .into(),
+    }
+}
+
+/*
+ * commands usage doesn't count, init and self-update run before the database is opened,
+ * and looking at the counts shouldn't add to them
+ * */
+const UNCOUNTED_CMDS: &[&str] = &["init", "self-update", "usage"];
+
+// the name usage counts a command under, with the subcommand for groups, eg. template apply
+pub fn usage_command_name(cmd_matches: &ArgMatches) -> Option<String> {
+    match cmd_matches.subcommand()? {
+        (name, _) if UNCOUNTED_CMDS.contains(&name) => None,
+        (name, sub_matches) => match sub_matches.subcommand_name() {
+            Some(sub_name) => Some(format!("{name} {sub_name}")),
+            None => Some(name.to_string()),
+        },
+    }
+}
+
+pub fn handle_cmd_usage(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    if arg_matches.get_flag("reset") {
+        match clear_usage(db_conn) {
+            Ok(count) => println!("Forgot the counts of {count} commands"),
+            Err(error) => println!("Error clearing usage = {:?}", error),
+        }
+        return;
+    }
+
+    let counting = config.get_parsed::<bool>("usage_stats") == Some(true);
+
+    let usage = match get_usage(db_conn) {
+        Ok(usage) => usage,
+        Err(error) => {
+            println!("Error fetching usage = {:?}", error);
+            return;
+        }
+    };
+
+    if usage.is_empty() {
+        match counting {
+            true => println!("Nothing counted yet"),
+            false => println!(
+                "Commands aren't counted, set usage_stats = true in config to start, the counts never leave this machine"
+            ),
+        }
+        return;
+    }
+
+    let mut usage_table = Table::new();
+
+    usage_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_width(100)
+        .set_header(vec![" Command ", " Runs ", " Last run "]);
+
+    for (command, runs, last_used) in &usage {
+        usage_table.add_row(vec![
+            command.clone(),
+            runs.to_string(),
+            format_local_timestamp(last_used),
+        ]);
+    }
+
+    print_table(&usage_table);
+
+    // a group counts as used once any of its subcommands is
+    let never_used: Vec<String> = construct_cmd_args()
+        .get_subcommands()
+        .map(|command| command.get_name().to_string())
+        .filter(|name| !UNCOUNTED_CMDS.contains(&name.as_str()))
+        .filter(|name| {
+            !usage
+                .iter()
+                .any(|(command, _, _)| command == name || command.starts_with(&format!("{name} ")))
+        })
+        .collect();
+
+    if !never_used.is_empty() {
+        println!("Never run: {}", never_used.join(", "));
+    }
+
+    if !counting {
+        println!("Counting is off, set usage_stats = true in config to go on");
+    }
+}
+
+pub fn handle_cmd_self_update(arg_matches: &ArgMatches) {
+    let release = match get_latest_release() {
+        Ok(release) => release,
+        Err(error) => {
+            println!("Error checking for a new release = {error}");
+            return;
+        }
+    };
+
+    if !release.is_newer() {
+        println!("daily-dose {CURRENT_VERSION} is the latest release");
+        return;
+    }
+
+    if arg_matches.get_flag("check") {
+        match release.binary_url() {
+            Some(_) => println!(
+                "daily-dose {} is out (this is {CURRENT_VERSION}), self-update installs it",
+                release.version
+            ),
+            None => println!(
+                "daily-dose {} is out (this is {CURRENT_VERSION}), but without a build for this platform ({})",
+                release.version,
+                binary_asset_name()
+            ),
+        }
+        return;
+    }
+
+    match install_release(&release) {
+        Ok(path) => println!(
+            "Updated {} from {CURRENT_VERSION} to {}",
+            path.display(),
+            release.version
+        ),
+        Err(error) => println!("Error updating = {error}"),
+    }
+}
+
+pub fn handle_cmd_seed(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let days = *arg_matches
+        .get_one::<u32>("days")
+        .expect("Days has a default");
+    let per_day = *arg_matches
+        .get_one::<u32>("per-day")
+        .expect("Per day has a default");
+
+    let task_count: u32 = match count_tasks_by_status(db_conn) {
+        Ok(counts) => counts.iter().map(|(_, count)| count).sum(),
+        Err(error) => {
+            println!("Error counting tasks = {:?}", error);
+            return;
+        }
+    };
+
+    // made up tasks mixed into real ones are a pain to get out again
+    if task_count > 0 {
+        if !arg_matches.get_flag("force") {
+            println!(
+                "Error = the database has {task_count} tasks already, seed another profile (eg. --profile demo) or pass --force"
+            );
+            return;
+        }
+
+        if !backup_before(db_conn, "seed") {
+            return;
+        }
+    }
+
+    let seed = match arg_matches.get_one::<u64>("seed") {
+        Some(seed) => *seed,
+        None => match getrandom::u64() {
+            Ok(seed) => seed,
+            Err(error) => {
+                println!("Error picking a seed = {error}");
+                return;
+            }
+        },
+    };
+
+    let tasks = seed_tasks(days, per_day, seed, today());
+
+    match insert_seed_tasks(db_conn, &tasks) {
+        Ok(()) => println!(
+            "Added {} made up tasks over {days} days (--seed {seed} makes them again)",
+            tasks.len()
+        ),
+        Err(error) => println!("Error adding tasks = {:?}", error),
+    }
+}
+
+// archive, done tasks whose date is further back than this go to archive.db
+const DEFAULT_ARCHIVE_AFTER: Lookback = Lookback::Days(90);
+
+pub fn handle_cmd_archive(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let lookback = match arg_matches.get_one::<Lookback>("older-than") {
+        Some(lookback) => *lookback,
+        None => config
+            .get_parsed("archive_after")
+            .unwrap_or(DEFAULT_ARCHIVE_AFTER),
+    };
+
+    let Some(before) = lookback.date_before(today()) else {
+        println!("Error = --older-than goes back too far");
+        return;
+    };
+    let before = iso_format_timestamp(&before);
+
+    match archive_tasks(db_conn, &before) {
+        Ok(0) => println!("No done tasks from before {before} to archive"),
+        Ok(archived) => println!(
+            "Archived {archived} done tasks from before {before} to {}",
+            get_archive_path().display()
+        ),
+        Err(error) => println!("Error archiving tasks = {:?}", error),
+    }
+}
+
+/*
+ * whether archive.db is attached for --archived, it isn't before anything got archived.
+ * None when it couldn't be, which has been reported
+ * */
+fn with_archive(arg_matches: &ArgMatches, db_conn: &Connection) -> Option<bool> {
+    if !arg_matches.get_flag("archived") {
+        return Some(false);
+    }
+
+    match attach_archive(db_conn, false) {
+        Ok(attached) => Some(attached),
+        Err(error) => {
+            println!("Error opening the archive = {:?}", error);
+            None
+        }
+    }
+}
+
+pub fn handle_cmd_merge(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let path = arg_matches
+        .get_one::<PathBuf>("FILE")
+        .expect("File is required");
+
+    // attaching a path that doesn't exist would create an empty database there
+    if !path.is_file() {
+        println!("Error = no database at {}", path.display());
+        return;
+    }
+
+    if !backup_before(db_conn, "merge") {
+        return;
+    }
+
+    match merge_db(db_conn, path) {
+        Ok(counts) => print_merge_counts(path, &counts),
+        Err(error) => println!("Error merging {} = {:?}", path.display(), error),
+    }
+}
+
+fn print_merge_counts(path: &Path, counts: &MergeCounts) {
+    println!(
+        "Merged {}: {} added, {} updated, {} deleted",
+        path.display(),
+        counts.added,
+        counts.updated,
+        counts.deleted
+    );
+
+    if counts.conflicts > 0 {
+        println!(
+            "{} tasks were changed on both sides, the later change was kept, review with `conflicts`",
+            counts.conflicts
+        );
+    }
+}
+
+/*
+ * two way merge with a remote copy: pull (merge) what changed there, then push this side's
+ * tasks back. --since 90d (or sync.since in config) keeps only tasks from then on in the
+ * remote, the full history stays here
+ *
+ * [sync]
+ * since = "90d"
+ * */
+pub fn handle_cmd_sync(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let path = arg_matches
+        .get_one::<PathBuf>("REMOTE")
+        .expect("Remote is required");
+
+    let since = arg_matches
+        .get_one::<Lookback>("since")
+        .copied()
+        .or_else(|| config.get_parsed("sync.since"));
+
+    let since_date = match since.map(|since| since.date_before(today())) {
+        Some(Some(date)) => Some(iso_format_timestamp(&date)),
+        Some(None) => {
+            println!("Error = --since goes back too far");
+            return;
+        }
+        None => None,
+    };
+
+    if !path.exists() {
+        if let Err(error) = create_sync_db(path) {
+            println!("Error creating {} = {:?}", path.display(), error);
+            return;
+        }
+        println!("Created {}", path.display());
+    }
+
+    if !backup_before(db_conn, "sync") {
+        return;
+    }
+
+    match merge_db(db_conn, path) {
+        Ok(counts) => print_merge_counts(path, &counts),
+        Err(error) => {
+            println!("Error pulling from {} = {:?}", path.display(), error);
+            return;
+        }
+    }
+
+    match push_db(db_conn, path, since_date.as_deref()) {
+        Ok(counts) => {
+            println!(
+                "Pushed {} tasks to {}, {} deleted there",
+                counts.pushed,
+                path.display(),
+                counts.deleted
+            );
+
+            if let Some(since_date) = since_date {
+                println!(
+                    "Only tasks from {since_date} on are kept there, {} older ones removed",
+                    counts.pruned
+                );
+            }
+        }
+        Err(error) => println!("Error pushing to {} = {:?}", path.display(), error),
+    }
+}
+
+// external_tasks source of caldav items, their external id is the href
+const CALDAV_SOURCE: &str = "caldav";
+
+// tasks from this far back on go out on their first sync, later ones keep syncing anyway
+const CALDAV_SINCE: Lookback = Lookback::Days(14);
+
+/*
+ * two way sync with a caldav task list, see caldav.rs for the setup. a change on one side
+ * since the last sync goes to the other side, on both sides the later change wins. deleting
+ * on one side deletes on the other, unless the task was changed there since
+ *
+ * tasks not synced before go out from --since (or caldav.since in config, 14d by default) on
+ * */
+pub fn handle_cmd_caldav(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let (Some(url), Some(username)) = (config.get("caldav.url"), config.get("caldav.username"))
+    else {
+        println!("Error = set url and username of the task list under [caldav] in config");
+        return;
+    };
+
+    let since = arg_matches
+        .get_one::<Lookback>("since")
+        .copied()
+        .or_else(|| config.get_parsed("caldav.since"))
+        .unwrap_or(CALDAV_SINCE);
+
+    // after is exclusive, the day before keeps tasks of the first day
+    let Some(since_date) = since.date_before(today()).and_then(|date| date.pred_opt()) else {
+        println!("Error = --since goes back too far");
+        return;
+    };
+
+    let account = match Account::new(url, username) {
+        Ok(account) => account,
+        Err(error) => {
+            println!("Error = {error}");
+            return;
+        }
+    };
+
+    let todos = match get_todos(&account, true) {
+        Ok(todos) => todos,
+        Err(error) => {
+            println!("Error = {error}");
+            return;
+        }
+    };
+
+    let synced = match get_external_tasks(db_conn, CALDAV_SOURCE) {
+        Ok(synced) => synced,
+        Err(error) => {
+            println!("Error fetching synced tasks = {:?}", error);
+            return;
+        }
+    };
+
+    let unsynced_tasks = match get_tasks(
+        db_conn,
+        &TaskFilter::new().after(&since_date.format("%F").to_string()),
+    ) {
+        Ok(tasks) => tasks
+            .into_iter()
+            .filter(|task| !synced.iter().any(|synced| synced.task_id == task.id))
+            .collect::<Vec<_>>(),
+        Err(error) => {
+            println!("Error fetching tasks = {:?}", error);
+            return;
+        }
+    };
+
+    if !backup_before(db_conn, "caldav") {
+        return;
+    }
+
+    let synced_by_href: HashMap<&str, &ExternalTask> = synced
+        .iter()
+        .map(|synced| (synced.external_id.as_str(), synced))
+        .collect();
+
+    // (href, task, completed_at, etag) to store here
+    let mut pulls = vec![];
+    // (href, task, calendar object there, etag) to write there
+    let mut pushes = vec![];
+    let mut deletes_there = vec![];
+    let mut deletes_here = vec![];
+    let mut forgotten = vec![];
+
+    for todo in &todos {
+        let fields = todo_fields(&todo.ics);
+        let pulled = |task_id: &str, current: Option<&Task>| {
+            todo_task(&fields, task_id, current).map(|task| {
+                (
+                    todo.href.clone(),
+                    task,
+                    fields.completed_at.clone(),
+                    todo.etag.clone(),
+                )
+            })
+        };
+
+        let Some(synced) = synced_by_href.get(todo.href.as_str()) else {
+            pulls.extend(pulled(&Ulid::new().to_string(), None));
+            continue;
+        };
+
+        let changed_there = synced.etag.as_deref() != Some(todo.etag.as_str());
+
+        let task = match get_task_by_id(db_conn, &synced.task_id) {
+            Ok(task) => task,
+            Err(rusqlite::Error::QueryReturnedNoRows) if changed_there => {
+                // changed there after it was deleted here, it comes back as a new task
+                forgotten.push(todo.href.clone());
+                pulls.extend(pulled(&Ulid::new().to_string(), None));
+                continue;
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                deletes_there.push((todo.href.clone(), todo.etag.clone()));
+                continue;
+            }
+            Err(error) => {
+                println!("Error fetching task = {:?}", error);
+                return;
+            }
+        };
+
+        let changed_here = task.updated_at > synced.synced_at;
+
+        match (changed_there, changed_here) {
+            (false, false) => {}
+            (true, true) if task.updated_at > fields.last_modified => {
+                pushes.push((todo.href.clone(), task, Some(&todo.ics), Some(&todo.etag)))
+            }
+            (false, true) => {
+                pushes.push((todo.href.clone(), task, Some(&todo.ics), Some(&todo.etag)))
+            }
+            (true, _) => pulls.extend(pulled(&task.id, Some(&task))),
+        }
+    }
+
+    for synced in &synced {
+        if todos.iter().any(|todo| todo.href == synced.external_id) {
+            continue;
+        }
+
+        match get_task_by_id(db_conn, &synced.task_id) {
+            // changed here after it was deleted there, it goes back
+            Ok(task) if task.updated_at > synced.synced_at => {
+                pushes.push((synced.external_id.clone(), task, None, None))
+            }
+            Ok(task) => deletes_here.push((synced.external_id.clone(), task.id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => forgotten.push(synced.external_id.clone()),
+            Err(error) => {
+                println!("Error fetching task = {:?}", error);
+                return;
+            }
+        }
+    }
+
+    for task in unsynced_tasks {
+        pushes.push((account.new_href(&task.id), task, None, None));
+    }
+
+    for href in &forgotten {
+        if let Err(error) = delete_external_task(db_conn, CALDAV_SOURCE, href) {
+            println!("Error updating synced tasks = {:?}", error);
+            return;
+        }
+    }
+
+    let external_tasks: Vec<(String, Task, Option<String>)> = pulls
+        .iter()
+        .map(|(href, task, completed_at, _)| (href.clone(), task.clone(), completed_at.clone()))
+        .collect();
+
+    let (added, updated) = match store_external_tasks(db_conn, CALDAV_SOURCE, &external_tasks) {
+        Ok(counts) => counts,
+        Err(error) => {
+            println!("Error storing tasks = {:?}", error);
+            return;
+        }
+    };
+
+    for (href, _, _, etag) in &pulls {
+        let result =
+            get_external_task_id(db_conn, CALDAV_SOURCE, href).and_then(|task_id| match task_id {
+                Some(task_id) => {
+                    set_external_sync_state(db_conn, CALDAV_SOURCE, href, &task_id, Some(etag))
+                }
+                None => Ok(()),
+            });
+
+        if let Err(error) = result {
+            println!("Error updating synced tasks = {:?}", error);
+            return;
+        }
+    }
+
+    let mut deleted_here = 0;
+    for (href, task_id) in &deletes_here {
+        match delete_task(db_conn, task_id)
+            .and_then(|()| delete_external_task(db_conn, CALDAV_SOURCE, href))
+        {
+            Ok(()) => deleted_here += 1,
+            Err(error) => println!("Error deleting task = {:?}", error),
+        }
+    }
+
+    let mut deleted_there = 0;
+    for (href, etag) in &deletes_there {
+        match delete_todo(&account, href, etag) {
+            Ok(()) => match delete_external_task(db_conn, CALDAV_SOURCE, href) {
+                Ok(()) => deleted_there += 1,
+                Err(error) => println!("Error updating synced tasks = {:?}", error),
+            },
+            Err(error) => println!("Error = {error}, left for the next sync"),
+        }
+    }
+
+    let mut pushed = vec![];
+    for (href, task, ics, etag) in &pushes {
+        match put_todo(
+            &account,
+            href,
+            &task_ics(task, ics.map(String::as_str)),
+            etag.map(String::as_str),
+        ) {
+            Ok(()) => pushed.push((href, &task.id)),
+            Err(error) => println!("Error = {error}, left for the next sync"),
+        }
+    }
+
+    // the server picks the etags of what was written, they are listed again to keep them
+    if !pushed.is_empty() {
+        let etags = get_todos(&account, false).unwrap_or_else(|error| {
+            println!("Error = {error}, the tasks sent go out once more on the next sync");
+            vec![]
+        });
+
+        for (href, task_id) in &pushed {
+            let etag = etags
+                .iter()
+                .find(|todo| &todo.href == *href)
+                .map(|todo| todo.etag.as_str());
+
+            if let Err(error) = set_external_sync_state(db_conn, CALDAV_SOURCE, href, task_id, etag)
+            {
+                println!("Error updating synced tasks = {:?}", error);
+                return;
+            }
+        }
+    }
+
+    println!(
+        "Synced with {}: {added} added and {updated} updated here, {} sent there, {deleted_here} deleted here, {deleted_there} deleted there",
+        account.url,
+        pushed.len()
+    );
+}
+
+pub fn handle_cmd_conflicts(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let conflicts = match get_merge_conflicts(db_conn) {
+        Ok(conflicts) => conflicts,
+        Err(error) => {
+            println!("Error fetching conflicts = {:?}", error);
+            return;
+        }
+    };
+
+    // numbered from 1 as listed
+    let pick = |conflict_no: &usize| match conflict_no
+        .checked_sub(1)
+        .and_then(|index| conflicts.get(index))
+    {
+        Some(conflict) => Some(conflict),
+        None => {
+            println!(
+                "Error = no conflict no. {conflict_no}, there are {}",
+                conflicts.len()
+            );
+            None
+        }
+    };
+
+    if let Some(conflict) = arg_matches.get_one::<usize>("take").and_then(pick) {
+        match take_merge_conflict(db_conn, conflict) {
+            Ok(()) => println!("Took back the version from {}", conflict.lost_from),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                println!("Error = the task was deleted since, dismiss the conflict instead")
+            }
+            Err(error) => println!("Error taking the version = {:?}", error),
+        }
+        return;
+    }
+
+    if let Some(conflict) = arg_matches.get_one::<usize>("dismiss").and_then(pick) {
+        match dismiss_merge_conflict(db_conn, conflict.rowid) {
+            Ok(()) => println!("Dismissed, the current version stays"),
+            Err(error) => println!("Error dismissing the conflict = {:?}", error),
+        }
+        return;
+    }
+
+    if conflicts.is_empty() {
+        println!("No conflicts");
+        return;
+    }
+
+    let mut conflicts_table = Table::new();
+
+    conflicts_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_width(100)
+        .set_header(vec![
+            " No ",
+            " Current ",
+            " Lost version ",
+            " Lost from ",
+            " Merged ",
+        ]);
+
+    for (index, conflict) in conflicts.iter().enumerate() {
+        let current = match get_task_by_id(db_conn, &conflict.task_id) {
+            Ok(task) => format!(
+                "{} {}",
+                format_status(task.status),
+                redact_description(&task.description)
+            ),
+            Err(rusqlite::Error::QueryReturnedNoRows) => "deleted".to_string(),
+            Err(error) => format!("{error}"),
+        };
+
+        let lost = match &conflict.lost {
+            Some(task) => format!(
+                "{} {}",
+                format_status(task.status),
+                redact_description(&task.description)
+            ),
+            None => "deleted".to_string(),
+        };
+
+        conflicts_table.add_row(vec![
+            (index + 1).to_string(),
+            current,
+            format!(
+                "{lost}\n(changed {})",
+                format_local_timestamp(conflict.lost_updated_at.as_deref().unwrap_or_default())
+            ),
+            conflict.lost_from.clone(),
+            format_local_timestamp(&conflict.merged_at),
+        ]);
+    }
+
+    print_table(&conflicts_table);
+}
+
+// app_state key of the progress marker of an unfinished import
+const IMPORT_PROGRESS_KEY: &str = "import";
+
+// tasks stored per transaction, an interrupted import loses at most one batch of work
+const IMPORT_BATCH_SIZE: usize = 500;
+
+/*
+ * import progress as kept in app_state, written along with every batch:
+ * <import id><TAB><lines done><TAB><file size><TAB><path>
+ *
+ * the file size tells apart a file changed in between, resuming would skip the wrong lines
+ * */
+struct ImportProgress {
+    id: String,
+    lines_done: usize,
+    file_size: u64,
+    path: PathBuf,
+}
+
+impl ImportProgress {
+    fn from_state(value: &str) -> Option<ImportProgress> {
+        let mut parts = value.splitn(4, '\t');
+
+        Some(ImportProgress {
+            id: parts.next()?.to_string(),
+            lines_done: parts.next()?.parse().ok()?,
+            file_size: parts.next()?.parse().ok()?,
+            path: PathBuf::from(parts.next()?),
+        })
+    }
+
+    fn to_state(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            self.id,
+            self.lines_done,
+            self.file_size,
+            self.path.display()
+        )
+    }
+}
+
+/*
+ * imports the task lines of --porcelain output (other lines are skipped), in batches
+ * each committed with a progress marker. an interrupted import stays unfinished until
+ * --resume carries on after the last batch, or --abort removes what it added
+ * */
+pub fn handle_cmd_import(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let progress = match get_app_state(db_conn, IMPORT_PROGRESS_KEY) {
+        Ok(progress) => progress.and_then(|value| ImportProgress::from_state(&value)),
+        Err(error) => {
+            println!("Error fetching import progress = {:?}", error);
+            return;
+        }
+    };
+
+    if let Some(app) = arg_matches.get_one::<ImportApp>("from") {
+        if let Some(progress) = progress {
+            println!(
+                "Error = import of {} is unfinished, run import --resume or import --abort first",
+                progress.path.display()
+            );
+            return;
+        }
+
+        let list = arg_matches.get_one::<String>("list");
+        let since = arg_matches
+            .contains_id("since")
+            .then(|| arg_matches.get_one::<Lookback>("since"));
+
+        match (app, list) {
+            (ImportApp::Gtasks, _) => import_gtasks(db_conn, config, list, since),
+            (ImportApp::Gitlab, Some(_)) => {
+                println!("Error = --list doesn't work with --from gitlab")
+            }
+            (ImportApp::Gitlab, None) => import_gitlab(db_conn, config, since),
+            (ImportApp::Reminders, _) if since.is_some() => {
+                println!("Error = --since only works with --from gtasks or gitlab")
+            }
+            (ImportApp::Reminders, Some(list)) => import_reminders(db_conn, list),
+            (ImportApp::Reminders, None) => println!("Error = --from reminders needs --list"),
+        }
+        return;
+    }
+
+    if let Some(path) = arg_matches.get_one::<PathBuf>("FILE") {
+        if let Some(progress) = progress {
+            println!(
+                "Error = import of {} is unfinished, run import --resume or import --abort first",
+                progress.path.display()
+            );
+            return;
+        }
+
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+
+        let file_size = match fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(error) => {
+                println!("Error reading {} = {error}", path.display());
+                return;
+            }
+        };
+
+        let progress = ImportProgress {
+            id: Ulid::new().to_string(),
+            lines_done: 0,
+            file_size,
+            path,
+        };
+
+        run_import(db_conn, progress);
+        return;
+    }
+
+    let Some(progress) = progress else {
+        println!("No unfinished import");
+        return;
+    };
+
+    if arg_matches.get_flag("abort") {
+        if !backup_before(db_conn, "import-abort") {
+            return;
+        }
+
+        match abort_import(db_conn, &progress.id, IMPORT_PROGRESS_KEY) {
+            Ok(removed) => println!(
+                "Rolled back import of {}, removed {removed} tasks",
+                progress.path.display()
+            ),
+            Err(error) => println!("Error rolling back import = {:?}", error),
+        }
+        return;
+    }
+
+    run_import(db_conn, progress);
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ImportApp {
+    // Apple Reminders, macOS only
+    Reminders,
+    // Google Tasks, see gtasks.rs for the setup
+    Gtasks,
+    // issues assigned to you, see gitlab.rs for the setup
+    Gitlab,
+}
+
+/*
+ * the whole list goes in with one transaction, there is nothing to resume. the progress
+ * marker names the list, so import --abort can still roll it back should finishing fail
+ * */
+fn import_reminders(db_conn: &Connection, list: &str) {
+    let tasks: Vec<Task> = match read_reminders(list) {
+        Ok(reminders) => reminders.iter().map(reminder_task).collect(),
+        Err(error) => {
+            println!("Error reading reminders list '{list}' = {error}");
+            return;
+        }
+    };
+
+    let progress = ImportProgress {
+        id: Ulid::new().to_string(),
+        lines_done: tasks.len(),
+        file_size: 0,
+        path: PathBuf::from(format!("reminders list {list}")),
+    };
+
+    let skipped = match insert_imported_tasks(
+        db_conn,
+        &progress.id,
+        &tasks,
+        IMPORT_PROGRESS_KEY,
+        &progress.to_state(),
+    ) {
+        Ok(inserted) => tasks.len() - inserted,
+        Err(error) => {
+            println!("Error importing tasks = {:?}", error);
+            return;
+        }
+    };
+
+    match finish_import(db_conn, &progress.id, IMPORT_PROGRESS_KEY) {
+        Ok(imported) => println!(
+            "Imported {imported} tasks from {}, {skipped} were imported before",
+            progress.path.display()
+        ),
+        Err(error) => println!("Error finishing import = {:?}", error),
+    }
+}
+
+// app_state key of when the last google tasks import started, for --since without a value
+const GTASKS_IMPORTED_KEY: &str = "gtasks.last_import";
+
+/*
+ * every list, or just --list, goes in with one transaction. tasks seen before are updated,
+ * so running it again picks up what got done or renamed in google tasks since. --since only
+ * asks for tasks changed in the window, --since on its own for those changed since last time
+ * */
+fn import_gtasks(
+    db_conn: &Connection,
+    config: &Config,
+    list: Option<&String>,
+    since: Option<Option<&Lookback>>,
+) {
+    let started_at = now_timestamp();
+
+    let updated_min = match since {
+        None => None,
+        Some(Some(lookback)) => match lookback.date_before(today()) {
+            Some(date) => Some(day_start_timestamp(&date)),
+            None => {
+                println!("Error = --since goes back too far");
+                return;
+            }
+        },
+        Some(None) => match get_app_state(db_conn, GTASKS_IMPORTED_KEY) {
+            Ok(Some(imported_at)) => Some(imported_at),
+            Ok(None) => {
+                println!("Error = nothing was imported from google tasks yet, leave out --since");
+                return;
+            }
+            Err(error) => {
+                println!("Error fetching the last import = {:?}", error);
+                return;
+            }
+        },
+    };
+
+    let (Some(client_id), Some(client_secret)) = (
+        config.get("gtasks.client_id"),
+        config.get("gtasks.client_secret"),
+    ) else {
+        println!(
+            "Error = set client_id and client_secret of a desktop oauth client under [gtasks] in config"
+        );
+        return;
+    };
+
+    let credentials = Credentials {
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string(),
+    };
+
+    let access_token = match get_access_token(&credentials) {
+        Ok(access_token) => access_token,
+        Err(error) => {
+            println!("Error = {error}");
+            return;
+        }
+    };
+
+    let task_lists = match get_google_task_lists(&access_token) {
+        Ok(task_lists) => task_lists,
+        Err(error) => {
+            println!("Error fetching task lists = {error}");
+            return;
+        }
+    };
+
+    let task_lists: Vec<_> = task_lists
+        .into_iter()
+        .filter(|(_, title)| list.is_none_or(|list| title == list))
+        .collect();
+
+    if let (Some(list), true) = (list, task_lists.is_empty()) {
+        println!("Error = no google tasks list named '{list}'");
+        return;
+    }
+
+    let mut tasks = vec![];
+
+    for (list_id, title) in &task_lists {
+        match get_google_tasks(&access_token, list_id, updated_min.as_deref()) {
+            // google tasks keeps empty tasks around, eg. a new one never typed into
+            Ok(list_tasks) => tasks.extend(
+                list_tasks
+                    .iter()
+                    .filter(|task| !task.title.trim().is_empty())
+                    .map(|task| {
+                        let (task_row, completed_at) = google_task(task);
+                        (task.id.clone(), task_row, completed_at)
+                    }),
+            ),
+            Err(error) => {
+                println!("Error fetching tasks of '{title}' = {error}");
+                return;
+            }
+        }
+    }
+
+    match store_external_tasks(db_conn, "gtasks", &tasks) {
+        Ok((added, updated)) => {
+            if let Err(error) = set_app_state(db_conn, GTASKS_IMPORTED_KEY, &started_at) {
+                println!("Error saving the import time = {:?}", error);
+            }
+            println!(
+                "Imported {added} new and {updated} changed tasks from {} google tasks lists",
+                task_lists.len()
+            );
+        }
+        Err(error) => println!("Error importing tasks = {:?}", error),
+    }
+}
+
+// app_state key of when the last gitlab import started, for --since without a value
+const GITLAB_IMPORTED_KEY: &str = "gitlab.last_import";
+
+/*
+ * open issues assigned to you, tagged with their reference (eg. #group/project!12). with
+ * --since closed ones changed in the window too, whose tasks are then done. a task marked
+ * done here stays done while its issue is still open
+ * */
+fn import_gitlab(db_conn: &Connection, config: &Config, since: Option<Option<&Lookback>>) {
+    let started_at = now_timestamp();
+
+    let updated_after = match since {
+        None => None,
+        Some(Some(lookback)) => match lookback.date_before(today()) {
+            Some(date) => Some(day_start_timestamp(&date)),
+            None => {
+                println!("Error = --since goes back too far");
+                return;
+            }
+        },
+        Some(None) => match get_app_state(db_conn, GITLAB_IMPORTED_KEY) {
+            Ok(Some(imported_at)) => Some(imported_at),
+            Ok(None) => {
+                println!("Error = nothing was imported from gitlab yet, leave out --since");
+                return;
+            }
+            Err(error) => {
+                println!("Error fetching the last import = {:?}", error);
+                return;
+            }
+        },
+    };
+
+    let url = config.get("gitlab.url").unwrap_or(DEFAULT_GITLAB_URL);
+
+    let token = match get_gitlab_token() {
+        Ok(token) => token,
+        Err(error) => {
+            println!("Error = {error}");
+            return;
+        }
+    };
+
+    let issues = match get_assigned_issues(url, &token, updated_after.as_deref()) {
+        Ok(issues) => issues,
+        Err(error) => {
+            println!("Error fetching issues = {error}");
+            return;
+        }
+    };
+
+    let mut tasks = vec![];
+
+    for issue in &issues {
+        let external_id = issue.external_id();
+
+        if !issue.closed {
+            let task = match get_external_task_id(db_conn, "gitlab", &external_id) {
+                Ok(Some(task_id)) => get_task_by_id(db_conn, &task_id).ok(),
+                Ok(None) => None,
+                Err(error) => {
+                    println!("Error fetching imported issues = {:?}", error);
+                    return;
+                }
+            };
+
+            if task.is_some_and(|task| task.status == Status::Done) {
+                continue;
+            }
+        }
+
+        let (task, closed_at) = issue_task(issue);
+        tasks.push((external_id, task, closed_at));
+    }
+
+    match store_external_tasks(db_conn, "gitlab", &tasks) {
+        Ok((added, updated)) => {
+            if let Err(error) = set_app_state(db_conn, GITLAB_IMPORTED_KEY, &started_at) {
+                println!("Error saving the import time = {:?}", error);
+            }
+            println!("Imported {added} new and {updated} changed issues from {url}");
+        }
+        Err(error) => println!("Error importing issues = {:?}", error),
+    }
+}
+
+fn run_import(db_conn: &Connection, mut progress: ImportProgress) {
+    let content = match fs::read(&progress.path) {
+        Ok(content) if content.len() as u64 != progress.file_size => {
+            println!(
+                "Error = {} changed since the import started, run import --abort",
+                progress.path.display()
+            );
+            return;
+        }
+        Ok(content) => String::from_utf8_lossy(&content).into_owned(),
+        Err(error) => {
+            println!("Error reading {} = {error}", progress.path.display());
+            return;
+        }
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
+
+    // a cut off or edited export is refused before anything gets stored
+    if let Err(error) = verify_export_checksum(&lines) {
+        println!("Error = {error}, nothing was imported");
+        return;
+    }
+
+    let mut batch = vec![];
+
+    for (index, line) in lines.iter().enumerate().skip(progress.lines_done) {
+        // the header, other tables and blank lines are skipped
+        if let ("task", fields) = parse_porcelain_line(line) {
+            match parse_porcelain_task(&fields) {
+                Ok(task) => batch.push(task),
+                Err(error) => {
+                    finish_progress();
+                    println!(
+                        "Error on line {} = {error}, run import --abort to roll back what got imported",
+                        index + 1
+                    );
+                    return;
+                }
+            }
+        }
+
+        if batch.len() == IMPORT_BATCH_SIZE || index + 1 == lines.len() {
+            progress.lines_done = index + 1;
+
+            if let Err(error) = insert_imported_tasks(
+                db_conn,
+                &progress.id,
+                &batch,
+                IMPORT_PROGRESS_KEY,
+                &progress.to_state(),
+            ) {
+                finish_progress();
+                println!("Error importing tasks = {:?}", error);
+                return;
+            }
+
+            batch.clear();
+            print_progress("Importing", progress.lines_done, lines.len());
+        }
+    }
+
+    finish_progress();
+
+    match finish_import(db_conn, &progress.id, IMPORT_PROGRESS_KEY) {
+        Ok(imported) => println!("Imported {imported} tasks from {}", progress.path.display()),
+        Err(error) => println!("Error finishing import = {:?}", error),
+    }
+}
+
+pub fn handle_cmd_report(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let format = *arg_matches
+        .get_one::<ReportFormat>("format")
+        .expect("Format has a default");
+
+    let template = match arg_matches.get_one::<PathBuf>("template") {
+        Some(path) => match fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| Template::parse(&text))
+        {
+            Ok(template) => Some(template),
+            Err(error) => {
+                println!("Error reading template {} = {error}", path.display());
+                return;
+            }
+        },
+        None => None,
+    };
+
+    // a template is filled in with the json report
+    let format = match template {
+        Some(_) => ReportFormat::Json,
+        None => format,
+    };
+
+    let output = &ReportOutput {
+        file: arg_matches.get_one::<PathBuf>("output"),
+        template,
+    };
+
+    if arg_matches.contains_id("sprint") {
+        report_sprint(
+            arg_matches.get_one::<String>("sprint"),
+            db_conn,
+            format,
+            output,
+        );
+    }
+
+    if arg_matches.get_flag("cycle-time") {
+        report_cycle_time(db_conn, format, output);
+    }
+
+    if arg_matches.get_flag("variance") {
+        report_variance(db_conn, format, output);
+    }
+
+    if arg_matches.contains_id("aging") {
+        let threshold_days = match arg_matches.get_one::<u32>("aging") {
+            Some(days) => *days,
+            None => config
+                .get_parsed("aging_days")
+                .unwrap_or(DEFAULT_AGING_DAYS),
+        };
+
+        report_aging(db_conn, threshold_days, format, output);
+    }
+
+    if arg_matches.get_flag("week") || arg_matches.get_flag("month") {
+        let today = today();
+
+        let (kind, title, dates): (&str, String, Vec<NaiveDate>) = if arg_matches.get_flag("week") {
+            let week_start = today - Days::new(today.weekday().num_days_from_monday() as u64);
+            (
+                "week",
+                format!("Week of {}", iso_format_timestamp(&week_start)),
+                week_start.iter_days().take(7).collect(),
+            )
+        } else {
+            let month_start = today.with_day(1).expect("Internal Error: Invalid day");
+            (
+                "month",
+                month_start.format("%B %Y").to_string(),
+                month_start
+                    .iter_days()
+                    .take_while(|date| date.month() == month_start.month())
+                    .collect(),
+            )
+        };
+
+        match arg_matches.get_many::<String>("profiles") {
+            Some(profiles) => report_profiles(
+                &profiles.collect::<Vec<_>>(),
+                arg_matches.get_flag("merge"),
+                kind,
+                &title,
+                &dates,
+                format,
+                output,
+            ),
+            None => report_period(db_conn, kind, &title, &dates, format, output),
+        }
+    }
+
+    if let Some((year, quarter)) = arg_matches.get_one::<(i32, u32)>("quarter") {
+        match quarter_range(*year, *quarter) {
+            Some(range) => report_rollup(
+                db_conn,
+                "quarter",
+                &format!("Q{quarter} {year}"),
+                range,
+                format,
+                output,
+            ),
+            None => println!("Invalid quarter"),
+        }
+    }
+
+    if arg_matches.contains_id("year") {
+        let year = arg_matches
+            .get_one::<i32>("year")
+            .copied()
+            .unwrap_or(today().year());
+
+        match year_range(year) {
+            Some(range) => report_rollup(db_conn, "year", &year.to_string(), range, format, output),
+            None => println!("Invalid year"),
+        }
+    }
+}
+
+const BUSIEST_WEEKS_SHOWN: usize = 3;
+const TOP_TAGS_SHOWN: usize = 5;
+
+// totals, busiest weeks and most used #tags over a longer period, for self reviews
+fn report_rollup(
+    db_conn: &Connection,
+    kind: &str,
+    title: &str,
+    (first_date, last_date): (NaiveDate, NaiveDate),
+    format: ReportFormat,
+    output: &ReportOutput,
+) {
+    let start_date = iso_format_timestamp(&first_date);
+    let end_date = iso_format_timestamp(&last_date);
+
+    let tasks = match get_tasks_by_date(db_conn, &start_date, Some(&end_date)) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let is_done = |task: &&Task| task.status == Status::Done;
+
+    let done = tasks.iter().filter(is_done).count();
+    let completion_rate = (!tasks.is_empty()).then(|| done * 100 / tasks.len());
+    let committed_points: u32 = tasks.iter().filter_map(|task| task.points).sum();
+    let done_points: u32 = tasks
+        .iter()
+        .filter(is_done)
+        .filter_map(|task| task.points)
+        .sum();
+
+    let mut weeks: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut tags: HashMap<String, usize> = HashMap::new();
+
+    for task in &tasks {
+        if let Ok(date) = NaiveDate::parse_from_str(task.effective_date(), "%F") {
+            let week = date.iso_week();
+            let counts = weeks
+                .entry(format!("{}-W{:02}", week.year(), week.week()))
+                .or_default();
+
+            counts.0 += 1;
+            if task.status == Status::Done {
+                counts.1 += 1;
+            }
+        }
+
+        for tag in task.all_tags() {
+            *tags.entry(tag).or_default() += 1;
+        }
+    }
+
+    // most tasks first, ties in calendar order
+    let mut weeks: Vec<(String, (usize, usize))> = weeks.into_iter().collect();
+    weeks.sort_by(|(week_a, (a, _)), (week_b, (b, _))| b.cmp(a).then(week_a.cmp(week_b)));
+    weeks.truncate(BUSIEST_WEEKS_SHOWN);
+
+    let mut tags: Vec<(String, usize)> = tags.into_iter().collect();
+    tags.sort_by(|(tag_a, a), (tag_b, b)| b.cmp(a).then(tag_a.cmp(tag_b)));
+    tags.truncate(TOP_TAGS_SHOWN);
+
+    if matches!(format, ReportFormat::Json) {
+        let report = render_report_json(
+            kind,
+            vec![
+                ("title", title.into()),
+                ("start_date", start_date.into()),
+                ("end_date", end_date.into()),
+                ("total", tasks.len().into()),
+                ("done", done.into()),
+                ("completion_rate", completion_rate.into()),
+                (
+                    "points",
+                    Json::Object(vec![
+                        ("committed", committed_points.into()),
+                        ("done", done_points.into()),
+                    ]),
+                ),
+                (
+                    "busiest_weeks",
+                    Json::Array(
+                        weeks
+                            .into_iter()
+                            .map(|(week, (total, done))| {
+                                Json::Object(vec![
+                                    ("week", week.into()),
+                                    ("tasks", total.into()),
+                                    ("done", done.into()),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                ),
+                (
+                    "top_tags",
+                    Json::Array(
+                        tags.into_iter()
+                            .map(|(tag, count)| {
+                                Json::Object(vec![("tag", tag.into()), ("tasks", count.into())])
+                            })
+                            .collect(),
+                    ),
+                ),
+            ],
+        );
+
+        write_report(output, report.into_bytes());
+        return;
+    }
+
+    let Some(completion_rate) = completion_rate else {
+        println!("No tasks in {title}");
+        return;
+    };
+
+    println!("{title}");
+    println!(
+        "Done {done} of {} tasks ({completion_rate}%), {done_points} of {committed_points} pts",
+        tasks.len(),
+    );
+
+    let mut weeks_table = Table::new();
+
+    weeks_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_header(vec![" Busiest week ", " Tasks ", " Done "]);
+
+    for (week, (total, done)) in weeks {
+        weeks_table.add_row(vec![week, total.to_string(), done.to_string()]);
+    }
+
+    print_table(&weeks_table);
+
+    if tags.is_empty() {
+        return;
+    }
+
+    let mut tags_table = Table::new();
+
+    tags_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_header(vec![" Top tag ", " Tasks "]);
+
+    for (tag, count) in tags {
+        tags_table.add_row(vec![format!("#{tag}"), count.to_string()]);
+    }
+
+    print_table(&tags_table);
+}
+
+fn report_period(
+    db_conn: &Connection,
+    kind: &str,
+    title: &str,
+    dates: &[NaiveDate],
+    format: ReportFormat,
+    output: &ReportOutput,
+) {
+    if matches!(format, ReportFormat::Pdf) && output.file.is_none() {
+        println!("PDF reports need an output file, pass -o <FILE>");
+        return;
+    }
+
+    let (Some(first_date), Some(last_date)) = (dates.first(), dates.last()) else {
+        return;
+    };
+
+    let start_date = iso_format_timestamp(first_date);
+    let end_date = iso_format_timestamp(last_date);
+
+    let tasks = match get_tasks_by_date(db_conn, &start_date, Some(&end_date)) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let journal: Vec<(String, String)> = match get_journal_entries(db_conn, &start_date, &end_date)
+    {
+        Ok(journal) => journal
+            .into_iter()
+            .map(|(date, note)| (date, redact_description(note.trim_end())))
+            .collect(),
+        Err(error) => {
+            println!("Error fetching journal = {:?}", error);
+            return;
+        }
+    };
+
+    let days = || -> Vec<(String, Vec<Task>)> {
+        dates
+            .iter()
+            .map(|date| {
+                let date = iso_format_timestamp(date);
+                let day_tasks = tasks
+                    .iter()
+                    .filter(|task| *task.effective_date() == date)
+                    .cloned()
+                    .collect();
+                (date, day_tasks)
+            })
+            .collect()
+    };
+
+    let report = match format {
+        ReportFormat::Text => {
+            let done = tasks
+                .iter()
+                .filter(|task| task.status == Status::Done)
+                .count();
+
+            let summary = format!("{title}\nDone {done} of {} tasks\n", tasks.len());
+
+            let journal_text = journal.iter().fold(String::new(), |text, (date, note)| {
+                format!("{text}\n{date}\n{note}\n")
+            });
+
+            if output.file.is_none() {
+                print!("{summary}");
+                render_grouped_tasks(tasks, false, true);
+
+                if !journal.is_empty() {
+                    print!("\nJournal\n{journal_text}");
+                }
+                return;
+            }
+
+            // the table is meant for the terminal, a file gets one line per task
+            let mut report = tasks.iter().fold(summary, |report, task| {
+                format!(
+                    "{report}{} {} [{}]\n",
+                    task.effective_date(),
+                    redact_description(&task.description),
+                    task.status
+                )
+            });
+
+            if !journal.is_empty() {
+                report.push_str(&format!("\nJournal\n{journal_text}"));
+            }
+
+            report.into_bytes()
+        }
+        ReportFormat::Html => render_period_html(title, &days(), &journal).into_bytes(),
+        ReportFormat::Pdf => render_period_pdf(title, &days()),
+        ReportFormat::Json => {
+            let done = tasks
+                .iter()
+                .filter(|task| task.status == Status::Done)
+                .count();
+
+            let days = days()
+                .into_iter()
+                .map(|(date, tasks)| {
+                    let note = journal
+                        .iter()
+                        .find(|(journal_date, _)| *journal_date == date)
+                        .map(|(_, note)| note.clone());
+
+                    Json::Object(vec![
+                        ("date", date.into()),
+                        ("tasks", Json::Array(tasks.iter().map(task_json).collect())),
+                        ("journal", note.into()),
+                    ])
+                })
+                .collect();
+
+            render_report_json(
+                kind,
+                vec![
+                    ("title", title.into()),
+                    ("start_date", start_date.into()),
+                    ("end_date", end_date.into()),
+                    ("total", tasks.len().into()),
+                    ("done", done.into()),
+                    ("days", Json::Array(days)),
+                ],
+            )
+            .into_bytes()
+        }
+    };
+
+    write_report(output, report);
+}
+
+/*
+ * the week or month report of several profiles, eg. the databases of a team kept as profiles
+ * by their lead. one report after the other, or with merge a single one with the tasks grouped
+ * by profile, as text or json. journals are left out of a merged report
+ * */
+fn report_profiles(
+    profiles: &[&String],
+    merge: bool,
+    kind: &str,
+    title: &str,
+    dates: &[NaiveDate],
+    format: ReportFormat,
+    output: &ReportOutput,
+) {
+    let mut connections = vec![];
+    for profile in profiles {
+        match open_profile(profile) {
+            Ok(connection) => connections.push((profile.as_str(), connection)),
+            Err(error) => {
+                println!("Error opening profile {profile} = {error}");
+                return;
+            }
+        }
+    }
+
+    if !merge {
+        if output.file.is_some() {
+            println!("One file can't hold a report per profile, pass --merge to combine them");
+            return;
+        }
+
+        for (profile, connection) in &connections {
+            println!("[{profile}]");
+            report_period(connection, kind, title, dates, format, output);
+        }
+        return;
+    }
+
+    let (Some(first_date), Some(last_date)) = (dates.first(), dates.last()) else {
+        return;
+    };
+
+    let start_date = iso_format_timestamp(first_date);
+    let end_date = iso_format_timestamp(last_date);
+
+    let mut groups: Vec<(&str, Vec<Task>)> = vec![];
+    for (profile, connection) in &connections {
+        match get_tasks_by_date(connection, &start_date, Some(&end_date)) {
+            Ok(tasks) => groups.push((profile, tasks)),
+            Err(error) => {
+                println!("Error fetching tasks of {profile} = {error}");
+                return;
+            }
+        }
+    }
+
+    let count_done = |tasks: &[Task]| {
+        tasks
+            .iter()
+            .filter(|task| task.status == Status::Done)
+            .count()
+    };
+
+    let total: usize = groups.iter().map(|(_, tasks)| tasks.len()).sum();
+    let done: usize = groups.iter().map(|(_, tasks)| count_done(tasks)).sum();
+
+    let report = match format {
+        ReportFormat::Text => {
+            let summary = format!(
+                "{title}, {}\nDone {done} of {total} tasks\n",
+                profiles
+                    .iter()
+                    .map(|profile| profile.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            if output.file.is_none() {
+                print!("{summary}");
+                for (profile, tasks) in groups {
+                    println!(
+                        "\n[{profile}] Done {} of {} tasks",
+                        count_done(&tasks),
+                        tasks.len()
+                    );
+                    render_grouped_tasks(tasks, false, true);
+                }
+                return;
+            }
+
+            // one line per task like report_period, under the profile's name
+            groups
+                .iter()
+                .fold(summary, |report, (profile, tasks)| {
+                    tasks
+                        .iter()
+                        .fold(format!("{report}\n[{profile}]\n"), |report, task| {
+                            format!(
+                                "{report}{} {} [{}]\n",
+                                task.effective_date(),
+                                redact_description(&task.description),
+                                task.status
+                            )
+                        })
+                })
+                .into_bytes()
+        }
+        ReportFormat::Json => {
+            let profiles = groups
+                .iter()
+                .map(|(profile, tasks)| {
+                    let days = dates
+                        .iter()
+                        .map(|date| {
+                            let date = iso_format_timestamp(date);
+                            let day_tasks = tasks
+                                .iter()
+                                .filter(|task| *task.effective_date() == date)
+                                .map(task_json)
+                                .collect();
+                            Json::Object(vec![
+                                ("date", date.into()),
+                                ("tasks", Json::Array(day_tasks)),
+                            ])
+                        })
+                        .collect();
+
+                    Json::Object(vec![
+                        ("profile", (*profile).into()),
+                        ("total", tasks.len().into()),
+                        ("done", count_done(tasks).into()),
+                        ("days", Json::Array(days)),
+                    ])
+                })
+                .collect();
+
+            render_report_json(
+                kind,
+                vec![
+                    ("title", title.into()),
+                    ("start_date", start_date.into()),
+                    ("end_date", end_date.into()),
+                    ("total", total.into()),
+                    ("done", done.into()),
+                    ("profiles", Json::Array(profiles)),
+                ],
+            )
+            .into_bytes()
+        }
+        ReportFormat::Html | ReportFormat::Pdf => {
+            println!("Merged reports come as text or json");
+            return;
+        }
+    };
+
+    write_report(output, report);
+}
+
+// the profile's database as its own config has it, read only
+fn open_profile(profile: &str) -> Result<Connection, String> {
+    let config = load_config(Some(profile));
+    let data_dir = resolve_data_dir(&config, Some(profile))?;
+
+    if !has_db(&data_dir) {
+        return Err(format!("no database in {}", data_dir.display()));
+    }
+
+    let connection = open_profile_db(&data_dir, load_encryption_key(&config))
+        .map_err(|error| error.to_string())?;
+
+    match is_task_table_migrated(&connection) {
+        Ok(true) => Ok(connection),
+        Ok(false) => Err(format!(
+            "its database schema is outdated, open it once with --profile {profile}"
+        )),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+// where a report goes, see write_report
+struct ReportOutput<'a> {
+    file: Option<&'a PathBuf>,
+    template: Option<Template>,
+}
+
+// to the output file if there is one, else to stdout, through the template if there is one
+fn write_report(output: &ReportOutput, report: Vec<u8>) {
+    // templates get the same object as --format json, read back
+    let report = match &output.template {
+        Some(template) => match parse_json(&String::from_utf8_lossy(&report)) {
+            Ok(context) => match template.render(&context) {
+                Ok(filled_in) => filled_in.into_bytes(),
+                Err(error) => {
+                    println!("Error filling in template = {error}");
+                    return;
+                }
+            },
+            Err(error) => {
+                println!("Error filling in template = {error}");
+                return;
+            }
+        },
+        None => report,
+    };
+
+    match output.file {
+        Some(path) => match fs::write(path, report) {
+            Ok(()) => println!("Report written to {}", path.display()),
+            Err(error) => println!("Error writing {} = {error}", path.display()),
+        },
+        None => print!("{}", String::from_utf8_lossy(&report)),
+    }
+}
+
+const DEFAULT_AGING_DAYS: u32 = 3;
+
+fn report_aging(
+    db_conn: &Connection,
+    threshold_days: u32,
+    format: ReportFormat,
+    output: &ReportOutput,
+) {
+    let stuck_tasks = match get_stuck_tasks(db_conn) {
+        Ok(stuck_tasks) => stuck_tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let now = Utc::now();
+
+    // tasks whose status change predates the history table count from when they were added
+    let mut aged_tasks: Vec<(Task, TimeDelta)> = stuck_tasks
+        .into_iter()
+        .filter_map(|(task, status_changed_at)| {
+            let since = match status_changed_at {
+                Some(changed_at) => DateTime::parse_from_rfc3339(&changed_at).ok()?.to_utc(),
+                None => task_created_at(&task)?,
+            };
+
+            Some((task, now - since))
+        })
+        .filter(|(_, age)| age.num_days() >= threshold_days as i64)
+        .collect();
+
+    aged_tasks.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    if matches!(format, ReportFormat::Json) {
+        let blocked_tasks = match get_tasks(db_conn, &TaskFilter::new().status(Status::Blocked)) {
+            Ok(blocked_tasks) => blocked_tasks,
+            Err(error) => {
+                println!("Error fetching blocked tasks = {error}");
+                return;
+            }
+        };
+
+        let aged_tasks = aged_tasks
+            .iter()
+            .map(|(task, age)| {
+                Json::Object(vec![
+                    ("task", task_json(task)),
+                    ("stuck_minutes", age.num_minutes().into()),
+                ])
+            })
+            .collect();
+
+        let report = render_report_json(
+            "aging",
+            vec![
+                ("threshold_days", threshold_days.into()),
+                ("tasks", Json::Array(aged_tasks)),
+                (
+                    "blockers",
+                    Json::Array(blocked_tasks.iter().map(task_json).collect()),
+                ),
+            ],
+        );
+
+        write_report(output, report.into_bytes());
+        return;
+    }
+
+    if aged_tasks.is_empty() {
+        println!("No tasks stuck for {threshold_days} days or more");
+        return;
+    }
+
+    let mut aging_table = Table::new();
+
+    aging_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_width(100)
+        .set_header(vec![" Description ", " Status ", " Stuck for ", " ID "]);
+
+    for (task, age) in aged_tasks {
+        aging_table.add_row(vec![
+            redact_description(&task.description),
+            format_status(task.status),
+            format_duration(age),
+            task.id,
+        ]);
+    }
+
+    print_table(&aging_table);
+
+    print_current_blockers(db_conn);
+}
+
+const SLOWEST_TASKS_SHOWN: usize = 5;
+
+fn report_cycle_time(db_conn: &Connection, format: ReportFormat, output: &ReportOutput) {
+    let completed = match get_completed_tasks(db_conn) {
+        Ok(completed) => completed,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let mut cycle_times: Vec<(Task, DateTime<Local>, TimeDelta)> = completed
+        .into_iter()
+        .filter_map(|(task, completed_at)| {
+            let created_at = task_created_at(&task)?;
+            let completed_at = DateTime::parse_from_rfc3339(&completed_at).ok()?;
+            let cycle_time = completed_at.to_utc() - created_at;
+
+            Some((task, completed_at.with_timezone(&Local), cycle_time))
+        })
+        .collect();
+
+    // completed tasks come ordered by completion, so weeks stay in order
+    let mut weeks: Vec<(String, Vec<TimeDelta>)> = vec![];
+
+    for (_, completed_at, cycle_time) in &cycle_times {
+        let week = completed_at.iso_week();
+        let week = format!("{}-W{:02}", week.year(), week.week());
+
+        match weeks.last_mut() {
+            Some((last_week, durations)) if *last_week == week => durations.push(*cycle_time),
+            _ => weeks.push((week, vec![*cycle_time])),
+        }
+    }
+
+    // week, no. done, average and median
+    let weeks: Vec<(String, usize, TimeDelta, TimeDelta)> = weeks
+        .into_iter()
+        .map(|(week, mut durations)| {
+            durations.sort();
+
+            let total: TimeDelta = durations.iter().sum();
+            let average = total / durations.len() as i32;
+
+            let middle = durations.len() / 2;
+            let median = if durations.len() % 2 == 0 {
+                (durations[middle - 1] + durations[middle]) / 2
+            } else {
+                durations[middle]
+            };
+
+            (week, durations.len(), average, median)
+        })
+        .collect();
+
+    cycle_times.sort_by(|(_, _, a), (_, _, b)| b.cmp(a));
+    cycle_times.truncate(SLOWEST_TASKS_SHOWN);
+
+    if matches!(format, ReportFormat::Json) {
+        let weeks = weeks
+            .into_iter()
+            .map(|(week, done, average, median)| {
+                Json::Object(vec![
+                    ("week", week.into()),
+                    ("done", done.into()),
+                    ("average_minutes", average.num_minutes().into()),
+                    ("median_minutes", median.num_minutes().into()),
+                ])
+            })
+            .collect();
+
+        let slowest = cycle_times
+            .iter()
+            .map(|(task, completed_at, cycle_time)| {
+                Json::Object(vec![
+                    ("task", task_json(task)),
+                    ("cycle_time_minutes", cycle_time.num_minutes().into()),
+                    ("completed_at", completed_at.format("%F").to_string().into()),
+                ])
+            })
+            .collect();
+
+        let report = render_report_json(
+            "cycle_time",
+            vec![
+                ("weeks", Json::Array(weeks)),
+                ("slowest", Json::Array(slowest)),
+            ],
+        );
+
+        write_report(output, report.into_bytes());
+        return;
+    }
+
+    if cycle_times.is_empty() {
+        println!("No completed tasks to report on yet");
+        return;
+    }
+
+    let mut weeks_table = Table::new();
+
+    weeks_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_header(vec![" Week ", " Done ", " Average ", " Median "]);
+
+    for (week, done, average, median) in weeks {
+        weeks_table.add_row(vec![
+            week,
+            done.to_string(),
+            format_duration(average),
+            format_duration(median),
+        ]);
+    }
+
+    print_table(&weeks_table);
+
+    let mut slowest_table = Table::new();
+
+    slowest_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_width(100)
+        .set_header(vec![" Slowest ", " Cycle time ", " Pts ", " Done on "]);
+
+    for (task, completed_at, cycle_time) in &cycle_times {
+        slowest_table.add_row(vec![
+            redact_description(&task.description),
+            format_duration(*cycle_time),
+            task.points
+                .map(|points| points.to_string())
+                .unwrap_or_default(),
+            completed_at.format("%F").to_string(),
+        ]);
+    }
+
+    print_table(&slowest_table);
+}
+
+// a week or #tag taking this many times the average time per point is where points run low
+const UNDERESTIMATE_RATIO: f64 = 1.5;
+
+// finished tasks with points, how much they were estimated at and how long they took
+#[derive(Default)]
+struct Variance {
+    tasks: usize,
+    points: u32,
+    took: TimeDelta,
+}
+
+impl Variance {
+    fn add(&mut self, points: u32, took: TimeDelta) {
+        self.tasks += 1;
+        self.points += points;
+        self.took += took;
+    }
+
+    fn per_point(&self) -> TimeDelta {
+        self.took / self.points.max(1) as i32
+    }
+
+    // how many times the average time per point, None when the average is zero
+    fn ratio(&self, average: &Variance) -> Option<f64> {
+        let average = average.per_point().num_seconds();
+
+        (average > 0).then(|| self.per_point().num_seconds() as f64 / average as f64)
+    }
+
+    fn json(&self, key: &'static str, name: &str, average: &Variance) -> Json {
+        Json::Object(vec![
+            (key, name.into()),
+            ("tasks", self.tasks.into()),
+            ("points", self.points.into()),
+            ("took_minutes", self.took.num_minutes().into()),
+            ("per_point_minutes", self.per_point().num_minutes().into()),
+            (
+                "ratio",
+                self.ratio(average)
+                    .map(|ratio| (ratio * 100.0).round() / 100.0)
+                    .into(),
+            ),
+        ])
+    }
+
+    fn row(&self, name: String, average: &Variance) -> Vec<String> {
+        vec![
+            name,
+            self.tasks.to_string(),
+            self.points.to_string(),
+            format_duration(self.took),
+            format_duration(self.per_point()),
+            self.ratio(average)
+                .map(|ratio| format!("{ratio:.1}x"))
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+/*
+ * points are the estimate and the time from starting a task to finishing it what it took,
+ * started being when it first went in progress, or when it was added if it never did.
+ * points are relative, so weeks (by when tasks were done) and #tags are held against the
+ * average time per point, the ones well above it are where points are set too low
+ * */
+fn report_variance(db_conn: &Connection, format: ReportFormat, output: &ReportOutput) {
+    let estimated = match get_estimated_tasks(db_conn) {
+        Ok(estimated) => estimated,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let mut average = Variance::default();
+    let mut weeks: Vec<(String, Variance)> = vec![];
+    let mut tags: HashMap<String, Variance> = HashMap::new();
+
+    for (task, started_at, completed_at) in estimated {
+        let started_at = match started_at {
+            Some(started_at) => DateTime::parse_from_rfc3339(&started_at)
+                .ok()
+                .map(|started_at| started_at.to_utc()),
+            None => task_created_at(&task),
+        };
+        let (Some(started_at), Ok(completed_at), Some(points)) = (
+            started_at,
+            DateTime::parse_from_rfc3339(&completed_at),
+            task.points,
+        ) else {
+            continue;
+        };
+
+        let took = (completed_at.to_utc() - started_at).max(TimeDelta::zero());
+        average.add(points, took);
+
+        // done tasks come ordered by completion, so weeks stay in order
+        let week = completed_at.with_timezone(&Local).iso_week();
+        let week = format!("{}-W{:02}", week.year(), week.week());
+        match weeks.last_mut() {
+            Some((last_week, variance)) if *last_week == week => variance.add(points, took),
+            _ => {
+                let mut variance = Variance::default();
+                variance.add(points, took);
+                weeks.push((week, variance));
+            }
+        }
+
+        for tag in task.all_tags() {
+            tags.entry(tag).or_default().add(points, took);
+        }
+    }
+
+    // slowest per point first, ties by name
+    let mut tags: Vec<(String, Variance)> = tags.into_iter().collect();
+    tags.sort_by(|(tag_a, a), (tag_b, b)| b.per_point().cmp(&a.per_point()).then(tag_a.cmp(tag_b)));
+
+    let is_underestimated = |variance: &Variance| {
+        variance
+            .ratio(&average)
+            .is_some_and(|ratio| ratio >= UNDERESTIMATE_RATIO)
+    };
+
+    // weeks as they are, tags with their #
+    let underestimated: Vec<(String, f64)> = weeks
+        .iter()
+        .map(|(week, variance)| (week.clone(), variance))
+        .chain(
+            tags.iter()
+                .map(|(tag, variance)| (format!("#{tag}"), variance)),
+        )
+        .filter(|(_, variance)| is_underestimated(variance))
+        .map(|(name, variance)| (name, variance.ratio(&average).unwrap_or_default()))
+        .collect();
+
+    if matches!(format, ReportFormat::Json) {
+        let report = render_report_json(
+            "variance",
+            vec![
+                ("tasks", average.tasks.into()),
+                ("points", average.points.into()),
+                (
+                    "per_point_minutes",
+                    average.per_point().num_minutes().into(),
+                ),
+                (
+                    "weeks",
+                    Json::Array(
+                        weeks
+                            .iter()
+                            .map(|(week, variance)| variance.json("week", week, &average))
+                            .collect(),
+                    ),
+                ),
+                (
+                    "tags",
+                    Json::Array(
+                        tags.iter()
+                            .map(|(tag, variance)| variance.json("tag", tag, &average))
+                            .collect(),
+                    ),
+                ),
+                (
+                    "underestimated",
+                    Json::Array(
+                        underestimated
+                            .into_iter()
+                            .map(|(name, _)| name.into())
+                            .collect(),
+                    ),
+                ),
+            ],
+        );
+
+        write_report(output, report.into_bytes());
+        return;
+    }
+
+    if average.tasks == 0 {
+        println!("No finished tasks with points to report on yet");
+        return;
+    }
+
+    println!(
+        "{} tasks, {} points done, {} per point on average",
+        average.tasks,
+        average.points,
+        format_duration(average.per_point())
+    );
+
+    let header = |name: &str| {
+        vec![
+            format!(" {name} "),
+            " Tasks ".to_string(),
+            " Pts ".to_string(),
+            " Took ".to_string(),
+            " Per point ".to_string(),
+            " Vs average ".to_string(),
+        ]
+    };
+
+    let mut weeks_table = Table::new();
+
+    weeks_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_header(header("Week"));
+
+    for (week, variance) in &weeks {
+        weeks_table.add_row(variance.row(week.clone(), &average));
+    }
+
+    print_table(&weeks_table);
+
+    if !tags.is_empty() {
+        let mut tags_table = Table::new();
+
+        tags_table
+            .load_preset(comfy_table::presets::ASCII_FULL)
+            .set_header(header("Tag"));
+
+        for (tag, variance) in &tags {
+            tags_table.add_row(variance.row(format!("#{tag}"), &average));
+        }
+
+        print_table(&tags_table);
+    }
+
+    for (name, ratio) in underestimated {
+        println!("Underestimated: {name} takes {ratio:.1}x the average time per point");
+    }
+}
+
+fn report_sprint(
+    name: Option<&String>,
+    db_conn: &Connection,
+    format: ReportFormat,
+    output: &ReportOutput,
+) {
+    let sprint = match name {
+        Some(name) => get_sprint_by_name(db_conn, name),
+        None => get_sprint_for_date(db_conn, &iso_format_timestamp(&today())),
+    };
+
+    let sprint = match sprint {
+        Ok(sprint) => sprint,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            println!("No such sprint");
+            return;
+        }
+        Err(error) => {
+            println!("Error fetching sprint = {:?}", error);
+            return;
+        }
+    };
+
+    let tasks = match get_tasks_by_date(db_conn, &sprint.start_date, Some(&sprint.end_date)) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let completed: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| task.status == Status::Done)
+        .collect();
+
+    let committed_points: u32 = tasks.iter().filter_map(|task| task.points).sum();
+    let completed_points: u32 = completed.iter().filter_map(|task| task.points).sum();
+
+    let completion_rate = match tasks.len() {
+        0 => 0,
+        total => completed.len() * 100 / total,
+    };
+
+    if matches!(format, ReportFormat::Json) {
+        let open_tasks = tasks
+            .iter()
+            .filter(|task| task.status != Status::Done)
+            .map(task_json)
+            .collect();
+
+        let report = render_report_json(
+            "sprint",
+            vec![
+                ("name", sprint.name.into()),
+                ("start_date", sprint.start_date.into()),
+                ("end_date", sprint.end_date.into()),
+                (
+                    "committed",
+                    Json::Object(vec![
+                        ("tasks", tasks.len().into()),
+                        ("points", committed_points.into()),
+                    ]),
+                ),
+                (
+                    "completed",
+                    Json::Object(vec![
+                        ("tasks", completed.len().into()),
+                        ("points", completed_points.into()),
+                    ]),
+                ),
+                (
+                    "completion_rate",
+                    (!tasks.is_empty()).then_some(completion_rate).into(),
+                ),
+                ("open_tasks", Json::Array(open_tasks)),
+            ],
+        );
+
+        write_report(output, report.into_bytes());
+        return;
+    }
+
+    println!(
+        "Sprint '{}' ({} to {})",
+        sprint.name, sprint.start_date, sprint.end_date
+    );
+    println!("Committed: {} tasks, {committed_points} pts", tasks.len());
+    println!(
+        "Completed: {} tasks, {completed_points} pts ({completion_rate}%)",
+        completed.len()
+    );
+
+    let open_tasks: Vec<Task> = tasks
+        .into_iter()
+        .filter(|task| task.status != Status::Done)
+        .collect();
+
+    if !open_tasks.is_empty() {
+        println!("Not completed");
+        render_grouped_tasks(open_tasks, true, false);
+    }
+
+    print_current_blockers(db_conn);
+}
+
+fn print_current_blockers(db_conn: &Connection) {
+    print!("{}", current_blockers(db_conn));
+}
+
+// empty without blocked tasks
+fn current_blockers(db_conn: &Connection) -> String {
+    let blocked_tasks = match get_tasks(db_conn, &TaskFilter::new().status(Status::Blocked)) {
+        Ok(blocked_tasks) => blocked_tasks,
+        Err(error) => return format!("Error fetching blocked tasks = {error}\n"),
+    };
+
+    if blocked_tasks.is_empty() {
+        return String::new();
+    }
+
+    let mut blockers = String::from("Current blockers\n");
+
+    for task in blocked_tasks {
+        let _ = match task.block_reason {
+            Some(reason) => writeln!(
+                blockers,
+                "- {}: {reason}",
+                redact_description(&task.description)
+            ),
+            None => writeln!(blockers, "- {}", redact_description(&task.description)),
+        };
+    }
+
+    blockers
+}
+
+pub fn handle_cmd_open(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+
+    let link_no = arg_matches.get_one::<u8>("link").copied().unwrap_or(1);
+
+    let task = get_todays_task(db_conn, *task_index);
+
+    let links = get_task_links(db_conn, &task.id).expect("Failed to fetch task links");
+
+    let Some(link) = links.get(link_no as usize - 1) else {
+        println!(
+            "Task '{}' has {} link(s)",
+            redact_description(&task.description),
+            links.len()
+        );
+        for (index, link) in links.iter().enumerate() {
+            println!("{}  {link}", index + 1);
+        }
+        return;
+    };
+
+    if let Err(error) = open_with_default_app(link) {
+        println!("Error opening {link} = {error}");
+    }
+}
+
+pub fn handle_cmd_attach(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let task_index = arg_matches
+        .get_one::<u8>("TASK_INDEX")
+        .expect("Tasks Index is required");
+
+    let task = get_todays_task(db_conn, *task_index);
+
+    if let Some(file) = arg_matches.get_one::<PathBuf>("FILE") {
+        let path = match attachment_path(file, &task.id, arg_matches.get_flag("copy")) {
+            Ok(path) => path,
+            Err(error) => {
+                println!("Error attaching {} = {error}", file.display());
+                return;
+            }
+        };
+
+        let path = path.to_string_lossy();
+
+        match insert_task_attachment(db_conn, &task.id, &path) {
+            Ok(()) => println!(
+                "Attached {path} to '{}'",
+                redact_description(&task.description)
+            ),
+            Err(error) => println!("Error saving attachment = {:?}", error),
+        }
+
+        return;
+    }
+
+    let attachments =
+        get_task_attachments(db_conn, &task.id).expect("Failed to fetch task attachments");
+
+    if arg_matches.contains_id("open") {
+        let attachment_no = arg_matches.get_one::<u8>("open").copied().unwrap_or(1);
+
+        let Some(path) = attachments.get(attachment_no as usize - 1) else {
+            println!(
+                "Task '{}' has {} attachment(s)",
+                redact_description(&task.description),
+                attachments.len()
+            );
+            return;
+        };
+
+        if let Err(error) = open_with_default_app(path) {
+            println!("Error opening {path} = {error}");
+        }
+
+        return;
+    }
+
+    if attachments.is_empty() {
+        println!(
+            "Task '{}' has no attachments",
+            redact_description(&task.description)
+        );
+    }
+
+    for (index, path) in attachments.iter().enumerate() {
+        println!("{}. {path}", index + 1);
+    }
+}
+
+pub fn handle_cmd_note(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let Some((task_id, note)) = task_target(arg_matches, db_conn, "NOTE") else {
+        return;
+    };
+
+    let task = match get_task_by_id(db_conn, &task_id) {
+        Ok(task) => task,
+        Err(error) => {
+            println!("Error fetching task = {:?}", error);
+            return;
+        }
+    };
+
+    // kept with the task's annotations, which merge and show on the card too
+    if let Some(note) = note {
+        match insert_task_annotation(db_conn, &task.id, note) {
+            Ok(()) => println!("Noted on '{}'", redact_description(&task.description)),
+            Err(error) => {
+                println!("Error saving note = {:?}", error);
+                return;
+            }
+        }
+
+        if let Err(error) = insert_task_links(db_conn, &task.id, &extract_urls(note)) {
+            println!("Error saving task links = {:?}", error);
+        }
+
+        return;
+    }
+
+    let notes = match get_task_annotations(db_conn, &task.id) {
+        Ok(notes) => notes,
+        Err(error) => {
+            println!("Error fetching notes = {:?}", error);
+            return;
+        }
+    };
+
+    if notes.is_empty() {
+        println!(
+            "Task '{}' has no notes",
+            redact_description(&task.description)
+        );
+    }
+
+    for (created_at, note) in notes {
+        println!("{}  {note}", format_local_timestamp(&created_at));
+    }
+}
+
+// absolute path of the file, or of its copy under <data dir>/attachments/<task id>/
+fn attachment_path(file: &Path, task_id: &str, copy: bool) -> io::Result<PathBuf> {
+    let file = fs::canonicalize(file)?;
+
+    if !copy {
+        return Ok(file);
+    }
+
+    let mut copy_dir = get_data_dir();
+    copy_dir.push("attachments");
+    copy_dir.push(task_id);
+
+    fs::create_dir_all(&copy_dir)?;
+
+    let file_name = file
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a file"))?;
+
+    let copy_path = copy_dir.join(file_name);
+
+    fs::copy(&file, &copy_path)?;
+
+    Ok(copy_path)
+}
+
+pub fn handle_cmd_view(arg_matches: &ArgMatches, db_conn: &Connection) {
+    match arg_matches.subcommand() {
+        Some(("save", sub_matches)) => {
+            let name = sub_matches
+                .get_one::<String>("NAME")
+                .expect("View name is required");
+
+            let filter = sub_matches
+                .get_one::<String>("filter")
+                .expect("Filter is required");
+
+            match save_view(db_conn, name, filter) {
+                Ok(()) => println!("Saved view '{name}', run it with `view {name}`"),
+                Err(error) => println!("Error saving view = {:?}", error),
+            }
+        }
+        Some(("list", _)) => match get_views(db_conn) {
+            Ok(views) if views.is_empty() => println!("No views saved yet"),
+            Ok(views) => {
+                for (name, filter) in views {
+                    println!("{name}: {filter}");
+                }
+            }
+            Err(error) => println!("Error fetching views = {:?}", error),
+        },
+        Some(("delete", sub_matches)) => {
+            let name = sub_matches
+                .get_one::<String>("NAME")
+                .expect("View name is required");
+
+            match delete_view(db_conn, name) {
+                Ok(0) => println!("No view named '{name}'"),
+                Ok(_) => println!("Deleted view '{name}'"),
+                Err(error) => println!("Error deleting view = {:?}", error),
+            }
+        }
+        // any other subcommand is the name of a view to run
+        Some((name, _)) => {
+            let expression = match get_view_filter(db_conn, name) {
+                Ok(expression) => expression,
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    println!("No view named '{name}'");
+                    return;
+                }
+                Err(error) => {
+                    println!("Error fetching view = {:?}", error);
+                    return;
+                }
+            };
+
+            // views span all dates unless the expression bounds them
+            let filter = match parse_filter_expression(&expression) {
+                Ok(filter) => filter,
+                Err(error) => {
+                    println!("Invalid filter saved in view '{name}' = {error}");
+                    return;
+                }
+            };
+
+            match get_tasks(db_conn, &filter) {
+                Ok(tasks) => render_grouped_tasks(tasks, false, false),
+                Err(error) => println!("Error fetching tasks = {error}"),
+            }
+        }
+        None => unreachable!("subcommand is required"),
+    }
+}
+
+pub fn handle_cmd_recent(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let limit = arg_matches
+        .get_one::<u32>("number")
+        .expect("Number has a default");
+
+    let tasks = match get_recent_tasks(db_conn, *limit) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let mut recent_table = Table::new();
+
+    recent_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_width(100)
+        .set_header(vec![" Updated ", " Date ", " Description ", " Change "]);
+
+    for task in &tasks {
+        let change = match get_last_change(db_conn, &task.id) {
+            Ok(Some(change)) if change.field == "created" => "created".to_string(),
+            Ok(Some(change)) => format!(
+                "{}: {} -> {}",
+                change.field,
+                change.old_value.unwrap_or_default(),
+                change.new_value.unwrap_or_default()
+            ),
+            // tasks changed before history was kept
+            Ok(None) => String::new(),
+            Err(error) => format!("{error}"),
+        };
+
+        recent_table.add_row(vec![
+            format_local_timestamp(task.updated_at.as_deref().unwrap_or_default()),
+            task.date.clone(),
+            redact_description(&task.description),
+            change,
+        ]);
+    }
+
+    print_table(&recent_table);
+}
+
+pub fn handle_cmd_focus(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let start_date = iso_format_timestamp(&today());
+
+    let tasks = get_tasks_by_date(db_conn, &start_date, None).expect("Failed to fetch tasks");
+
+    let in_progress_task = tasks
+        .iter()
+        .position(|task| task.status == Status::InProgress);
+
+    if arg_matches.get_flag("prompt") {
+        if let Some(position) = in_progress_task {
+            println!("> {}", redact_description(&tasks[position].description));
+        }
+        return;
+    }
+
+    let task = match arg_matches.get_one::<u8>("TASK_INDEX") {
+        Some(task_index) => Some(get_todays_task(db_conn, *task_index)),
+        None => in_progress_task
+            .or_else(|| tasks.iter().position(|task| task.status == Status::Todo))
+            .and_then(|position| tasks.into_iter().nth(position)),
+    };
+
+    let Some(mut task) = task else {
+        println!("Nothing to focus on today");
+        return;
+    };
+
+    if task.status != Status::InProgress
+        && let Err(error) = update_task_status(db_conn, &mut task, Status::InProgress)
+    {
+        print_update_error(&task.id, error);
+        return;
+    }
+
+    render_focus_banner(&task);
+}
+
+pub fn handle_cmd_next(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let days = arg_matches
+        .get_one::<u32>("days")
+        .expect("Days has a default");
+
+    let today = today();
+
+    let start_date = iso_format_timestamp(&(today + Days::new(1)));
+    let end_date = iso_format_timestamp(&(today + Days::new(*days as u64)));
+
+    // the due column shows up as soon as one of them has a due date
+    let filter = TaskFilter::new()
+        .date_range(&start_date, &end_date)
+        .or_due_in_range()
+        .sort(SortKey::Date);
+
+    match get_tasks(db_conn, &filter) {
+        Ok(tasks) if tasks.is_empty() => println!("Nothing coming up in the next {days} days"),
+        Ok(tasks) => render_grouped_tasks(tasks, true, true),
+        Err(error) => println!("Error fetching tasks = {error}"),
+    }
+}
+
+pub fn handle_cmd_serve(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let address = arg_matches
+        .get_one::<String>("address")
+        .expect("Address has a default");
+
+    println!("Serving metrics on http://{address}/metrics");
+
+    if let Err(error) = serve_metrics(db_conn, address) {
+        println!("Error serving metrics = {error}");
+    }
+}
+
+// how long the daemon sleeps at most, so a suspended machine catches up soon after waking
+const DAEMON_MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/*
+ * a small cron: every job runs as its own daily-dose process when it's due, taking the
+ * database lock like any other command. a job missed while the machine was asleep runs once
+ * on waking. stays in the foreground, meant to be run by eg. systemd or launchd
+ * */
+pub fn handle_cmd_daemon(config: &Config, profile: Option<&str>) {
+    let jobs: Result<Vec<Job>, String> = config
+        .section("daemon")
+        .into_iter()
+        .map(|(name, line)| parse_job(name, line))
+        .collect();
+
+    let jobs = match jobs {
+        Ok(jobs) if jobs.is_empty() => {
+            println!("Error = no jobs under [daemon] in config, eg. standup = \"09:25 standup\"");
+            return;
+        }
+        Ok(jobs) => jobs,
+        Err(error) => {
+            println!("Error = {error}");
+            return;
+        }
+    };
+
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(error) => {
+            println!("Error finding the daily-dose executable = {error}");
+            return;
+        }
+    };
+
+    let mut next_runs: Vec<_> = jobs
+        .iter()
+        .map(|job| job.schedule.next_after(Local::now()))
+        .collect();
+
+    for (job, next_run) in jobs.iter().zip(&next_runs) {
+        println!(
+            "{}: `{}`, next at {}",
+            job.name,
+            job.args.join(" "),
+            next_run.format("%F %R")
+        );
+    }
+
+    loop {
+        for (job, next_run) in jobs.iter().zip(next_runs.iter_mut()) {
+            if *next_run > Local::now() {
+                continue;
+            }
+
+            run_daemon_job(&exe, profile, job);
+            *next_run = job.schedule.next_after(Local::now());
+        }
+
+        let earliest = next_runs.iter().min().copied().unwrap_or_else(Local::now);
+        let until_earliest = (earliest - Local::now()).to_std().unwrap_or_default();
+
+        thread::sleep(until_earliest.min(DAEMON_MAX_SLEEP));
+    }
+}
+
+fn run_daemon_job(exe: &Path, profile: Option<&str>, job: &Job) {
+    println!("[{}] {}", Local::now().format("%F %R"), job.name);
+
+    let mut command = process::Command::new(exe);
+    if let Some(profile) = profile {
+        command.args(["--profile", profile]);
+    }
+
+    match command.args(&job.args).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => println!("Error = {} exited with {status}", job.name),
+        Err(error) => println!("Error running {} = {error}", job.name),
+    }
+}
+
+// done, total and no. of days with tasks of one month
+#[derive(Debug, Default, Clone, Copy)]
+struct MonthStats {
+    done: usize,
+    total: usize,
+    active_days: usize,
+}
+
+impl MonthStats {
+    fn completion_rate(&self) -> Option<usize> {
+        (self.total > 0).then(|| self.done * 100 / self.total)
+    }
+
+    fn load(&self) -> Option<f64> {
+        (self.active_days > 0).then(|| self.total as f64 / self.active_days as f64)
+    }
+
+    // the fields shared by a month and a year's total in the json output
+    fn json_fields(&self) -> Vec<(&'static str, Json)> {
+        vec![
+            ("done", self.done.into()),
+            ("total", self.total.into()),
+            ("completion_rate", self.completion_rate().into()),
+            ("load", self.load().into()),
+        ]
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum StatsFormat {
+    Text,
+    // see json.rs for the schema
+    Json,
+}
+
+/*
+ * per month stats of this year, or of two years side by side with --compare
+ * load is the average no. of tasks on days that had any
+ * */
+pub fn handle_cmd_stats(arg_matches: &ArgMatches, db_conn: &Connection) {
+    let years: Vec<i32> = match arg_matches.get_many::<i32>("compare") {
+        Some(years) => years.copied().collect(),
+        None => vec![today().year()],
+    };
+
+    let mut year_stats: Vec<[MonthStats; 12]> = vec![];
+
+    for year in &years {
+        let Some((first_date, last_date)) = year_range(*year) else {
+            println!("Invalid year {year}");
+            return;
+        };
+
+        let day_counts = match get_day_counts(
+            db_conn,
+            &iso_format_timestamp(&first_date),
+            &iso_format_timestamp(&last_date),
+        ) {
+            Ok(day_counts) => day_counts,
+            Err(error) => {
+                println!("Error fetching tasks = {error}");
+                return;
+            }
+        };
+
+        let mut months = [MonthStats::default(); 12];
+
+        for (date, total, done) in day_counts {
+            let Ok(date) = NaiveDate::parse_from_str(&date, "%F") else {
+                continue;
+            };
+
+            let month = &mut months[date.month0() as usize];
+            month.total += total as usize;
+            month.done += done as usize;
+            month.active_days += 1;
+        }
+
+        year_stats.push(months);
+    }
+
+    let year_total = |months: &[MonthStats; 12]| {
+        months
+            .iter()
+            .fold(MonthStats::default(), |total, month| MonthStats {
+                done: total.done + month.done,
+                total: total.total + month.total,
+                active_days: total.active_days + month.active_days,
+            })
+    };
+
+    if matches!(
+        arg_matches.get_one::<StatsFormat>("format"),
+        Some(StatsFormat::Json)
+    ) {
+        let years = years
+            .iter()
+            .zip(&year_stats)
+            .map(|(year, months)| {
+                let month_objects = months
+                    .iter()
+                    .zip(1u32..)
+                    .map(|(stats, month)| {
+                        let mut fields = vec![("month", month.into())];
+                        fields.extend(stats.json_fields());
+                        Json::Object(fields)
+                    })
+                    .collect();
+
+                Json::Object(vec![
+                    ("year", (*year).into()),
+                    ("months", Json::Array(month_objects)),
+                    ("total", Json::Object(year_total(months).json_fields())),
+                ])
+            })
+            .collect();
+
+        print!(
+            "{}",
+            render_report_json("stats", vec![("years", Json::Array(years))])
+        );
+        return;
+    }
+
+    let mut stats_table = Table::new();
+
+    let mut header = vec![" Month ".to_string()];
+    for year in &years {
+        header.extend([
+            format!(" Done {year} "),
+            format!(" Rate {year} "),
+            format!(" Load {year} "),
+        ]);
+    }
+
+    stats_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_header(header);
+
+    let stat_cells = |stats: &MonthStats| {
+        let rate = match stats.completion_rate() {
+            Some(rate) => format!("{rate}%"),
+            None => "-".to_string(),
+        };
+
+        let load = match stats.load() {
+            Some(load) => format!("{load:.1}"),
+            None => "-".to_string(),
+        };
+
+        [stats.done.to_string(), rate, load]
+    };
+
+    for month in 0..12 {
+        let mut row = vec![NaiveDate::from_ymd_opt(2000, month as u32 + 1, 1)
+            .expect("Every month has a first day")
+            .format("%B")
+            .to_string()];
+
+        for months in &year_stats {
+            row.extend(stat_cells(&months[month]));
+        }
+
+        stats_table.add_row(row);
+    }
+
+    let mut total_row = vec!["Total".to_string()];
+    for months in &year_stats {
+        total_row.extend(stat_cells(&year_total(months)));
+    }
+
+    stats_table.add_row(total_row);
+
+    print_table(&stats_table);
+}
+
+// lookbacks of onthisday without --ago or config
+const DEFAULT_LOOKBACKS: [Lookback; 2] = [Lookback::Months(1), Lookback::Years(1)];
+
+/*
+ * lookbacks come from --ago, else from config as a comma separated list:
+ *
+ * onthisday = "1w, 1m, 1y"
+ * */
+pub fn handle_cmd_onthisday(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let lookbacks: Vec<Lookback> = match arg_matches.get_many::<Lookback>("ago") {
+        Some(lookbacks) => lookbacks.copied().collect(),
+        None => match config.get("onthisday") {
+            Some(lookbacks) => lookbacks
+                .split(',')
+                .filter_map(|lookback| match lookback.parse() {
+                    Ok(lookback) => Some(lookback),
+                    Err(error) => {
+                        println!("Ignoring {error} in config");
+                        None
+                    }
+                })
+                .collect(),
+            None => DEFAULT_LOOKBACKS.to_vec(),
+        },
+    };
+
+    let today = today();
+
+    for lookback in lookbacks {
+        let Some(date) = lookback.date_before(today) else {
+            continue;
+        };
+
+        let date = iso_format_timestamp(&date);
+
+        match get_tasks_by_date(db_conn, &date, None) {
+            Ok(tasks) if tasks.is_empty() => {
+                println!("{} ({date}): no tasks", lookback.describe())
+            }
+            Ok(tasks) => {
+                println!("{} ({date})", lookback.describe());
+                render_tasks_table(&vec![(&date, &tasks)], false);
+            }
+            Err(error) => println!("Error fetching tasks = {error}"),
+        }
+    }
+}
+
+fn standup_args() -> Vec<Arg> {
+    #[allow(unused_mut)]
+    let mut args = vec![
+        arg!(--"since-last" "Report what got done since the last standup instead of since yesterday")
+            .action(clap::ArgAction::SetTrue),
+    ];
+
+    // only in builds with the summarize feature, see summarize.rs
+    #[cfg(feature = "summarize")]
+    args.push(arg!(--summarize "Condense the standup into a few sentences with the model set up under [summarize] in config"));
+
+    args
+}
+
+const LAST_STANDUP_KEY: &str = "last_standup_at";
+
+#[cfg_attr(not(feature = "summarize"), allow(unused_variables))]
+pub fn handle_cmd_standup(arg_matches: &ArgMatches, db_conn: &Connection, config: &Config) {
+    let today = today();
+
+    let yesterday_start = day_start_timestamp(&(today - Days::new(1)));
+
+    // the first standup has nothing to go back to, it covers yesterday as usual
+    let since = if arg_matches.get_flag("since-last") {
+        match get_app_state(db_conn, LAST_STANDUP_KEY) {
+            Ok(last_standup) => last_standup.unwrap_or(yesterday_start),
+            Err(error) => {
+                println!("Error fetching last standup = {:?}", error);
+                return;
+            }
+        }
+    } else {
+        yesterday_start
+    };
+
+    let completed = match get_tasks_completed_since(db_conn, &since) {
+        Ok(completed) => completed,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let planned = match get_tasks_by_date(db_conn, &iso_format_timestamp(&today), None) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            println!("Error fetching tasks = {error}");
+            return;
+        }
+    };
+
+    let mut standup = format!("Done since {}\n", format_local_timestamp(&since));
+
+    if completed.is_empty() {
+        standup.push_str("- nothing\n");
+    }
+
+    for task in &completed {
+        let _ = writeln!(standup, "- {}", redact_description(&task.description));
+    }
+
+    standup.push_str("Today\n");
+
+    let planned: Vec<&Task> = planned
+        .iter()
+        .filter(|task| task.status != Status::Done)
+        .collect();
+
+    if planned.is_empty() {
+        standup.push_str("- nothing planned yet\n");
+    }
+
+    for task in planned {
+        let _ = writeln!(standup, "- {}", redact_description(&task.description));
+    }
+
+    standup.push_str(&current_blockers(db_conn));
+
+    // the full standup is printed instead when summarizing fails
+    #[cfg(feature = "summarize")]
+    let standup = match arg_matches.get_flag("summarize") {
+        true => match summarize_standup(config, &standup) {
+            Ok(summary) => format!("{}\n", summary.join("\n")),
+            Err(error) => {
+                format!("Error summarizing = {error}, the full standup instead\n{standup}")
+            }
+        },
+        false => standup,
+    };
+
+    print!("{standup}");
+
+    if let Err(error) = set_app_state(db_conn, LAST_STANDUP_KEY, &now_timestamp()) {
+        println!("Error saving standup time = {:?}", error);
+    }
 }
@@ -0,0 +1,166 @@
+use std::{fmt::Write, path::PathBuf};
+
+use clap::Command;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Shell {
+    Bash,
+    Fish,
+}
+
+// where the shell picks up completions by itself, no rc file has to be touched
+pub fn get_completions_path(shell: Shell, bin_name: &str) -> Option<PathBuf> {
+    match shell {
+        Shell::Bash => dirs::data_dir().map(|data_dir| {
+            data_dir
+                .join("bash-completion")
+                .join("completions")
+                .join(bin_name)
+        }),
+        Shell::Fish => dirs::config_dir().map(|config_dir| {
+            config_dir
+                .join("fish")
+                .join("completions")
+                .join(format!("{bin_name}.fish"))
+        }),
+    }
+}
+
+/*
+ * completion script generated from the command definitions, so it can't go stale:
+ * subcommands (and theirs, eg. template save) and the flags of each, global flags included
+ * */
+pub fn render_completions(shell: Shell, cmd: &Command, bin_name: &str) -> String {
+    match shell {
+        Shell::Bash => render_bash(cmd, bin_name),
+        Shell::Fish => render_fish(cmd, bin_name),
+    }
+}
+
+// --long and -s flags of a command, positional arguments have neither
+fn flags_of(cmd: &Command) -> Vec<String> {
+    let mut flags = vec![];
+
+    for arg in cmd.get_arguments() {
+        if let Some(long) = arg.get_long() {
+            flags.push(format!("--{long}"));
+        }
+        if let Some(short) = arg.get_short() {
+            flags.push(format!("-{short}"));
+        }
+    }
+
+    flags
+}
+
+fn words_of(cmd: &Command) -> String {
+    let mut words: Vec<String> = cmd
+        .get_subcommands()
+        .map(|sub_cmd| sub_cmd.get_name().to_string())
+        .collect();
+    words.extend(flags_of(cmd));
+
+    words.join(" ")
+}
+
+fn render_bash(cmd: &Command, bin_name: &str) -> String {
+    let function = format!("_{}", bin_name.replace(['-', '.'], "_"));
+
+    let mut script = String::new();
+
+    let _ = writeln!(script, "{function}() {{");
+    let _ = writeln!(script, "    local cur=${{COMP_WORDS[COMP_CWORD]}} cmd=\"\"");
+    let _ = writeln!(
+        script,
+        "    for word in \"${{COMP_WORDS[@]:1:COMP_CWORD-1}}\"; do"
+    );
+    let _ = writeln!(
+        script,
+        "        case $word in -*) ;; *) cmd=\"$cmd $word\" ;; esac"
+    );
+    let _ = writeln!(script, "    done");
+    let _ = writeln!(script, "    case \"${{cmd# }}\" in");
+    let _ = writeln!(
+        script,
+        "        \"\") COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+        words_of(cmd)
+    );
+
+    for sub_cmd in cmd.get_subcommands() {
+        for nested_cmd in sub_cmd.get_subcommands() {
+            let _ = writeln!(
+                script,
+                "        \"{} {}\"*) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+                sub_cmd.get_name(),
+                nested_cmd.get_name(),
+                words_of(nested_cmd)
+            );
+        }
+
+        let _ = writeln!(
+            script,
+            "        {}*) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+            sub_cmd.get_name(),
+            words_of(sub_cmd)
+        );
+    }
+
+    let _ = writeln!(script, "    esac");
+    let _ = writeln!(script, "}}");
+    let _ = writeln!(script, "complete -F {function} {bin_name}");
+
+    script
+}
+
+fn render_fish(cmd: &Command, bin_name: &str) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"));
+    let about = |cmd: &Command| {
+        cmd.get_about()
+            .map(|about| about.to_string())
+            .unwrap_or_default()
+    };
+
+    let mut script = String::new();
+
+    for sub_cmd in cmd.get_subcommands() {
+        let _ = writeln!(
+            script,
+            "complete -c {bin_name} -f -n __fish_use_subcommand -a {} -d {}",
+            sub_cmd.get_name(),
+            quote(&about(sub_cmd))
+        );
+
+        for nested_cmd in sub_cmd.get_subcommands() {
+            let _ = writeln!(
+                script,
+                "complete -c {bin_name} -f -n '__fish_seen_subcommand_from {}' -a {} -d {}",
+                sub_cmd.get_name(),
+                nested_cmd.get_name(),
+                quote(&about(nested_cmd))
+            );
+        }
+
+        for arg in sub_cmd.get_arguments() {
+            let Some(long) = arg.get_long() else {
+                continue;
+            };
+
+            let help = arg
+                .get_help()
+                .map(|help| help.to_string())
+                .unwrap_or_default();
+
+            let _ = writeln!(
+                script,
+                "complete -c {bin_name} -n '__fish_seen_subcommand_from {}' -l {long}{} -d {}",
+                sub_cmd.get_name(),
+                arg.get_short()
+                    .map(|short| format!(" -s {short}"))
+                    .unwrap_or_default(),
+                quote(&help)
+            );
+        }
+    }
+
+    script
+}
@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/*
+ * settings are read from <config dir>/daily-dose/config.toml,
+ * or profiles/<name>.toml next to it for --profile <name>
+ * only the small subset of toml we need is understood:
+ *
+ * # comment
+ * daily_template = "standup"
+ * [colors]
+ * incident = "red"
+ *
+ * keys inside a section are stored as "section.key"
+ * */
+#[derive(Debug, Default)]
+pub struct Config {
+    values: HashMap<String, String>,
+}
+
+impl Config {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|value| value.as_str())
+    }
+
+    // keys (without the section) and values of a [section], in key order
+    pub fn section(&self, name: &str) -> Vec<(&str, &str)> {
+        let prefix = format!("{name}.");
+
+        let mut entries: Vec<(&str, &str)> = self
+            .values
+            .iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix(&prefix)?, value.as_str())))
+            .collect();
+        entries.sort();
+
+        entries
+    }
+
+    // None when unset, an invalid value is reported and treated as unset
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Option<T> {
+        match self.get(key)?.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                println!("Ignoring invalid {key} in config");
+                None
+            }
+        }
+    }
+}
+
+pub fn get_config_path(profile: Option<&str>) -> PathBuf {
+    let mut config_dir = dirs::config_dir().expect("Could not find config directory in OS");
+
+    config_dir.push("daily-dose");
+
+    match profile {
+        Some(profile) => {
+            config_dir.push("profiles");
+            config_dir.push(format!("{profile}.toml"));
+        }
+        None => config_dir.push("config.toml"),
+    }
+
+    config_dir
+}
+
+// the data directory used last time, so the database can be found after storage changes
+pub fn get_last_data_dir_path(profile: Option<&str>) -> PathBuf {
+    get_config_path(profile).with_extension("data_dir")
+}
+
+pub fn load_config(profile: Option<&str>) -> Config {
+    // no config file is fine, everything has a default
+    match fs::read_to_string(get_config_path(profile)) {
+        Ok(content) => parse_config(&content),
+        Err(_) => Config::default(),
+    }
+}
+
+// written by init, every setting commented out at its default
+const STARTER_CONFIG: &str = "# daily-dose settings, uncomment to change
+
+# window list shows without --month or --year, month or a no. of days like 7d
+# list_range = \"month\"
+
+# until when tasks still go to the day before, eg. \"03:00\" for night owls
+# day_boundary = \"00:00\"
+
+# window of the todo, doing and blocked shortcuts, eg. 7d or 2w
+# shortcut_range = \"14d\"
+
+# where the database is kept: xdg, home (~/.daily-dose) or custom (data_dir)
+# changing it moves the existing database on the next run
+# storage = \"xdg\"
+
+# tasks added to every new day, from `template save`
+# daily_template = \"standup\"
+
+# ascii, emoji or none
+# status_style = \"ascii\"
+
+# descriptions longer than this are cut in tables, --full shows them whole
+# description_max_length = 60
+
+# ask before storing descriptions that look like tokens or keys
+# secret_check = true
+
+# ask before adding a task that looks like one still open, eg. carried over from earlier
+# duplicate_check = false
+
+# print the day's `journal` entry above its tasks in show
+# show_journal = false
+
+# points that fit in a day, add and show warn when a date's tasks add up to more
+# daily_capacity = 6
+
+# how long ago done tasks are before `archive` moves them to archive.db, eg. 6m or 1y
+# archive_after = \"90d\"
+
+# count which commands get run, see `usage`. the counts stay in the database on this machine
+# usage_stats = false
+
+# encrypt descriptions and notes, run `key generate` first
+# encrypt_descriptions = true
+
+# rows of the task table styled by a regex on the description, the first match wins
+# [highlight]
+# \"(?i)prod|incident\" = \"bold red\"
+# \"#review\" = \"black on yellow\"
+
+# description of tasks with the tag or project styled like [highlight], the first match wins
+# [colors]
+# incident = \"bold red\"
+# acme = \"cyan\"
+
+# tasks `sync` keeps in the remote, eg. 90d, 12w or 3m back
+# [sync]
+# since = \"90d\"
+
+# task list `caldav` syncs with, the password goes to the keyring on the first sync
+# [caldav]
+# url = \"https://cloud.example.com/remote.php/dav/calendars/me/tasks/\"
+# username = \"me\"
+# tasks not synced before go out from this far back on
+# since = \"14d\"
+
+# desktop oauth client for `import --from gtasks`, from the google cloud console
+# [gtasks]
+# client_id = \"....apps.googleusercontent.com\"
+# client_secret = \"...\"
+
+# `import --from gitlab`, the token goes to the keyring on the first import
+# [gitlab]
+# url = \"https://gitlab.com\"
+# comment on the issue when its task is marked done
+# comment_on_done = false
+
+# model for `standup --summarize`, in builds with the summarize feature
+# [summarize]
+# endpoint = \"http://localhost:11434/v1/chat/completions\"
+# model = \"llama3.2\"
+
+# jobs `daemon` runs, name = \"<when> <command>\", when is HH:MM, hourly, nightly or every 15m
+# [daemon]
+# standup = \"09:25 standup\"
+# backup = \"nightly backup --rotate\"
+
+# [backup]
+# daily = 7
+# weekly = 4
+# monthly = 12
+";
+
+// a fresh config file, data_dir is set when the database doesn't live in the default place
+pub fn write_starter_config(path: &Path, data_dir: Option<&Path>) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let content = match data_dir {
+        Some(data_dir) => format!("{}\n{STARTER_CONFIG}", data_dir_line(data_dir)),
+        None => STARTER_CONFIG.to_string(),
+    };
+
+    fs::write(path, content)
+}
+
+// top level keys have to come before the first [section], so the line goes first
+pub fn prepend_data_dir(path: &Path, data_dir: &Path) -> io::Result<()> {
+    let content = fs::read_to_string(path)?;
+
+    fs::write(path, format!("{}\n{content}", data_dir_line(data_dir)))
+}
+
+fn data_dir_line(data_dir: &Path) -> String {
+    let data_dir = data_dir
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+
+    format!("data_dir = \"{data_dir}\"")
+}
+
+fn parse_config(content: &str) -> Config {
+    let mut values = HashMap::new();
+    let mut section = String::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            section = name.trim().to_string();
+            continue;
+        }
+
+        match line.split_once('=') {
+            Some((key, value)) => {
+                let key = unquote(key.trim());
+                let key = if section.is_empty() {
+                    key
+                } else {
+                    format!("{section}.{key}")
+                };
+
+                values.insert(key, parse_value(value.trim()));
+            }
+            None => println!("Ignoring invalid config line {} = {line}", line_no + 1),
+        }
+    }
+
+    Config { values }
+}
+
+fn parse_value(value: &str) -> String {
+    if value.starts_with('"') {
+        return unquote(value);
+    }
+
+    // trailing comments are only stripped from bare values, # is fine inside quotes
+    match value.split_once('#') {
+        Some((value, _)) => value.trim().to_string(),
+        None => value.to_string(),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.rsplit_once('"'))
+    {
+        Some((inner, _)) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
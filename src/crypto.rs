@@ -0,0 +1,254 @@
+use std::{
+    env,
+    io::{self, Write},
+    process::{self, Stdio},
+};
+
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit};
+use sha2::{Digest, Sha256};
+
+use crate::utils::prompt_line;
+
+/*
+ * ChaCha20-Poly1305 (RFC 8439) for sealing descriptions and notes before they are stored
+ * sealed values are text, so they fit the existing TEXT columns:
+ *
+ * enc:v1:<hex of 12 byte nonce><hex of ciphertext><hex of 16 byte tag>
+ *
+ * the key lives in the OS keyring (see load_key), never in the database or config
+ * */
+pub const SEALED_PREFIX: &str = "enc:v1:";
+
+pub type Key = [u8; 32];
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+pub fn seal(key: &Key, plaintext: &str) -> String {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::fill(&mut nonce).expect("OS random source is available");
+
+    // the tag comes right after the ciphertext, there's no additional data
+    let ciphertext = ChaCha20Poly1305::new(&(*key).into())
+        .encrypt(&nonce.into(), plaintext.as_bytes())
+        .expect("Descriptions are far below the size limit");
+
+    format!("{SEALED_PREFIX}{}{}", to_hex(&nonce), to_hex(&ciphertext))
+}
+
+// None when the value was sealed with another key or got mangled
+pub fn open(key: &Key, sealed: &str) -> Option<String> {
+    let bytes = from_hex(sealed.strip_prefix(SEALED_PREFIX)?)?;
+
+    if bytes.len() < NONCE_LEN + TAG_LEN {
+        return None;
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = nonce.try_into().ok()?;
+
+    let plaintext = ChaCha20Poly1305::new(&(*key).into())
+        .decrypt(&nonce.into(), ciphertext)
+        .ok()?;
+
+    String::from_utf8(plaintext).ok()
+}
+
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+/*
+ * the key is stored hex encoded in the OS keyring under service daily-dose, account
+ * encryption-key. secret-tool (libsecret) is used on linux, security on macos.
+ * DAILY_DOSE_KEY overrides the keyring, for machines without one
+ * */
+const KEYRING_SERVICE: &str = "daily-dose";
+const KEYRING_ACCOUNT: &str = "encryption-key";
+const KEY_ENV: &str = "DAILY_DOSE_KEY";
+
+pub fn load_key() -> Result<Key, String> {
+    let hex_key = match env::var(KEY_ENV) {
+        Ok(hex_key) => hex_key,
+        Err(_) => read_keyring(KEYRING_ACCOUNT)
+            .map_err(|error| format!("{error}, set {KEY_ENV} instead"))?
+            .ok_or("No encryption key in the keyring, run `key generate` first")?,
+    };
+
+    from_hex(hex_key.trim())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "Encryption key is not 64 hex characters".to_string())
+}
+
+// stores a fresh random key in the keyring, refuses to replace an existing one
+pub fn generate_key() -> Result<(), String> {
+    if read_keyring(KEYRING_ACCOUNT).is_ok_and(|key| key.is_some()) {
+        return Err(
+            "An encryption key is in the keyring already, replacing it would make sealed tasks unreadable"
+                .to_string(),
+        );
+    }
+
+    let mut key: Key = [0; 32];
+    getrandom::fill(&mut key).map_err(|error| error.to_string())?;
+
+    write_keyring(KEYRING_ACCOUNT, "daily-dose encryption key", &to_hex(&key))
+}
+
+// other secrets (eg. the google tasks token) go under their own account of the same service
+pub fn read_keyring(account: &str) -> Result<Option<String>, String> {
+    let output = if cfg!(target_os = "macos") {
+        process::Command::new("security")
+            .args(["find-generic-password", "-s", KEYRING_SERVICE])
+            .args(["-a", account, "-w"])
+            .output()
+    } else if cfg!(target_os = "linux") {
+        process::Command::new("secret-tool")
+            .args(["lookup", "service", KEYRING_SERVICE, "account", account])
+            .output()
+    } else {
+        return Err("No keyring support on this OS".to_string());
+    };
+
+    match output {
+        Ok(output) if output.status.success() && !output.stdout.is_empty() => Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        )),
+        Ok(_) => Ok(None),
+        Err(error) => Err(format!("Could not read the keyring = {error}")),
+    }
+}
+
+pub fn write_keyring(account: &str, label: &str, secret: &str) -> Result<(), String> {
+    let status = if cfg!(target_os = "macos") {
+        process::Command::new("security")
+            .args(["add-generic-password", "-U", "-s", KEYRING_SERVICE])
+            .args(["-a", account, "-w", secret])
+            .status()
+    } else if cfg!(target_os = "linux") {
+        // secret-tool reads the secret from stdin, so it doesn't show up in the process list
+        process::Command::new("secret-tool")
+            .args(["store", &format!("--label={label}")])
+            .args(["service", KEYRING_SERVICE, "account", account])
+            .stdin(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                child
+                    .stdin
+                    .take()
+                    .expect("stdin is piped")
+                    .write_all(secret.as_bytes())?;
+                child.wait()
+            })
+    } else {
+        Err(io::Error::other("no keyring support on this OS"))
+    };
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("Storing {label} in the keyring failed ({status})")),
+        Err(error) => Err(format!("Could not write the keyring = {error}")),
+    }
+}
+
+/*
+ * a secret of another service (eg. a gitlab token): from the environment variable when set,
+ * else from the keyring, else asked for once and kept in the keyring
+ * */
+pub fn get_secret(
+    account: &str,
+    env_var: &str,
+    prompt: &str,
+    label: &str,
+) -> Result<String, String> {
+    if let Ok(secret) = env::var(env_var)
+        && !secret.is_empty()
+    {
+        return Ok(secret);
+    }
+
+    if let Some(secret) = read_keyring(account)? {
+        return Ok(secret);
+    }
+
+    let secret = match prompt_line(prompt) {
+        Ok(Some(secret)) if !secret.is_empty() => secret,
+        Ok(_) => return Err(format!("no {label}, set {env_var} or run in a terminal")),
+        Err(error) => return Err(error.to_string()),
+    };
+
+    write_keyring(account, &format!("daily-dose {label}"), &secret)?;
+
+    Ok(secret)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
+
+// sha-256 as lowercase hex, for the release checksums self-update checks
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key() -> Key {
+        from_hex("8dbd6bea876d36f2f5eefa79887097e8a4613712454159e7858a9dff2afa979f")
+            .and_then(|key| key.try_into().ok())
+            .expect("32 byte hex key")
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let sealed = seal(&test_key(), "deploy ü 🚀");
+
+        assert!(is_sealed(&sealed));
+        assert_eq!(open(&test_key(), &sealed).as_deref(), Some("deploy ü 🚀"));
+    }
+
+    // sealed by an earlier build, whatever stores values has to keep opening them
+    #[test]
+    fn opens_values_sealed_before() {
+        let sealed = "enc:v1:9a6f9afad9af6caf80397fa08bd398a8ad09069be1037c6a6cd610e53dcfde6098da623c72d18fa56f";
+
+        assert_eq!(open(&test_key(), sealed).as_deref(), Some("a sealed note"));
+    }
+
+    #[test]
+    fn open_rejects_a_tampered_value_or_another_key() {
+        let sealed = seal(&test_key(), "deploy");
+
+        let mut tampered = sealed.clone();
+        let last = tampered.pop().expect("sealed values aren't empty");
+        tampered.push(if last == '0' { '1' } else { '0' });
+
+        assert_eq!(open(&test_key(), &tampered), None);
+        assert_eq!(open(&[7; 32], &sealed), None);
+    }
+
+    #[test]
+    fn sha256_matches_fips_180_4_examples() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -1,30 +1,557 @@
-use std::fs;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
 
-use rusqlite::{named_params, Connection, Error};
+use chrono::SecondsFormat;
+use regex::Regex;
+use rusqlite::{
+    backup::Progress, functions::FunctionFlags, named_params, params_from_iter, types::Value,
+    Connection, Error, OpenFlags, OptionalExtension, Row, ToSql, Transaction, TransactionBehavior,
+    MAIN_DB,
+};
+use strum::{Display, EnumString};
 use ulid::Ulid;
 
-use crate::{Status, Task};
+use crate::{
+    crypto::{is_sealed, open, seal, Key},
+    utils::{extract_tags, now_timestamp, task_created_at},
+    Goal, Priority, Sprint, Status, Task, TaskChange,
+};
 
-pub fn get_db_path() -> String {
-    let mut data_dir = dirs::data_dir().expect("Could not find data directory in OS");
+/*
+ * schema changes on top of the initial tasks table
+ * index + 1 of the last applied migration is kept in sqlite's user_version pragma
+ * so every statement here runs exactly once per database, never edit or reorder them
+ * */
+const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE tasks ADD COLUMN updated_at TEXT",
+    "CREATE TABLE template_tasks (
+        template TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        description TEXT NOT NULL,
+        PRIMARY KEY (template, position)
+    )",
+    "CREATE TABLE daily_template_dates (date TEXT PRIMARY KEY)",
+    "ALTER TABLE tasks ADD COLUMN deferred_until TEXT",
+    "ALTER TABLE tasks ADD COLUMN points INTEGER",
+    "CREATE TABLE sprints (
+        name TEXT PRIMARY KEY,
+        start_date TEXT NOT NULL,
+        end_date TEXT NOT NULL
+    )",
+    "CREATE TABLE task_links (
+        task_id TEXT NOT NULL,
+        url TEXT NOT NULL,
+        PRIMARY KEY (task_id, url)
+    )",
+    "CREATE TABLE task_attachments (
+        task_id TEXT NOT NULL,
+        path TEXT NOT NULL,
+        PRIMARY KEY (task_id, path)
+    )",
+    "CREATE TABLE views (name TEXT PRIMARY KEY, filter TEXT NOT NULL)",
+    "CREATE TABLE task_history (
+        task_id TEXT NOT NULL,
+        changed_at TEXT NOT NULL,
+        field TEXT NOT NULL,
+        old_value TEXT,
+        new_value TEXT
+    )",
+    "ALTER TABLE tasks ADD COLUMN completed_at TEXT",
+    "CREATE TABLE task_annotations (
+        task_id TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        note TEXT NOT NULL
+    )",
+    "ALTER TABLE tasks ADD COLUMN block_reason TEXT",
+    "CREATE TABLE app_state (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+    "ALTER TABLE tasks ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+    UPDATE tasks SET position = (
+        SELECT COUNT(*) FROM tasks AS other
+            WHERE COALESCE(other.deferred_until, other.date) = COALESCE(tasks.deferred_until, tasks.date)
+                AND other.id <= tasks.id
+    )",
+    "CREATE TABLE imported_tasks (
+        import_id TEXT NOT NULL,
+        task_id TEXT NOT NULL,
+        PRIMARY KEY (import_id, task_id)
+    )",
+    "CREATE TABLE deleted_tasks (task_id TEXT PRIMARY KEY, deleted_at TEXT NOT NULL)",
+    "CREATE TABLE merge_conflicts (
+        task_id TEXT NOT NULL,
+        merged_at TEXT NOT NULL,
+        lost_from TEXT NOT NULL,
+        deleted INTEGER NOT NULL,
+        description TEXT,
+        status TEXT,
+        date TEXT,
+        updated_at TEXT,
+        deferred_until TEXT,
+        points INTEGER,
+        block_reason TEXT
+    )",
+    "CREATE TABLE external_tasks (
+        source TEXT NOT NULL,
+        external_id TEXT NOT NULL,
+        task_id TEXT NOT NULL,
+        PRIMARY KEY (source, external_id)
+    )",
+    "ALTER TABLE external_tasks ADD COLUMN etag TEXT",
+    "ALTER TABLE external_tasks ADD COLUMN synced_at TEXT",
+    "CREATE TABLE goals (name TEXT PRIMARY KEY, quarter TEXT NOT NULL)",
+    "CREATE TABLE task_goals (task_id TEXT PRIMARY KEY, goal TEXT NOT NULL)",
+    "CREATE TABLE journal (date TEXT PRIMARY KEY, note TEXT NOT NULL, updated_at TEXT NOT NULL)",
+    "CREATE TABLE usage (command TEXT PRIMARY KEY, runs INTEGER NOT NULL, last_used TEXT NOT NULL)",
+    // see get_day_counts
+    "CREATE TABLE day_counts (date TEXT PRIMARY KEY, total INTEGER NOT NULL, done INTEGER NOT NULL);
+    INSERT INTO day_counts (date, total, done)
+        SELECT COALESCE(deferred_until, date), COUNT(*), SUM(status = 'done') FROM tasks GROUP BY 1;
+    CREATE TRIGGER day_counts_insert AFTER INSERT ON tasks BEGIN
+        INSERT INTO day_counts (date, total, done)
+            VALUES (COALESCE(NEW.deferred_until, NEW.date), 1, NEW.status = 'done')
+            ON CONFLICT (date) DO UPDATE SET total = total + 1, done = done + excluded.done;
+    END;
+    CREATE TRIGGER day_counts_delete AFTER DELETE ON tasks BEGIN
+        UPDATE day_counts SET total = total - 1, done = done - (OLD.status = 'done')
+            WHERE date = COALESCE(OLD.deferred_until, OLD.date);
+        DELETE FROM day_counts WHERE date = COALESCE(OLD.deferred_until, OLD.date) AND total = 0;
+    END;
+    CREATE TRIGGER day_counts_update AFTER UPDATE OF status, date, deferred_until ON tasks BEGIN
+        UPDATE day_counts SET total = total - 1, done = done - (OLD.status = 'done')
+            WHERE date = COALESCE(OLD.deferred_until, OLD.date);
+        DELETE FROM day_counts WHERE date = COALESCE(OLD.deferred_until, OLD.date) AND total = 0;
+        INSERT INTO day_counts (date, total, done)
+            VALUES (COALESCE(NEW.deferred_until, NEW.date), 1, NEW.status = 'done')
+            ON CONFLICT (date) DO UPDATE SET total = total + 1, done = done + excluded.done;
+    END",
+    // see archive_tasks
+    "CREATE TABLE archived_tasks (task_id TEXT PRIMARY KEY, archived_at TEXT NOT NULL)",
+    // see resolve_task_id
+    "ALTER TABLE tasks ADD COLUMN short_id TEXT;
+    UPDATE tasks SET short_id = numbered.short_id FROM (
+        SELECT id, substr(date, 6, 2) || substr(date, 9, 2) || '-' || ROW_NUMBER() OVER (
+            PARTITION BY substr(date, 6, 2) || substr(date, 9, 2) ORDER BY date, position, id
+        ) AS short_id FROM tasks
+    ) AS numbered WHERE numbered.id = tasks.id;
+    CREATE UNIQUE INDEX tasks_short_id ON tasks (short_id);
+    CREATE TABLE short_id_counters (day TEXT PRIMARY KEY, last INTEGER NOT NULL);
+    INSERT INTO short_id_counters (day, last)
+        SELECT substr(short_id, 1, 4), COUNT(*) FROM tasks GROUP BY 1;
+    CREATE TRIGGER tasks_short_id AFTER INSERT ON tasks WHEN NEW.short_id IS NULL BEGIN
+        INSERT INTO short_id_counters (day, last)
+            VALUES (substr(NEW.date, 6, 2) || substr(NEW.date, 9, 2), 1)
+            ON CONFLICT (day) DO UPDATE SET last = last + 1;
+        UPDATE tasks SET short_id = (
+            SELECT day || '-' || last FROM short_id_counters
+                WHERE day = substr(NEW.date, 6, 2) || substr(NEW.date, 9, 2)
+        ) WHERE id = NEW.id;
+    END",
+    // see SortKey::order_by
+    "ALTER TABLE tasks ADD COLUMN priority TEXT;
+    ALTER TABLE merge_conflicts ADD COLUMN priority TEXT",
+    // see insert_task_tags
+    "CREATE TABLE task_tags (task_id TEXT NOT NULL, tag TEXT NOT NULL, PRIMARY KEY (task_id, tag))",
+    // see get_projects
+    "ALTER TABLE tasks ADD COLUMN project TEXT;
+    ALTER TABLE merge_conflicts ADD COLUMN project TEXT",
+    // see TaskFilter::overdue
+    "ALTER TABLE tasks ADD COLUMN due TEXT;
+    ALTER TABLE merge_conflicts ADD COLUMN due TEXT",
+    // see get_subtasks
+    "ALTER TABLE tasks ADD COLUMN parent_id TEXT;
+    CREATE INDEX tasks_parent_id ON tasks (parent_id)",
+    // see insert_task_dep
+    "CREATE TABLE task_deps (
+        task_id TEXT NOT NULL,
+        depends_on TEXT NOT NULL,
+        PRIMARY KEY (task_id, depends_on)
+    )",
+    // see save_template
+    "ALTER TABLE template_tasks ADD COLUMN priority TEXT;
+    ALTER TABLE template_tasks ADD COLUMN tags TEXT",
+];
+
+// descriptions are read through unseal, see register_seal_functions
+// tags are the ones attached in task_tags, in the order they were
+const TASK_COLUMNS: &str = "id, unseal(description), status, date, updated_at, deferred_until, \
+    points, block_reason, position, short_id, priority, project, due, parent_id, (
+        SELECT group_concat(tag, ' ') FROM (
+            SELECT tag FROM task_tags WHERE task_id = tasks.id ORDER BY rowid
+        )
+    )";
+
+// a snoozed task belongs to the date it was deferred to
+const EFFECTIVE_DATE: &str = "COALESCE(deferred_until, date)";
+
+// how long a statement waits on a lock held by another connection before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/*
+ * wal = true in config lets readers (the metrics server, a list) carry on while a command
+ * writes, instead of waiting on it. off by default, the -wal and -shm files next to the db
+ * don't survive being synced around (dropbox, nfs, ...)
+ * */
+pub fn enable_wal(conn: &Connection) -> Result<(), Error> {
+    conn.pragma_update_and_check(None, "journal_mode", "wal", |_| Ok(()))
+}
+
+/*
+ * transactions that write take the write lock up front. a deferred one reading first can't
+ * upgrade its lock while another connection writes, that fails straight away with
+ * SQLITE_BUSY instead of waiting out BUSY_TIMEOUT
+ * */
+fn write_transaction(conn: &Connection) -> Result<Transaction<'_>, Error> {
+    Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+}
+
+const DB_FILE_NAME: &str = "storage.db";
+
+/*
+ * where the data directory goes, storage in config or DAILY_DOSE_STORAGE
+ *
+ * xdg     the platform's data directory: $XDG_DATA_HOME or ~/.local/share on linux,
+ *         ~/Library/Application Support on macos unless $XDG_DATA_HOME is set,
+ *         %LOCALAPPDATA% on windows
+ * home    a dotfile directory, ~/.daily-dose (%USERPROFILE%\.daily-dose on windows)
+ * custom  data_dir in config or DAILY_DOSE_DATA_DIR, implied when either is set
+ * */
+#[derive(EnumString, Display, Debug, Default, Clone, Copy, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub enum Storage {
+    #[default]
+    Xdg,
+    Home,
+    Custom,
+}
+
+// set once at startup, from the storage setting and the profile
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_data_dir(data_dir: PathBuf) {
+    let _ = DATA_DIR.set(data_dir);
+}
+
+// dirs ignores XDG_DATA_HOME on macos, but whoever sets it there expects it to be used
+#[cfg(target_os = "macos")]
+fn platform_data_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|data_dir| data_dir.is_absolute())
+        .or_else(dirs::data_dir)
+}
+
+// roaming %APPDATA% is synced between machines, no place for a live sqlite file
+#[cfg(windows)]
+fn platform_data_dir() -> Option<PathBuf> {
+    dirs::data_local_dir()
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn platform_data_dir() -> Option<PathBuf> {
+    dirs::data_dir()
+}
+
+// every profile has its own directory, except a custom one which its config names already
+pub fn get_storage_dir(
+    storage: Storage,
+    custom_dir: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<PathBuf, String> {
+    let mut data_dir = match storage {
+        Storage::Xdg => platform_data_dir()
+            .ok_or("Could not find data directory in OS")?
+            .join("daily-dose"),
+        Storage::Home => dirs::home_dir()
+            .ok_or("Could not find home directory in OS")?
+            .join(".daily-dose"),
+        Storage::Custom => {
+            return custom_dir.map(Path::to_path_buf).ok_or_else(|| {
+                "storage = \"custom\" needs data_dir in config or DAILY_DOSE_DATA_DIR".to_string()
+            })
+        }
+    };
+
+    if let Some(profile) = profile {
+        data_dir.push("profiles");
+        data_dir.push(profile);
+    }
+
+    Ok(data_dir)
+}
+
+// where the database lives unless storage or data_dir say otherwise
+pub fn get_default_data_dir(profile: Option<&str>) -> PathBuf {
+    get_storage_dir(Storage::default(), None, profile).expect("Could not find data directory in OS")
+}
+
+// the places the database may have been left in by another storage setting or an older version
+pub fn get_known_data_dirs(profile: Option<&str>) -> Vec<PathBuf> {
+    let mut data_dirs: Vec<PathBuf> = [Storage::Xdg, Storage::Home]
+        .into_iter()
+        .filter_map(|storage| get_storage_dir(storage, None, profile).ok())
+        .collect();
+
+    // versions before %LOCALAPPDATA% kept it in roaming %APPDATA%
+    if cfg!(windows)
+        && let Some(mut data_dir) = dirs::data_dir()
+    {
+        data_dir.push("daily-dose");
+        if let Some(profile) = profile {
+            data_dir.push("profiles");
+            data_dir.push(profile);
+        }
+        data_dirs.push(data_dir);
+    }
 
-    data_dir.push("daily-dose");
+    data_dirs
+}
+
+pub fn has_db(data_dir: &Path) -> bool {
+    data_dir.join(DB_FILE_NAME).exists()
+}
+
+/*
+ * moves everything in the data directory (database, backups, attachments) to another one.
+ * -wal and -shm go before storage.db, so an interrupted move never leaves the database
+ * without its journal, running it again picks up where it stopped
+ * */
+pub fn move_data_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    let mut entries = vec![];
+    for entry in fs::read_dir(from)? {
+        entries.push(entry?.file_name());
+    }
+
+    entries.sort_by_key(|name| {
+        let name = name.to_string_lossy();
+        match name.strip_prefix(DB_FILE_NAME) {
+            Some(suffix) if suffix.starts_with('-') => 0,
+            Some("") => 1,
+            _ => 2,
+        }
+    });
+
+    for name in entries {
+        // the lock belongs to whoever is moving, it stays behind
+        if Path::new(&name)
+            .extension()
+            .is_some_and(|ext| ext == "lock")
+        {
+            continue;
+        }
+
+        move_entry(&from.join(&name), &to.join(&name))?;
+    }
+
+    Ok(())
+}
+
+fn move_entry(from: &Path, to: &Path) -> io::Result<()> {
+    // rename doesn't cross file systems, eg. onto a mounted drive, nor onto a non-empty dir
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            move_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::remove_dir(from)
+    } else {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    }
+}
+
+// copied attachments are stored with absolute paths into the old data directory
+pub fn relocate_attachments(db_conn: &Connection, from: &Path, to: &Path) -> Result<usize, Error> {
+    // with the trailing separator, so attachments2/ next to it isn't matched
+    let from = from.join("attachments").join("");
+    let to = to.join("attachments").join("");
+
+    db_conn.execute(
+        "UPDATE task_attachments SET path = ?2 || substr(path, length(?1) + 1)
+        WHERE substr(path, 1, length(?1)) = ?1",
+        [from.to_string_lossy(), to.to_string_lossy()],
+    )
+}
+
+pub fn get_data_dir() -> PathBuf {
+    let data_dir = DATA_DIR
+        .get()
+        .cloned()
+        .unwrap_or_else(|| get_default_data_dir(None));
 
     fs::create_dir_all(&data_dir).expect("Failed to create directory");
 
-    data_dir.push("storage.db");
+    data_dir
+}
+
+pub fn get_db_path() -> String {
+    let mut data_dir = get_data_dir();
+
+    data_dir.push(DB_FILE_NAME);
 
     let db_path = data_dir.to_str().expect("Path conversion failed to str");
 
     db_path.to_string()
 }
 
-pub fn open_db_connection() -> Result<Connection, Error> {
-    let path = get_db_path();
-    let connection = Connection::open(path)?;
+// key is set when descriptions are to be encrypted, see register_seal_functions
+// read_only opens without write access, so nothing can change the database by accident
+pub fn open_db_connection(key: Option<Key>, read_only: bool) -> Result<Connection, Error> {
+    open_db_at(&get_db_path(), key, read_only)
+}
+
+// another profile's database, read only, eg. for report --profiles
+pub fn open_profile_db(data_dir: &Path, key: Option<Key>) -> Result<Connection, Error> {
+    open_db_at(&data_dir.join(DB_FILE_NAME).to_string_lossy(), key, true)
+}
+
+fn open_db_at(path: &str, key: Option<Key>, read_only: bool) -> Result<Connection, Error> {
+    let connection = match read_only {
+        true => Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_URI
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?,
+        false => Connection::open(path)?,
+    };
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    // INSERT OR REPLACE (merge, sync) only fires the delete triggers of day_counts with this
+    connection.pragma_update(None, "recursive_triggers", true)?;
+    register_regexp_function(&connection)?;
+    register_has_tag_function(&connection)?;
+    register_seal_functions(&connection, key)?;
     Ok(connection)
 }
 
+/*
+ * seal(X) encrypts X when there is a key (encrypt_descriptions in config), else leaves it as is
+ * unseal(X) decrypts sealed values and passes plain ones through, so a database holding both
+ * keeps working. this way descriptions, notes and their history are never stored in clear text,
+ * while dates and statuses stay queryable
+ * */
+fn register_seal_functions(conn: &Connection, key: Option<Key>) -> Result<(), Error> {
+    // a fresh nonce each call, so not deterministic
+    conn.create_scalar_function("seal", 1, FunctionFlags::SQLITE_UTF8, move |ctx| {
+        let value: Option<String> = ctx.get(0)?;
+
+        Ok(match (value, &key) {
+            (Some(value), Some(key)) if !is_sealed(&value) => Some(seal(key, &value)),
+            (value, _) => value,
+        })
+    })?;
+
+    conn.create_scalar_function(
+        "unseal",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            let value: Option<String> = ctx.get(0)?;
+
+            Ok(value.map(|value| match &key {
+                _ if !is_sealed(&value) => value,
+                Some(key) => open(key, &value).unwrap_or_else(|| "[undecryptable]".to_string()),
+                None => "[encrypted]".to_string(),
+            }))
+        },
+    )
+}
+
+// seals what was stored in clear text before encryption got turned on, returns the no. of values
+pub fn seal_plain_values(db_conn: &Connection) -> Result<usize, Error> {
+    let tx = write_transaction(db_conn)?;
+
+    let mut sealed = 0;
+
+    for (table, column, condition) in [
+        ("tasks", "description", "1"),
+        ("template_tasks", "description", "1"),
+        ("task_annotations", "note", "1"),
+        ("journal", "note", "1"),
+        ("task_history", "old_value", SEALED_HISTORY_FIELDS),
+        ("task_history", "new_value", SEALED_HISTORY_FIELDS),
+    ] {
+        sealed += tx.execute(
+            &format!(
+                "UPDATE {table} SET {column} = seal({column})
+                    WHERE {condition} AND {column} NOT LIKE 'enc:v1:%'"
+            ),
+            [],
+        )?;
+    }
+
+    tx.commit()?;
+
+    Ok(sealed)
+}
+
+/*
+ * sqlite parses `X REGEXP Y` but ships no implementation for it,
+ * it calls a user function regexp(Y, X) which we provide here.
+ * the compiled pattern is cached by sqlite as aux data for the rest of the statement
+ * */
+fn register_regexp_function(conn: &Connection) -> Result<(), Error> {
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let regex = ctx.get_or_create_aux(0, |pattern| -> Result<Regex, String> {
+                Regex::new(pattern.as_str().map_err(|error| error.to_string())?)
+                    .map_err(|error| error.to_string())
+            })?;
+
+            let text = ctx.get_raw(1).as_str().unwrap_or_default();
+
+            Ok(regex.is_match(text))
+        },
+    )
+}
+
+// has_tag(text, tag), whether the text has the #tag, see extract_tags
+fn register_has_tag_function(conn: &Connection) -> Result<(), Error> {
+    conn.create_scalar_function(
+        "has_tag",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let text = ctx.get_raw(0).as_str().unwrap_or_default();
+            let tag = ctx.get_raw(1).as_str().unwrap_or_default();
+
+            Ok(extract_tags(text).iter().any(|found| found == tag))
+        },
+    )
+}
+
+// VACUUM INTO writes a compacted copy without blocking writers for long, the target must not exist
+pub fn backup_db(conn: &Connection, path: &Path) -> Result<(), Error> {
+    conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+
+    Ok(())
+}
+
+// no. of tasks in the backup at path, fails on anything but a daily-dose database
+pub fn count_backup_tasks(path: &Path) -> Result<u32, Error> {
+    let backup_conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    backup_conn.query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))
+}
+
+// replaces the whole database with the backup at path
+pub fn restore_db(conn: &mut Connection, path: &Path) -> Result<(), Error> {
+    conn.restore(MAIN_DB, path, None::<fn(Progress)>)
+}
+
 pub fn create_task_table(conn: &Connection) -> Result<(), Error> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS tasks (
@@ -39,21 +566,293 @@ pub fn create_task_table(conn: &Connection) -> Result<(), Error> {
     Ok(())
 }
 
+// false when migrate_task_table still has work to do
+// no. of migrations applied, 0 for a database created just now
+pub fn get_schema_version(conn: &Connection) -> Result<u32, Error> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+pub fn is_task_table_migrated(conn: &Connection) -> Result<bool, Error> {
+    Ok(get_schema_version(conn)? as usize >= MIGRATIONS.len())
+}
+
+pub fn migrate_task_table(conn: &mut Connection) -> Result<(), Error> {
+    loop {
+        // immediate transaction so two processes can't apply the same migration twice
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let version: u32 = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        let Some(migration) = MIGRATIONS.get(version as usize) else {
+            return Ok(());
+        };
+
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", version + 1)?;
+        tx.commit()?;
+    }
+}
+
+/*
+ * updates below are guarded by the updated_at value the caller read the task with
+ * if someone else modified the task in between, no row matches and the update is rejected
+ * with StatementChangedRows(0) instead of silently overwriting their change
+ * */
+pub fn is_conflict_error(error: &Error) -> bool {
+    matches!(error, Error::StatementChangedRows(0))
+}
+
+// on success the caller's copy picks up the new updated_at, so it can be updated again
+fn ensure_row_changed(task: &mut Task, changed_rows: usize, now: &str) -> Result<(), Error> {
+    match changed_rows {
+        0 => Err(Error::StatementChangedRows(0)),
+        _ => {
+            task.updated_at = Some(now.to_string());
+            Ok(())
+        }
+    }
+}
+
+// history entries whose values are descriptions
+const SEALED_HISTORY_FIELDS: &str = "field IN ('created', 'description')";
+
+// append only log of task changes, shown by `recent`
+fn record_change(
+    db_conn: &Connection,
+    task_id: &str,
+    changed_at: &str,
+    field: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+) -> Result<(), Error> {
+    // the same fields as SEALED_HISTORY_FIELDS
+    let values = match field {
+        "created" | "description" => "seal(?4), seal(?5)",
+        _ => "?4, ?5",
+    };
+
+    // cached, bulk inserts record a change for every task
+    db_conn
+        .prepare_cached(&format!(
+            "INSERT INTO task_history (task_id, changed_at, field, old_value, new_value)
+                VALUES (?1, ?2, ?3, {values})"
+        ))?
+        .execute((task_id, changed_at, field, old_value, new_value))?;
+
+    Ok(())
+}
+
+const TASK_CHANGE_COLUMNS: &str = "changed_at, field, unseal(old_value), unseal(new_value)";
+
+fn task_change_from_row(row: &Row) -> Result<TaskChange, Error> {
+    Ok(TaskChange {
+        changed_at: row.get(0)?,
+        field: row.get(1)?,
+        old_value: row.get(2)?,
+        new_value: row.get(3)?,
+    })
+}
+
+pub fn get_last_change(db_conn: &Connection, task_id: &str) -> Result<Option<TaskChange>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_CHANGE_COLUMNS} FROM task_history
+            WHERE task_id = :id ORDER BY changed_at DESC, rowid DESC LIMIT 1"
+    ))?;
+
+    let mut rows = stmt.query_map(named_params! { ":id": task_id }, task_change_from_row)?;
+
+    rows.next().transpose()
+}
+
+// oldest change first
+pub fn get_task_history(db_conn: &Connection, task_id: &str) -> Result<Vec<TaskChange>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_CHANGE_COLUMNS} FROM task_history
+            WHERE task_id = :id ORDER BY changed_at, rowid"
+    ))?;
+
+    let rows = stmt.query_map(named_params! { ":id": task_id }, task_change_from_row)?;
+
+    rows.collect()
+}
+
+// most recently created or modified tasks first
+pub fn get_recent_tasks(db_conn: &Connection, limit: u32) -> Result<Vec<Task>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM tasks WHERE updated_at IS NOT NULL
+            ORDER BY updated_at DESC LIMIT :limit"
+    ))?;
+
+    let rows = stmt.query_map(named_params! { ":limit": limit }, task_from_row)?;
+
+    rows.collect()
+}
+
+// done tasks with the time they were completed, tasks finished before it was recorded are left out
+pub fn get_completed_tasks(db_conn: &Connection) -> Result<Vec<(Task, String)>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS}, completed_at FROM tasks
+            WHERE status = 'done' AND completed_at IS NOT NULL ORDER BY completed_at"
+    ))?;
+
+    let rows = stmt.query_map([], |row| Ok((task_from_row(row)?, row.get(15)?)))?;
+
+    rows.collect()
+}
+
+/*
+ * done tasks with points, along with the time they first went in progress (if they did and
+ * it was recorded) and the time they were done, ordered by the latter
+ * */
+pub fn get_estimated_tasks(
+    db_conn: &Connection,
+) -> Result<Vec<(Task, Option<String>, String)>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS}, (
+                SELECT MIN(changed_at) FROM task_history
+                    WHERE task_id = tasks.id AND field = 'status' AND new_value = 'in_progress'
+            ), completed_at FROM tasks
+            WHERE status = 'done' AND completed_at IS NOT NULL AND points > 0
+            ORDER BY completed_at"
+    ))?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((task_from_row(row)?, row.get(15)?, row.get(16)?))
+    })?;
+
+    rows.collect()
+}
+
+// in progress and blocked tasks along with the time they last changed status, if recorded
+pub fn get_stuck_tasks(db_conn: &Connection) -> Result<Vec<(Task, Option<String>)>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS}, (
+                SELECT MAX(changed_at) FROM task_history
+                    WHERE task_id = tasks.id AND field = 'status'
+            ) FROM tasks WHERE status IN ('in_progress', 'blocked')"
+    ))?;
+
+    let rows = stmt.query_map([], |row| Ok((task_from_row(row)?, row.get(15)?)))?;
+
+    rows.collect()
+}
+
+pub fn get_tasks_completed_since(db_conn: &Connection, since: &str) -> Result<Vec<Task>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM tasks
+            WHERE status = 'done' AND completed_at >= :since ORDER BY completed_at"
+    ))?;
+
+    let rows = stmt.query_map(named_params! { ":since": since }, task_from_row)?;
+
+    rows.collect()
+}
+
+/*
+ * no. of tasks and of done ones per (effective) date, days without tasks left out
+ * day_counts is kept up to date by triggers on tasks, so stats over years of tasks don't have
+ * to read (and unseal) every one of them
+ * */
+pub fn get_day_counts(
+    db_conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<(String, u32, u32)>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT date, total, done FROM day_counts WHERE date BETWEEN ?1 AND ?2 ORDER BY date",
+    )?;
+
+    let rows = stmt.query_map([start_date, end_date], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?;
+
+    rows.collect()
+}
+
+// no. of tasks per status, statuses without tasks are left out
+pub fn count_tasks_by_status(db_conn: &Connection) -> Result<Vec<(Status, u32)>, Error> {
+    let mut stmt = db_conn.prepare("SELECT status, COUNT(*) FROM tasks GROUP BY status")?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+fn task_from_row(row: &Row) -> Result<Task, Error> {
+    Ok(Task {
+        id: row.get(0)?,
+        description: row.get(1)?,
+        status: row.get(2)?,
+        date: row.get(3)?,
+        updated_at: row.get(4)?,
+        deferred_until: row.get(5)?,
+        points: row.get(6)?,
+        block_reason: row.get(7)?,
+        position: row.get(8)?,
+        short_id: row.get(9)?,
+        priority: row.get(10)?,
+        project: row.get(11)?,
+        due: row.get(12)?,
+        parent_id: row.get(13)?,
+        tags: row
+            .get::<_, Option<String>>(14)?
+            .map(|tags| tags.split(' ').map(str::to_string).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/*
+ * a task's position is its index within its (effective) date, shown in the Idx column and
+ * used by mark, snooze, ... to pick it. new and snoozed in tasks go to the end of the date,
+ * deleted tasks leave a gap, so the index of a task never changes under the user's feet
+ * */
+fn next_position(db_conn: &Connection, date: &str) -> Result<u32, Error> {
+    db_conn.query_row(
+        &format!("SELECT COALESCE(MAX(position), 0) + 1 FROM tasks WHERE {EFFECTIVE_DATE} = ?1"),
+        [date],
+        |row| row.get(0),
+    )
+}
+
+// the last position on every date with tasks
+fn last_positions(db_conn: &Connection) -> Result<HashMap<String, u32>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {EFFECTIVE_DATE}, MAX(position) FROM tasks GROUP BY 1"
+    ))?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn insert_task(
     db_conn: &Connection,
     desc: &str,
     status: Status,
     timestamp: &str,
+    points: Option<u32>,
+    priority: Option<Priority>,
+    project: Option<&str>,
+    due: Option<&str>,
 ) -> Result<String, Error> {
     let uid = Ulid::new();
 
     let doc_id = uid.to_string();
 
+    let now = now_timestamp();
+
+    let completed_at = (status == Status::Done).then_some(&now);
+
+    let position = next_position(db_conn, timestamp)?;
+
     db_conn.execute(
-        "INSERT INTO tasks (id, description, status, date) VALUES (?1, ?2, ?3, ?4)",
-        (&doc_id, desc, status, timestamp),
+        "INSERT INTO tasks (id, description, status, date, updated_at, points, completed_at, position, priority, project, due) VALUES (?1, seal(?2), ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        (&doc_id, desc, status, timestamp, &now, points, completed_at, position, priority, project, due),
     )?;
 
+    record_change(db_conn, &doc_id, &now, "created", None, Some(desc))?;
+
     Ok(doc_id)
 }
 
@@ -65,26 +864,19 @@ pub fn get_tasks_by_date(
     // https://docs.rs/rusqlite/latest/rusqlite/struct.Statement.html#use-with-positional-parameters-1
     let (query, params) = match end_date {
         Some(end_date) => {
-            ("SELECT id, description, status, date FROM tasks WHERE date BETWEEN :start_date AND :end_date ORDER BY id", named_params! {
+            (format!("SELECT {TASK_COLUMNS} FROM tasks WHERE {EFFECTIVE_DATE} BETWEEN :start_date AND :end_date ORDER BY {EFFECTIVE_DATE}, position"), named_params! {
                 ":start_date": start_date,
                 ":end_date": end_date.to_string(),
             })
         },
-        None => ("SELECT id, description, status, date FROM tasks WHERE date = :start_date ORDER BY id", named_params! {
+        None => (format!("SELECT {TASK_COLUMNS} FROM tasks WHERE {EFFECTIVE_DATE} = :start_date ORDER BY position"), named_params! {
                 ":start_date": start_date,
         }),
     };
 
-    let mut stmt = db_conn.prepare(query)?;
+    let mut stmt = db_conn.prepare(&query)?;
 
-    let rows = stmt.query_map(params, |row| {
-        Ok(Task {
-            id: row.get(0)?,
-            description: row.get(1)?,
-            status: row.get(2)?,
-            date: row.get(3)?,
-        })
-    })?;
+    let rows = stmt.query_map(params, task_from_row)?;
 
     let mut tasks: Vec<Task> = vec![];
 
@@ -95,44 +887,2465 @@ pub fn get_tasks_by_date(
     Ok(tasks)
 }
 
-pub fn update_task_description(
-    db_conn: &Connection,
-    task_id: &str,
-    desc: &str,
-) -> Result<(), Error> {
-    db_conn.execute(
-        "UPDATE tasks SET description = :description WHERE id = :id",
-        named_params! {
-            ":description": desc,
-            ":id":task_id
-        },
-    )?;
+/*
+ * filters for listing tasks, every condition set is AND-ed
+ *
+ * let filter = TaskFilter::new()
+ *     .date_range("2024-06-01", "2024-06-30")
+ *     .search("deploy");
+ * */
+#[derive(Debug, Default, Clone)]
+pub struct TaskFilter {
+    start_date: Option<String>,
+    end_date: Option<String>,
+    before: Option<String>,
+    after: Option<String>,
+    statuses: Vec<Status>,
+    priorities: Vec<Priority>,
+    projects: Vec<String>,
+    tags: Vec<String>,
+    searches: Vec<String>,
+    search_regex: Option<String>,
+    due_before: Option<String>,
+    due_in_range: bool,
+    sort: SortKey,
+    archived: bool,
+}
 
-    Ok(())
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+pub enum SortKey {
+    // within a date, in Idx order
+    #[default]
+    Date,
+    Status,
+    // ulids are time ordered, so id order is creation order
+    Created,
+    Description,
 }
-pub fn update_task_status(
+
+impl SortKey {
+    // id last keeps the order stable between tasks with equal keys
+    fn order_by(&self) -> String {
+        match self {
+            // high priority first, tasks without one go with the medium ones
+            SortKey::Date => format!(
+                "{EFFECTIVE_DATE}, CASE priority WHEN 'high' THEN 0 WHEN 'low' THEN 2 ELSE 1 END, position, id"
+            ),
+            SortKey::Status => "CASE status
+                WHEN 'in_progress' THEN 0
+                WHEN 'todo' THEN 1
+                WHEN 'blocked' THEN 2
+                ELSE 3
+            END, id"
+                .to_string(),
+            SortKey::Created => "id".to_string(),
+            SortKey::Description => "unseal(description) COLLATE NOCASE, id".to_string(),
+        }
+    }
+}
+
+impl TaskFilter {
+    pub fn new() -> Self {
+        TaskFilter::default()
+    }
+
+    pub fn date_range(mut self, start_date: &str, end_date: &str) -> Self {
+        self.start_date = Some(start_date.to_string());
+        self.end_date = Some(end_date.to_string());
+        self
+    }
+
+    // dates strictly before / after the given date
+    pub fn before(mut self, date: &str) -> Self {
+        self.before = Some(date.to_string());
+        self
+    }
+
+    pub fn after(mut self, date: &str) -> Self {
+        self.after = Some(date.to_string());
+        self
+    }
+
+    // tasks of archive.db too, which has to be attached first, see attach_archive
+    pub fn include_archived(mut self) -> Self {
+        self.archived = true;
+        self
+    }
+
+    pub fn is_date_bounded(&self) -> bool {
+        self.start_date.is_some()
+            || self.end_date.is_some()
+            || self.before.is_some()
+            || self.after.is_some()
+    }
+
+    // calling it multiple times matches tasks having any of the statuses
+    pub fn status(mut self, status: Status) -> Self {
+        self.statuses.push(status);
+        self
+    }
+
+    // like status, any of the priorities
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priorities.push(priority);
+        self
+    }
+
+    // like status, any of the projects, their case doesn't matter
+    pub fn project(mut self, project: &str) -> Self {
+        self.projects.push(project.to_string());
+        self
+    }
+
+    // attached or a #tag of the description, every tag given has to match
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    // plain substring match on the description, every query given has to match
+    pub fn search(mut self, query: &str) -> Self {
+        self.searches.push(format!("%{query}%"));
+        self
+    }
+
+    pub fn search_regex(mut self, pattern: &str) -> Self {
+        self.search_regex = Some(pattern.to_string());
+        self
+    }
+
+    // date_range also takes tasks due within it, whatever date they are on
+    pub fn or_due_in_range(mut self) -> Self {
+        self.due_in_range = true;
+        self
+    }
+
+    // unfinished tasks due before the date, whatever date they are on
+    pub fn overdue(mut self, today: &str) -> Self {
+        self.due_before = Some(today.to_string());
+        self
+    }
+
+    pub fn sort(mut self, sort: SortKey) -> Self {
+        self.sort = sort;
+        self
+    }
+}
+
+pub fn get_tasks(db_conn: &Connection, filter: &TaskFilter) -> Result<Vec<Task>, Error> {
+    let (where_clause, params) = task_conditions(filter);
+
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM {} {where_clause} ORDER BY {}",
+        task_source(filter),
+        filter.sort.order_by()
+    ))?;
+
+    let rows = stmt.query_map(params_from_iter(params), task_from_row)?;
+
+    rows.collect()
+}
+
+/*
+ * the tasks of a filter one at a time straight off the cursor, for listings too big to hold
+ * at once. they come by date (newest first when descending), the filter's sort within a date
+ * */
+pub fn for_each_task_by_date(
     db_conn: &Connection,
-    task_id: &str,
-    status: Status,
+    filter: &TaskFilter,
+    descending: bool,
+    mut f: impl FnMut(Task),
 ) -> Result<(), Error> {
-    db_conn.execute(
-        "UPDATE tasks SET status = :status WHERE id = :id",
-        named_params! {
-            ":status": status,
-            ":id":task_id
+    let (where_clause, params) = task_conditions(filter);
+
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM {} {where_clause} ORDER BY {EFFECTIVE_DATE} {}, {}",
+        task_source(filter),
+        match descending {
+            true => "DESC",
+            false => "ASC",
         },
-    )?;
+        filter.sort.order_by()
+    ))?;
+
+    let mut rows = stmt.query(params_from_iter(params))?;
+
+    while let Some(row) = rows.next()? {
+        f(task_from_row(row)?);
+    }
 
     Ok(())
 }
 
-pub fn delete_task(db_conn: &Connection, task_id: &str) -> Result<(), Error> {
-    db_conn.execute(
-        "delete from tasks where id = :id",
-        named_params! {
-            ":id":task_id
-        },
-    )?;
+// the tasks table, or the tasks of both databases when the filter includes the archive
+fn task_source(filter: &TaskFilter) -> String {
+    match filter.archived {
+        false => "tasks".to_string(),
+        true => format!(
+            "(SELECT {ARCHIVE_COLUMNS} FROM main.tasks
+                UNION ALL SELECT {ARCHIVE_COLUMNS} FROM {ARCHIVE_SCHEMA}.tasks) AS tasks"
+        ),
+    }
+}
 
-    Ok(())
+// the WHERE clause of a filter and its parameters
+fn task_conditions(filter: &TaskFilter) -> (String, Vec<&dyn ToSql>) {
+    let mut conditions: Vec<String> = vec![];
+    let mut params: Vec<&dyn ToSql> = vec![];
+
+    if filter.due_in_range
+        && let (Some(start_date), Some(end_date)) = (&filter.start_date, &filter.end_date)
+    {
+        conditions.push(format!(
+            "({EFFECTIVE_DATE} BETWEEN ? AND ? OR due BETWEEN ? AND ?)"
+        ));
+        params.extend([start_date, end_date, start_date, end_date].map(|date| date as &dyn ToSql));
+    } else {
+        if let Some(start_date) = &filter.start_date {
+            conditions.push(format!("{EFFECTIVE_DATE} >= ?"));
+            params.push(start_date);
+        }
+
+        if let Some(end_date) = &filter.end_date {
+            conditions.push(format!("{EFFECTIVE_DATE} <= ?"));
+            params.push(end_date);
+        }
+    }
+
+    if let Some(before) = &filter.before {
+        conditions.push(format!("{EFFECTIVE_DATE} < ?"));
+        params.push(before);
+    }
+
+    if let Some(after) = &filter.after {
+        conditions.push(format!("{EFFECTIVE_DATE} > ?"));
+        params.push(after);
+    }
+
+    if let Some(due_before) = &filter.due_before {
+        conditions.push("due < ? AND status != 'done'".to_string());
+        params.push(due_before);
+    }
+
+    if !filter.statuses.is_empty() {
+        let placeholders = vec!["?"; filter.statuses.len()].join(", ");
+        conditions.push(format!("status IN ({placeholders})"));
+        params.extend(filter.statuses.iter().map(|status| status as &dyn ToSql));
+    }
+
+    if !filter.priorities.is_empty() {
+        let placeholders = vec!["?"; filter.priorities.len()].join(", ");
+        conditions.push(format!("priority IN ({placeholders})"));
+        params.extend(
+            filter
+                .priorities
+                .iter()
+                .map(|priority| priority as &dyn ToSql),
+        );
+    }
+
+    if !filter.projects.is_empty() {
+        let placeholders = vec!["?"; filter.projects.len()].join(", ");
+        conditions.push(format!("project COLLATE NOCASE IN ({placeholders})"));
+        params.extend(filter.projects.iter().map(|project| project as &dyn ToSql));
+    }
+
+    for tag in &filter.tags {
+        conditions.push(
+            "(id IN (SELECT task_id FROM task_tags WHERE tag = ?) OR has_tag(unseal(description), ?))"
+                .to_string(),
+        );
+        params.extend([tag as &dyn ToSql, tag]);
+    }
+
+    for search in &filter.searches {
+        conditions.push("unseal(description) LIKE ?".to_string());
+        params.push(search);
+    }
+
+    if let Some(search_regex) = &filter.search_regex {
+        conditions.push("unseal(description) REGEXP ?".to_string());
+        params.push(search_regex);
+    }
+
+    let where_clause = match conditions.is_empty() {
+        true => String::new(),
+        false => format!("WHERE {}", conditions.join(" AND ")),
+    };
+
+    (where_clause, params)
+}
+
+// tasks from dates before the given date which never got done
+pub fn get_overdue_tasks(db_conn: &Connection, before_date: &str) -> Result<Vec<Task>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM tasks WHERE {EFFECTIVE_DATE} < :before_date AND status != :done ORDER BY {EFFECTIVE_DATE}, position"
+    ))?;
+
+    let rows = stmt.query_map(
+        named_params! {
+            ":before_date": before_date,
+            ":done": Status::Done,
+        },
+        task_from_row,
+    )?;
+
+    rows.collect()
+}
+
+/*
+ * a task's id from its id or short id, for --id
+ *
+ * short ids are the month and day of the task's date and a count of the tasks added for that
+ * day of the year, eg. 0612-3. the tasks_short_id trigger gives them out from
+ * short_id_counters, which go on counting over the years and past deleted or archived tasks,
+ * so a short id never comes back for another task. they are this database's own, merge and
+ * sync leave them out
+ * */
+pub fn resolve_task_id(db_conn: &Connection, reference: &str) -> Result<String, Error> {
+    db_conn.query_row(
+        "SELECT id FROM tasks WHERE id = ?1 OR short_id = ?1",
+        [reference],
+        |row| row.get(0),
+    )
+}
+
+pub fn get_task_by_id(db_conn: &Connection, task_id: &str) -> Result<Task, Error> {
+    db_conn.query_row(
+        &format!("SELECT {TASK_COLUMNS} FROM tasks WHERE id = :id"),
+        named_params! {
+            ":id": task_id
+        },
+        task_from_row,
+    )
+}
+
+pub fn update_task_description(
+    db_conn: &Connection,
+    task: &mut Task,
+    desc: &str,
+) -> Result<(), Error> {
+    let now = now_timestamp();
+
+    let changed_rows = db_conn.execute(
+        "UPDATE tasks SET description = seal(:description), updated_at = :now
+            WHERE id = :id AND updated_at IS :updated_at",
+        named_params! {
+            ":description": desc,
+            ":now": &now,
+            ":id": task.id,
+            ":updated_at": task.updated_at,
+        },
+    )?;
+
+    ensure_row_changed(task, changed_rows, &now)?;
+
+    record_change(
+        db_conn,
+        &task.id,
+        &now,
+        "description",
+        Some(&task.description),
+        Some(desc),
+    )?;
+
+    task.description = desc.to_string();
+
+    Ok(())
+}
+
+pub fn update_task_status(
+    db_conn: &Connection,
+    task: &mut Task,
+    status: Status,
+) -> Result<(), Error> {
+    let now = now_timestamp();
+
+    let changed_rows = db_conn.execute(
+        "UPDATE tasks SET status = :status, updated_at = :now,
+                completed_at = CASE WHEN :status = 'done' THEN :now END,
+                block_reason = CASE WHEN :status = 'blocked' THEN block_reason END
+            WHERE id = :id AND updated_at IS :updated_at",
+        named_params! {
+            ":status": status,
+            ":now": &now,
+            ":id": task.id,
+            ":updated_at": task.updated_at,
+        },
+    )?;
+
+    ensure_row_changed(task, changed_rows, &now)?;
+
+    record_change(
+        db_conn,
+        &task.id,
+        &now,
+        "status",
+        Some(&task.status.to_string()),
+        Some(&status.to_string()),
+    )?;
+
+    task.status = status;
+    if status != Status::Blocked {
+        task.block_reason = None;
+    }
+
+    Ok(())
+}
+
+pub fn update_task_blocked(
+    db_conn: &Connection,
+    task: &mut Task,
+    reason: Option<&str>,
+) -> Result<(), Error> {
+    let now = now_timestamp();
+
+    let changed_rows = db_conn.execute(
+        "UPDATE tasks SET status = :status, block_reason = :reason, updated_at = :now,
+                completed_at = NULL
+            WHERE id = :id AND updated_at IS :updated_at",
+        named_params! {
+            ":status": Status::Blocked,
+            ":reason": reason,
+            ":now": &now,
+            ":id": task.id,
+            ":updated_at": task.updated_at,
+        },
+    )?;
+
+    ensure_row_changed(task, changed_rows, &now)?;
+
+    if task.status != Status::Blocked {
+        record_change(
+            db_conn,
+            &task.id,
+            &now,
+            "status",
+            Some(&task.status.to_string()),
+            Some(&Status::Blocked.to_string()),
+        )?;
+    }
+
+    if task.block_reason.as_deref() != reason {
+        record_change(
+            db_conn,
+            &task.id,
+            &now,
+            "block_reason",
+            task.block_reason.as_deref(),
+            reason,
+        )?;
+    }
+
+    task.status = Status::Blocked;
+    task.block_reason = reason.map(str::to_string);
+
+    Ok(())
+}
+
+pub fn update_task_points(db_conn: &Connection, task: &mut Task, points: u32) -> Result<(), Error> {
+    let now = now_timestamp();
+
+    let changed_rows = db_conn.execute(
+        "UPDATE tasks SET points = :points, updated_at = :now
+            WHERE id = :id AND updated_at IS :updated_at",
+        named_params! {
+            ":points": points,
+            ":now": &now,
+            ":id": task.id,
+            ":updated_at": task.updated_at,
+        },
+    )?;
+
+    ensure_row_changed(task, changed_rows, &now)?;
+
+    record_change(
+        db_conn,
+        &task.id,
+        &now,
+        "points",
+        task.points.map(|points| points.to_string()).as_deref(),
+        Some(&points.to_string()),
+    )?;
+
+    task.points = Some(points);
+
+    Ok(())
+}
+
+pub fn update_task_priority(
+    db_conn: &Connection,
+    task: &mut Task,
+    priority: Priority,
+) -> Result<(), Error> {
+    let now = now_timestamp();
+
+    let changed_rows = db_conn.execute(
+        "UPDATE tasks SET priority = :priority, updated_at = :now
+            WHERE id = :id AND updated_at IS :updated_at",
+        named_params! {
+            ":priority": priority,
+            ":now": &now,
+            ":id": task.id,
+            ":updated_at": task.updated_at,
+        },
+    )?;
+
+    ensure_row_changed(task, changed_rows, &now)?;
+
+    record_change(
+        db_conn,
+        &task.id,
+        &now,
+        "priority",
+        task.priority
+            .map(|priority| priority.to_string())
+            .as_deref(),
+        Some(&priority.to_string()),
+    )?;
+
+    task.priority = Some(priority);
+
+    Ok(())
+}
+
+pub fn update_task_project(
+    db_conn: &Connection,
+    task: &mut Task,
+    project: &str,
+) -> Result<(), Error> {
+    let now = now_timestamp();
+
+    let changed_rows = db_conn.execute(
+        "UPDATE tasks SET project = :project, updated_at = :now
+            WHERE id = :id AND updated_at IS :updated_at",
+        named_params! {
+            ":project": project,
+            ":now": &now,
+            ":id": task.id,
+            ":updated_at": task.updated_at,
+        },
+    )?;
+
+    ensure_row_changed(task, changed_rows, &now)?;
+
+    record_change(
+        db_conn,
+        &task.id,
+        &now,
+        "project",
+        task.project.as_deref(),
+        Some(project),
+    )?;
+
+    task.project = Some(project.to_string());
+
+    Ok(())
+}
+
+pub fn update_task_due(db_conn: &Connection, task: &mut Task, due: &str) -> Result<(), Error> {
+    let now = now_timestamp();
+
+    let changed_rows = db_conn.execute(
+        "UPDATE tasks SET due = :due, updated_at = :now
+            WHERE id = :id AND updated_at IS :updated_at",
+        named_params! {
+            ":due": due,
+            ":now": &now,
+            ":id": task.id,
+            ":updated_at": task.updated_at,
+        },
+    )?;
+
+    ensure_row_changed(task, changed_rows, &now)?;
+
+    record_change(
+        db_conn,
+        &task.id,
+        &now,
+        "due",
+        task.due.as_deref(),
+        Some(due),
+    )?;
+
+    task.due = Some(due.to_string());
+
+    Ok(())
+}
+
+pub fn update_task_deferred_until(
+    db_conn: &Connection,
+    task: &mut Task,
+    deferred_until: &str,
+) -> Result<(), Error> {
+    let now = now_timestamp();
+
+    let position = next_position(db_conn, deferred_until)?;
+
+    let changed_rows = db_conn.execute(
+        "UPDATE tasks SET deferred_until = :deferred_until, position = :position, updated_at = :now
+            WHERE id = :id AND updated_at IS :updated_at",
+        named_params! {
+            ":deferred_until": deferred_until,
+            ":position": position,
+            ":now": &now,
+            ":id": task.id,
+            ":updated_at": task.updated_at,
+        },
+    )?;
+
+    ensure_row_changed(task, changed_rows, &now)?;
+
+    record_change(
+        db_conn,
+        &task.id,
+        &now,
+        "deferred_until",
+        task.deferred_until.as_deref(),
+        Some(deferred_until),
+    )?;
+
+    task.deferred_until = Some(deferred_until.to_string());
+    task.position = position;
+
+    Ok(())
+}
+
+// all or nothing, a task gone without its tombstone would come back with the next merge
+pub fn delete_task(db_conn: &Connection, task_id: &str) -> Result<(), Error> {
+    let tx = write_transaction(db_conn)?;
+    delete_task_rows(&tx, task_id)?;
+    tx.commit()
+}
+
+// delete_task for callers already in a transaction
+fn delete_task_rows(db_conn: &Connection, task_id: &str) -> Result<(), Error> {
+    db_conn.execute(
+        "delete from tasks where id = :id",
+        named_params! {
+            ":id":task_id
+        },
+    )?;
+
+    // so a merge with a copy that still has the task doesn't bring it back
+    db_conn.execute(
+        "INSERT OR REPLACE INTO deleted_tasks (task_id, deleted_at) VALUES (?1, ?2)",
+        (task_id, now_timestamp()),
+    )?;
+
+    for table in TASK_DETAIL_TABLES {
+        db_conn.execute(
+            &format!("DELETE FROM {table} WHERE task_id = :id"),
+            named_params! {
+                ":id": task_id
+            },
+        )?;
+    }
+
+    // the tasks depending on it have one less to wait for
+    db_conn.execute(
+        "DELETE FROM task_deps WHERE depends_on = :id",
+        named_params! {
+            ":id": task_id
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn insert_task_links(
+    db_conn: &Connection,
+    task_id: &str,
+    urls: &[String],
+) -> Result<(), Error> {
+    for url in urls {
+        db_conn.execute(
+            "INSERT OR IGNORE INTO task_links (task_id, url) VALUES (?1, ?2)",
+            (task_id, url),
+        )?;
+    }
+
+    Ok(())
+}
+
+/*
+ * tags attached to a task, besides the #tags written in its description. like links they are
+ * details of the task, kept apart from its fields, so they don't change its updated_at
+ * */
+pub fn insert_task_tags(db_conn: &Connection, task_id: &str, tags: &[String]) -> Result<(), Error> {
+    for tag in tags {
+        db_conn.execute(
+            "INSERT OR IGNORE INTO task_tags (task_id, tag) VALUES (?1, ?2)",
+            (task_id, tag),
+        )?;
+    }
+
+    Ok(())
+}
+
+// the no. of tags that were attached
+pub fn delete_task_tags(
+    db_conn: &Connection,
+    task_id: &str,
+    tags: &[String],
+) -> Result<usize, Error> {
+    let mut removed = 0;
+    for tag in tags {
+        removed += db_conn.execute(
+            "DELETE FROM task_tags WHERE task_id = ?1 AND tag = ?2",
+            (task_id, tag),
+        )?;
+    }
+
+    Ok(removed)
+}
+
+/*
+ * the task depends on another one, it waits for that to be done. like tags these are merged
+ * but removing one isn't, a merge with a copy that still has it brings it back
+ * */
+pub fn insert_task_dep(db_conn: &Connection, task_id: &str, depends_on: &str) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT OR IGNORE INTO task_deps (task_id, depends_on) VALUES (?1, ?2)",
+        (task_id, depends_on),
+    )?;
+
+    Ok(())
+}
+
+// the no. of dependencies removed, 0 or 1
+pub fn delete_task_dep(
+    db_conn: &Connection,
+    task_id: &str,
+    depends_on: &str,
+) -> Result<usize, Error> {
+    db_conn.execute(
+        "DELETE FROM task_deps WHERE task_id = ?1 AND depends_on = ?2",
+        (task_id, depends_on),
+    )
+}
+
+// the tasks the task depends on, by date
+pub fn get_dependencies(db_conn: &Connection, task_id: &str) -> Result<Vec<Task>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM tasks
+            WHERE id IN (SELECT depends_on FROM task_deps WHERE task_id = ?1)
+            ORDER BY {EFFECTIVE_DATE}, position"
+    ))?;
+
+    let rows = stmt.query_map([task_id], task_from_row)?;
+
+    rows.collect()
+}
+
+// the tasks depending on the task, by date
+pub fn get_dependents(db_conn: &Connection, task_id: &str) -> Result<Vec<Task>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM tasks
+            WHERE id IN (SELECT task_id FROM task_deps WHERE depends_on = ?1)
+            ORDER BY {EFFECTIVE_DATE}, position"
+    ))?;
+
+    let rows = stmt.query_map([task_id], task_from_row)?;
+
+    rows.collect()
+}
+
+// whether the task waits on the other one, directly or through the tasks it depends on
+pub fn depends_on_task(db_conn: &Connection, task_id: &str, other_id: &str) -> Result<bool, Error> {
+    db_conn.query_row(
+        "WITH RECURSIVE deps(id) AS (
+            SELECT depends_on FROM task_deps WHERE task_id = ?1
+            UNION SELECT task_deps.depends_on FROM task_deps JOIN deps ON task_deps.task_id = deps.id
+        ) SELECT EXISTS (SELECT 1 FROM deps WHERE id = ?2)",
+        (task_id, other_id),
+        |row| row.get(0),
+    )
+}
+
+// every dependency as (task, the task it depends on), in the order they were added
+pub fn get_task_deps(db_conn: &Connection) -> Result<Vec<(String, String)>, Error> {
+    let mut stmt = db_conn.prepare("SELECT task_id, depends_on FROM task_deps ORDER BY rowid")?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+// links in the order they were added
+pub fn get_task_links(db_conn: &Connection, task_id: &str) -> Result<Vec<String>, Error> {
+    let mut stmt =
+        db_conn.prepare("SELECT url FROM task_links WHERE task_id = :id ORDER BY rowid")?;
+
+    let rows = stmt.query_map(named_params! { ":id": task_id }, |row| row.get(0))?;
+
+    rows.collect()
+}
+
+// a task of a template, added as todo wherever the template is applied
+#[derive(Debug)]
+pub struct TemplateTask {
+    pub description: String,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+}
+
+// the tasks' descriptions, priorities and attached tags, tags are kept space separated
+pub fn save_template(db_conn: &Connection, name: &str, tasks: &[Task]) -> Result<(), Error> {
+    let tx = write_transaction(db_conn)?;
+
+    // saving under an existing name replaces the old template
+    tx.execute(
+        "DELETE FROM template_tasks WHERE template = :template",
+        named_params! {
+            ":template": name
+        },
+    )?;
+
+    for (position, task) in tasks.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO template_tasks (template, position, description, priority, tags)
+                VALUES (?1, ?2, seal(?3), ?4, ?5)",
+            (
+                name,
+                position as i64,
+                &task.description,
+                task.priority,
+                task.tags.join(" "),
+            ),
+        )?;
+    }
+
+    tx.commit()
+}
+
+pub fn get_template_tasks(db_conn: &Connection, name: &str) -> Result<Vec<TemplateTask>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT unseal(description), priority, tags FROM template_tasks
+            WHERE template = :template ORDER BY position",
+    )?;
+
+    let rows = stmt.query_map(named_params! { ":template": name }, |row| {
+        Ok(TemplateTask {
+            description: row.get(0)?,
+            priority: row.get(1)?,
+            tags: row
+                .get::<_, Option<String>>(2)?
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+        })
+    })?;
+
+    rows.collect()
+}
+
+// all of the template's tasks on the date or, when one fails, none of them
+pub fn insert_template_tasks(
+    db_conn: &Connection,
+    template_tasks: &[TemplateTask],
+    timestamp: &str,
+) -> Result<(), Error> {
+    let tx = write_transaction(db_conn)?;
+
+    for template_task in template_tasks {
+        insert_template_task(&tx, template_task, timestamp)?;
+    }
+
+    tx.commit()
+}
+
+// the template's task as a todo on the date, with its priority and tags
+fn insert_template_task(
+    db_conn: &Connection,
+    template_task: &TemplateTask,
+    timestamp: &str,
+) -> Result<String, Error> {
+    let task_id = insert_task(
+        db_conn,
+        &template_task.description,
+        Status::Todo,
+        timestamp,
+        None,
+        template_task.priority,
+        None,
+        None,
+    )?;
+
+    insert_task_tags(db_conn, &task_id, &template_task.tags)?;
+
+    Ok(task_id)
+}
+
+pub fn get_templates(db_conn: &Connection) -> Result<Vec<(String, u32)>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT template, count(*) FROM template_tasks GROUP BY template ORDER BY template",
+    )?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+pub fn delete_template(db_conn: &Connection, name: &str) -> Result<usize, Error> {
+    db_conn.execute(
+        "DELETE FROM template_tasks WHERE template = :template",
+        named_params! {
+            ":template": name
+        },
+    )
+}
+
+/*
+ * inserts the template's tasks on the given date only once, returns the no. of tasks inserted
+ * or None when there is no such template, the date is then left to be populated later
+ * */
+pub fn populate_date_from_template(
+    db_conn: &Connection,
+    template: &str,
+    timestamp: &str,
+) -> Result<Option<usize>, Error> {
+    let tx = write_transaction(db_conn)?;
+
+    let template_tasks = get_template_tasks(&tx, template)?;
+    if template_tasks.is_empty() {
+        return Ok(None);
+    }
+
+    let is_new_date = tx.execute(
+        "INSERT OR IGNORE INTO daily_template_dates (date) VALUES (?1)",
+        [timestamp],
+    )? == 1;
+
+    if !is_new_date {
+        return Ok(Some(0));
+    }
+
+    for template_task in &template_tasks {
+        insert_template_task(&tx, template_task, timestamp)?;
+    }
+
+    tx.commit()?;
+
+    Ok(Some(template_tasks.len()))
+}
+
+fn sprint_from_row(row: &Row) -> Result<Sprint, Error> {
+    Ok(Sprint {
+        name: row.get(0)?,
+        start_date: row.get(1)?,
+        end_date: row.get(2)?,
+    })
+}
+
+pub fn insert_sprint(db_conn: &Connection, sprint: &Sprint) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT INTO sprints (name, start_date, end_date) VALUES (?1, ?2, ?3)",
+        (&sprint.name, &sprint.start_date, &sprint.end_date),
+    )?;
+
+    Ok(())
+}
+
+pub fn get_sprint_by_name(db_conn: &Connection, name: &str) -> Result<Sprint, Error> {
+    db_conn.query_row(
+        "SELECT name, start_date, end_date FROM sprints WHERE name = :name",
+        named_params! {
+            ":name": name
+        },
+        sprint_from_row,
+    )
+}
+
+// with overlapping sprints the most recently started one wins
+pub fn get_sprint_for_date(db_conn: &Connection, date: &str) -> Result<Sprint, Error> {
+    db_conn.query_row(
+        "SELECT name, start_date, end_date FROM sprints
+            WHERE :date BETWEEN start_date AND end_date ORDER BY start_date DESC LIMIT 1",
+        named_params! {
+            ":date": date
+        },
+        sprint_from_row,
+    )
+}
+
+pub fn get_sprints(db_conn: &Connection) -> Result<Vec<Sprint>, Error> {
+    let mut stmt =
+        db_conn.prepare("SELECT name, start_date, end_date FROM sprints ORDER BY start_date")?;
+
+    let rows = stmt.query_map([], sprint_from_row)?;
+
+    rows.collect()
+}
+
+pub fn insert_goal(db_conn: &Connection, goal: &Goal) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT INTO goals (name, quarter) VALUES (?1, ?2)",
+        (&goal.name, &goal.quarter),
+    )?;
+
+    Ok(())
+}
+
+pub fn goal_exists(db_conn: &Connection, name: &str) -> Result<bool, Error> {
+    db_conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM goals WHERE name = ?1)",
+        [name],
+        |row| row.get(0),
+    )
+}
+
+// a task counts towards one goal, linking it again moves it over
+pub fn set_task_goal(db_conn: &Connection, task_id: &str, goal: &str) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT OR REPLACE INTO task_goals (task_id, goal) VALUES (?1, ?2)",
+        (task_id, goal),
+    )?;
+
+    Ok(())
+}
+
+/*
+ * a subtask is a task with a parent_id, given once when it is added. the parent can be on
+ * another date, and deleting it leaves the subtasks as tasks of their own
+ * */
+pub fn set_task_parent(db_conn: &Connection, task_id: &str, parent_id: &str) -> Result<(), Error> {
+    db_conn.execute(
+        "UPDATE tasks SET parent_id = ?2 WHERE id = ?1",
+        (task_id, parent_id),
+    )?;
+
+    Ok(())
+}
+
+// the subtasks of the task and theirs in turn, by date
+pub fn get_subtasks(db_conn: &Connection, task_id: &str) -> Result<Vec<Task>, Error> {
+    let mut stmt = db_conn.prepare(&format!(
+        "WITH RECURSIVE subtasks(id) AS (
+            SELECT id FROM tasks WHERE parent_id = ?1
+            UNION SELECT tasks.id FROM tasks JOIN subtasks ON tasks.parent_id = subtasks.id
+        ) SELECT {TASK_COLUMNS} FROM tasks WHERE id IN subtasks ORDER BY {EFFECTIVE_DATE}, position"
+    ))?;
+
+    let rows = stmt.query_map([task_id], task_from_row)?;
+
+    rows.collect()
+}
+
+#[derive(Debug)]
+pub struct ProjectCount {
+    pub name: String,
+    pub open: u32,
+    pub total: u32,
+}
+
+/*
+ * every project some task was given, with how many of its tasks are not done yet. a project
+ * only exists through its tasks, names differing in case only count as one
+ * */
+pub fn get_projects(db_conn: &Connection) -> Result<Vec<ProjectCount>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT MIN(project), COUNT(CASE WHEN status != 'done' THEN 1 END), COUNT(*)
+            FROM tasks
+            WHERE project IS NOT NULL
+            GROUP BY project COLLATE NOCASE
+            ORDER BY project COLLATE NOCASE",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(ProjectCount {
+            name: row.get(0)?,
+            open: row.get(1)?,
+            total: row.get(2)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+#[derive(Debug)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    pub done: u32,
+    pub total: u32,
+}
+
+// goals of the quarter (eg. 2024-Q3) with their linked tasks counted
+pub fn get_goal_progress(db_conn: &Connection, quarter: &str) -> Result<Vec<GoalProgress>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT goals.name, goals.quarter,
+                COUNT(CASE WHEN tasks.status = 'done' THEN 1 END), COUNT(tasks.id)
+            FROM goals
+            LEFT JOIN task_goals ON task_goals.goal = goals.name
+            LEFT JOIN tasks ON tasks.id = task_goals.task_id
+            WHERE goals.quarter = :quarter
+            GROUP BY goals.name
+            ORDER BY goals.name",
+    )?;
+
+    let rows = stmt.query_map(named_params! { ":quarter": quarter }, |row| {
+        Ok(GoalProgress {
+            goal: Goal {
+                name: row.get(0)?,
+                quarter: row.get(1)?,
+            },
+            done: row.get(2)?,
+            total: row.get(3)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+pub fn insert_task_attachment(
+    db_conn: &Connection,
+    task_id: &str,
+    path: &str,
+) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT OR IGNORE INTO task_attachments (task_id, path) VALUES (?1, ?2)",
+        (task_id, path),
+    )?;
+
+    Ok(())
+}
+
+pub fn get_task_attachments(db_conn: &Connection, task_id: &str) -> Result<Vec<String>, Error> {
+    let mut stmt =
+        db_conn.prepare("SELECT path FROM task_attachments WHERE task_id = :id ORDER BY rowid")?;
+
+    let rows = stmt.query_map(named_params! { ":id": task_id }, |row| row.get(0))?;
+
+    rows.collect()
+}
+
+// annotations are append only, there is no way to edit or remove one
+pub fn insert_task_annotation(
+    db_conn: &Connection,
+    task_id: &str,
+    note: &str,
+) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT INTO task_annotations (task_id, created_at, note) VALUES (?1, ?2, seal(?3))",
+        (task_id, now_timestamp(), note),
+    )?;
+
+    Ok(())
+}
+
+// (created_at, note) pairs, oldest first
+pub fn get_task_annotations(
+    db_conn: &Connection,
+    task_id: &str,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT created_at, unseal(note) FROM task_annotations WHERE task_id = :id ORDER BY rowid",
+    )?;
+
+    let rows = stmt.query_map(named_params! { ":id": task_id }, |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+
+    rows.collect()
+}
+
+pub fn get_journal_entry(db_conn: &Connection, date: &str) -> Result<Option<String>, Error> {
+    db_conn
+        .query_row(
+            "SELECT unseal(note) FROM journal WHERE date = :date",
+            named_params! { ":date": date },
+            |row| row.get(0),
+        )
+        .optional()
+}
+
+// an empty note removes the date's entry
+pub fn set_journal_entry(db_conn: &Connection, date: &str, note: &str) -> Result<(), Error> {
+    match note.trim().is_empty() {
+        true => db_conn.execute("DELETE FROM journal WHERE date = ?1", [date])?,
+        false => db_conn.execute(
+            "INSERT OR REPLACE INTO journal (date, note, updated_at) VALUES (?1, seal(?2), ?3)",
+            (date, note, now_timestamp()),
+        )?,
+    };
+
+    Ok(())
+}
+
+// (date, note) pairs of the dates from start to end that have one, oldest first
+pub fn get_journal_entries(
+    db_conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT date, unseal(note) FROM journal WHERE date BETWEEN ?1 AND ?2 ORDER BY date",
+    )?;
+
+    let rows = stmt.query_map([start_date, end_date], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+/*
+ * a statement of the user's own, for query. query_only makes sqlite refuse writes to the
+ * database, but pragmas (journal_mode, query_only itself), attach and vacuum into get past it,
+ * so only statements reading rows are taken to begin with. prepare takes a single statement
+ * returns the column names and the rows
+ * */
+pub fn run_query(
+    db_conn: &Connection,
+    sql: &str,
+) -> Result<(Vec<String>, Vec<Vec<Value>>), String> {
+    let keyword = sql
+        .trim_start()
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+
+    if !matches!(keyword.as_str(), "SELECT" | "WITH" | "VALUES" | "EXPLAIN") {
+        return Err("only SELECT, WITH, VALUES and EXPLAIN statements can be run".to_string());
+    }
+
+    db_conn
+        .pragma_update(None, "query_only", true)
+        .map_err(|error| error.to_string())?;
+
+    let result = (|| {
+        let mut stmt = db_conn.prepare(sql)?;
+        if !stmt.readonly() {
+            return Err(Error::InvalidQuery);
+        }
+
+        let columns: Vec<String> = stmt
+            .column_names()
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        let rows = stmt
+            .query_map([], |row| {
+                (0..columns.len())
+                    .map(|index| row.get::<_, Value>(index))
+                    .collect()
+            })?
+            .collect::<Result<Vec<Vec<Value>>, Error>>()?;
+
+        Ok((columns, rows))
+    })();
+
+    db_conn
+        .pragma_update(None, "query_only", false)
+        .map_err(|error| error.to_string())?;
+
+    result.map_err(|error| match error {
+        Error::InvalidQuery => "the statement would change the database".to_string(),
+        error => error.to_string(),
+    })
+}
+
+// small key value store for things the app has to remember between runs
+pub fn get_app_state(db_conn: &Connection, key: &str) -> Result<Option<String>, Error> {
+    match db_conn.query_row(
+        "SELECT value FROM app_state WHERE key = :key",
+        named_params! { ":key": key },
+        |row| row.get(0),
+    ) {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::QueryReturnedNoRows) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+pub fn set_app_state(db_conn: &Connection, key: &str, value: &str) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT OR REPLACE INTO app_state (key, value) VALUES (?1, ?2)",
+        (key, value),
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_app_state(db_conn: &Connection, key: &str) -> Result<(), Error> {
+    db_conn.execute("DELETE FROM app_state WHERE key = ?1", [key])?;
+
+    Ok(())
+}
+
+/*
+ * how often each command was run, with usage_stats = true in config. local to this
+ * database, merge and sync leave it out
+ * */
+pub fn record_usage(db_conn: &Connection, command: &str) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT INTO usage (command, runs, last_used) VALUES (?1, 1, ?2)
+            ON CONFLICT (command) DO UPDATE SET runs = runs + 1, last_used = excluded.last_used",
+        (command, now_timestamp()),
+    )?;
+
+    Ok(())
+}
+
+// command, runs and when it was last run, the most run first
+pub fn get_usage(db_conn: &Connection) -> Result<Vec<(String, u32, String)>, Error> {
+    let mut stmt = db_conn
+        .prepare("SELECT command, runs, last_used FROM usage ORDER BY runs DESC, command")?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+
+    rows.collect()
+}
+
+pub fn clear_usage(db_conn: &Connection) -> Result<usize, Error> {
+    db_conn.execute("DELETE FROM usage", [])
+}
+
+/*
+ * a batch of an import goes in as one transaction, together with the progress marker
+ * (app_state, see handle_cmd_import) saying how far the import got. whatever happens,
+ * the marker matches what is stored, so an import can be resumed or rolled back from it
+ *
+ * tasks keep their ids, ones already present are skipped, returns the no. of tasks added
+ * */
+pub fn insert_imported_tasks(
+    db_conn: &Connection,
+    import_id: &str,
+    tasks: &[Task],
+    progress_key: &str,
+    progress: &str,
+) -> Result<usize, Error> {
+    let tx = write_transaction(db_conn)?;
+
+    let now = now_timestamp();
+    let mut inserted = 0;
+
+    // looked up once per batch, next_position scans the whole table for every task
+    let mut last_positions = last_positions(&tx)?;
+
+    let mut insert_task = tx.prepare_cached(
+        "INSERT OR IGNORE INTO tasks (id, description, status, date, updated_at, deferred_until, points, block_reason, position, priority, project, due, parent_id) VALUES (?1, seal(?2), ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+    )?;
+    let mut insert_imported =
+        tx.prepare_cached("INSERT INTO imported_tasks (import_id, task_id) VALUES (?1, ?2)")?;
+
+    for task in tasks {
+        let last_position = last_positions
+            .entry(task.effective_date().clone())
+            .or_default();
+        let position = *last_position + 1;
+
+        let is_new = insert_task.execute((
+            &task.id,
+            &task.description,
+            task.status,
+            &task.date,
+            &now,
+            &task.deferred_until,
+            task.points,
+            &task.block_reason,
+            position,
+            task.priority,
+            &task.project,
+            &task.due,
+            &task.parent_id,
+        ))? == 1;
+
+        if !is_new {
+            continue;
+        }
+
+        *last_position = position;
+        insert_imported.execute((import_id, &task.id))?;
+        insert_task_tags(&tx, &task.id, &task.tags)?;
+        record_change(
+            &tx,
+            &task.id,
+            &now,
+            "created",
+            None,
+            Some(&task.description),
+        )?;
+        inserted += 1;
+    }
+
+    set_app_state(&tx, progress_key, progress)?;
+
+    drop(insert_task);
+    drop(insert_imported);
+    tx.commit()?;
+
+    Ok(inserted)
+}
+
+/*
+ * tasks made up by seed, in one go. they keep their ids and updated times, so they look
+ * added and worked on back then, and go after whatever is on their dates already
+ * */
+pub fn insert_seed_tasks(db_conn: &Connection, tasks: &[Task]) -> Result<(), Error> {
+    let tx = write_transaction(db_conn)?;
+
+    for task in tasks {
+        let position = next_position(&tx, task.effective_date())?;
+        let updated_at = task.updated_at.as_deref().unwrap_or_default();
+        let completed_at = (task.status == Status::Done).then_some(updated_at);
+
+        tx.execute(
+            "INSERT INTO tasks (id, description, status, date, updated_at, points, block_reason, completed_at, position) VALUES (?1, seal(?2), ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (
+                &task.id,
+                &task.description,
+                task.status,
+                &task.date,
+                updated_at,
+                task.points,
+                &task.block_reason,
+                completed_at,
+                position,
+            ),
+        )?;
+
+        let created_at = task_created_at(task)
+            .map(|created_at| created_at.to_rfc3339_opts(SecondsFormat::Micros, true))
+            .unwrap_or_else(|| updated_at.to_string());
+        record_change(
+            &tx,
+            &task.id,
+            &created_at,
+            "created",
+            None,
+            Some(&task.description),
+        )?;
+
+        if task.status != Status::Todo {
+            let status = task.status.to_string();
+            record_change(
+                &tx,
+                &task.id,
+                updated_at,
+                "status",
+                Some("todo"),
+                Some(&status),
+            )?;
+        }
+    }
+
+    tx.commit()
+}
+
+// the import is done, forgets which tasks it added. returns how many that were
+pub fn finish_import(
+    db_conn: &Connection,
+    import_id: &str,
+    progress_key: &str,
+) -> Result<usize, Error> {
+    let tx = write_transaction(db_conn)?;
+
+    let imported = tx.execute(
+        "DELETE FROM imported_tasks WHERE import_id = ?1",
+        [import_id],
+    )?;
+    delete_app_state(&tx, progress_key)?;
+
+    tx.commit()?;
+
+    Ok(imported)
+}
+
+/*
+ * tasks from another app, each with its id over there and when it was completed
+ * a task seen before (by source and external id) is updated rather than added again, one
+ * deleted here since stays deleted. returns the no. of tasks added and updated
+ * */
+pub fn store_external_tasks(
+    db_conn: &Connection,
+    source: &str,
+    tasks: &[(String, Task, Option<String>)],
+) -> Result<(usize, usize), Error> {
+    let tx = write_transaction(db_conn)?;
+
+    let now = now_timestamp();
+    let (mut added, mut updated) = (0, 0);
+
+    for (external_id, task, completed_at) in tasks {
+        let task_id: Option<String> = tx
+            .query_row(
+                "SELECT task_id FROM external_tasks WHERE source = ?1 AND external_id = ?2",
+                (source, external_id),
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let Some(task_id) = task_id else {
+            let position = next_position(&tx, &task.date)?;
+
+            tx.execute(
+                "INSERT INTO tasks (id, description, status, date, updated_at, completed_at, position) VALUES (?1, seal(?2), ?3, ?4, ?5, ?6, ?7)",
+                (&task.id, &task.description, task.status, &task.date, &now, completed_at, position),
+            )?;
+            tx.execute(
+                "INSERT INTO external_tasks (source, external_id, task_id) VALUES (?1, ?2, ?3)",
+                (source, external_id, &task.id),
+            )?;
+            record_change(
+                &tx,
+                &task.id,
+                &now,
+                "created",
+                None,
+                Some(&task.description),
+            )?;
+
+            added += 1;
+            continue;
+        };
+
+        let current = tx
+            .query_row(
+                &format!("SELECT {TASK_COLUMNS} FROM tasks WHERE id = ?1"),
+                [&task_id],
+                task_from_row,
+            )
+            .optional()?;
+
+        let Some(current) = current else {
+            continue;
+        };
+
+        if current.description == task.description
+            && current.status == task.status
+            && current.date == task.date
+        {
+            continue;
+        }
+
+        let position = match current.date == task.date {
+            true => current.position,
+            false => next_position(&tx, &task.date)?,
+        };
+
+        tx.execute(
+            "UPDATE tasks SET description = seal(:description), status = :status, date = :date,
+                position = :position, completed_at = :completed_at, updated_at = :now
+                WHERE id = :id",
+            named_params! {
+                ":description": task.description,
+                ":status": task.status,
+                ":date": task.date,
+                ":position": position,
+                ":completed_at": completed_at,
+                ":now": &now,
+                ":id": task_id,
+            },
+        )?;
+
+        if current.description != task.description {
+            record_change(
+                &tx,
+                &task_id,
+                &now,
+                "description",
+                Some(&current.description),
+                Some(&task.description),
+            )?;
+        }
+
+        if current.status != task.status {
+            record_change(
+                &tx,
+                &task_id,
+                &now,
+                "status",
+                Some(&current.status.to_string()),
+                Some(&task.status.to_string()),
+            )?;
+        }
+
+        updated += 1;
+    }
+
+    tx.commit()?;
+
+    Ok((added, updated))
+}
+
+// the id over in the source app of a task stored by store_external_tasks
+pub fn get_external_id(
+    db_conn: &Connection,
+    source: &str,
+    task_id: &str,
+) -> Result<Option<String>, Error> {
+    db_conn
+        .query_row(
+            "SELECT external_id FROM external_tasks WHERE source = ?1 AND task_id = ?2",
+            (source, task_id),
+            |row| row.get(0),
+        )
+        .optional()
+}
+
+// the task stored for the id in the source app, None when it was never stored
+pub fn get_external_task_id(
+    db_conn: &Connection,
+    source: &str,
+    external_id: &str,
+) -> Result<Option<String>, Error> {
+    db_conn
+        .query_row(
+            "SELECT task_id FROM external_tasks WHERE source = ?1 AND external_id = ?2",
+            (source, external_id),
+            |row| row.get(0),
+        )
+        .optional()
+}
+
+// a task stored for an item of a synced app, with what the last sync saw of the item
+pub struct ExternalTask {
+    pub external_id: String,
+    pub task_id: String,
+    pub etag: Option<String>,
+    pub synced_at: Option<String>,
+}
+
+pub fn get_external_tasks(db_conn: &Connection, source: &str) -> Result<Vec<ExternalTask>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT external_id, task_id, etag, synced_at FROM external_tasks WHERE source = ?1",
+    )?;
+
+    let rows = stmt.query_map([source], |row| {
+        Ok(ExternalTask {
+            external_id: row.get(0)?,
+            task_id: row.get(1)?,
+            etag: row.get(2)?,
+            synced_at: row.get(3)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+// marks the item and its task as in sync as of now, the etag is the version of the item
+pub fn set_external_sync_state(
+    db_conn: &Connection,
+    source: &str,
+    external_id: &str,
+    task_id: &str,
+    etag: Option<&str>,
+) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT OR REPLACE INTO external_tasks (source, external_id, task_id, etag, synced_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+        (source, external_id, task_id, etag, now_timestamp()),
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_external_task(
+    db_conn: &Connection,
+    source: &str,
+    external_id: &str,
+) -> Result<(), Error> {
+    db_conn.execute(
+        "DELETE FROM external_tasks WHERE source = ?1 AND external_id = ?2",
+        (source, external_id),
+    )?;
+
+    Ok(())
+}
+
+// removes every task the import added so far, returns the no. of tasks removed
+pub fn abort_import(
+    db_conn: &Connection,
+    import_id: &str,
+    progress_key: &str,
+) -> Result<usize, Error> {
+    let tx = write_transaction(db_conn)?;
+
+    let imported_ids = "SELECT task_id FROM imported_tasks WHERE import_id = ?1";
+
+    tx.execute(
+        &format!(
+            "INSERT OR REPLACE INTO deleted_tasks (task_id, deleted_at)
+                SELECT task_id, ?2 FROM ({imported_ids})"
+        ),
+        (import_id, now_timestamp()),
+    )?;
+    tx.execute(
+        &format!("DELETE FROM task_history WHERE task_id IN ({imported_ids})"),
+        [import_id],
+    )?;
+    let removed = tx.execute(
+        &format!("DELETE FROM tasks WHERE id IN ({imported_ids})"),
+        [import_id],
+    )?;
+    tx.execute(
+        "DELETE FROM imported_tasks WHERE import_id = ?1",
+        [import_id],
+    )?;
+    delete_app_state(&tx, progress_key)?;
+
+    tx.commit()?;
+
+    Ok(removed)
+}
+
+const ARCHIVE_FILE_NAME: &str = "archive.db";
+
+// archive.db while attached, see attach_archive
+const ARCHIVE_SCHEMA: &str = "archive";
+
+// what TASK_COLUMNS and the filters read, the same in both databases
+const ARCHIVE_COLUMNS: &str = "id, description, status, date, updated_at, deferred_until, \
+    points, block_reason, position, short_id, priority, project, due, parent_id";
+
+pub fn get_archive_path() -> PathBuf {
+    get_data_dir().join(ARCHIVE_FILE_NAME)
+}
+
+/*
+ * archive.db keeps the done tasks archive moved out, with their history, so the database
+ * every command opens stays small. it is only attached for --archived and archive itself
+ *
+ * its tables are copies of tasks and task_history without the constraints, columns added to
+ * those later get added to the copies here. false when there is no archive to attach
+ * */
+pub fn attach_archive(db_conn: &Connection, create: bool) -> Result<bool, Error> {
+    let path = get_archive_path();
+    if !create && !path.exists() {
+        return Ok(false);
+    }
+
+    db_conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {ARCHIVE_SCHEMA}"),
+        [path.to_string_lossy()],
+    )?;
+
+    // written to by archive only, that never runs read-only
+    if db_conn.is_readonly(ARCHIVE_SCHEMA)? {
+        return Ok(true);
+    }
+
+    for table in ["tasks", "task_history"] {
+        db_conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {ARCHIVE_SCHEMA}.{table} AS SELECT * FROM main.{table} WHERE 0"
+            ),
+            [],
+        )?;
+
+        let archived = table_columns(db_conn, ARCHIVE_SCHEMA, table)?;
+
+        for (name, kind) in table_columns(db_conn, "main", table)? {
+            if !archived.iter().any(|(archived, _)| *archived == name) {
+                db_conn.execute(
+                    &format!("ALTER TABLE {ARCHIVE_SCHEMA}.{table} ADD COLUMN {name} {kind}"),
+                    [],
+                )?;
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+// names and declared types
+fn table_columns(
+    db_conn: &Connection,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut stmt = db_conn.prepare("SELECT name, type FROM pragma_table_info(?1, ?2)")?;
+
+    let rows = stmt.query_map([table, schema], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+/*
+ * moves the tasks done before the date to archive.db, returns how many
+ *
+ * day_counts goes on counting them, so stats still cover the archive. archived_tasks
+ * remembers them for merge, which would otherwise bring them back from a database that
+ * still has them
+ * */
+pub fn archive_tasks(db_conn: &Connection, before: &str) -> Result<usize, Error> {
+    attach_archive(db_conn, true)?;
+
+    let result = move_to_archive(db_conn, before);
+
+    db_conn.execute(&format!("DETACH DATABASE {ARCHIVE_SCHEMA}"), [])?;
+
+    // the pages freed only leave the file with a vacuum
+    if let Ok(1..) = result {
+        db_conn.execute("VACUUM", [])?;
+    }
+
+    result
+}
+
+fn move_to_archive(db_conn: &Connection, before: &str) -> Result<usize, Error> {
+    let tx = write_transaction(db_conn)?;
+    let now = now_timestamp();
+
+    tx.execute(
+        &format!(
+            "INSERT OR REPLACE INTO main.archived_tasks (task_id, archived_at)
+                SELECT id, ?2 FROM main.tasks WHERE status = ?3 AND {EFFECTIVE_DATE} < ?1"
+        ),
+        (before, &now, Status::Done),
+    )?;
+
+    let archived_ids = "SELECT task_id FROM main.archived_tasks WHERE archived_at = ?1";
+
+    for (table, id_column) in [("tasks", "id"), ("task_history", "task_id")] {
+        let columns: Vec<String> = table_columns(&tx, "main", table)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let columns = columns.join(", ");
+
+        tx.execute(
+            &format!(
+                "INSERT INTO {ARCHIVE_SCHEMA}.{table} ({columns})
+                    SELECT {columns} FROM main.{table} WHERE {id_column} IN ({archived_ids})"
+            ),
+            [&now],
+        )?;
+    }
+
+    // counted once more first, the delete below takes them off again
+    tx.execute(
+        &format!(
+            "INSERT INTO main.day_counts (date, total, done)
+                SELECT {EFFECTIVE_DATE}, COUNT(*), COUNT(*) FROM main.tasks
+                    WHERE id IN ({archived_ids}) GROUP BY 1
+                ON CONFLICT (date) DO UPDATE SET
+                    total = total + excluded.total, done = done + excluded.done"
+        ),
+        [&now],
+    )?;
+
+    tx.execute(
+        &format!("DELETE FROM main.task_history WHERE task_id IN ({archived_ids})"),
+        [&now],
+    )?;
+    let archived = tx.execute(
+        &format!("DELETE FROM main.tasks WHERE id IN ({archived_ids})"),
+        [&now],
+    )?;
+
+    tx.commit()?;
+
+    Ok(archived)
+}
+
+pub fn save_view(db_conn: &Connection, name: &str, filter: &str) -> Result<(), Error> {
+    db_conn.execute(
+        "INSERT OR REPLACE INTO views (name, filter) VALUES (?1, ?2)",
+        (name, filter),
+    )?;
+
+    Ok(())
+}
+
+pub fn get_view_filter(db_conn: &Connection, name: &str) -> Result<String, Error> {
+    db_conn.query_row(
+        "SELECT filter FROM views WHERE name = :name",
+        named_params! {
+            ":name": name
+        },
+        |row| row.get(0),
+    )
+}
+
+pub fn get_views(db_conn: &Connection) -> Result<Vec<(String, String)>, Error> {
+    let mut stmt = db_conn.prepare("SELECT name, filter FROM views ORDER BY name")?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+pub fn delete_view(db_conn: &Connection, name: &str) -> Result<usize, Error> {
+    db_conn.execute(
+        "DELETE FROM views WHERE name = :name",
+        named_params! {
+            ":name": name
+        },
+    )
+}
+
+// every column of a task as stored, descriptions stay sealed on their way between databases
+const MERGE_COLUMNS: &str = "id, description, status, date, updated_at, deferred_until, points, \
+    block_reason, position, completed_at, priority, project, due, parent_id";
+
+// the other database while merging, attached to the same connection
+const MERGE_SCHEMA: &str = "other";
+
+#[derive(Debug, Default)]
+pub struct MergeCounts {
+    pub added: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub conflicts: usize,
+}
+
+/*
+ * merges another copy of the database (a synced conflicted copy, the other side of a git
+ * merge, ...) into this one, task by task instead of one file replacing the other
+ *
+ * tasks are matched on their ulid. when both have changed, the later updated_at wins. a
+ * version the winner's history has been through is just older, anything else was changed on
+ * both sides, the losing version is kept in merge_conflicts for `conflicts`. deleted tasks
+ * leave a tombstone in deleted_tasks, so they are deleted on the other side too
+ * */
+pub fn merge_db(db_conn: &Connection, path: &Path) -> Result<MergeCounts, Error> {
+    with_attached(db_conn, path, |db_conn| {
+        merge_attached(db_conn, &path.to_string_lossy())
+    })
+}
+
+fn with_attached<T>(
+    db_conn: &Connection,
+    path: &Path,
+    work: impl FnOnce(&Connection) -> Result<T, Error>,
+) -> Result<T, Error> {
+    db_conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {MERGE_SCHEMA}"),
+        [path.to_string_lossy()],
+    )?;
+
+    let result =
+        check_attached_version(db_conn, &path.to_string_lossy()).and_then(|()| work(db_conn));
+
+    db_conn.execute(&format!("DETACH DATABASE {MERGE_SCHEMA}"), [])?;
+
+    result
+}
+
+// a schema mismatch would copy columns into the wrong places
+fn check_attached_version(db_conn: &Connection, source: &str) -> Result<(), Error> {
+    let other_version: u32 =
+        db_conn.pragma_query_value(Some(MERGE_SCHEMA), "user_version", |row| row.get(0))?;
+
+    if other_version as usize != MIGRATIONS.len() {
+        return Err(Error::InvalidParameterName(format!(
+            "{source} has schema version {other_version} instead of {}, open it once with this version first",
+            MIGRATIONS.len()
+        )));
+    }
+
+    Ok(())
+}
+
+fn merge_attached(db_conn: &Connection, source: &str) -> Result<MergeCounts, Error> {
+    let tx = write_transaction(db_conn)?;
+    let now = now_timestamp();
+    let mut counts = MergeCounts::default();
+
+    let other_tasks: Vec<(String, Option<String>)> = tx
+        .prepare(&format!("SELECT id, updated_at FROM {MERGE_SCHEMA}.tasks"))?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    for (task_id, other_updated_at) in other_tasks {
+        let local_updated_at: Option<Option<String>> = tx
+            .query_row(
+                "SELECT updated_at FROM main.tasks WHERE id = ?1",
+                [&task_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match local_updated_at {
+            Some(local_updated_at) if local_updated_at == other_updated_at => {}
+            // NULL, from before updated_at was tracked, is older than anything
+            Some(local_updated_at) if local_updated_at > other_updated_at => {
+                if !has_been_at(&tx, "main", &task_id, other_updated_at.as_deref())? {
+                    counts.conflicts +=
+                        log_lost_version(&tx, MERGE_SCHEMA, &task_id, &now, source)?;
+                }
+            }
+            Some(local_updated_at) => {
+                if !has_been_at(&tx, MERGE_SCHEMA, &task_id, local_updated_at.as_deref())? {
+                    counts.conflicts += log_lost_version(&tx, "main", &task_id, &now, "local")?;
+                }
+                copy_other_task(&tx, &task_id)?;
+                counts.updated += 1;
+            }
+            None => {
+                let deleted_at: Option<String> = tx
+                    .query_row(
+                        "SELECT deleted_at FROM main.deleted_tasks WHERE task_id = ?1",
+                        [&task_id],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+
+                let archived = tx
+                    .query_row(
+                        "SELECT 1 FROM main.archived_tasks WHERE task_id = ?1",
+                        [&task_id],
+                        |_| Ok(()),
+                    )
+                    .optional()?
+                    .is_some();
+
+                match deleted_at {
+                    // archive.db has it already
+                    _ if archived => {}
+                    // the other side simply hasn't seen the delete yet
+                    Some(deleted_at) if other_updated_at <= Some(deleted_at.clone()) => {}
+                    Some(deleted_at) => {
+                        counts.conflicts +=
+                            log_lost_delete(&tx, &task_id, &deleted_at, &now, "local")?;
+                        tx.execute(
+                            "DELETE FROM main.deleted_tasks WHERE task_id = ?1",
+                            [&task_id],
+                        )?;
+                        copy_other_task(&tx, &task_id)?;
+                        counts.added += 1;
+                    }
+                    None => {
+                        copy_other_task(&tx, &task_id)?;
+                        counts.added += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let other_deletes: Vec<(String, String)> = tx
+        .prepare(&format!(
+            "SELECT task_id, deleted_at FROM {MERGE_SCHEMA}.deleted_tasks"
+        ))?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    for (task_id, deleted_at) in other_deletes {
+        let local_updated_at: Option<Option<String>> = tx
+            .query_row(
+                "SELECT updated_at FROM main.tasks WHERE id = ?1",
+                [&task_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        // changed here after it was deleted there, the change wins over the delete
+        if let Some(Some(local_updated_at)) = &local_updated_at
+            && *local_updated_at > deleted_at
+        {
+            counts.conflicts += log_lost_delete(&tx, &task_id, &deleted_at, &now, source)?;
+            continue;
+        }
+
+        if local_updated_at.is_some() {
+            delete_task_rows(&tx, &task_id)?;
+            counts.deleted += 1;
+        }
+
+        // when it was deleted there, not now
+        tx.execute(
+            "INSERT OR REPLACE INTO main.deleted_tasks (task_id, deleted_at) VALUES (?1, ?2)",
+            (&task_id, &deleted_at),
+        )?;
+    }
+
+    merge_task_details(&tx, "main", MERGE_SCHEMA)?;
+
+    tx.commit()?;
+
+    Ok(counts)
+}
+
+// whether the task went through the version saved at updated_at, ie. it's an older one
+fn has_been_at(
+    db_conn: &Connection,
+    schema: &str,
+    task_id: &str,
+    updated_at: Option<&str>,
+) -> Result<bool, Error> {
+    let Some(updated_at) = updated_at else {
+        return Ok(true);
+    };
+
+    db_conn.query_row(
+        &format!(
+            "SELECT EXISTS (SELECT 1 FROM {schema}.task_history WHERE task_id = ?1 AND changed_at = ?2)"
+        ),
+        (task_id, updated_at),
+        |row| row.get(0),
+    )
+}
+
+fn copy_other_task(db_conn: &Connection, task_id: &str) -> Result<(), Error> {
+    db_conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO main.tasks ({MERGE_COLUMNS}, short_id)
+                SELECT {MERGE_COLUMNS}, (SELECT short_id FROM main.tasks WHERE id = ?1)
+                FROM {MERGE_SCHEMA}.tasks WHERE id = ?1"
+        ),
+        [task_id],
+    )?;
+
+    Ok(())
+}
+
+fn log_lost_version(
+    db_conn: &Connection,
+    schema: &str,
+    task_id: &str,
+    merged_at: &str,
+    lost_from: &str,
+) -> Result<usize, Error> {
+    db_conn.execute(
+        &format!(
+            "INSERT INTO merge_conflicts (task_id, merged_at, lost_from, deleted, description,
+                status, date, updated_at, deferred_until, points, block_reason, priority, project,
+                due)
+                SELECT id, ?2, ?3, 0, description, status, date, updated_at, deferred_until,
+                points, block_reason, priority, project, due FROM {schema}.tasks AS lost WHERE id = ?1 AND NOT EXISTS (
+                    SELECT 1 FROM merge_conflicts
+                        WHERE task_id = ?1 AND deleted = 0 AND updated_at IS lost.updated_at
+                )"
+        ),
+        (task_id, merged_at, lost_from),
+    )
+}
+
+fn log_lost_delete(
+    db_conn: &Connection,
+    task_id: &str,
+    deleted_at: &str,
+    merged_at: &str,
+    lost_from: &str,
+) -> Result<usize, Error> {
+    db_conn.execute(
+        "INSERT INTO merge_conflicts (task_id, merged_at, lost_from, deleted, updated_at)
+            SELECT ?1, ?2, ?3, 1, ?4 WHERE NOT EXISTS (
+                SELECT 1 FROM merge_conflicts WHERE task_id = ?1 AND deleted = 1 AND updated_at = ?4
+            )",
+        (task_id, merged_at, lost_from, deleted_at),
+    )
+}
+
+// links, notes, history, ... of the tasks there are now in into, the union of both sides
+fn merge_task_details(db_conn: &Connection, into: &str, from: &str) -> Result<(), Error> {
+    let task_ids = format!("SELECT id FROM {into}.tasks");
+
+    for table in [
+        "task_links",
+        "task_attachments",
+        "task_goals",
+        "task_tags",
+        "task_deps",
+    ] {
+        db_conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {into}.{table}
+                    SELECT * FROM {from}.{table} WHERE task_id IN ({task_ids})"
+            ),
+            [],
+        )?;
+    }
+
+    db_conn.execute(
+        &format!(
+            "INSERT INTO {into}.task_history
+                SELECT * FROM {from}.task_history AS from_history
+                WHERE task_id IN ({task_ids}) AND NOT EXISTS (
+                    SELECT 1 FROM {into}.task_history
+                        WHERE task_id = from_history.task_id
+                            AND changed_at = from_history.changed_at
+                            AND field = from_history.field
+                )"
+        ),
+        [],
+    )?;
+
+    db_conn.execute(
+        &format!(
+            "INSERT INTO {into}.task_annotations
+                SELECT * FROM {from}.task_annotations AS from_note
+                WHERE task_id IN ({task_ids}) AND NOT EXISTS (
+                    SELECT 1 FROM {into}.task_annotations
+                        WHERE task_id = from_note.task_id
+                            AND created_at = from_note.created_at
+                )"
+        ),
+        [],
+    )?;
+
+    // the entry of a date edited last wins
+    db_conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {into}.journal
+                SELECT * FROM {from}.journal AS from_entry
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM {into}.journal
+                        WHERE date = from_entry.date AND updated_at >= from_entry.updated_at
+                )"
+        ),
+        [],
+    )?;
+
+    // named things only missing in into are added, the same name keeps what's there
+    for table in [
+        "sprints",
+        "views",
+        "template_tasks",
+        "daily_template_dates",
+        "goals",
+    ] {
+        db_conn.execute(
+            &format!("INSERT OR IGNORE INTO {into}.{table} SELECT * FROM {from}.{table}"),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+// the tables holding details of a task, by task_id
+const TASK_DETAIL_TABLES: [&str; 7] = [
+    "task_links",
+    "task_tags",
+    "task_deps",
+    "task_attachments",
+    "task_history",
+    "task_annotations",
+    "task_goals",
+];
+
+// tasks dated since or later, a snoozed task by the date it was snoozed to
+const SINCE_CONDITION: &str = "(?1 IS NULL OR COALESCE(deferred_until, date) >= ?1)";
+
+#[derive(Debug, Default)]
+pub struct PushCounts {
+    pub pushed: usize,
+    pub deleted: usize,
+    pub pruned: usize,
+}
+
+/*
+ * the other half of a sync, after merge_db pulled in everything the remote has: it gets
+ * this side's version of every task dated since, and the deletes. tasks older than since
+ * are pruned from the remote without a tombstone, they stay here and a constrained remote
+ * (a phone, a small hosted tier) only ever carries the recent ones, so pulls stay small too
+ * */
+pub fn push_db(
+    db_conn: &Connection,
+    path: &Path,
+    since: Option<&str>,
+) -> Result<PushCounts, Error> {
+    with_attached(db_conn, path, |db_conn| push_attached(db_conn, since))
+}
+
+fn push_attached(db_conn: &Connection, since: Option<&str>) -> Result<PushCounts, Error> {
+    let tx = write_transaction(db_conn)?;
+    let mut counts = PushCounts::default();
+
+    tx.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {MERGE_SCHEMA}.deleted_tasks SELECT * FROM main.deleted_tasks"
+        ),
+        [],
+    )?;
+
+    counts.deleted = tx.execute(
+        &format!(
+            "DELETE FROM {MERGE_SCHEMA}.tasks WHERE id IN (SELECT task_id FROM main.deleted_tasks)"
+        ),
+        [],
+    )?;
+
+    counts.pushed = tx.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {MERGE_SCHEMA}.tasks ({MERGE_COLUMNS}, short_id)
+                SELECT {MERGE_COLUMNS}, (SELECT short_id FROM {MERGE_SCHEMA}.tasks WHERE id = local.id)
+                FROM main.tasks AS local
+                WHERE {SINCE_CONDITION} AND NOT EXISTS (
+                    SELECT 1 FROM {MERGE_SCHEMA}.tasks
+                        WHERE id = local.id AND updated_at IS local.updated_at
+                )"
+        ),
+        [since],
+    )?;
+
+    counts.pruned = tx.execute(
+        &format!("DELETE FROM {MERGE_SCHEMA}.tasks WHERE NOT {SINCE_CONDITION}"),
+        [since],
+    )?;
+
+    for table in TASK_DETAIL_TABLES {
+        tx.execute(
+            &format!(
+                "DELETE FROM {MERGE_SCHEMA}.{table}
+                    WHERE task_id NOT IN (SELECT id FROM {MERGE_SCHEMA}.tasks)"
+            ),
+            [],
+        )?;
+    }
+
+    merge_task_details(&tx, MERGE_SCHEMA, "main")?;
+
+    tx.commit()?;
+
+    Ok(counts)
+}
+
+// an empty database with the current schema, for a remote synced to the first time
+pub fn create_sync_db(path: &Path) -> Result<(), Error> {
+    let mut conn = Connection::open(path)?;
+
+    create_task_table(&conn)?;
+    migrate_task_table(&mut conn)
+}
+
+// a version that lost out in a merge, logged once however often the same copy is merged
+pub struct MergeConflict {
+    pub rowid: i64,
+    pub task_id: String,
+    pub merged_at: String,
+    pub lost_from: String,
+    pub lost: Option<Task>,
+    pub lost_updated_at: Option<String>,
+}
+
+pub fn get_merge_conflicts(db_conn: &Connection) -> Result<Vec<MergeConflict>, Error> {
+    let mut stmt = db_conn.prepare(
+        "SELECT task_id, unseal(description), status, date, updated_at, deferred_until, points,
+            block_reason, 0, NULL, priority, project, due, NULL, NULL, rowid, merged_at,
+            lost_from, deleted
+            FROM merge_conflicts ORDER BY merged_at, rowid",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        let deleted: bool = row.get(18)?;
+
+        Ok(MergeConflict {
+            rowid: row.get(15)?,
+            task_id: row.get(0)?,
+            merged_at: row.get(16)?,
+            lost_from: row.get(17)?,
+            lost: match deleted {
+                true => None,
+                false => Some(task_from_row(row)?),
+            },
+            lost_updated_at: row.get(4)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+pub fn dismiss_merge_conflict(db_conn: &Connection, rowid: i64) -> Result<(), Error> {
+    db_conn.execute("DELETE FROM merge_conflicts WHERE rowid = ?1", [rowid])?;
+
+    Ok(())
+}
+
+/*
+ * brings back the version that lost: the task gets its fields (or is deleted again for a
+ * lost delete) as a fresh change, so it wins the next merge too
+ * */
+pub fn take_merge_conflict(db_conn: &Connection, conflict: &MergeConflict) -> Result<(), Error> {
+    let tx = write_transaction(db_conn)?;
+
+    let current = tx
+        .query_row(
+            &format!("SELECT {TASK_COLUMNS} FROM tasks WHERE id = ?1"),
+            [&conflict.task_id],
+            task_from_row,
+        )
+        .optional()?
+        .ok_or(Error::QueryReturnedNoRows)?;
+
+    match &conflict.lost {
+        None => delete_task_rows(&tx, &conflict.task_id)?,
+        Some(lost) => {
+            let now = now_timestamp();
+
+            tx.execute(
+                "UPDATE tasks SET description = seal(:description), status = :status, date = :date,
+                    deferred_until = :deferred_until, points = :points, priority = :priority,
+                    project = :project, due = :due, block_reason = :block_reason, updated_at = :now WHERE id = :id",
+                named_params! {
+                    ":description": lost.description,
+                    ":status": lost.status,
+                    ":date": lost.date,
+                    ":deferred_until": lost.deferred_until,
+                    ":points": lost.points,
+                    ":priority": lost.priority,
+                    ":project": lost.project,
+                    ":due": lost.due,
+                    ":block_reason": lost.block_reason,
+                    ":now": &now,
+                    ":id": conflict.task_id,
+                },
+            )?;
+
+            if current.description != lost.description {
+                record_change(
+                    &tx,
+                    &conflict.task_id,
+                    &now,
+                    "description",
+                    Some(&current.description),
+                    Some(&lost.description),
+                )?;
+            }
+
+            if current.status != lost.status {
+                record_change(
+                    &tx,
+                    &conflict.task_id,
+                    &now,
+                    "status",
+                    Some(&current.status.to_string()),
+                    Some(&lost.status.to_string()),
+                )?;
+            }
+        }
+    }
+
+    dismiss_merge_conflict(&tx, conflict.rowid)?;
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regexp(pattern: &str, text: &str) -> Result<bool, Error> {
+        let conn = Connection::open_in_memory()?;
+        register_regexp_function(&conn)?;
+
+        conn.query_row("SELECT ?1 REGEXP ?2", (text, pattern), |row| row.get(0))
+    }
+
+    #[test]
+    fn regexp_matches_anywhere_in_the_text() {
+        assert!(regexp(r"JIRA-\d+", "fix JIRA-142 rollout").unwrap());
+        assert!(regexp(r"(?i)^deploy\b", "Deploy api").unwrap());
+        assert!(!regexp(r"^deploy$", "deploy api").unwrap());
+    }
+
+    #[test]
+    fn regexp_handles_long_text_and_nested_repeats() {
+        let long_description = "a".repeat(12_000);
+
+        assert!(!regexp(".*x", &long_description).unwrap());
+        assert!(!regexp("(a|a)*b", &long_description).unwrap());
+    }
+
+    #[test]
+    fn regexp_rejects_an_invalid_pattern() {
+        assert!(regexp("(unclosed", "text").is_err());
+    }
 }
@@ -0,0 +1,224 @@
+use chrono::{DateTime, Local};
+use ulid::Ulid;
+
+use crate::{
+    crypto::get_secret,
+    json::{parse_json, JsonValue},
+    utils::{form_encode, percent_encode, run_curl, today},
+    Status, Task,
+};
+
+/*
+ * gitlab issues through its rest api (v4), with curl doing the https
+ *
+ * a personal access token with the api scope (read_api is enough without comments), taken
+ * from DAILY_DOSE_GITLAB_TOKEN or the OS keyring, asked for on the first import otherwise
+ *
+ * [gitlab]
+ * url = "https://gitlab.example.com"
+ * comment_on_done = true
+ * */
+pub const DEFAULT_GITLAB_URL: &str = "https://gitlab.com";
+
+const TOKEN_ACCOUNT: &str = "gitlab-token";
+const TOKEN_ENV: &str = "DAILY_DOSE_GITLAB_TOKEN";
+
+// the note left on an issue when its task is marked done, with comment_on_done
+const DONE_COMMENT: &str = "Marked done in daily-dose.";
+
+// an issue as the api returns it, dates are rfc 3339 but due_date is YYYY-MM-DD
+#[derive(Debug)]
+pub struct Issue {
+    pub project_id: u64,
+    pub iid: u64,
+    // eg. group/project#12
+    pub reference: String,
+    pub title: String,
+    pub closed: bool,
+    pub due_date: Option<String>,
+    pub closed_at: Option<String>,
+}
+
+impl Issue {
+    // project id and iid, what the api needs to find the issue again
+    pub fn external_id(&self) -> String {
+        format!("{}/{}", self.project_id, self.iid)
+    }
+
+    // eg. group/project!12, as tagged on the task
+    pub fn tag(&self) -> String {
+        let project = self
+            .reference
+            .rsplit_once('#')
+            .map(|(project, _)| project)
+            .unwrap_or(&self.reference);
+
+        format!("{project}!{}", self.iid)
+    }
+}
+
+pub fn get_gitlab_token() -> Result<String, String> {
+    get_secret(
+        TOKEN_ACCOUNT,
+        TOKEN_ENV,
+        "GitLab personal access token (api scope): ",
+        "gitlab token",
+    )
+}
+
+/*
+ * issues assigned to the token's user, across projects. only open ones unless updated_after
+ * (rfc 3339) is set, then closed ones changed since too, so closing an issue reaches its task
+ * */
+pub fn get_assigned_issues(
+    url: &str,
+    token: &str,
+    updated_after: Option<&str>,
+) -> Result<Vec<Issue>, String> {
+    let mut params = vec![("scope", "assigned_to_me"), ("per_page", "100")];
+    match updated_after {
+        Some(updated_after) => params.extend([("state", "all"), ("updated_after", updated_after)]),
+        None => params.push(("state", "opened")),
+    }
+
+    let mut issues = vec![];
+
+    // the pages run out with an empty one
+    for page in 1.. {
+        let page = page.to_string();
+        let mut page_params = params.clone();
+        page_params.push(("page", &page));
+
+        let response = request(
+            token,
+            &[format!(
+                "url = \"{}/api/v4/issues?{}\"",
+                url.trim_end_matches('/'),
+                form_encode(&page_params)
+            )],
+        )?;
+
+        let items = response.as_array();
+        if items.is_empty() {
+            break;
+        }
+
+        issues.extend(items.iter().filter_map(|item| {
+            let text = |key: &str| {
+                item.get(key)
+                    .and_then(JsonValue::as_str)
+                    .map(str::to_string)
+            };
+
+            Some(Issue {
+                project_id: item.get("project_id")?.as_u64()?,
+                iid: item.get("iid")?.as_u64()?,
+                reference: item.get("references")?.get("full")?.as_str()?.to_string(),
+                title: text("title").unwrap_or_default(),
+                closed: text("state").as_deref() == Some("closed"),
+                due_date: text("due_date"),
+                closed_at: text("closed_at"),
+            })
+        }));
+    }
+
+    Ok(issues)
+}
+
+// leaves DONE_COMMENT on the issue of the external id
+pub fn comment_done(url: &str, token: &str, external_id: &str) -> Result<(), String> {
+    let Some((project_id, iid)) = external_id.split_once('/') else {
+        return Err(format!("Unexpected gitlab issue id '{external_id}'"));
+    };
+
+    request(
+        token,
+        &[
+            format!(
+                "url = \"{}/api/v4/projects/{}/issues/{}/notes\"",
+                url.trim_end_matches('/'),
+                percent_encode(project_id),
+                percent_encode(iid)
+            ),
+            format!("data = \"{}\"", form_encode(&[("body", DONE_COMMENT)])),
+        ],
+    )?;
+
+    Ok(())
+}
+
+/*
+ * a closed issue goes to the day it was closed, an open one to its due date or today.
+ * the task is tagged with the issue's reference, eg. "Fix login #group/project!12"
+ * */
+pub fn issue_task(issue: &Issue) -> (Task, Option<String>) {
+    let closed_at = issue
+        .closed_at
+        .as_deref()
+        .and_then(|closed_at| DateTime::parse_from_rfc3339(closed_at).ok());
+
+    let date = match (issue.closed, closed_at, &issue.due_date) {
+        (true, Some(closed_at), _) => closed_at.with_timezone(&Local).date_naive().to_string(),
+        (_, _, Some(due_date)) => due_date.clone(),
+        _ => today().to_string(),
+    };
+
+    let task = Task {
+        id: Ulid::new().to_string(),
+        description: format!("{} #{}", issue.title.trim(), issue.tag()),
+        status: match issue.closed {
+            true => Status::Done,
+            false => Status::Todo,
+        },
+        date,
+        updated_at: None,
+        deferred_until: None,
+        points: None,
+        block_reason: None,
+        position: 0,
+        short_id: None,
+        priority: None,
+        project: None,
+        due: None,
+        parent_id: None,
+        tags: vec![],
+    };
+
+    let closed_at = closed_at.filter(|_| issue.closed).map(|closed_at| {
+        closed_at
+            .to_utc()
+            .to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+    });
+
+    (task, closed_at)
+}
+
+// the token goes in a header, gitlab's own PRIVATE-TOKEN
+fn request(token: &str, config_lines: &[String]) -> Result<JsonValue, String> {
+    let mut config_lines = config_lines.to_vec();
+    config_lines.push(format!("header = \"PRIVATE-TOKEN: {token}\""));
+
+    let (body, status) = run_curl(&config_lines)?;
+
+    match status.as_str() {
+        "200" | "201" => parse_json(&body),
+        // errors of a proxy in front of gitlab may not be json
+        status => Err(format!(
+            "GitLab answered {status} = {}",
+            parse_json(&body)
+                .map(|response| api_error(&response))
+                .unwrap_or_else(|_| "unknown error".to_string())
+        )),
+    }
+}
+
+// eg. {"message": "401 Unauthorized"} or {"error": "insufficient_scope", ...}
+fn api_error(response: &JsonValue) -> String {
+    response
+        .get("message")
+        .or_else(|| response.get("error_description"))
+        .or_else(|| response.get("error"))
+        .and_then(JsonValue::as_str)
+        .unwrap_or("unknown error")
+        .to_string()
+}
@@ -0,0 +1,405 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+};
+
+use chrono::{DateTime, Local};
+use ulid::Ulid;
+
+use crate::{
+    crypto::{read_keyring, write_keyring},
+    json::{parse_json, JsonValue},
+    utils::{form_encode, open_with_default_app, percent_encode, run_curl, today},
+    Status, Task,
+};
+
+/*
+ * google tasks through its rest api, with curl doing the https
+ *
+ * google only hands out tokens to registered clients, so the user brings their own: an
+ * oauth client of type desktop app from the google cloud console, in config as
+ *
+ * [gtasks]
+ * client_id = "....apps.googleusercontent.com"
+ * client_secret = "..."
+ *
+ * the first import signs in through the browser, the refresh token goes to the OS keyring
+ * */
+const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const API_URL: &str = "https://tasks.googleapis.com/tasks/v1";
+const SCOPE: &str = "https://www.googleapis.com/auth/tasks.readonly";
+
+const TOKEN_ACCOUNT: &str = "google-tasks-token";
+
+pub struct Credentials {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+// a task as the api returns it, dates are rfc 3339
+#[derive(Debug)]
+pub struct GoogleTask {
+    pub id: String,
+    pub title: String,
+    pub completed: bool,
+    pub due: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+// a fresh access token, signing in first when there is no refresh token yet (or it was revoked)
+pub fn get_access_token(credentials: &Credentials) -> Result<String, String> {
+    if let Some(refresh_token) = read_keyring(TOKEN_ACCOUNT)? {
+        let response = post_form(
+            TOKEN_URL,
+            &[
+                ("client_id", &credentials.client_id),
+                ("client_secret", &credentials.client_secret),
+                ("refresh_token", &refresh_token),
+                ("grant_type", "refresh_token"),
+            ],
+        )?;
+
+        match response.get("access_token").and_then(JsonValue::as_str) {
+            Some(access_token) => return Ok(access_token.to_string()),
+            None => println!("Signing in to Google again, the saved sign in was not accepted"),
+        }
+    }
+
+    sign_in(credentials)
+}
+
+/*
+ * the loopback flow for desktop apps: the browser comes back to a port on this machine with
+ * the code. state guards against someone else's code, the verifier (pkce) against the code
+ * being used by anyone but this process
+ * */
+fn sign_in(credentials: &Credentials) -> Result<String, String> {
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(|error| error.to_string())?;
+    let port = listener
+        .local_addr()
+        .map_err(|error| error.to_string())?
+        .port();
+    let redirect_uri = format!("http://127.0.0.1:{port}");
+
+    let state = random_hex(16)?;
+    let code_verifier = random_hex(32)?;
+
+    let auth_url = format!(
+        "{AUTH_URL}?{}",
+        form_encode(&[
+            ("client_id", &credentials.client_id),
+            ("redirect_uri", &redirect_uri),
+            ("response_type", "code"),
+            ("scope", SCOPE),
+            ("access_type", "offline"),
+            ("prompt", "consent"),
+            ("state", &state),
+            ("code_challenge", &code_verifier),
+            ("code_challenge_method", "plain"),
+        ])
+    );
+
+    println!("Sign in to Google to allow reading your tasks:\n{auth_url}");
+    let _ = open_with_default_app(&auth_url);
+
+    let code = wait_for_code(&listener, &state)?;
+
+    let response = post_form(
+        TOKEN_URL,
+        &[
+            ("client_id", &credentials.client_id),
+            ("client_secret", &credentials.client_secret),
+            ("code", &code),
+            ("code_verifier", &code_verifier),
+            ("redirect_uri", &redirect_uri),
+            ("grant_type", "authorization_code"),
+        ],
+    )?;
+
+    let access_token = response
+        .get("access_token")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| format!("Google refused the sign in = {}", api_error(&response)))?;
+
+    if let Some(refresh_token) = response.get("refresh_token").and_then(JsonValue::as_str) {
+        write_keyring(
+            TOKEN_ACCOUNT,
+            "daily-dose google tasks token",
+            refresh_token,
+        )?;
+    }
+
+    Ok(access_token.to_string())
+}
+
+// the browser's redirect, eg. GET /?state=...&code=... HTTP/1.1
+fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String, String> {
+    for stream in listener.incoming() {
+        let mut stream = stream.map_err(|error| error.to_string())?;
+
+        let mut request_line = String::new();
+        BufReader::new(&stream)
+            .read_line(&mut request_line)
+            .map_err(|error| error.to_string())?;
+
+        let query = request_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|target| target.split_once('?'))
+            .map(|(_, query)| query)
+            .unwrap_or_default();
+
+        let param = |name: &str| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| percent_decode(value))
+        };
+
+        // eg. the browser asking for a favicon
+        if param("code").is_none() && param("error").is_none() {
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            continue;
+        }
+
+        let body = "Signed in, you can close this tab and go back to the terminal.\n";
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+
+        if let Some(error) = param("error") {
+            return Err(format!("Google sign in failed = {error}"));
+        }
+
+        if param("state").as_deref() != Some(state) {
+            return Err("Google sign in came back with the wrong state, try again".to_string());
+        }
+
+        return param("code").ok_or_else(|| "Google sign in returned no code".to_string());
+    }
+
+    Err("Google sign in was not completed".to_string())
+}
+
+// (id, title) of every task list
+pub fn get_google_task_lists(access_token: &str) -> Result<Vec<(String, String)>, String> {
+    let items = get_all_pages(
+        access_token,
+        &format!("{API_URL}/users/@me/lists?maxResults=100"),
+    )?;
+
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            Some((
+                item.get("id")?.as_str()?.to_string(),
+                item.get("title")?.as_str()?.to_string(),
+            ))
+        })
+        .collect())
+}
+
+// completed and hidden tasks too, only the ones changed since updated_min (rfc 3339) when set
+pub fn get_google_tasks(
+    access_token: &str,
+    list_id: &str,
+    updated_min: Option<&str>,
+) -> Result<Vec<GoogleTask>, String> {
+    let mut params = vec![
+        ("showCompleted", "true"),
+        ("showHidden", "true"),
+        ("maxResults", "100"),
+    ];
+    if let Some(updated_min) = updated_min {
+        params.push(("updatedMin", updated_min));
+    }
+
+    let items = get_all_pages(
+        access_token,
+        &format!(
+            "{API_URL}/lists/{}/tasks?{}",
+            percent_encode(list_id),
+            form_encode(&params)
+        ),
+    )?;
+
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            let text = |key: &str| {
+                item.get(key)
+                    .and_then(JsonValue::as_str)
+                    .map(str::to_string)
+            };
+
+            Some(GoogleTask {
+                id: text("id")?,
+                title: text("title").unwrap_or_default(),
+                completed: text("status").as_deref() == Some("completed"),
+                due: text("due"),
+                completed_at: text("completed"),
+            })
+        })
+        .collect())
+}
+
+/*
+ * a done task goes to the day it was completed, an open one to its due date or today.
+ * due dates carry no time, google stores them as midnight utc, so the date part is the date
+ * */
+pub fn google_task(task: &GoogleTask) -> (Task, Option<String>) {
+    let completed_at = task
+        .completed_at
+        .as_deref()
+        .and_then(|completed_at| DateTime::parse_from_rfc3339(completed_at).ok());
+
+    let date = match (task.completed, completed_at, &task.due) {
+        (true, Some(completed_at), _) => {
+            completed_at.with_timezone(&Local).date_naive().to_string()
+        }
+        (_, _, Some(due)) if due.len() >= 10 => due[..10].to_string(),
+        _ => today().to_string(),
+    };
+
+    let task_row = Task {
+        id: Ulid::new().to_string(),
+        description: task.title.trim().to_string(),
+        status: match task.completed {
+            true => Status::Done,
+            false => Status::Todo,
+        },
+        date,
+        updated_at: None,
+        deferred_until: None,
+        points: None,
+        block_reason: None,
+        position: 0,
+        short_id: None,
+        priority: None,
+        project: None,
+        due: None,
+        parent_id: None,
+        tags: vec![],
+    };
+
+    let completed_at = completed_at.filter(|_| task.completed).map(|completed_at| {
+        completed_at
+            .to_utc()
+            .to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+    });
+
+    (task_row, completed_at)
+}
+
+// the items of every page, following nextPageToken
+fn get_all_pages(access_token: &str, url: &str) -> Result<Vec<JsonValue>, String> {
+    let mut items = vec![];
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let page_url = match &page_token {
+            Some(page_token) => format!("{url}&pageToken={}", percent_encode(page_token)),
+            None => url.to_string(),
+        };
+
+        let response = curl(&[
+            format!("url = \"{page_url}\""),
+            format!("header = \"Authorization: Bearer {access_token}\""),
+        ])?;
+
+        if response.get("error").is_some() {
+            return Err(format!(
+                "Google refused the request = {}",
+                api_error(&response)
+            ));
+        }
+
+        items.extend(
+            response
+                .get("items")
+                .map(JsonValue::as_array)
+                .unwrap_or_default()
+                .iter()
+                .cloned(),
+        );
+
+        match response.get("nextPageToken").and_then(JsonValue::as_str) {
+            Some(next_page_token) => page_token = Some(next_page_token.to_string()),
+            None => return Ok(items),
+        }
+    }
+}
+
+fn post_form(url: &str, fields: &[(&str, &str)]) -> Result<JsonValue, String> {
+    curl(&[
+        format!("url = \"{url}\""),
+        format!("data = \"{}\"", form_encode(fields)),
+    ])
+}
+
+// the request goes to curl as a config, everything put in there is percent encoded or a plain token
+fn curl(config_lines: &[String]) -> Result<JsonValue, String> {
+    let (body, status) = run_curl(config_lines)?;
+
+    let response = parse_json(&body)?;
+
+    match status.as_str() {
+        // a refused refresh token comes back as 400 with an error, the caller handles that
+        "200" | "400" => Ok(response),
+        status => Err(format!(
+            "Google answered {status} = {}",
+            api_error(&response)
+        )),
+    }
+}
+
+// error responses of the token endpoint and the api look different
+fn api_error(response: &JsonValue) -> String {
+    let error = response.get("error");
+
+    error
+        .and_then(|error| error.get("message"))
+        .or_else(|| response.get("error_description"))
+        .or(error)
+        .and_then(JsonValue::as_str)
+        .unwrap_or("unknown error")
+        .to_string()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = vec![];
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => match value
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    decoded.push(byte);
+                    index += 3;
+                    continue;
+                }
+                None => decoded.push(b'%'),
+            },
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn random_hex(len: usize) -> Result<String, String> {
+    let mut bytes = vec![0; len];
+    getrandom::fill(&mut bytes).map_err(|error| error.to_string())?;
+
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
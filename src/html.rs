@@ -0,0 +1,138 @@
+use std::fmt::Write;
+
+use crate::{utils::redact_description, Status, Task};
+
+/*
+ * self contained html page for a period summary, styles are inlined in the page
+ * so the file can be mailed around or pasted into a wiki as is
+ *
+ * days is every date of the period in order, including the ones without tasks,
+ * which still show up in the heatmap. journal is the (date, note) entries of the period
+ * */
+pub fn render_period_html(
+    title: &str,
+    days: &[(String, Vec<Task>)],
+    journal: &[(String, String)],
+) -> String {
+    let mut html = String::new();
+
+    let total = days.iter().map(|(_, tasks)| tasks.len()).sum::<usize>();
+    let done = days
+        .iter()
+        .flat_map(|(_, tasks)| tasks)
+        .filter(|task| task.status == Status::Done)
+        .count();
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body {{ font-family: -apple-system, Segoe UI, Helvetica, Arial, sans-serif; color: #24292f; margin: 2em auto; max-width: 56em; }}
+table {{ border-collapse: collapse; width: 100%; margin-bottom: 1.5em; }}
+th, td {{ border: 1px solid #d0d7de; padding: 0.4em 0.6em; text-align: left; }}
+th {{ background: #f6f8fa; }}
+.badge {{ border-radius: 1em; padding: 0.1em 0.6em; font-size: 0.85em; color: #fff; white-space: nowrap; }}
+.todo {{ background: #6e7781; }}
+.in_progress {{ background: #0969da; }}
+.done {{ background: #1a7f37; }}
+.blocked {{ background: #cf222e; }}
+.heatmap td {{ text-align: center; width: 3em; }}
+.journal {{ white-space: pre-wrap; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p>Done {done} of {total} tasks</p>
+",
+        title = escape_html(title)
+    );
+
+    html.push_str("<table class=\"heatmap\">\n<tr>");
+    for (date, _) in days {
+        let _ = write!(html, "<th>{}</th>", escape_html(date));
+    }
+    html.push_str("</tr>\n<tr>");
+    for (_, tasks) in days {
+        let done = tasks
+            .iter()
+            .filter(|task| task.status == Status::Done)
+            .count();
+        let _ = write!(
+            html,
+            "<td style=\"background: {}\">{done}</td>",
+            heatmap_color(done)
+        );
+    }
+    html.push_str("</tr>\n</table>\n");
+
+    for (date, tasks) in days.iter().filter(|(_, tasks)| !tasks.is_empty()) {
+        let _ = write!(
+            html,
+            "<h2>{}</h2>\n<table>\n<tr><th>Description</th><th>Status</th><th>Pts</th></tr>\n",
+            escape_html(date)
+        );
+
+        for task in tasks {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td><span class=\"badge {status}\">{status}</span></td><td>{}</td></tr>",
+                escape_html(&redact_description(&task.description)),
+                task.points
+                    .map(|points| points.to_string())
+                    .unwrap_or_default(),
+                status = task.status,
+            );
+        }
+
+        html.push_str("</table>\n");
+    }
+
+    if !journal.is_empty() {
+        html.push_str("<h2>Journal</h2>\n");
+    }
+
+    for (date, note) in journal {
+        let _ = writeln!(
+            html,
+            "<h3>{}</h3>\n<p class=\"journal\">{}</p>",
+            escape_html(date),
+            escape_html(note)
+        );
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    html
+}
+
+// github style green shades, darker the more got done that day
+fn heatmap_color(done: usize) -> &'static str {
+    match done {
+        0 => "#ebedf0",
+        1 => "#9be9a8",
+        2..=3 => "#40c463",
+        4..=5 => "#30a14e",
+        _ => "#216e39",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
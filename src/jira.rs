@@ -0,0 +1,63 @@
+use std::fmt::Write;
+
+use crate::{Status, Task};
+
+// jira cuts summaries off at 255 characters and refuses the row instead
+const SUMMARY_MAX_CHARS: usize = 255;
+
+/*
+ * csv for jira's importer (System > External system import > CSV), one issue per task
+ *
+ * Summary, Status, Due Date, then one Labels column per tag, jira only takes several labels
+ * as repeated columns of the same name. due dates are YYYY-MM-DD, set the importer's date
+ * format to yyyy-MM-dd. the statuses are the ones of jira's default workflow, blocked has
+ * to be mapped to one of the project's statuses while importing
+ * */
+pub fn render_jira_csv(tasks: &[Task]) -> String {
+    let label_columns = tasks
+        .iter()
+        .map(|task| task.all_tags().len())
+        .max()
+        .unwrap_or_default()
+        .max(1);
+
+    let mut csv = String::new();
+
+    let _ = write!(csv, "Summary,Status,Due Date");
+    for _ in 0..label_columns {
+        let _ = write!(csv, ",Labels");
+    }
+    let _ = write!(csv, "\r\n");
+
+    for task in tasks {
+        let summary: String = task.description.chars().take(SUMMARY_MAX_CHARS).collect();
+        let status = match task.status {
+            Status::Todo => "To Do",
+            Status::InProgress => "In Progress",
+            Status::Done => "Done",
+            Status::Blocked => "Blocked",
+        };
+
+        let mut labels = task.all_tags();
+        labels.resize(label_columns, String::new());
+
+        // a snoozed task is due the day it was deferred to
+        let due_date = task.deferred_until.as_deref().unwrap_or(&task.date);
+
+        let _ = write!(csv, "{},{status},{due_date}", csv_field(&summary));
+        for label in labels {
+            let _ = write!(csv, ",{}", csv_field(&label));
+        }
+        let _ = write!(csv, "\r\n");
+    }
+
+    csv
+}
+
+// quoted only when needed, quotes inside doubled (rfc 4180)
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\r', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
@@ -0,0 +1,413 @@
+use std::fmt::{self, Display, Write};
+
+use crate::{
+    utils::{display_options, redact_description},
+    Task,
+};
+
+/*
+ * --format json output of report and stats, one object per run
+ * bump JSON_VERSION on any incompatible change, new fields may be added any time
+ *
+ * every object starts with
+ *   "version": 1, "report": <kind>
+ *
+ * task     {"id", "short_id", "date", "status", "project", "priority", "points", "snoozed_until",
+ *          "block_reason", "due", "parent_id", "description", "tags"}
+ *          tags are the description's #tags and the attached ones, without the #
+ *          dates as YYYY-MM-DD, statuses in snake_case, missing values are null
+ *          list --jsonl prints these alone, one per line without version and report
+ *
+ * sprint      "name", "start_date", "end_date", "committed": {"tasks", "points"},
+ *             "completed": {"tasks", "points"}, "completion_rate", "open_tasks": [task]
+ * cycle_time  "weeks": [{"week", "done", "average_minutes", "median_minutes"}],
+ *             "slowest": [{"task", "cycle_time_minutes", "completed_at"}]
+ * variance    "tasks", "points", "per_point_minutes",
+ *             "weeks": [{"week", "tasks", "points", "took_minutes", "per_point_minutes", "ratio"}],
+ *             "tags": [{"tag", ...the same}], "underestimated": [week or #tag]
+ *             ratio is the time per point over the average one, null when that is zero
+ * aging       "threshold_days", "tasks": [{"task", "stuck_minutes"}], "blockers": [task]
+ * week, month "title", "start_date", "end_date", "total", "done",
+ *             "days": [{"date", "tasks": [task], "journal"}]
+ *             with report --profiles --merge "days" makes way for
+ *             "profiles": [{"profile", "total", "done", "days": [{"date", "tasks": [task]}]}]
+ * quarter, year
+ *             "title", "start_date", "end_date", "total", "done", "completion_rate",
+ *             "points": {"committed", "done"}, "busiest_weeks": [{"week", "tasks", "done"}],
+ *             "top_tags": [{"tag", "tasks"}]
+ * stats       "years": [{"year", "months": [{"month", "done", "total", "completion_rate", "load"}],
+ *             "total": {"done", "total", "completion_rate", "load"}}]
+ * query       "columns": [name], "rows": [[value]], values as sqlite returns them, blobs as hex
+ *
+ * completion_rate is a whole percentage, weeks are iso weeks like 2024-W23,
+ * rates and loads of periods without tasks are null
+ * */
+const JSON_VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub enum Json {
+    Null,
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Int(value as i64)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Self {
+        Json::Int(value as i64)
+    }
+}
+
+impl From<i32> for Json {
+    fn from(value: i32) -> Self {
+        Json::Int(value as i64)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(value: i64) -> Self {
+        Json::Int(value)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Float(value)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(value: Vec<Json>) -> Self {
+        Json::Array(value)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Int(value) => write!(f, "{value}"),
+            // json has no NaN or infinity
+            Json::Float(value) if !value.is_finite() => f.write_str("null"),
+            Json::Float(value) => write!(f, "{value}"),
+            Json::String(value) => write_json_string(f, value),
+            Json::Array(values) => {
+                f.write_char('[')?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_json_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+
+    f.write_char('"')
+}
+
+pub fn task_json(task: &Task) -> Json {
+    Json::Object(vec![
+        ("id", task.id.as_str().into()),
+        ("short_id", task.short_id.clone().into()),
+        ("date", task.date.as_str().into()),
+        ("status", task.status.to_string().into()),
+        ("project", task.project.clone().into()),
+        (
+            "priority",
+            task.priority.map(|priority| priority.to_string()).into(),
+        ),
+        ("points", task.points.into()),
+        ("snoozed_until", task.deferred_until.clone().into()),
+        ("block_reason", task.block_reason.clone().into()),
+        ("due", task.due.clone().into()),
+        ("parent_id", task.parent_id.clone().into()),
+        ("description", redact_description(&task.description).into()),
+        (
+            "tags",
+            match display_options().redact {
+                Some(_) => Json::Array(vec![]),
+                None => Json::Array(task.all_tags().into_iter().map(Json::from).collect()),
+            },
+        ),
+    ])
+}
+
+// the report's fields behind version and kind, as one line
+pub fn render_report_json(report: &str, fields: Vec<(&'static str, Json)>) -> String {
+    let mut object = vec![("version", JSON_VERSION.into()), ("report", report.into())];
+    object.extend(fields);
+
+    format!("{}\n", Json::Object(object))
+}
+
+/*
+ * json read back from elsewhere (api responses), keys are owned unlike in Json
+ * numbers are kept as f64, which is all the callers need
+ * */
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    // the field of an object, None for anything else or a missing field
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    // whole, non negative numbers only, eg. ids
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(value) if *value >= 0.0 && value.fract() == 0.0 => {
+                Some(*value as u64)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[JsonValue] {
+        match self {
+            JsonValue::Array(values) => values,
+            _ => &[],
+        }
+    }
+}
+
+pub fn parse_json(text: &str) -> Result<JsonValue, String> {
+    let mut parser = JsonParser {
+        chars: text.chars().peekable(),
+    };
+
+    let value = parser.value()?;
+
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("Unexpected '{c}' after the json value")),
+    }
+}
+
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("Expected '{expected}' in json, found '{c}'")),
+            None => Err(format!("Expected '{expected}' in json, found the end")),
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+
+        match self.chars.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(JsonValue::String),
+            Some('t') => self.literal("true", JsonValue::Bool(true)),
+            Some('f') => self.literal("false", JsonValue::Bool(false)),
+            Some('n') => self.literal("null", JsonValue::Null),
+            Some(_) => self.number(),
+            None => Err("Unexpected end of json".to_string()),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, String> {
+        for expected in word.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("Invalid json literal, expected {word}"));
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<JsonValue, String> {
+        let mut number = String::new();
+
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            number.push(c);
+        }
+
+        number
+            .parse()
+            .map(JsonValue::Number)
+            .map_err(|_| format!("Invalid json number '{number}'"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('u') => {
+                        let high = self.hex_escape()?;
+                        // characters outside the bmp come as a surrogate pair
+                        let code = match high {
+                            0xd800..=0xdbff => {
+                                self.expect('\\')?;
+                                self.expect('u')?;
+                                let low = self.hex_escape()?;
+                                0x10000
+                                    + ((high - 0xd800) << 10)
+                                    + (low.wrapping_sub(0xdc00) & 0x3ff)
+                            }
+                            code => code,
+                        };
+                        value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    Some(c) => value.push(c),
+                    None => return Err("Unterminated json string".to_string()),
+                },
+                Some(c) => value.push(c),
+                None => return Err("Unterminated json string".to_string()),
+            }
+        }
+    }
+
+    fn hex_escape(&mut self) -> Result<u32, String> {
+        let hex: String = (0..4).filter_map(|_| self.chars.next()).collect();
+
+        u32::from_str_radix(&hex, 16).map_err(|_| format!("Invalid json escape \\u{hex}"))
+    }
+
+    fn array(&mut self) -> Result<JsonValue, String> {
+        self.expect('[')?;
+
+        let mut values = vec![];
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&']').is_some() {
+            return Ok(JsonValue::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(values)),
+                _ => return Err("Expected ',' or ']' in json array".to_string()),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, String> {
+        self.expect('{')?;
+
+        let mut fields = vec![];
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Ok(JsonValue::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(fields)),
+                _ => return Err("Expected ',' or '}' in json object".to_string()),
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+use ulid::Ulid;
+
+// how long to keep trying before giving up, waits double from the first to the longest
+const LOCK_WAIT: Duration = Duration::from_secs(10);
+const LOCK_RETRY_FIRST_INTERVAL: Duration = Duration::from_millis(10);
+const LOCK_RETRY_LONGEST_INTERVAL: Duration = Duration::from_millis(500);
+
+// a lock older than this is assumed to be left behind by a crashed process
+const STALE_LOCK_AGE: Duration = Duration::from_secs(60);
+
+// a held lock is touched this often, so prompts and slow syncs never let it go stale
+const LOCK_REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+
+/*
+ * advisory lock living next to the database file (storage.db.lock)
+ *
+ * sqlite's own locking is unreliable when the db sits on a synced folder (dropbox, nfs, ...)
+ * as the sync client copies the file around without caring about sqlite locks.
+ * a plain lock file gets synced along with the db, so teammates' processes will see it too.
+ *
+ * while the guard lives a thread keeps the file's mtime fresh, and once it is dropped the
+ * file is removed, unless it no longer holds what we wrote (someone took it over meanwhile)
+ * */
+pub struct DbLock {
+    path: PathBuf,
+    owner: String,
+    stop_refresh: Option<Sender<()>>,
+    refresh: Option<JoinHandle<()>>,
+}
+
+impl Drop for DbLock {
+    fn drop(&mut self) {
+        // dropping the sender wakes the refresh thread up and ends it
+        self.stop_refresh.take();
+        if let Some(refresh) = self.refresh.take() {
+            let _ = refresh.join();
+        }
+
+        if holds_lock(&self.path, &self.owner) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn holds_lock(path: &Path, owner: &str) -> bool {
+    fs::read_to_string(path).is_ok_and(|contents| contents == owner)
+}
+
+fn refresh_lock(path: PathBuf, owner: String) -> (Sender<()>, JoinHandle<()>) {
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let refresh = thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(LOCK_REFRESH_INTERVAL) {
+            if !holds_lock(&path, &owner) {
+                return;
+            }
+            let _ = OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+        }
+    });
+
+    (stop, refresh)
+}
+
+pub fn get_lock_path(db_path: &str) -> PathBuf {
+    PathBuf::from(format!("{db_path}.lock"))
+}
+
+pub fn acquire_db_lock(db_path: &str) -> io::Result<DbLock> {
+    let path = get_lock_path(db_path);
+    let started = Instant::now();
+    let mut interval = LOCK_RETRY_FIRST_INTERVAL;
+
+    // a quick write by the server or another cli run is waited out without noticing
+    while started.elapsed() < LOCK_WAIT {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                // the id tells our lock apart from a later one, pids repeat across machines
+                let owner = format!("pid={} id={}\n", process::id(), Ulid::new());
+                file.write_all(owner.as_bytes())?;
+
+                let (stop_refresh, refresh) = refresh_lock(path.clone(), owner.clone());
+
+                return Ok(DbLock {
+                    path,
+                    owner,
+                    stop_refresh: Some(stop_refresh),
+                    refresh: Some(refresh),
+                });
+            }
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                if is_stale_lock(&path) {
+                    let _ = fs::remove_file(&path);
+                    continue;
+                }
+                thread::sleep(interval);
+                interval = (interval * 2).min(LOCK_RETRY_LONGEST_INTERVAL);
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    let holder = fs::read_to_string(&path).unwrap_or_default();
+
+    Err(io::Error::new(
+        io::ErrorKind::WouldBlock,
+        format!(
+            "Database is locked by another process ({}), remove {} if that process is gone",
+            holder.trim(),
+            path.display()
+        ),
+    ))
+}
+
+fn is_stale_lock(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE_LOCK_AGE)
+}
@@ -1,4 +1,6 @@
-use std::str::FromStr;
+use std::{env, str::FromStr};
+
+use chrono::NaiveTime;
 
 use rusqlite::{
     types::{FromSql, ToSqlOutput},
@@ -8,19 +10,63 @@ use strum::{Display, EnumString};
 
 use crate::{
     cmd_handler::{
-        construct_cmd_args, handle_cmd_add, handle_cmd_delete, handle_cmd_list, handle_cmd_mark,
-        handle_cmd_show, handle_cmd_unmark, handle_cmd_update,
+        apply_daily_template, backup_before, construct_cmd_args, finish_relocation, handle_cmd_add,
+        handle_cmd_archive, handle_cmd_attach, handle_cmd_backup, handle_cmd_block,
+        handle_cmd_caldav, handle_cmd_conflicts, handle_cmd_daemon, handle_cmd_delete,
+        handle_cmd_deps, handle_cmd_export, handle_cmd_focus, handle_cmd_goal, handle_cmd_import,
+        handle_cmd_init, handle_cmd_journal, handle_cmd_key, handle_cmd_key_generate,
+        handle_cmd_list, handle_cmd_log, handle_cmd_mark, handle_cmd_merge, handle_cmd_next,
+        handle_cmd_note, handle_cmd_onthisday, handle_cmd_open, handle_cmd_prio,
+        handle_cmd_projects, handle_cmd_query, handle_cmd_recent, handle_cmd_report,
+        handle_cmd_restore, handle_cmd_seed, handle_cmd_self_update, handle_cmd_serve,
+        handle_cmd_show, handle_cmd_snooze, handle_cmd_sprint, handle_cmd_standup,
+        handle_cmd_stats, handle_cmd_status_shortcut, handle_cmd_sync, handle_cmd_tag,
+        handle_cmd_template, handle_cmd_unmark, handle_cmd_untag, handle_cmd_update,
+        handle_cmd_usage, handle_cmd_view, is_mutating_cmd, load_encryption_key, relocate_data_dir,
+        resolve_data_dir, usage_command_name,
+    },
+    config::load_config,
+    database::{
+        create_task_table, enable_wal, get_db_path, get_schema_version, is_task_table_migrated,
+        migrate_task_table, open_db_connection, record_usage, set_data_dir,
+    },
+    lock::acquire_db_lock,
+    utils::{
+        build_display_options, color_rules, extract_tags, highlight_rules, render_tasks_table,
+        set_color_rules, set_day_boundary, set_display_options, set_highlight_rules, today,
     },
-    database::{create_task_table, open_db_connection},
-    utils::render_tasks_table,
 };
 
+mod backup;
+mod caldav;
 mod cmd_handler;
+mod completions;
+mod config;
+mod crypto;
 mod database;
+mod gitlab;
+mod gtasks;
+mod html;
+mod jira;
+mod json;
+mod lock;
+mod markdown;
+mod pdf;
+mod reminders;
+mod scheduler;
+mod seed;
+mod server;
+mod slack;
+#[cfg(feature = "summarize")]
+mod summarize;
+mod template;
+mod update;
 mod utils;
+mod xlsx;
 
-#[derive(Display, EnumString, Debug)]
+#[derive(Display, EnumString, clap::ValueEnum, Debug, PartialEq, Clone, Copy)]
 #[strum(serialize_all = "snake_case")]
+#[value(rename_all = "snake_case")]
 enum Status {
     Todo,
     InProgress,
@@ -28,12 +74,90 @@ enum Status {
     Blocked,
 }
 
-#[derive(Debug)]
+// None on a task until it is set, it sorts like medium, see SortKey::order_by
+#[derive(Display, EnumString, clap::ValueEnum, Debug, PartialEq, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Debug, Clone)]
 struct Task {
     id: String,
     description: String,
     status: Status,
     date: String,
+    // None for tasks created before updated_at was tracked
+    updated_at: Option<String>,
+    // set when the task was snoozed, it shows up on this date instead of `date`
+    deferred_until: Option<String>,
+    // effort estimate for teams estimating in story points
+    points: Option<u32>,
+    // what a blocked task is waiting on, cleared once it leaves blocked
+    block_reason: Option<String>,
+    // 1 based index within its date, see next_position
+    position: u32,
+    // eg. 0612-3, given when the task is stored, see resolve_task_id
+    short_id: Option<String>,
+    priority: Option<Priority>,
+    // client or context the task is for, see get_projects
+    project: Option<String>,
+    // YYYY-MM-DD the task has to be done by, date stays the day it is planned for
+    due: Option<String>,
+    // id of the task this is a subtask of, see set_task_parent
+    parent_id: Option<String>,
+    // attached with add --tag or tag, the #tags of the description aren't in here, see all_tags
+    tags: Vec<String>,
+}
+
+// tasks belong to a sprint by their date falling in between start and end
+#[derive(Debug)]
+struct Sprint {
+    name: String,
+    start_date: String,
+    end_date: String,
+}
+
+// a quarterly objective, tasks are linked to it one by one (add or update --goal)
+#[derive(Debug)]
+struct Goal {
+    name: String,
+    // eg. 2024-Q3
+    quarter: String,
+}
+
+// one entry of a task's history, field is "created" for the task's creation
+#[derive(Debug)]
+struct TaskChange {
+    changed_at: String,
+    field: String,
+    old_value: Option<String>,
+    new_value: Option<String>,
+}
+
+impl Task {
+    fn effective_date(&self) -> &String {
+        self.deferred_until.as_ref().unwrap_or(&self.date)
+    }
+
+    // what tables show to refer to the task, the short id once it has one
+    fn short_or_id(&self) -> &str {
+        self.short_id.as_deref().unwrap_or(&self.id)
+    }
+
+    // the #tags of the description followed by the attached ones not among them
+    fn all_tags(&self) -> Vec<String> {
+        let mut tags = extract_tags(&self.description);
+        for tag in &self.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
 }
 
 impl ToSql for Status {
@@ -58,40 +182,336 @@ impl FromSql for Status {
     }
 }
 
-fn main() -> Result<(), Box<Error>> {
-    let db_conn = open_db_connection().expect("Failed open storage connection");
+impl ToSql for Priority {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
 
-    create_task_table(&db_conn).expect("Failed to create table");
+impl FromSql for Priority {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        value.as_str().map(|s| Priority::from_str(s).unwrap())
+    }
+}
 
+fn main() -> Result<(), Box<Error>> {
     let cmd_matches = construct_cmd_args().get_matches();
 
+    // a profile keeps its own config and database, eg. work and personal
+    let profile = cmd_matches
+        .get_one::<String>("profile")
+        .cloned()
+        .or_else(|| env::var("DAILY_DOSE_PROFILE").ok());
+
+    let config = load_config(profile.as_deref());
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("init") {
+        handle_cmd_init(arg_matches, profile.as_deref(), &config);
+        return Ok(());
+    }
+
+    // replaces the binary, the database isn't needed for that
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("self-update") {
+        handle_cmd_self_update(arg_matches);
+        return Ok(());
+    }
+
+    let data_dir = match resolve_data_dir(&config, profile.as_deref()) {
+        Ok(data_dir) => data_dir,
+        Err(error) => {
+            println!("Error = {error}");
+            std::process::exit(1);
+        }
+    };
+
+    // with encryption turned on the database can't be opened before there is a key
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("key")
+        && arg_matches.subcommand_name() == Some("generate")
+    {
+        handle_cmd_key_generate();
+        return Ok(());
+    }
+
+    // for a synced replica or a demo, nothing gets written however the commands are used
+    let read_only = cmd_matches.get_flag("read-only")
+        || env::var("DAILY_DOSE_READONLY").is_ok_and(|value| value == "1" || value == "true");
+
+    if read_only && is_mutating_cmd(&cmd_matches) {
+        println!(
+            "Error = '{}' changes the database, which read-only mode doesn't allow",
+            cmd_matches.subcommand_name().unwrap_or_default()
+        );
+        std::process::exit(1);
+    }
+
+    // a changed storage setting leaves the database behind, moving writes so not when read-only
+    let old_data_dir = match read_only {
+        true => None,
+        false => relocate_data_dir(profile.as_deref(), &data_dir),
+    };
+    set_data_dir(data_dir.clone());
+
+    let encryption_key = load_encryption_key(&config);
+
+    let mut db_conn =
+        open_db_connection(encryption_key, read_only).expect("Failed open storage connection");
+
+    if read_only {
+        // migrating writes, so an outdated database has to be opened once without read-only
+        if !is_task_table_migrated(&db_conn).expect("Failed to read schema version") {
+            println!("Error = database schema is outdated, open it once without read-only mode");
+            std::process::exit(1);
+        }
+    } else {
+        if config.get_parsed("wal") == Some(true) {
+            enable_wal(&db_conn).expect("Failed to switch to wal journal mode");
+        }
+
+        create_task_table(&db_conn).expect("Failed to create table");
+
+        // a brand new database has nothing worth backing up
+        let schema_version = get_schema_version(&db_conn).expect("Failed to read schema version");
+        if schema_version > 0
+            && !is_task_table_migrated(&db_conn).expect("Failed to read schema version")
+            && !backup_before(&db_conn, "migrate")
+        {
+            std::process::exit(1);
+        }
+
+        migrate_task_table(&mut db_conn).expect("Failed to migrate table");
+
+        finish_relocation(
+            &db_conn,
+            profile.as_deref(),
+            &data_dir,
+            old_data_dir.as_deref(),
+        );
+    }
+
+    set_display_options(build_display_options(&config, &cmd_matches));
+    set_highlight_rules(highlight_rules(&config));
+    set_color_rules(color_rules(&config));
+
+    if let Some(boundary) = config.get("day_boundary") {
+        match NaiveTime::parse_from_str(boundary, "%H:%M") {
+            Ok(boundary) => set_day_boundary(boundary),
+            Err(_) => println!("Invalid day_boundary '{boundary}' in config, expected eg. 03:00"),
+        }
+    }
+
+    let lock_db = || match acquire_db_lock(&get_db_path()) {
+        Ok(lock) => lock,
+        Err(error) => {
+            println!("Error = {error}");
+            std::process::exit(1);
+        }
+    };
+
+    // held until the end of main, so the whole read-modify-write of a command is covered
+    let db_lock = is_mutating_cmd(&cmd_matches).then(lock_db);
+
+    let usage_stats = config.get_parsed::<bool>("usage_stats") == Some(true);
+
+    // the daily template adds tasks, not something to do in read-only mode
+    if !read_only {
+        // it and usage stats write for commands that only read too, under the lock as well
+        let _write_lock =
+            match db_lock.is_none() && (config.get("daily_template").is_some() || usage_stats) {
+                true => Some(lock_db()),
+                false => None,
+            };
+
+        apply_daily_template(&db_conn, &config, &today());
+
+        // opt-in, and a count that can't be stored isn't worth failing the command over
+        if usage_stats && let Some(command) = usage_command_name(&cmd_matches) {
+            let _ = record_usage(&db_conn, &command);
+        }
+    }
+
     if let Some(arg_matches) = cmd_matches.subcommand_matches("list") {
-        handle_cmd_list(arg_matches, &db_conn);
+        handle_cmd_list(arg_matches, &db_conn, &config);
+    }
+
+    if let Some((name @ ("todo" | "doing" | "blocked"), arg_matches)) = cmd_matches.subcommand() {
+        handle_cmd_status_shortcut(name, arg_matches, &db_conn, &config);
     }
 
     if let Some(arg_matches) = cmd_matches.subcommand_matches("show") {
-        handle_cmd_show(arg_matches, &db_conn);
+        handle_cmd_show(arg_matches, &db_conn, &config);
     }
 
     if let Some(arg_matches) = cmd_matches.subcommand_matches("add") {
-        handle_cmd_add(arg_matches, &db_conn);
+        handle_cmd_add(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("log") {
+        handle_cmd_log(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("query") {
+        handle_cmd_query(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("usage") {
+        handle_cmd_usage(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("seed") {
+        handle_cmd_seed(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("archive") {
+        handle_cmd_archive(arg_matches, &db_conn, &config);
     }
 
     if let Some(arg_matches) = cmd_matches.subcommand_matches("update") {
-        handle_cmd_update(arg_matches, &db_conn);
+        handle_cmd_update(arg_matches, &db_conn, &config);
     }
 
     if let Some(arg_matches) = cmd_matches.subcommand_matches("mark") {
-        handle_cmd_mark(arg_matches, &db_conn);
+        handle_cmd_mark(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("prio") {
+        handle_cmd_prio(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("tag") {
+        handle_cmd_tag(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("untag") {
+        handle_cmd_untag(arg_matches, &db_conn);
     }
 
     if let Some(arg_matches) = cmd_matches.subcommand_matches("unmark") {
         handle_cmd_unmark(arg_matches, &db_conn);
     }
 
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("block") {
+        handle_cmd_block(arg_matches, &db_conn);
+    }
+
     if let Some(arg_matches) = cmd_matches.subcommand_matches("delete") {
         handle_cmd_delete(arg_matches, &db_conn);
     }
 
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("snooze") {
+        handle_cmd_snooze(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("open") {
+        handle_cmd_open(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("attach") {
+        handle_cmd_attach(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("note") {
+        handle_cmd_note(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("recent") {
+        handle_cmd_recent(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("focus") {
+        handle_cmd_focus(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("standup") {
+        handle_cmd_standup(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("next") {
+        handle_cmd_next(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("stats") {
+        handle_cmd_stats(arg_matches, &db_conn);
+    }
+
+    if cmd_matches.subcommand_matches("daemon").is_some() {
+        handle_cmd_daemon(&config, profile.as_deref());
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("serve") {
+        handle_cmd_serve(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("onthisday") {
+        handle_cmd_onthisday(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("template") {
+        handle_cmd_template(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("view") {
+        handle_cmd_view(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("sprint") {
+        handle_cmd_sprint(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("journal") {
+        handle_cmd_journal(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("goal") {
+        handle_cmd_goal(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("deps") {
+        handle_cmd_deps(arg_matches, &db_conn);
+    }
+
+    if cmd_matches.subcommand_matches("projects").is_some() {
+        handle_cmd_projects(&db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("key") {
+        handle_cmd_key(arg_matches, &db_conn, encryption_key.is_some());
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("backup") {
+        handle_cmd_backup(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("export") {
+        handle_cmd_export(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("restore") {
+        handle_cmd_restore(arg_matches, &mut db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("merge") {
+        handle_cmd_merge(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("sync") {
+        handle_cmd_sync(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("caldav") {
+        handle_cmd_caldav(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("conflicts") {
+        handle_cmd_conflicts(arg_matches, &db_conn);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("import") {
+        handle_cmd_import(arg_matches, &db_conn, &config);
+    }
+
+    if let Some(arg_matches) = cmd_matches.subcommand_matches("report") {
+        handle_cmd_report(arg_matches, &db_conn, &config);
+    }
+
     Ok(())
 }
@@ -0,0 +1,249 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+/*
+ * the bit of markdown that ends up in a one line description or note: `code`, **bold**,
+ * *italic* or _italic_ and [text](url). nothing block level, and no nesting, the inside of a
+ * span is taken as is
+ *
+ * render_markdown styles them with ansi codes for lines printed straight to a terminal, cut
+ * at limit characters of what shows. table cells can't hold ansi codes (they would count
+ * towards the column widths), they get plain_markdown, the markup left out and links as
+ * text (url)
+ * */
+enum Span<'a> {
+    Text(&'a str),
+    Code(&'a str),
+    Bold(&'a str),
+    Italic(&'a str),
+    Link(&'a str, &'a str),
+}
+
+pub fn render_markdown(text: &str, limit: Option<usize>) -> String {
+    let styled = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+
+    render(text, limit, styled)
+}
+
+pub fn plain_markdown(text: &str) -> String {
+    spans(text).into_iter().map(plain_span).collect()
+}
+
+fn render(text: &str, limit: Option<usize>, styled: bool) -> String {
+    let spans = match limit {
+        Some(limit) => cut(spans(text), limit),
+        None => spans(text),
+    };
+
+    spans
+        .into_iter()
+        .map(|span| match (span, styled) {
+            (Span::Code(code), true) => format!("\x1b[36m{code}\x1b[39m"),
+            (Span::Bold(bold), true) => format!("\x1b[1m{bold}\x1b[22m"),
+            (Span::Italic(italic), true) => format!("\x1b[3m{italic}\x1b[23m"),
+            (Span::Link(label, url), true) if label != url => {
+                format!("\x1b[4m{label}\x1b[24m \x1b[2m({url})\x1b[22m")
+            }
+            (Span::Link(_, url), true) => format!("\x1b[4m{url}\x1b[24m"),
+            (span, _) => plain_span(span),
+        })
+        .collect()
+}
+
+fn plain_span(span: Span) -> String {
+    match span {
+        Span::Text(text) | Span::Code(text) | Span::Bold(text) | Span::Italic(text) => {
+            text.to_string()
+        }
+        Span::Link(label, url) if label == url => url.to_string(),
+        Span::Link(label, url) => format!("{label} ({url})"),
+    }
+}
+
+fn plain_len(span: &Span) -> usize {
+    match span {
+        Span::Text(text) | Span::Code(text) | Span::Bold(text) | Span::Italic(text) => {
+            text.chars().count()
+        }
+        Span::Link(label, url) if label == url => url.chars().count(),
+        Span::Link(label, url) => label.chars().count() + url.chars().count() + 3,
+    }
+}
+
+// the spans of the first limit characters shown, ending in … like truncate_description
+fn cut(spans: Vec<Span<'_>>, limit: usize) -> Vec<Span<'_>> {
+    if spans.iter().map(plain_len).sum::<usize>() <= limit {
+        return spans;
+    }
+
+    let mut budget = limit.saturating_sub(1);
+    let mut kept = vec![];
+
+    for span in spans {
+        let len = plain_len(&span);
+        if len <= budget {
+            budget -= len;
+            kept.push(span);
+            continue;
+        }
+
+        let first_chars = |text: &'_ str| -> usize {
+            text.char_indices()
+                .nth(budget)
+                .map_or(text.len(), |(index, _)| index)
+        };
+
+        // a cut link loses its url, what is left of the label is just text
+        let span = match span {
+            Span::Text(text) => Span::Text(text[..first_chars(text)].trim_end()),
+            Span::Code(code) => Span::Code(code[..first_chars(code)].trim_end()),
+            Span::Bold(bold) => Span::Bold(bold[..first_chars(bold)].trim_end()),
+            Span::Italic(italic) => Span::Italic(italic[..first_chars(italic)].trim_end()),
+            Span::Link(label, _) => Span::Text(label[..first_chars(label)].trim_end()),
+        };
+        kept.push(span);
+        break;
+    }
+
+    kept.push(Span::Text("…"));
+    kept
+}
+
+fn spans(text: &str) -> Vec<Span<'_>> {
+    let mut spans = vec![];
+    // start of the text not in a span yet
+    let mut plain_from = 0;
+    let mut index = 0;
+
+    while index < text.len() {
+        let rest = &text[index..];
+
+        let found = match rest.as_bytes()[0] {
+            b'`' => delimited(rest, "`").map(|(code, len)| (Span::Code(code), len)),
+            b'*' if rest.starts_with("**") => {
+                delimited(rest, "**").map(|(bold, len)| (Span::Bold(bold), len))
+            }
+            b'*' => delimited(rest, "*").map(|(italic, len)| (Span::Italic(italic), len)),
+            // snake_case and the like stay as they are
+            b'_' if !text[..index]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric) =>
+            {
+                delimited(rest, "_")
+                    .filter(|(_, len)| {
+                        !rest[*len..]
+                            .chars()
+                            .next()
+                            .is_some_and(char::is_alphanumeric)
+                    })
+                    .map(|(italic, len)| (Span::Italic(italic), len))
+            }
+            b'[' => link(rest),
+            _ => None,
+        };
+
+        match found {
+            Some((span, len)) => {
+                if plain_from < index {
+                    spans.push(Span::Text(&text[plain_from..index]));
+                }
+                spans.push(span);
+                index += len;
+                plain_from = index;
+            }
+            None => index += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+
+    if plain_from < text.len() {
+        spans.push(Span::Text(&text[plain_from..]));
+    }
+
+    spans
+}
+
+// the inside of a span opened by marker at the start of text, and the whole span's length
+fn delimited<'a>(text: &'a str, marker: &str) -> Option<(&'a str, usize)> {
+    let inner = &text[marker.len()..];
+    let end = inner.find(marker)?;
+    let content = &inner[..end];
+
+    // "2 * 3 * 4" isn't emphasis
+    if content.is_empty() || content.starts_with(' ') || content.ends_with(' ') {
+        return None;
+    }
+
+    Some((content, marker.len() * 2 + end))
+}
+
+// [label](url)
+fn link(text: &str) -> Option<(Span<'_>, usize)> {
+    let label_end = text.find("](")?;
+    let label = &text[1..label_end];
+    let url_start = label_end + 2;
+    let url_end = url_start + text[url_start..].find(')')?;
+    let url = &text[url_start..url_end];
+
+    if label.is_empty() || label.contains('[') || url.is_empty() || url.contains(' ') {
+        return None;
+    }
+
+    Some((Span::Link(label, url), url_end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_leaves_out_the_markup() {
+        assert_eq!(
+            plain_markdown("run `make` **now**, *then* _rest_"),
+            "run make now, then rest"
+        );
+        assert_eq!(
+            plain_markdown("see [the docs](https://example.com) or [https://a.b](https://a.b)"),
+            "see the docs (https://example.com) or https://a.b"
+        );
+    }
+
+    #[test]
+    fn text_that_only_looks_like_markup_stays() {
+        for text in [
+            "snake_case_name",
+            "2 * 3 * 4",
+            "an *unclosed marker",
+            "``",
+            "[label](with space)",
+            "[](https://example.com)",
+            "a_b_ c",
+        ] {
+            assert_eq!(plain_markdown(text), text);
+        }
+    }
+
+    #[test]
+    fn styled_spans() {
+        assert_eq!(
+            render("`a` **b** *c* [d](e)", None, true),
+            "\x1b[36ma\x1b[39m \x1b[1mb\x1b[22m \x1b[3mc\x1b[23m \x1b[4md\x1b[24m \x1b[2m(e)\x1b[22m"
+        );
+        assert_eq!(render("[e](e)", None, true), "\x1b[4me\x1b[24m");
+        assert_eq!(render("**b**", None, false), "b");
+    }
+
+    #[test]
+    fn cuts_at_the_limit_of_what_shows() {
+        assert_eq!(render("short **text**", Some(10), false), "short text");
+        assert_eq!(render("a **bold** word", Some(6), false), "a bol…");
+        assert_eq!(
+            render("**bold** word", Some(6), true),
+            "\x1b[1mbold\x1b[22m…"
+        );
+        // a cut link loses its url
+        assert_eq!(render("[a label](https://x.y)", Some(5), true), "a la…");
+    }
+}
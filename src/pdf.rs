@@ -0,0 +1,154 @@
+use std::fmt::Write;
+
+use crate::{utils::redact_description, Status, Task};
+
+// a4 portrait, in points
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+const MARGIN: u32 = 50;
+
+const FONT_SIZE: u32 = 10;
+const TITLE_FONT_SIZE: u32 = 16;
+const LINE_HEIGHT: u32 = 14;
+
+// roughly what fits in a line of helvetica at FONT_SIZE between the margins
+const MAX_LINE_CHARS: usize = 95;
+
+/*
+ * one page pdf summary of a period, written by hand as the format is simple enough
+ * for plain text on a single page: a catalog, one page, its content stream and
+ * the builtin helvetica font, which every pdf reader has to provide
+ *
+ * whatever doesn't fit on the page is left out with a note of how many tasks were dropped
+ * */
+pub fn render_period_pdf(title: &str, days: &[(String, Vec<Task>)]) -> Vec<u8> {
+    let tasks: Vec<&Task> = days.iter().flat_map(|(_, tasks)| tasks).collect();
+
+    let done = tasks
+        .iter()
+        .filter(|task| task.status == Status::Done)
+        .count();
+    let completion_rate = match tasks.len() {
+        0 => 0,
+        total => done * 100 / total,
+    };
+
+    let mut lines = vec![format!(
+        "Done {done} of {} tasks ({completion_rate}%)",
+        tasks.len()
+    )];
+
+    for status in [Status::InProgress, Status::Blocked, Status::Todo] {
+        let count = tasks.iter().filter(|task| task.status == status).count();
+        if count > 0 {
+            lines.push(format!("{status}: {count}"));
+        }
+    }
+
+    for (date, tasks) in days.iter().filter(|(_, tasks)| !tasks.is_empty()) {
+        lines.push(String::new());
+        lines.push(date.clone());
+
+        for task in tasks {
+            let mut line = format!(
+                "  [{}] {}",
+                task.status,
+                redact_description(&task.description)
+            );
+            if let Some(points) = task.points {
+                line.push_str(&format!(" ({points} pts)"));
+            }
+            lines.push(line);
+        }
+    }
+
+    // title takes up two lines
+    let max_lines = ((PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT - 2) as usize;
+    if lines.len() > max_lines {
+        let dropped = lines[max_lines - 1..]
+            .iter()
+            .filter(|line| line.starts_with("  ["))
+            .count();
+        lines.truncate(max_lines - 1);
+        lines.push(format!("... {dropped} more task(s) not shown"));
+    }
+
+    let mut content = String::new();
+    let _ = write!(
+        content,
+        "BT\n/F1 {TITLE_FONT_SIZE} Tf\n{MARGIN} {} Td\n({}) Tj\n/F1 {FONT_SIZE} Tf\n0 -{} Td\n",
+        PAGE_HEIGHT - MARGIN,
+        escape_pdf_text(title),
+        2 * LINE_HEIGHT
+    );
+    for line in &lines {
+        let _ = writeln!(
+            content,
+            "({}) Tj\n0 -{LINE_HEIGHT} Td",
+            escape_pdf_text(line)
+        );
+    }
+    content.push_str("ET\n");
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+                /Resources << /Font << /F1 4 0 R >> >> /Contents 5 0 R >>"
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        format!(
+            "<< /Length {} >>\nstream\n{content}endstream",
+            content.len()
+        ),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = vec![];
+
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = write!(pdf, "{} 0 obj\n{object}\nendobj\n", index + 1);
+    }
+
+    let xref_offset = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(pdf, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+        objects.len() + 1
+    );
+
+    pdf.into_bytes()
+}
+
+/*
+ * text strings are limited to what the font encoding covers, everything else becomes '?'
+ * lines are cut to the page width as there is no wrapping
+ * */
+fn escape_pdf_text(text: &str) -> String {
+    let mut escaped = String::new();
+
+    for (index, c) in text.chars().enumerate() {
+        if index == MAX_LINE_CHARS {
+            escaped.push_str("...");
+            break;
+        }
+
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+
+    escaped
+}
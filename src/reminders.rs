@@ -0,0 +1,177 @@
+use chrono::NaiveDate;
+use ulid::Ulid;
+
+use crate::{
+    utils::{fnv1a, today},
+    Status, Task,
+};
+
+// a reminder as printed by READ_LIST_SCRIPT
+#[derive(Debug)]
+pub struct Reminder {
+    pub id: String,
+    pub name: String,
+    pub due: Option<NaiveDate>,
+    pub completed: bool,
+    pub completed_on: Option<NaiveDate>,
+    pub created_on: Option<NaiveDate>,
+}
+
+/*
+ * prints every reminder of the list named by the argument, one per line:
+ * id<TAB>name<TAB>due<TAB>completed<TAB>completion date<TAB>creation date
+ *
+ * dates are built from their parts, `as text` would follow the locale. each property is
+ * fetched for the whole list at once, one apple event per reminder takes minutes on long lists
+ * */
+#[cfg(target_os = "macos")]
+const READ_LIST_SCRIPT: &str = r#"
+on isoDate(theDate)
+    if theDate is missing value then return ""
+    set theMonth to text -2 thru -1 of ("0" & ((month of theDate) as integer))
+    set theDay to text -2 thru -1 of ("0" & (day of theDate))
+    return ((year of theDate) as text) & "-" & theMonth & "-" & theDay
+end isoDate
+
+on oneLine(theText)
+    set AppleScript's text item delimiters to {tab, linefeed, return}
+    set theParts to text items of theText
+    set AppleScript's text item delimiters to " "
+    set theText to theParts as text
+    set AppleScript's text item delimiters to ""
+    return theText
+end oneLine
+
+on run argv
+    set output to {}
+    tell application "Reminders"
+        set theList to list (item 1 of argv)
+        set ids to id of reminders of theList
+        set names to name of reminders of theList
+        set dueDates to due date of reminders of theList
+        set doneFlags to completed of reminders of theList
+        set doneDates to completion date of reminders of theList
+        set createdDates to creation date of reminders of theList
+    end tell
+    repeat with i from 1 to count of ids
+        set end of output to (item i of ids) & tab & my oneLine(item i of names) & tab & ¬
+            my isoDate(item i of dueDates) & tab & ((item i of doneFlags) as text) & tab & ¬
+            my isoDate(item i of doneDates) & tab & my isoDate(item i of createdDates)
+    end repeat
+    set AppleScript's text item delimiters to linefeed
+    return output as text
+end run
+"#;
+
+// asks Reminders through osascript, the first run prompts for access to reminders
+#[cfg(target_os = "macos")]
+pub fn read_reminders(list: &str) -> Result<Vec<Reminder>, String> {
+    use std::{
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    let mut osascript = Command::new("osascript")
+        .args(["-", list])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Could not run osascript = {error}"))?;
+
+    if let Some(mut stdin) = osascript.stdin.take() {
+        stdin
+            .write_all(READ_LIST_SCRIPT.as_bytes())
+            .map_err(|error| error.to_string())?;
+    }
+
+    let output = osascript
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(parse_reminder_line)
+        .collect()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn read_reminders(_list: &str) -> Result<Vec<Reminder>, String> {
+    Err("Apple Reminders can only be read on macOS".to_string())
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_reminder_line(line: &str) -> Result<Reminder, String> {
+    let fields: Vec<&str> = line.split('\t').collect();
+
+    let [id, name, due, completed, completed_on, created_on] = fields[..] else {
+        return Err(format!("Unexpected reminder line '{line}'"));
+    };
+
+    let date = |value: &str| match value {
+        "" => Ok(None),
+        value => NaiveDate::parse_from_str(value, "%F")
+            .map(Some)
+            .map_err(|_| format!("Invalid reminder date '{value}'")),
+    };
+
+    Ok(Reminder {
+        id: id.to_string(),
+        name: name.trim().to_string(),
+        due: date(due)?,
+        completed: completed == "true",
+        completed_on: date(completed_on)?,
+        created_on: date(created_on)?,
+    })
+}
+
+/*
+ * a done reminder lands on the day it was done, an open one on its due date, reminders
+ * without either on the day they were created
+ *
+ * the task id comes from the reminder's own id, importing the same list again skips the
+ * reminders already imported instead of adding them twice
+ * */
+pub fn reminder_task(reminder: &Reminder) -> Task {
+    let date = match reminder.completed {
+        true => reminder.completed_on.or(reminder.due),
+        false => reminder.due,
+    }
+    .or(reminder.created_on)
+    .unwrap_or_else(today);
+
+    // the creation date, unlike the date, stays the same when a reminder is done later
+    let timestamp_ms = reminder
+        .created_on
+        .unwrap_or(date)
+        .and_hms_opt(0, 0, 0)
+        .map(|datetime| datetime.and_utc().timestamp_millis().max(0) as u64)
+        .unwrap_or_default();
+    let random = fnv1a(reminder.id.bytes()) as u128;
+
+    Task {
+        id: Ulid::from_parts(timestamp_ms, random).to_string(),
+        description: reminder.name.clone(),
+        status: match reminder.completed {
+            true => Status::Done,
+            false => Status::Todo,
+        },
+        date: date.format("%F").to_string(),
+        updated_at: None,
+        deferred_until: None,
+        points: None,
+        block_reason: None,
+        position: 0,
+        short_id: None,
+        priority: None,
+        project: None,
+        due: None,
+        parent_id: None,
+        tags: vec![],
+    }
+}
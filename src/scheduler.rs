@@ -0,0 +1,168 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Days, Local, NaiveDateTime, NaiveTime, TimeDelta};
+
+/*
+ * jobs of `daemon`, one per key under [daemon] in config: the job's name = "<when> <command>"
+ * the command is any daily-dose command line, run as its own process like cron would
+ *
+ * [daemon]
+ * standup = "09:25 standup"
+ * backup = "nightly backup --rotate"
+ * caldav = "every 30m caldav"
+ * */
+#[derive(Debug)]
+pub struct Job {
+    pub name: String,
+    pub schedule: Schedule,
+    pub args: Vec<String>,
+}
+
+/*
+ * 09:25        every day at 09:25 local time
+ * nightly      every day at 03:00
+ * hourly       at the start of every hour
+ * every 15m    every 15 minutes (or eg. 2h), counted from midnight, so :00, :15, :30 and :45
+ * */
+#[derive(Debug, Clone, Copy)]
+pub enum Schedule {
+    Daily(NaiveTime),
+    Every(TimeDelta),
+}
+
+const NIGHTLY_AT: NaiveTime = NaiveTime::from_hms_opt(3, 0, 0).expect("03:00 is a valid time");
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "nightly" => return Ok(Schedule::Daily(NIGHTLY_AT)),
+            "hourly" => return Ok(Schedule::Every(TimeDelta::hours(1))),
+            _ => {}
+        }
+
+        if let Some(interval) = value.strip_prefix("every ") {
+            let interval = interval.trim();
+            let invalid = || format!("Invalid interval '{interval}', eg. 15m or 2h");
+
+            let interval = match (interval.strip_suffix('m'), interval.strip_suffix('h')) {
+                (Some(minutes), _) => TimeDelta::minutes(minutes.parse().map_err(|_| invalid())?),
+                (_, Some(hours)) => TimeDelta::hours(hours.parse().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            };
+
+            if interval < TimeDelta::minutes(1) || interval > TimeDelta::days(1) {
+                return Err("Intervals go from 1m to 24h".to_string());
+            }
+
+            return Ok(Schedule::Every(interval));
+        }
+
+        NaiveTime::parse_from_str(value, "%H:%M")
+            .map(Schedule::Daily)
+            .map_err(|_| format!("Invalid time '{value}', eg. 09:25, hourly, nightly or every 15m"))
+    }
+}
+
+impl Schedule {
+    // the first time strictly after now the job is due
+    pub fn next_after(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let now = now.naive_local();
+        let midnight = now.date().and_time(NaiveTime::MIN);
+
+        let next = match self {
+            Schedule::Daily(time) => match now.date().and_time(*time) {
+                today if today > now => today,
+                today => today + Days::new(1),
+            },
+            Schedule::Every(interval) => {
+                let elapsed = now - midnight;
+                let periods = elapsed.num_seconds() / interval.num_seconds() + 1;
+
+                (midnight + *interval * periods as i32).min(midnight + Days::new(1))
+            }
+        };
+
+        to_local(next)
+    }
+}
+
+// a time skipped by a daylight saving change runs an hour later
+fn to_local(time: NaiveDateTime) -> DateTime<Local> {
+    time.and_local_timezone(Local)
+        .earliest()
+        .or_else(|| {
+            (time + TimeDelta::hours(1))
+                .and_local_timezone(Local)
+                .earliest()
+        })
+        .unwrap_or_else(Local::now)
+}
+
+pub fn parse_job(name: &str, line: &str) -> Result<Job, String> {
+    let line = line.trim();
+
+    // "every 15m" is the only schedule of two words
+    let schedule_words = match line.starts_with("every ") {
+        true => 2,
+        false => 1,
+    };
+
+    let mut command = line;
+    for _ in 0..schedule_words {
+        command = command.trim_start();
+        command = &command[command.find(char::is_whitespace).unwrap_or(command.len())..];
+    }
+
+    let schedule = line[..line.len() - command.len()]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let args = split_args(command)?;
+
+    if args.is_empty() {
+        return Err(format!(
+            "Job {name} has no command, eg. {name} = \"09:25 standup\""
+        ));
+    }
+
+    if args.first().is_some_and(|command| command == "daemon") {
+        return Err(format!("Job {name} can't run another daemon"));
+    }
+
+    Ok(Job {
+        name: name.to_string(),
+        schedule: schedule.parse()?,
+        args,
+    })
+}
+
+// words of a command line, "two words" in quotes are one
+fn split_args(command: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    let mut in_quotes = false;
+
+    for c in command.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !arg.is_empty() {
+                    args.push(std::mem::take(&mut arg));
+                }
+            }
+            c => arg.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("Unclosed quote in command".to_string());
+    }
+
+    if !arg.is_empty() {
+        args.push(arg);
+    }
+
+    Ok(args)
+}
@@ -0,0 +1,184 @@
+use chrono::{Days, Local, NaiveDate, NaiveTime, SecondsFormat, TimeDelta, Utc};
+use ulid::Ulid;
+
+use crate::{Status, Task};
+
+/*
+ * made up tasks for seed, to try the tool out or take screenshots without real work showing
+ *
+ * per_day tasks on each of the last days, today included. older days are mostly done, the
+ * closer to today the more is still todo or in progress. tasks are added during working hours
+ * and worked on a little later, which is what their ids and updated times say
+ *
+ * the same seed on the same day gives the same tasks, so runs can be compared
+ * */
+pub fn seed_tasks(days: u32, per_day: u32, seed: u64, today: NaiveDate) -> Vec<Task> {
+    let mut rng = Rng::new(seed);
+    let mut tasks = vec![];
+
+    for days_ago in (0..days as u64).rev() {
+        let Some(date) = today.checked_sub_days(Days::new(days_ago)) else {
+            continue;
+        };
+
+        for position in 1..=per_day {
+            tasks.push(seed_task(&mut rng, date, days_ago, position));
+        }
+    }
+
+    tasks
+}
+
+fn seed_task(rng: &mut Rng, date: NaiveDate, days_ago: u64, position: u32) -> Task {
+    let verb = rng.pick(VERBS);
+    let subject = rng.pick(SUBJECTS);
+
+    let mut description = format!("{verb} {subject}");
+    // about half the tasks get a tag, a few two
+    let tag_count = match rng.below(10) {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    };
+    for _ in 0..tag_count {
+        let tag = format!(" #{}", rng.pick(TAGS));
+        if !description.contains(&tag) {
+            description.push_str(&tag);
+        }
+    }
+
+    // the chance of a task being done, in percent
+    let done_chance = match days_ago {
+        0 => 30,
+        1..=2 => 60,
+        3..=6 => 80,
+        _ => 92,
+    };
+
+    let roll = rng.below(100);
+    let status = match roll {
+        roll if roll < done_chance => Status::Done,
+        roll if roll < done_chance + (100 - done_chance) / 3 => Status::InProgress,
+        _ if rng.below(4) == 0 => Status::Blocked,
+        _ => Status::Todo,
+    };
+
+    let block_reason = (status == Status::Blocked).then(|| rng.pick(BLOCK_REASONS).to_string());
+
+    // estimated now and then, in fibonacci like most teams do
+    let points = match rng.below(3) {
+        0 => Some([1, 2, 3, 5, 8][rng.below(5) as usize]),
+        _ => None,
+    };
+
+    // added between 08:30 and 17:30 local time, worked on up to a few hours later
+    let added_at = date.and_time(NaiveTime::MIN) + TimeDelta::minutes(510 + rng.below(540) as i64);
+    let added_at = added_at
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|added_at| added_at.with_timezone(&Utc))
+        .unwrap_or_else(|| added_at.and_utc())
+        // today's working hours may not have come yet
+        .min(Utc::now());
+
+    let updated_at = match status {
+        Status::Todo => added_at,
+        _ => added_at + TimeDelta::minutes(15 + rng.below(300) as i64),
+    }
+    .min(Utc::now());
+
+    let id = Ulid::from_parts(added_at.timestamp_millis() as u64, rng.next_u128());
+
+    Task {
+        id: id.to_string(),
+        description,
+        status,
+        date: date.format("%F").to_string(),
+        updated_at: Some(updated_at.to_rfc3339_opts(SecondsFormat::Micros, true)),
+        deferred_until: None,
+        points,
+        block_reason,
+        position,
+        short_id: None,
+        priority: None,
+        project: None,
+        due: None,
+        parent_id: None,
+        tags: vec![],
+    }
+}
+
+// xorshift64*, plenty for made up data and no dependency for it
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // the state must not be 0
+        Rng((seed ^ 0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn next_u128(&mut self) -> u128 {
+        ((self.next_u64() as u128) << 64) | self.next_u64() as u128
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    fn pick<'a>(&mut self, words: &[&'a str]) -> &'a str {
+        words[self.below(words.len() as u64) as usize]
+    }
+}
+
+const VERBS: &[&str] = &[
+    "Fix",
+    "Review",
+    "Write tests for",
+    "Refactor",
+    "Document",
+    "Investigate",
+    "Deploy",
+    "Pair on",
+    "Profile",
+    "Clean up",
+    "Design",
+    "Update",
+];
+
+const SUBJECTS: &[&str] = &[
+    "login redirect",
+    "billing api",
+    "search indexing",
+    "onboarding flow",
+    "flaky ci job",
+    "payment webhooks",
+    "user settings page",
+    "rate limiter",
+    "email templates",
+    "dashboard charts",
+    "database migrations",
+    "release notes",
+    "csv export",
+    "mobile layout",
+    "cache invalidation",
+    "audit log",
+];
+
+const TAGS: &[&str] = &[
+    "backend", "frontend", "infra", "bug", "docs", "meeting", "review", "perf",
+];
+
+const BLOCK_REASONS: &[&str] = &[
+    "waiting on design",
+    "staging is down",
+    "needs api keys from ops",
+    "waiting for review",
+    "unclear requirements",
+];
@@ -0,0 +1,134 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use rusqlite::Connection;
+
+use crate::{
+    database::{count_tasks_by_status, get_db_path, get_tasks_completed_since},
+    utils::{day_start_timestamp, today},
+    Status,
+};
+
+/*
+ * bare bones http server behind `serve`, it only knows GET /metrics
+ * connections are handled one after another, a scrape every few seconds is all it has to take
+ * */
+pub fn serve_metrics(db_conn: &Connection, address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+
+    for stream in listener.incoming() {
+        // a client hanging up halfway is no reason to stop serving the next one
+        if let Err(error) = stream.and_then(|stream| handle_connection(db_conn, stream)) {
+            println!("Error handling request = {error}");
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(db_conn: &Connection, mut stream: TcpStream) -> io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    // eg. GET /metrics HTTP/1.1, headers and body are of no interest
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+
+    let (status, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => match render_metrics(db_conn) {
+            Ok(metrics) => ("200 OK", metrics),
+            Err(error) => ("500 Internal Server Error", format!("{error}\n")),
+        },
+        (Some("GET"), _) => ("404 Not Found", "Only /metrics is served\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "Only GET is supported\n".to_string(),
+        ),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+
+    stream.flush()
+}
+
+/*
+ * prometheus text format, every metric is a gauge read fresh on each scrape:
+ *
+ * daily_dose_tasks{status="todo"}     tasks per status, all dates
+ * daily_dose_open_tasks               tasks not done yet
+ * daily_dose_blocked_tasks
+ * daily_dose_completed_today          tasks marked done since local midnight
+ * daily_dose_db_size_bytes
+ * */
+fn render_metrics(db_conn: &Connection) -> Result<String, rusqlite::Error> {
+    let status_counts = count_tasks_by_status(db_conn)?;
+
+    let count_of = |status: Status| {
+        status_counts
+            .iter()
+            .find(|(counted, _)| *counted == status)
+            .map_or(0, |(_, count)| *count)
+    };
+
+    let completed_today = get_tasks_completed_since(db_conn, &day_start_timestamp(&today()))?.len();
+
+    // a missing file just means nothing was written yet
+    let db_size = fs::metadata(get_db_path()).map_or(0, |metadata| metadata.len());
+
+    let mut metrics = String::new();
+
+    let _ = writeln!(metrics, "# HELP daily_dose_tasks Tasks per status");
+    let _ = writeln!(metrics, "# TYPE daily_dose_tasks gauge");
+    for status in [
+        Status::Todo,
+        Status::InProgress,
+        Status::Blocked,
+        Status::Done,
+    ] {
+        let _ = writeln!(
+            metrics,
+            "daily_dose_tasks{{status=\"{status}\"}} {}",
+            count_of(status)
+        );
+    }
+
+    let gauges = [
+        (
+            "daily_dose_open_tasks",
+            "Tasks not done yet",
+            (count_of(Status::Todo) + count_of(Status::InProgress) + count_of(Status::Blocked))
+                as u64,
+        ),
+        (
+            "daily_dose_blocked_tasks",
+            "Tasks waiting on something",
+            count_of(Status::Blocked) as u64,
+        ),
+        (
+            "daily_dose_completed_today",
+            "Tasks marked done since local midnight",
+            completed_today as u64,
+        ),
+        (
+            "daily_dose_db_size_bytes",
+            "Size of the task database file",
+            db_size,
+        ),
+    ];
+
+    for (name, help, value) in gauges {
+        let _ = writeln!(metrics, "# HELP {name} {help}");
+        let _ = writeln!(metrics, "# TYPE {name} gauge");
+        let _ = writeln!(metrics, "{name} {value}");
+    }
+
+    Ok(metrics)
+}
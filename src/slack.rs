@@ -0,0 +1,96 @@
+use crate::{json::Json, utils::redact_description, Status, Task};
+
+// slack refuses section texts longer than this, a longer status goes in several sections
+const SECTION_MAX_CHARS: usize = 3000;
+
+// the order the statuses are posted in, as in a standup
+const STATUS_ORDER: [(Status, &str); 4] = [
+    (Status::Done, "Done"),
+    (Status::InProgress, "In progress"),
+    (Status::Todo, "Todo"),
+    (Status::Blocked, "Blocked"),
+];
+
+/*
+ * a block kit message, ready to POST to an incoming webhook or chat.postMessage
+ *
+ * a section per status with its tasks as bullets (blocked ones with their reason), statuses
+ * without tasks left out, then a context block with the dates. text is the fallback slack
+ * shows in notifications
+ * */
+pub fn render_slack_blocks(tasks: &[Task], dates: &str) -> String {
+    let mut blocks = vec![];
+
+    for (status, label) in STATUS_ORDER {
+        let lines: Vec<String> = tasks
+            .iter()
+            .filter(|task| task.status == status)
+            .map(|task| {
+                let description = mrkdwn_escape(&redact_description(&task.description));
+
+                match &task.block_reason {
+                    Some(reason) if status == Status::Blocked => {
+                        format!("• {description} ({})", mrkdwn_escape(reason))
+                    }
+                    _ => format!("• {description}"),
+                }
+            })
+            .collect();
+
+        if lines.is_empty() {
+            continue;
+        }
+
+        let mut text = format!("*{label}*");
+        for line in lines {
+            if text.chars().count() + 1 + line.chars().count() > SECTION_MAX_CHARS {
+                blocks.push(section(std::mem::take(&mut text)));
+            }
+
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.extend(line.chars().take(SECTION_MAX_CHARS));
+        }
+        blocks.push(section(text));
+    }
+
+    if blocks.is_empty() {
+        blocks.push(section("No tasks".to_string()));
+    }
+
+    blocks.push(Json::Object(vec![
+        ("type", "context".into()),
+        (
+            "elements",
+            Json::Array(vec![Json::Object(vec![
+                ("type", "mrkdwn".into()),
+                ("text", dates.into()),
+            ])]),
+        ),
+    ]));
+
+    let message = Json::Object(vec![
+        ("text", format!("Tasks, {dates}").into()),
+        ("blocks", Json::Array(blocks)),
+    ]);
+
+    format!("{message}\n")
+}
+
+fn section(text: String) -> Json {
+    Json::Object(vec![
+        ("type", "section".into()),
+        (
+            "text",
+            Json::Object(vec![("type", "mrkdwn".into()), ("text", text.into())]),
+        ),
+    ])
+}
+
+// slack reads <...> as links and mentions, the rest of mrkdwn is kept
+fn mrkdwn_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,120 @@
+use crate::{
+    config::Config,
+    crypto::get_secret,
+    json::{parse_json, Json, JsonValue},
+    utils::{curl_config_string, run_curl},
+};
+
+/*
+ * standup --summarize, built only with the summarize feature (cargo build --features summarize)
+ * the standup goes to a chat completions endpoint, openai's or any compatible one, which
+ * includes local models served by eg. ollama or llama.cpp. nothing is sent without
+ *
+ * [summarize]
+ * endpoint = "http://localhost:11434/v1/chat/completions"
+ * model = "llama3.2"
+ *
+ * endpoints on this machine get no key, others one from DAILY_DOSE_SUMMARIZE_KEY or the keyring
+ * */
+const KEY_ACCOUNT: &str = "summarize-key";
+const KEY_ENV: &str = "DAILY_DOSE_SUMMARIZE_KEY";
+
+// a local model on a slow machine takes its time
+const MAX_SECONDS: u32 = 120;
+
+const INSTRUCTIONS: &str = "You condense a developer's standup notes. Answer with 3 or 4 short \
+bullet sentences, each on its own line starting with \"- \", covering what got done, what is \
+planned today and any blockers. Use only what the notes say, no preamble.";
+
+// the summary bullets, the standup as printed without --summarize goes in
+pub fn summarize_standup(config: &Config, standup: &str) -> Result<Vec<String>, String> {
+    let (Some(endpoint), Some(model)) = (
+        config.get("summarize.endpoint"),
+        config.get("summarize.model"),
+    ) else {
+        return Err("set endpoint and model under [summarize] in config".to_string());
+    };
+
+    let request = Json::Object(vec![
+        ("model", model.into()),
+        (
+            "messages",
+            Json::Array(vec![
+                Json::Object(vec![
+                    ("role", "system".into()),
+                    ("content", INSTRUCTIONS.into()),
+                ]),
+                Json::Object(vec![("role", "user".into()), ("content", standup.into())]),
+            ]),
+        ),
+        ("temperature", 0.2.into()),
+    ]);
+
+    let mut config_lines = vec![
+        format!("url = {}", curl_config_string(endpoint)),
+        format!("max-time = {MAX_SECONDS}"),
+        "header = \"Content-Type: application/json\"".to_string(),
+        format!("data-binary = {}", curl_config_string(&request.to_string())),
+    ];
+
+    if !is_local(endpoint) {
+        let key = get_secret(
+            KEY_ACCOUNT,
+            KEY_ENV,
+            "API key for summarizing: ",
+            "summarize key",
+        )?;
+        config_lines.push(format!(
+            "header = {}",
+            curl_config_string(&format!("Authorization: Bearer {key}"))
+        ));
+    }
+
+    let (body, status) = run_curl(&config_lines)?;
+    let response = parse_json(&body).map_err(|_| format!("{endpoint} answered {status}"))?;
+
+    if status != "200" {
+        let message = response
+            .get("error")
+            .and_then(|error| error.get("message").or(Some(error)))
+            .and_then(JsonValue::as_str)
+            .unwrap_or("unknown error");
+
+        return Err(format!("{endpoint} answered {status} = {message}"));
+    }
+
+    let summary: Vec<String> = response
+        .get("choices")
+        .and_then(|choices| choices.as_array().first())
+        .and_then(|choice| choice.get("message"))
+        .and_then(|message| message.get("content"))
+        .and_then(JsonValue::as_str)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.trim().trim_start_matches(['-', '*', '•']).trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("- {line}"))
+        .collect();
+
+    match summary.is_empty() {
+        true => Err(format!("{endpoint} answered without a summary")),
+        false => Ok(summary),
+    }
+}
+
+// localhost, 127.0.0.1 or [::1], whatever the scheme and port
+fn is_local(endpoint: &str) -> bool {
+    let host = endpoint
+        .split_once("://")
+        .map_or(endpoint, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
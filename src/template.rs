@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use minijinja::{syntax::SyntaxConfig, Environment, UndefinedBehavior, Value};
+
+use crate::json::JsonValue;
+
+/*
+ * report --template, a jinja template (minijinja) filled in with the report's --format json
+ * object (see json.rs for its fields), for standups in whatever exact shape a channel wants
+ *
+ * {{ title }}                  a field, dotted paths go deeper, eg. {{ points.done }}
+ * {{ tasks | length }}         any of jinja's filters, eg. upper, join(", "), default("-")
+ * {% for day in days %}        with loop.index, loop.first and loop.last inside
+ * {% endfor %}
+ * {% if done %} {% elif ... %} {% else %} {% endif %}
+ * {# a comment #}
+ *
+ * a tag or comment alone on its line takes the line with it, missing fields are empty and
+ * false, and default also fills in null or empty ones
+ * */
+#[derive(Debug)]
+pub struct Template {
+    source: String,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Template, String> {
+        let source = take_standalone_lines(text);
+
+        environment()
+            .template_from_str(&source)
+            .map_err(|error| error.to_string())?;
+
+        Ok(Template { source })
+    }
+
+    pub fn render(&self, context: &JsonValue) -> Result<String, String> {
+        environment()
+            .render_str(&self.source, to_value(context))
+            .map_err(|error| error.to_string())
+    }
+}
+
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+
+    env.set_syntax(
+        SyntaxConfig::builder()
+            .keep_trailing_newline(true)
+            .build()
+            .expect("Default delimiters are valid"),
+    );
+    env.set_undefined_behavior(UndefinedBehavior::Chainable);
+
+    // jinja's default only fills in undefined values, a report has nulls and empty lists too
+    env.add_filter("default", |value: Value, fallback: Value| {
+        match value.is_true() {
+            true => value,
+            false => fallback,
+        }
+    });
+
+    env
+}
+
+/*
+ * a block tag or comment alone on its line takes the line with it. unlike jinja's trim_blocks
+ * a tag at the end of a line of text keeps its line break. the line break moves inside the
+ * tag, so line numbers in errors still match the file
+ * */
+fn take_standalone_lines(text: &str) -> String {
+    text.split_inclusive('\n')
+        .map(|line| {
+            let tag = line.trim();
+            let close = match tag.get(..2) {
+                Some("{%") => "%}",
+                Some("{#") => "#}",
+                _ => return line.to_string(),
+            };
+
+            // the first tag has to end the line, else there is more than one thing on it
+            if tag.len() < 4 || tag.find(close) != Some(tag.len() - 2) {
+                return line.to_string();
+            }
+
+            match line.ends_with('\n') {
+                true => format!("{}\n{close}", &tag[..tag.len() - 2]),
+                false => tag.to_string(),
+            }
+        })
+        .collect()
+}
+
+// whole numbers as integers, so a count shows as 3 and not 3.0
+fn to_value(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::from(()),
+        JsonValue::Bool(value) => Value::from(*value),
+        JsonValue::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+            Value::from(*value as i64)
+        }
+        JsonValue::Number(value) => Value::from(*value),
+        JsonValue::String(text) => Value::from(text.as_str()),
+        JsonValue::Array(items) => items.iter().map(to_value).collect(),
+        JsonValue::Object(fields) => Value::from(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), to_value(value)))
+                .collect::<BTreeMap<_, _>>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::parse_json;
+
+    fn render(template: &str, context: &str) -> String {
+        Template::parse(template)
+            .and_then(|template| template.render(&parse_json(context)?))
+            .expect("template renders")
+    }
+
+    #[test]
+    fn standalone_tags_take_their_line() {
+        let template = "{# tasks #}\n{% for task in tasks %}\n  - {{ task }}\n{% endfor %}\ndone\n";
+
+        assert_eq!(
+            render(template, r#"{"tasks": ["a", "b"]}"#),
+            "  - a\n  - b\ndone\n"
+        );
+    }
+
+    #[test]
+    fn tags_inside_a_line_keep_its_line_break() {
+        let template = "{% for n in [1, 2] %}{{ n }}{% if loop.last %}!{% endif %}\n{% endfor %}";
+
+        assert_eq!(render(template, "{}"), "1\n2!\n");
+    }
+
+    #[test]
+    fn missing_null_and_empty_fields() {
+        let context = r#"{"points": 3, "priority": null, "tags": []}"#;
+
+        assert_eq!(
+            render(
+                "{{ points }} {{ a.b.c }}|{{ priority | default('-') }}",
+                context
+            ),
+            "3 |-"
+        );
+        assert_eq!(render("{{ tags | default('none') }}", context), "none");
+        assert_eq!(render("{% if not a.b %}no{% endif %}", context), "no");
+    }
+
+    #[test]
+    fn parse_reports_unclosed_blocks() {
+        assert!(Template::parse("{% if done %}\n").is_err());
+    }
+}
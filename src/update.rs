@@ -0,0 +1,210 @@
+use std::{
+    env::{self, consts},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    crypto::sha256_hex,
+    json::parse_json,
+    utils::{curl_config_string, run_curl},
+};
+
+/*
+ * self-update from the github releases, with curl doing the https
+ *
+ * a release carries a binary per platform, named after rust's arch and os, and the sha-256
+ * of each in a SHA256SUMS file (as sha256sum writes it):
+ *
+ * daily_dose-x86_64-linux
+ * daily_dose-aarch64-macos
+ * daily_dose-x86_64-windows.exe
+ * SHA256SUMS
+ *
+ * the binary is downloaded next to the running one and only moved over it once its checksum
+ * matches, so a broken download never leaves a broken install behind. the sums come from the
+ * same release, so whoever can replace the binary can replace them too: this is about
+ * corruption, not authenticity, there is no signature to check
+ * */
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/KrishnenduDakshi2002/daily-dose/releases/latest";
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+// a binary of a few MB on a slow connection
+const MAX_SECONDS: u32 = 300;
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub struct Release {
+    // without the v of the tag, eg. 1.4.0
+    pub version: String,
+    // name and download url
+    assets: Vec<(String, String)>,
+}
+
+impl Release {
+    pub fn is_newer(&self) -> bool {
+        version_parts(&self.version) > version_parts(CURRENT_VERSION)
+    }
+
+    // the binary for this platform, None when the release has no build of it
+    pub fn binary_url(&self) -> Option<&str> {
+        self.asset_url(&binary_asset_name())
+    }
+
+    fn asset_url(&self, name: &str) -> Option<&str> {
+        self.assets
+            .iter()
+            .find(|(asset, _)| asset == name)
+            .map(|(_, url)| url.as_str())
+    }
+}
+
+pub fn binary_asset_name() -> String {
+    format!(
+        "daily_dose-{}-{}{}",
+        consts::ARCH,
+        consts::OS,
+        consts::EXE_SUFFIX
+    )
+}
+
+pub fn get_latest_release() -> Result<Release, String> {
+    let (body, status) = run_curl(&[
+        format!("url = {}", curl_config_string(LATEST_RELEASE_URL)),
+        "location".to_string(),
+        "header = \"Accept: application/vnd.github+json\"".to_string(),
+        "user-agent = \"daily-dose\"".to_string(),
+    ])?;
+
+    match status.as_str() {
+        "200" => {}
+        "404" => return Err("no release published yet".to_string()),
+        status => return Err(format!("GitHub answered {status}")),
+    }
+
+    let release = parse_json(&body)?;
+
+    let version = release
+        .get("tag_name")
+        .and_then(|tag| tag.as_str())
+        .ok_or("the release has no tag")?
+        .trim_start_matches('v')
+        .to_string();
+
+    let assets = release
+        .get("assets")
+        .map(|assets| assets.as_array())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|asset| {
+            let name = asset.get("name")?.as_str()?;
+            let url = asset.get("browser_download_url")?.as_str()?;
+            Some((name.to_string(), url.to_string()))
+        })
+        .collect();
+
+    Ok(Release { version, assets })
+}
+
+// replaces the running binary with the release's, returns where it is
+pub fn install_release(release: &Release) -> Result<PathBuf, String> {
+    let name = binary_asset_name();
+
+    let binary_url = release
+        .binary_url()
+        .ok_or(format!("the release has no {name}"))?;
+    let checksums_url = release.asset_url(CHECKSUMS_ASSET).ok_or(format!(
+        "the release has no {CHECKSUMS_ASSET} to check {name} against"
+    ))?;
+
+    let (checksums, status) = run_curl(&download_config(checksums_url))?;
+    if status != "200" {
+        return Err(format!("downloading {CHECKSUMS_ASSET} answered {status}"));
+    }
+
+    // sha256sum puts a * in front of names hashed in binary mode
+    let expected = checksums
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, file)| file.trim().trim_start_matches('*') == name)
+        .map(|(checksum, _)| checksum.to_lowercase())
+        .ok_or(format!("{CHECKSUMS_ASSET} has no checksum for {name}"))?;
+
+    let current = env::current_exe()
+        .and_then(fs::canonicalize)
+        .map_err(|error| format!("Could not find the running binary = {error}"))?;
+
+    // next to the current one, so the final rename stays on one file system
+    let download = current.with_extension("new");
+
+    let mut config_lines = download_config(binary_url);
+    config_lines.push(format!(
+        "output = {}",
+        curl_config_string(&download.to_string_lossy())
+    ));
+
+    let downloaded = run_curl(&config_lines).and_then(|(_, status)| match status.as_str() {
+        "200" => fs::read(&download).map_err(|error| error.to_string()),
+        status => Err(format!("downloading {name} answered {status}")),
+    });
+
+    let result = downloaded.and_then(|binary| match sha256_hex(&binary) == expected {
+        true => replace_binary(&download, &current),
+        false => Err(format!(
+            "the checksum of {name} doesn't match {CHECKSUMS_ASSET}, nothing was changed"
+        )),
+    });
+
+    // nothing to clean up once it has been moved in
+    let _ = fs::remove_file(&download);
+
+    result.map(|()| current)
+}
+
+fn download_config(url: &str) -> Vec<String> {
+    vec![
+        format!("url = {}", curl_config_string(url)),
+        // release assets redirect to their storage
+        "location".to_string(),
+        format!("max-time = {MAX_SECONDS}"),
+        "user-agent = \"daily-dose\"".to_string(),
+    ]
+}
+
+/*
+ * unix lets a running binary be renamed over, windows doesn't let it be replaced but does let
+ * it be moved aside. the .old one is left behind until the next update
+ * */
+fn replace_binary(download: &Path, current: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(download, fs::Permissions::from_mode(0o755)).map_err(|error| {
+            format!("Could not make {} executable = {error}", download.display())
+        })?;
+    }
+
+    if cfg!(windows) {
+        let old = current.with_extension("old");
+        let _ = fs::remove_file(&old);
+
+        fs::rename(current, &old)
+            .map_err(|error| format!("Could not move {} aside = {error}", current.display()))?;
+    }
+
+    fs::rename(download, current)
+        .map_err(|error| format!("Could not replace {} = {error}", current.display()))
+}
+
+// 1.10.0 after 1.9.2, anything after a - (eg. 1.2.0-rc1) is left out
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
@@ -1,12 +1,34 @@
-use chrono::{Datelike, Local, NaiveDate};
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    io::{self, IsTerminal, Write},
+    process,
+    str::FromStr,
+    sync::{Once, OnceLock},
+};
+
+use chrono::{
+    DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveTime, SecondsFormat, TimeDelta, Utc,
+    Weekday,
+};
 use clap::ArgMatches;
+use regex::Regex;
+use strum::EnumString;
+use ulid::Ulid;
 
-use comfy_table::{Attribute, Cell, Color, ContentArrangement, Table};
+use comfy_table::{
+    Attribute, Cell, CellAlignment, Color, ColumnConstraint, ContentArrangement, Table, Width,
+};
 
-use crate::Task;
+use crate::{
+    config::Config,
+    database::TaskFilter,
+    markdown::{plain_markdown, render_markdown},
+    Priority, Status, Task,
+};
 
 pub fn construct_timestamp(arg_matches: &ArgMatches) -> NaiveDate {
-    let mut timestamp = Local::now().date_naive();
+    let mut timestamp = today();
     /*
      * reason of this year to day approach is only for day case
      * as no. of day will depend on the month
@@ -57,6 +79,581 @@ pub fn construct_timestamp(arg_matches: &ArgMatches) -> NaiveDate {
     timestamp
 }
 
+/*
+ * parses human friendly dates passed to flags like --date
+ * accepts iso dates (2024-06-14), today / tomorrow / yesterday and weekday names
+ * a weekday name means its next occurrence, today included (friday on a friday is today)
+ * */
+pub fn parse_date_arg(value: &str) -> Result<NaiveDate, String> {
+    parse_date(value, 0)
+}
+
+// like parse_date_arg for dates that have to be later than today, friday on a friday is next week
+pub fn parse_later_date_arg(value: &str) -> Result<NaiveDate, String> {
+    parse_date(value, 1)
+}
+
+// weekday names are looked up from today plus days_from on
+fn parse_date(value: &str, days_from: u64) -> Result<NaiveDate, String> {
+    let today = today();
+    let value = value.trim().to_lowercase();
+
+    match value.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today + Days::new(1)),
+        "yesterday" => return Ok(today - Days::new(1)),
+        _ => {}
+    }
+
+    if let Ok(weekday) = value.parse::<Weekday>() {
+        let from = today + Days::new(days_from);
+        let days_ahead =
+            (7 + weekday.num_days_from_monday() - from.weekday().num_days_from_monday()) % 7;
+        return Ok(from + Days::new(days_ahead as u64));
+    }
+
+    NaiveDate::parse_from_str(&value, "%F").map_err(|_| {
+        format!(
+            "Invalid date '{value}', expected YYYY-MM-DD, today, tomorrow, yesterday or a weekday"
+        )
+    })
+}
+
+/*
+ * http(s) urls mentioned in a text, in order and once each, without what is around them:
+ * "see https://x.com.", "<https://x.com>", "[the pr](https://x.com/pr/1)". a closing paren
+ * stays when the url opened one, as in wikipedia links
+ * */
+pub fn extract_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = vec![];
+
+    for word in text.split_whitespace() {
+        let Some(start) = word.find("https://").or_else(|| word.find("http://")) else {
+            continue;
+        };
+
+        let mut url = &word[start..];
+        loop {
+            let trimmed = url.trim_end_matches(['.', ',', ';', ':', ']', '>', '"', '\'', '`', '*']);
+            url = match trimmed.strip_suffix(')') {
+                Some(rest) if rest.matches('(').count() < trimmed.matches(')').count() => rest,
+                _ => trimmed,
+            };
+
+            if url == trimmed {
+                break;
+            }
+        }
+
+        if !urls.iter().any(|known| known == url) {
+            urls.push(url.to_string());
+        }
+    }
+
+    urls
+}
+
+// what a secret looks like, checked by secret_check before storing a description
+const SECRET_PATTERNS: [(&str, &str); 7] = [
+    ("an AWS access key", r"\b(AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("a bearer token", r"(?i)\bbearer\s+[a-z0-9._~+/-]{20,}"),
+    ("a GitHub token", r"\bgh[pousr]_[A-Za-z0-9]{36,}"),
+    ("a Slack token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}"),
+    ("a private key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----"),
+    ("a JWT", r"\beyJ[\w-]{10,}\.[\w-]{10,}\.[\w-]{10,}"),
+    (
+        "a password or api key",
+        r"(?i)\b(api[_-]?key|secret|token|password|passwd)\s*[:=]\s*\S{8,}",
+    ),
+];
+
+// names of the kinds of secret found in the text, eg. "an AWS access key"
+pub fn find_secrets(text: &str) -> Vec<&'static str> {
+    SECRET_PATTERNS
+        .iter()
+        .filter(|(_, pattern)| {
+            Regex::new(pattern)
+                .expect("Secret patterns are valid")
+                .is_match(text)
+        })
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/*
+ * how alike two descriptions are, from 0 to 1: the share of letter pairs they have in common
+ * (dice coefficient), after lowercasing and leaving out punctuation and markup. close enough
+ * to catch "Fix login bug" against "fix the login bug", typos included
+ * */
+pub fn description_similarity(first: &str, second: &str) -> f64 {
+    let pairs = |text: &str| -> Vec<(char, char)> {
+        let words: Vec<String> = text
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect();
+        let chars: Vec<char> = words.join(" ").chars().collect();
+        chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    };
+
+    let first = pairs(first);
+    let mut second = pairs(second);
+
+    if first.is_empty() || second.is_empty() {
+        return 0.0;
+    }
+
+    let total = first.len() + second.len();
+    let mut shared = 0;
+    for pair in first {
+        if let Some(index) = second.iter().position(|other| *other == pair) {
+            second.swap_remove(index);
+            shared += 1;
+        }
+    }
+
+    (2 * shared) as f64 / total as f64
+}
+
+// reads a line from stdin after showing the prompt, None when not attached to a terminal
+pub fn prompt_line(prompt: &str) -> io::Result<Option<String>> {
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+
+    print!("{prompt}");
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+
+    Ok(Some(line.trim().to_string()))
+}
+
+// hands the url (or file path) over to the OS to open in the default application
+pub fn open_with_default_app(target: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        process::Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut command = process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        process::Command::new("xdg-open")
+    };
+
+    command.arg(target).spawn()?;
+
+    Ok(())
+}
+
+/*
+ * the text as edited in $VISUAL or $EDITOR (vi, notepad on windows, when neither is set),
+ * through a file in the temp directory named after file_name, removed again afterwards
+ * */
+pub fn edit_in_editor(text: &str, file_name: &str) -> Result<String, String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| match cfg!(target_os = "windows") {
+            true => "notepad".to_string(),
+            false => "vi".to_string(),
+        });
+
+    let path = env::temp_dir().join(format!("daily-dose-{}-{file_name}", process::id()));
+    fs::write(&path, text)
+        .map_err(|error| format!("Could not write {} = {error}", path.display()))?;
+
+    // the editor may come with arguments, eg. "code --wait"
+    let mut words = editor.split_whitespace();
+    let status = process::Command::new(words.next().expect("Editor isn't empty"))
+        .args(words)
+        .arg(&path)
+        .status();
+
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => return Err(format!("{editor} exited with {status}, nothing saved")),
+        Err(error) => return Err(format!("Could not run {editor} = {error}")),
+    }
+
+    edited.map_err(|error| format!("Could not read {} back = {error}", path.display()))
+}
+
+/*
+ * runs curl with the request given as a config on stdin, so tokens and secrets don't show up
+ * in the process list. returns the body and the http status code of the response
+ * */
+pub fn run_curl(config_lines: &[String]) -> Result<(String, String), String> {
+    let mut child = process::Command::new("curl")
+        .args(["--silent", "--show-error", "--config", "-"])
+        .args(["--write-out", "\n%{http_code}"])
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Could not run curl = {error}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(config_lines.join("\n").as_bytes())
+            .map_err(|error| error.to_string())?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+
+    Ok((body.to_string(), status.trim().to_string()))
+}
+
+// a quoted value of a curl config, which takes backslash escapes
+pub fn curl_config_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for char in value.chars() {
+        match char {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            char => quoted.push(char),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+// query strings and form bodies, everything but the unreserved characters escaped
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+pub fn form_encode(fields: &[(&str, &str)]) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| format!("{key}={}", percent_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/*
+ * parses a --filter expression into the task filter
+ * whitespace separated terms, all of them have to match:
+ *
+ * status:todo           status:todo,blocked matches either
+ * priority:high         priority:high,medium matches either
+ * tag:backend           attached, or #backend in the description
+ * project:acme          project:acme,globex matches either
+ * before:2024-06-01     after:monday    on:yesterday
+ * re:JIRA-\d+           regex on the description
+ * rollout "two words"   anything else is a description search
+ * */
+pub fn parse_filter_expression(expression: &str) -> Result<TaskFilter, String> {
+    let mut filter = TaskFilter::new();
+
+    for term in split_filter_terms(expression)? {
+        let Some((key, value)) = term.split_once(':').filter(|(key, _)| is_filter_key(key)) else {
+            filter = filter.search(&term);
+            continue;
+        };
+
+        let value = value.trim_matches('"');
+
+        filter = match key {
+            "status" => {
+                for status in value.split(',') {
+                    let status = Status::from_str(status)
+                        .map_err(|_| format!("Unknown status '{status}' in filter"))?;
+                    filter = filter.status(status);
+                }
+                filter
+            }
+            "priority" => {
+                for priority in value.split(',') {
+                    let priority = Priority::from_str(priority)
+                        .map_err(|_| format!("Unknown priority '{priority}' in filter"))?;
+                    filter = filter.priority(priority);
+                }
+                filter
+            }
+            "tag" => filter.tag(&parse_tag_arg(value)?),
+            "project" => value
+                .split(',')
+                .fold(filter, |filter, project| filter.project(project)),
+            "before" => filter.before(&iso_format_timestamp(&parse_date_arg(value)?)),
+            "after" => filter.after(&iso_format_timestamp(&parse_date_arg(value)?)),
+            "on" => {
+                let date = iso_format_timestamp(&parse_date_arg(value)?);
+                filter.date_range(&date, &date)
+            }
+            "re" => {
+                Regex::new(value).map_err(|error| error.to_string())?;
+                filter.search_regex(value)
+            }
+            _ => unreachable!("checked by is_filter_key"),
+        };
+    }
+
+    Ok(filter)
+}
+
+fn is_filter_key(key: &str) -> bool {
+    matches!(
+        key,
+        "status" | "priority" | "tag" | "project" | "before" | "after" | "on" | "re"
+    )
+}
+
+// splits on whitespace, keeping "quoted phrases" (also after a key:) together
+fn split_filter_terms(expression: &str) -> Result<Vec<String>, String> {
+    let mut terms = vec![];
+    let mut term = String::new();
+    let mut in_quotes = false;
+
+    for c in expression.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                // a bare quoted phrase is a search term, drop its quotes
+                if !term.contains(':') {
+                    continue;
+                }
+                term.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("Unclosed quote in filter".to_string());
+    }
+
+    if !term.is_empty() {
+        terms.push(term);
+    }
+
+    Ok(terms)
+}
+
+// first and last day of the month, None for an invalid month or year
+pub fn month_range(year: i32, month: u32) -> Option<(NaiveDate, NaiveDate)> {
+    let first_date = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let last_date = first_date.checked_add_months(Months::new(1))? - Days::new(1);
+
+    Some((first_date, last_date))
+}
+
+pub fn year_range(year: i32) -> Option<(NaiveDate, NaiveDate)> {
+    Some((
+        NaiveDate::from_ymd_opt(year, 1, 1)?,
+        NaiveDate::from_ymd_opt(year, 12, 31)?,
+    ))
+}
+
+// first and last day of a quarter (1 to 4)
+pub fn quarter_range(year: i32, quarter: u32) -> Option<(NaiveDate, NaiveDate)> {
+    let (first_date, _) = month_range(year, quarter * 3 - 2)?;
+    let (_, last_date) = month_range(year, quarter * 3)?;
+
+    Some((first_date, last_date))
+}
+
+// --quarter value, Q2 for this year's second quarter or 2024-Q2 for an earlier year's
+pub fn parse_quarter_arg(value: &str) -> Result<(i32, u32), String> {
+    let value = value.trim().to_uppercase();
+
+    let (year, quarter) = match value.split_once('-') {
+        Some((year, quarter)) => (year.parse().ok(), quarter),
+        None => (Some(today().year()), value.as_str()),
+    };
+
+    match (year, quarter.strip_prefix('Q').map(str::parse::<u32>)) {
+        (Some(year), Some(Ok(quarter))) if (1..=4).contains(&quarter) => Ok((year, quarter)),
+        _ => Err(format!(
+            "Invalid quarter '{value}', expected Q1 to Q4 or eg. 2024-Q2"
+        )),
+    }
+}
+
+// #words of a description, lowercased and without the #
+pub fn extract_tags(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .map(|tag| {
+            tag.trim_end_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+// a tag given to add --tag, tag and untag, with or without its #, made to match what extract_tags finds
+pub fn parse_tag_arg(tag: &str) -> Result<String, String> {
+    let parsed = tag
+        .trim_start_matches('#')
+        .trim_end_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+
+    if parsed.is_empty() || parsed.contains(char::is_whitespace) {
+        return Err(format!(
+            "Invalid tag '{tag}', expected a single word like backend"
+        ));
+    }
+
+    Ok(parsed)
+}
+
+// the attached tags to go after a description, the ones it has as #tags already left out
+fn tags_suffix(task: &Task) -> String {
+    if display_options().redact.is_some() {
+        return String::new();
+    }
+
+    let described = extract_tags(&task.description);
+
+    task.tags
+        .iter()
+        .filter(|tag| !described.contains(tag))
+        .map(|tag| format!(" #{tag}"))
+        .collect()
+}
+
+/*
+ * window `list` shows without --month or --year, list_range in config:
+ *
+ * list_range = "month"    first of the month up to today, the default
+ * list_range = "7d"       the last 7 days, today included
+ * */
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ListRange {
+    #[default]
+    MonthToDate,
+    LastDays(u32),
+}
+
+impl FromStr for ListRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == "month" {
+            return Ok(ListRange::MonthToDate);
+        }
+
+        match value.strip_suffix('d').map(str::parse) {
+            Some(Ok(days)) if days > 0 => Ok(ListRange::LastDays(days)),
+            _ => Err(format!(
+                "Invalid list range '{value}', expected month or eg. 7d"
+            )),
+        }
+    }
+}
+
+impl ListRange {
+    // first and last day of the window ending today
+    pub fn dates(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            ListRange::MonthToDate => (today.with_day(1).unwrap_or(today), today),
+            ListRange::LastDays(days) => (today - Days::new(*days as u64 - 1), today),
+        }
+    }
+}
+
+// how far `onthisday` looks back (and `sync --since`), written as 7d, 2w, 1m or 1y
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lookback {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+}
+
+impl FromStr for Lookback {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let error = || format!("Invalid lookback '{value}', expected eg. 7d, 2w, 1m or 1y");
+
+        let unit = value.chars().last().ok_or_else(error)?;
+        let amount: u32 = value[..value.len() - unit.len_utf8()]
+            .parse()
+            .ok()
+            .filter(|amount| *amount > 0)
+            .ok_or_else(error)?;
+
+        match unit {
+            'd' => Ok(Lookback::Days(amount)),
+            'w' => Ok(Lookback::Weeks(amount)),
+            'm' => Ok(Lookback::Months(amount)),
+            'y' => Ok(Lookback::Years(amount)),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl Lookback {
+    // same day of the month back, month ends clamp (a month before 31st march is 29th february)
+    pub fn date_before(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Lookback::Days(days) => date.checked_sub_days(Days::new(*days as u64)),
+            Lookback::Weeks(weeks) => date.checked_sub_days(Days::new(*weeks as u64 * 7)),
+            Lookback::Months(months) => date.checked_sub_months(Months::new(*months)),
+            Lookback::Years(years) => date.checked_sub_months(Months::new(*years * 12)),
+        }
+    }
+
+    // eg. 1 month ago, 2 weeks ago
+    pub fn describe(&self) -> String {
+        let (amount, unit) = match self {
+            Lookback::Days(days) => (days, "day"),
+            Lookback::Weeks(weeks) => (weeks, "week"),
+            Lookback::Months(months) => (months, "month"),
+            Lookback::Years(years) => (years, "year"),
+        };
+
+        match amount {
+            1 => format!("1 {unit} ago"),
+            amount => format!("{amount} {unit}s ago"),
+        }
+    }
+}
+
+// stored utc timestamps (see now_timestamp) in local time, eg. 2024-06-14 17:05
+pub fn format_local_timestamp(timestamp: &str) -> String {
+    match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(datetime) => datetime.with_timezone(&Local).format("%F %R").to_string(),
+        Err(_) => timestamp.to_string(),
+    }
+}
+
 pub fn iso_format_timestamp(timestamp: &NaiveDate) -> String {
     // iso date format by chrono
     // date + time
@@ -66,69 +663,1580 @@ pub fn iso_format_timestamp(timestamp: &NaiveDate) -> String {
     format!("{}", timestamp.format("%F"))
 }
 
-pub fn render_tasks_table(grouped_tasks: &Vec<(&String, &Vec<Task>)>, include_id: bool) {
-    let mut tasks_table = Table::new();
+// utc with micro seconds, so the stored values sort and compare as plain strings
+pub fn now_timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true)
+}
 
-    tasks_table
-        .load_preset(comfy_table::presets::ASCII_FULL)
-        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
-        .set_width(100);
+static DAY_BOUNDARY: OnceLock<NaiveTime> = OnceLock::new();
 
-    let header_cell = |title: &str| {
-        Cell::new(title)
-            .fg(Color::Rgb {
-                r: 205,
-                g: 214,
-                b: 244,
-            })
-            .bg(Color::Rgb {
-                r: 49,
-                g: 50,
-                b: 68,
-            })
-            .add_attribute(Attribute::Bold)
+// day_boundary in config, eg. "03:00" for night owls, midnight by default
+pub fn set_day_boundary(boundary: NaiveTime) {
+    let _ = DAY_BOUNDARY.set(boundary);
+}
+
+fn day_boundary() -> NaiveTime {
+    DAY_BOUNDARY.get().copied().unwrap_or(NaiveTime::MIN)
+}
+
+// the date tasks go to now, still the day before until the day boundary
+pub fn today() -> NaiveDate {
+    (Local::now().naive_local() - (day_boundary() - NaiveTime::MIN)).date()
+}
+
+/*
+ * the day boundary starting the date (local midnight by default) as a stored utc timestamp,
+ * to compare against completed_at. a boundary skipped by a daylight saving change is an hour later
+ * */
+pub fn day_start_timestamp(date: &NaiveDate) -> String {
+    let start = date.and_time(day_boundary());
+
+    start
+        .and_local_timezone(Local)
+        .earliest()
+        .or_else(|| {
+            (start + TimeDelta::hours(1))
+                .and_local_timezone(Local)
+                .earliest()
+        })
+        .expect("Local day start exists")
+        .to_utc()
+        .to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+// task ids are ulids, which carry the time the task was added
+pub fn task_created_at(task: &Task) -> Option<DateTime<Utc>> {
+    let ulid = Ulid::from_string(&task.id).ok()?;
+
+    Some(DateTime::<Utc>::from(ulid.datetime()))
+}
+
+// rough human readable duration, 2d 4h / 3h 12m / 5m
+pub fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes().max(0);
+
+    match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, minutes) => format!("{minutes}m"),
+        (0, hours, minutes) => format!("{hours}h {minutes}m"),
+        (days, hours, _) => format!("{days}d {hours}h"),
+    }
+}
+
+// no. of days an unfinished task has been lying around since its date, None if not overdue
+pub fn overdue_days(task: &Task) -> Option<i64> {
+    if task.status == Status::Done {
+        return None;
+    }
+
+    let date = NaiveDate::parse_from_str(task.effective_date(), "%F").ok()?;
+    let days = (today() - date).num_days();
+
+    (days > 0).then_some(days)
+}
+
+// no. of days an unfinished task is past its due date, None if not due yet or without one
+pub fn days_past_due(task: &Task) -> Option<i64> {
+    if task.status == Status::Done {
+        return None;
+    }
+
+    let due = NaiveDate::parse_from_str(task.due.as_deref()?, "%F").ok()?;
+    let days = (today() - due).num_days();
+
+    (days > 0).then_some(days)
+}
+
+/*
+ * how tables get printed, decided once at startup from the command line and config
+ * and read wherever output is rendered, so it doesn't have to be passed down every handler
+ * */
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DisplayOptions {
+    // labeled lines instead of box drawn tables, for screen readers
+    pub accessible: bool,
+    // one line per task instead of a table, for narrow terminals
+    pub compact: bool,
+    // stable tab separated output for scripts, see print_porcelain_header
+    pub porcelain: bool,
+    // longer descriptions are cut with …, None shows them in full
+    pub description_limit: Option<usize>,
+    pub status_style: StatusStyle,
+    // task tables take the whole terminal instead of 100 columns
+    pub wide: bool,
+    // false keeps every row on one line, however wide the table gets
+    pub wrap: bool,
+    pub column_widths: ColumnWidths,
+    // descriptions get masked for screen sharing, None shows them
+    pub redact: Option<RedactStyle>,
+}
+
+/*
+ * max widths of the task table columns, from the [columns] section of the config:
+ *
+ * [columns]
+ * status = 30
+ * wrap = false
+ *
+ * description has no limit by default, so it gets whatever the other columns leave
+ * */
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnWidths {
+    pub date: Option<u16>,
+    pub description: Option<u16>,
+    pub status: Option<u16>,
+    pub project: Option<u16>,
+    pub due: Option<u16>,
+    pub priority: Option<u16>,
+    pub points: Option<u16>,
+    pub index: Option<u16>,
+}
+
+impl Default for ColumnWidths {
+    fn default() -> Self {
+        ColumnWidths {
+            date: Some(14),
+            description: None,
+            status: Some(30),
+            project: Some(16),
+            due: Some(24),
+            priority: Some(8),
+            points: Some(7),
+            // wide enough for an id with --include-id
+            index: Some(30),
+        }
+    }
+}
+
+// characters of a description shown in tables unless --full or description_max_length say otherwise
+const DEFAULT_DESCRIPTION_LIMIT: usize = 60;
+
+pub fn build_display_options(config: &Config, cmd_matches: &ArgMatches) -> DisplayOptions {
+    // 0 turns truncation off for good
+    let description_limit = match config.get_parsed("description_max_length") {
+        _ if cmd_matches.get_flag("full") => None,
+        Some(0) => None,
+        Some(limit) => Some(limit),
+        None => Some(DEFAULT_DESCRIPTION_LIMIT),
     };
 
-    let mut headers = vec![
-        header_cell(" Date "),
-        header_cell(" Description "),
-        header_cell(" Status "),
-    ];
+    // --redact without a style takes the one from config, or words
+    let redact = match cmd_matches.get_one::<RedactStyle>("redact") {
+        Some(style) => Some(*style),
+        None if cmd_matches.contains_id("redact") => {
+            Some(config.get_parsed("redact").unwrap_or_default())
+        }
+        None => config.get_parsed("redact"),
+    };
 
-    if include_id {
-        headers.push(header_cell(" ID "));
-    } else {
-        headers.push(header_cell(" Idx "));
+    let default_widths = ColumnWidths::default();
+    let column_width = |column: &str, default: Option<u16>| {
+        config.get_parsed(&format!("columns.{column}")).or(default)
+    };
+
+    DisplayOptions {
+        accessible: cmd_matches.get_flag("accessible") || config.get("accessible") == Some("true"),
+        compact: cmd_matches.get_flag("compact"),
+        porcelain: cmd_matches.get_flag("porcelain"),
+        description_limit,
+        status_style: config.get_parsed("status_style").unwrap_or_default(),
+        wide: cmd_matches.get_flag("wide"),
+        wrap: config.get_parsed("columns.wrap").unwrap_or(true),
+        column_widths: ColumnWidths {
+            date: column_width("date", default_widths.date),
+            description: column_width("description", default_widths.description),
+            status: column_width("status", default_widths.status),
+            project: column_width("project", default_widths.project),
+            due: column_width("due", default_widths.due),
+            priority: column_width("priority", default_widths.priority),
+            points: column_width("points", default_widths.points),
+            index: column_width("index", default_widths.index),
+        },
+        redact,
     }
+}
 
-    tasks_table.set_header(headers);
+// status_style in config
+#[derive(EnumString, Debug, Default, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum StatusStyle {
+    #[default]
+    Plain,
+    // [ ] [~] [!] [x] in front of the status, tells states apart without relying on color
+    Symbols, // easier to scan once pasted into chat
+    Emoji,
+}
 
-    let mut last_used_date = "";
-    for (date, tasks) in grouped_tasks.iter() {
-        for (index, task) in tasks.iter().enumerate() {
-            let display_date = if date.as_str() == last_used_date {
-                ""
-            } else {
-                date
-            };
+// --redact and redact in config
+#[derive(EnumString, Debug, Default, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum RedactStyle {
+    // the first few words followed by …
+    #[default]
+    Words,
+    // nothing but a bar, not even the length shows
+    Full,
+}
 
-            let mut cells = vec![
-                Cell::new(display_date),
-                Cell::new(&task.description).fg(Color::Red),
-                Cell::new(&task.status),
-            ];
+const REDACT_WORDS_SHOWN: usize = 3;
 
-            if include_id {
-                cells.push(Cell::new(&task.id));
-            } else {
-                cells.push(Cell::new(index + 1));
+// the description as it may be shown, masked when redacting
+pub fn redact_description(description: &str) -> String {
+    match display_options().redact {
+        None => description.to_string(),
+        Some(RedactStyle::Full) => "████████".to_string(),
+        Some(RedactStyle::Words) => {
+            let words: Vec<&str> = description.split_whitespace().collect();
+
+            match words.len() > REDACT_WORDS_SHOWN {
+                true => format!("{} …", words[..REDACT_WORDS_SHOWN].join(" ")),
+                false => words.join(" "),
             }
+        }
+    }
+}
 
-            tasks_table.add_row(cells);
+// redacted first, so the cut is made on what is shown
+pub fn truncate_description(description: &str) -> String {
+    let description = redact_description(description);
 
-            last_used_date = date;
+    match display_options().description_limit {
+        Some(limit) if description.chars().count() > limit => {
+            let truncated: String = description.chars().take(limit.saturating_sub(1)).collect();
+            format!("{}…", truncated.trim_end())
         }
+        _ => description,
+    }
+}
+
+pub fn format_status(status: Status) -> String {
+    match status_marker(status) {
+        Some(marker) => format!("{marker} {status}"),
+        None => status.to_string(),
     }
+}
+
+// symbol (or emoji) standing for the status in the configured style, None for plain
+fn status_marker(status: Status) -> Option<&'static str> {
+    let marker = match display_options().status_style {
+        StatusStyle::Plain => return None,
+        StatusStyle::Symbols => status_symbol(status),
+        StatusStyle::Emoji => match status {
+            Status::Todo => "⬜",
+            Status::InProgress => "🚧",
+            Status::Blocked => "⛔",
+            Status::Done => "✅",
+        },
+    };
 
-    println!("{tasks_table}");
+    Some(marker)
+}
+
+fn status_symbol(status: Status) -> &'static str {
+    match status {
+        Status::Todo => "[ ]",
+        Status::InProgress => "[~]",
+        Status::Blocked => "[!]",
+        Status::Done => "[x]",
+    }
+}
+
+/*
+ * rows of the task table styled by what their description says, from the [highlight] section
+ * of the config, "<regex>" = "<style>". the first rule matching in key order wins
+ *
+ * [highlight]
+ * "(?i)prod|incident" = "bold red"
+ * "#review" = "black on yellow"
+ *
+ * styles are colors (black, red, green, yellow, blue, magenta, cyan, white, grey), on <color>
+ * for the background, and bold, dim, italic, underline
+ * */
+#[derive(Debug)]
+pub struct HighlightRule {
+    regex: Regex,
+    style: CellStyle,
+}
+
+#[derive(Debug, Default)]
+struct CellStyle {
+    foreground: Option<Color>,
+    background: Option<Color>,
+    attributes: Vec<Attribute>,
+}
+
+impl FromStr for CellStyle {
+    type Err = String;
+
+    fn from_str(style: &str) -> Result<Self, Self::Err> {
+        let mut cell_style = CellStyle::default();
+
+        let mut words = style.split_whitespace();
+        while let Some(word) = words.next() {
+            match word.to_lowercase().as_str() {
+                "bold" => cell_style.attributes.push(Attribute::Bold),
+                "dim" => cell_style.attributes.push(Attribute::Dim),
+                "italic" => cell_style.attributes.push(Attribute::Italic),
+                "underline" => cell_style.attributes.push(Attribute::Underlined),
+                "on" => {
+                    let color = words.next().unwrap_or_default();
+                    cell_style.background = Some(parse_color(color)?);
+                }
+                color => cell_style.foreground = Some(parse_color(color)?),
+            }
+        }
+
+        Ok(cell_style)
+    }
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    match color.to_lowercase().as_str() {
+        "black" => Ok(Color::Black),
+        "red" => Ok(Color::Red),
+        "green" => Ok(Color::Green),
+        "yellow" => Ok(Color::Yellow),
+        "blue" => Ok(Color::Blue),
+        "magenta" => Ok(Color::Magenta),
+        "cyan" => Ok(Color::Cyan),
+        "white" => Ok(Color::White),
+        "grey" | "gray" => Ok(Color::Grey),
+        _ => Err(format!("Unknown color or style '{color}'")),
+    }
+}
+
+impl CellStyle {
+    fn apply(&self, mut cell: Cell) -> Cell {
+        if let Some(color) = self.foreground {
+            cell = cell.fg(color);
+        }
+        if let Some(color) = self.background {
+            cell = cell.bg(color);
+        }
+        cell.add_attributes(self.attributes.clone())
+    }
+}
+
+// invalid rules are reported and left out
+pub fn highlight_rules(config: &Config) -> Vec<HighlightRule> {
+    config
+        .section("highlight")
+        .into_iter()
+        .filter_map(|(pattern, style)| {
+            let rule = Regex::new(pattern)
+                .map_err(|error| error.to_string())
+                .and_then(|regex| {
+                    Ok(HighlightRule {
+                        regex,
+                        style: style.parse()?,
+                    })
+                });
+
+            match rule {
+                Ok(rule) => Some(rule),
+                Err(error) => {
+                    println!("Ignoring highlight rule \"{pattern}\" in config = {error}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+static HIGHLIGHT_RULES: OnceLock<Vec<HighlightRule>> = OnceLock::new();
+
+pub fn set_highlight_rules(rules: Vec<HighlightRule>) {
+    let _ = HIGHLIGHT_RULES.set(rules);
+}
+
+fn highlight_rule(description: &str) -> Option<&'static HighlightRule> {
+    HIGHLIGHT_RULES
+        .get()?
+        .iter()
+        .find(|rule| rule.regex.is_match(description))
+}
+
+/*
+ * the description cell of tasks with a tag or project, from the [colors] section of the config,
+ * "<tag or project>" = "<style>" with the same styles as [highlight]. case doesn't matter and
+ * the first rule matching in key order wins, a [highlight] rule matching the row goes over it
+ *
+ * [colors]
+ * incident = "bold red"
+ * acme = "cyan"
+ * */
+#[derive(Debug)]
+pub struct ColorRule {
+    name: String,
+    style: CellStyle,
+}
+
+// invalid rules are reported and left out
+pub fn color_rules(config: &Config) -> Vec<ColorRule> {
+    config
+        .section("colors")
+        .into_iter()
+        .filter_map(|(name, style)| match style.parse() {
+            Ok(style) => Some(ColorRule {
+                name: name.trim_start_matches('#').to_lowercase(),
+                style,
+            }),
+            Err(error) => {
+                println!("Ignoring color rule \"{name}\" in config = {error}");
+                None
+            }
+        })
+        .collect()
+}
+
+static COLOR_RULES: OnceLock<Vec<ColorRule>> = OnceLock::new();
+
+pub fn set_color_rules(rules: Vec<ColorRule>) {
+    let _ = COLOR_RULES.set(rules);
+}
+
+fn color_rule(task: &Task) -> Option<&'static ColorRule> {
+    let tags = task.all_tags();
+
+    COLOR_RULES.get()?.iter().find(|rule| {
+        tags.contains(&rule.name)
+            || task
+                .project
+                .as_ref()
+                .is_some_and(|project| project.eq_ignore_ascii_case(&rule.name))
+    })
+}
+
+static DISPLAY_OPTIONS: OnceLock<DisplayOptions> = OnceLock::new();
+
+pub fn set_display_options(options: DisplayOptions) {
+    let _ = DISPLAY_OPTIONS.set(options);
+}
+
+pub fn display_options() -> DisplayOptions {
+    DISPLAY_OPTIONS.get().copied().unwrap_or_default()
+}
+
+/*
+ * --porcelain output contract, bump PORCELAIN_VERSION on any incompatible change:
+ *
+ * # daily-dose porcelain v2          printed once, before anything else
+ * task<TAB>id<TAB>date<TAB>status<TAB>points<TAB>snoozed_until<TAB>block_reason<TAB>priority
+ *     <TAB>tags<TAB>project<TAB>due<TAB>parent_id<TAB>description
+ * columns<TAB>name...                other tables, names of the row fields that follow
+ * row<TAB>value...
+ * checksum<TAB>tasks<TAB>hash        opening an export, after the header, see export_checksum
+ *
+ * task fields are raw values, dates as YYYY-MM-DD and statuses in snake_case,
+ * missing values are empty fields. tabs, newlines and backslashes inside values
+ * are escaped as \t, \n and \\. tags are the attached ones, separated by spaces
+ *
+ * v1 task lines had none of the fields after block_reason, import still reads them
+ * */
+const PORCELAIN_VERSION: u32 = 2;
+
+// fields of a task line, after the kind
+const TASK_FIELDS: usize = 12;
+const V1_TASK_FIELDS: usize = 7;
+
+static PORCELAIN_HEADER: Once = Once::new();
+
+pub fn porcelain_header() -> String {
+    format!("# daily-dose porcelain v{PORCELAIN_VERSION}")
+}
+
+fn print_porcelain_header() {
+    PORCELAIN_HEADER.call_once(|| println!("{}", porcelain_header()));
+}
+
+// the description is passed in, so it can be redacted when shown
+pub fn porcelain_task_line(task: &Task, description: &str) -> String {
+    let fields = [
+        task.id.clone(),
+        task.date.clone(),
+        task.status.to_string(),
+        task.points
+            .map(|points| points.to_string())
+            .unwrap_or_default(),
+        task.deferred_until.clone().unwrap_or_default(),
+        task.block_reason.clone().unwrap_or_default(),
+        task.priority
+            .map(|priority| priority.to_string())
+            .unwrap_or_default(),
+        task.tags.join(" "),
+        task.project.clone().unwrap_or_default(),
+        task.due.clone().unwrap_or_default(),
+        task.parent_id.clone().unwrap_or_default(),
+        description.to_string(),
+    ];
+
+    porcelain_line("task", &fields)
+}
+
+/*
+ * the checksum line of an export: no. of task lines and a 64 bit FNV-1a hash of
+ * them, each with its trailing newline. enough to tell a cut off or edited file, it
+ * is no protection against someone changing the file on purpose
+ * */
+pub fn export_checksum(task_lines: &[&str]) -> String {
+    let hash = fnv1a(
+        task_lines
+            .iter()
+            .flat_map(|line| line.bytes().chain([b'\n'])),
+    );
+
+    porcelain_line(
+        "checksum",
+        &[task_lines.len().to_string(), format!("{hash:016x}")],
+    )
+}
+
+pub fn fnv1a(bytes: impl Iterator<Item = u8>) -> u64 {
+    bytes.fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+const PLACEHOLDER_WORDS: [&str; 16] = [
+    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliet",
+    "kilo", "lima", "mike", "november", "oscar", "papa",
+];
+
+/*
+ * text with every word swapped for a placeholder, for export --anonymize
+ * the same word always gets the same placeholder and #tags stay tags, so searches,
+ * tag counts and column widths behave about the same as with the real text
+ * */
+pub fn anonymize_text(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            let (prefix, word) = match word.strip_prefix('#') {
+                Some(tag) => ("#", tag),
+                None => ("", word),
+            };
+
+            let hash = fnv1a(word.to_lowercase().bytes());
+            let placeholder = PLACEHOLDER_WORDS[(hash % PLACEHOLDER_WORDS.len() as u64) as usize];
+
+            format!("{prefix}{placeholder}{}", hash % 100)
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/*
+ * checks the checksum line of an export against its task lines, returns the no. of tasks
+ * None for porcelain output without a checksum line, which can't be checked
+ * */
+pub fn verify_export_checksum(lines: &[&str]) -> Result<Option<usize>, String> {
+    let Some(checksum) = lines.iter().find(|line| line.starts_with("checksum\t")) else {
+        return Ok(None);
+    };
+
+    let task_lines: Vec<&str> = lines
+        .iter()
+        .filter(|line| line.starts_with("task\t"))
+        .copied()
+        .collect();
+
+    match export_checksum(&task_lines) == *checksum {
+        true => Ok(Some(task_lines.len())),
+        false => Err(format!(
+            "Checksum mismatch, the file has {} task lines and isn't the export it claims to be ({})",
+            task_lines.len(),
+            checksum.replace('\t', " ")
+        )),
+    }
+}
+
+fn porcelain_line(kind: &str, fields: &[String]) -> String {
+    let mut line = kind.to_string();
+
+    for field in fields {
+        line.push('\t');
+        line.push_str(
+            &field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n"),
+        );
+    }
+
+    line
+}
+
+// splits a porcelain line back into its kind and unescaped fields
+pub fn parse_porcelain_line(line: &str) -> (&str, Vec<String>) {
+    let mut parts = line.split('\t');
+    let kind = parts.next().unwrap_or_default();
+
+    let fields = parts
+        .map(|field| {
+            let mut value = String::new();
+            let mut chars = field.chars();
+
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    value.push(c);
+                    continue;
+                }
+
+                match chars.next() {
+                    Some('t') => value.push('\t'),
+                    Some('n') => value.push('\n'),
+                    Some(escaped) => value.push(escaped),
+                    None => value.push('\\'),
+                }
+            }
+
+            value
+        })
+        .collect();
+
+    (kind, fields)
+}
+
+// the fields of a porcelain task line as a task, ready to be stored again
+pub fn parse_porcelain_task(fields: &[String]) -> Result<Task, String> {
+    // a v1 line is a v2 one without the fields v2 added before the description
+    let v1_fields;
+    let fields = match fields {
+        [v1 @ .., description] if fields.len() == V1_TASK_FIELDS => {
+            v1_fields = [
+                v1.to_vec(),
+                vec![String::new(); TASK_FIELDS - V1_TASK_FIELDS],
+                vec![description.clone()],
+            ]
+            .concat();
+            &v1_fields
+        }
+        fields => fields,
+    };
+
+    let fields: &[String; TASK_FIELDS] = fields.try_into().map_err(|_| {
+        format!(
+            "Expected {V1_TASK_FIELDS} or {TASK_FIELDS} task fields, found {}",
+            fields.len()
+        )
+    })?;
+
+    // v1's fields, then the ones v2 added before the description
+    let [id, date, status, points, snoozed_until, block_reason, added @ .., description] = fields;
+    let [priority, tags, project, due, parent_id] = added;
+
+    let optional = |value: &String| (!value.is_empty()).then(|| value.clone());
+
+    for date in [
+        Some(date),
+        optional(snoozed_until).as_ref(),
+        optional(due).as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        NaiveDate::parse_from_str(date, "%F")
+            .map_err(|_| format!("Invalid date '{date}', expected YYYY-MM-DD"))?;
+    }
+
+    Ok(Task {
+        id: id.clone(),
+        description: description.clone(),
+        status: Status::from_str(status).map_err(|_| format!("Unknown status '{status}'"))?,
+        date: date.clone(),
+        updated_at: None,
+        deferred_until: optional(snoozed_until),
+        points: match points.as_str() {
+            "" => None,
+            points => Some(
+                points
+                    .parse()
+                    .map_err(|_| format!("Invalid points '{points}'"))?,
+            ),
+        },
+        block_reason: optional(block_reason),
+        position: 0,
+        short_id: None,
+        priority: match priority.as_str() {
+            "" => None,
+            priority => Some(
+                Priority::from_str(priority)
+                    .map_err(|_| format!("Unknown priority '{priority}'"))?,
+            ),
+        },
+        project: optional(project),
+        due: optional(due),
+        parent_id: optional(parent_id),
+        tags: tags.split_whitespace().map(str::to_string).collect(),
+    })
+}
+
+// Importing [############------------]  50%
+const PROGRESS_WIDTH: usize = 24;
+
+/*
+ * a progress bar on stderr, redrawn in place until finish_progress clears it. only on a
+ * terminal and never for porcelain or accessible output, where redrawing is just noise
+ * */
+pub fn print_progress(label: &str, done: usize, total: usize) {
+    if !show_progress() {
+        return;
+    }
+
+    let ratio = match total {
+        0 => 1.0,
+        total => done.min(total) as f64 / total as f64,
+    };
+    let filled = (ratio * PROGRESS_WIDTH as f64).round() as usize;
+
+    eprint!(
+        "\r{label} [{}{}] {:>3}%",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_WIDTH - filled),
+        (ratio * 100.0).floor() as u32
+    );
+    let _ = io::stderr().flush();
+}
+
+pub fn finish_progress() {
+    if show_progress() {
+        eprint!("\r\x1b[2K");
+        let _ = io::stderr().flush();
+    }
+}
+
+fn show_progress() -> bool {
+    let options = display_options();
+    io::stderr().is_terminal() && !options.porcelain && !options.accessible
+}
+
+/*
+ * in accessible mode every row becomes one line of "Header: value" pairs,
+ * empty cells are left out so nothing is read out without a value
+ * */
+pub fn print_table(table: &Table) {
+    if display_options().porcelain {
+        print_porcelain_header();
+
+        if let Some(header) = table.header() {
+            let columns: Vec<String> = header
+                .cell_iter()
+                .map(|cell| cell.content().trim().to_string())
+                .collect();
+            println!("{}", porcelain_line("columns", &columns));
+        }
+
+        for row in table.row_iter() {
+            let values: Vec<String> = row.cell_iter().map(|cell| cell.content()).collect();
+            println!("{}", porcelain_line("row", &values));
+        }
+
+        return;
+    }
+
+    if !display_options().accessible {
+        println!("{table}");
+        return;
+    }
+
+    let headers: Vec<String> = table
+        .header()
+        .map(|header| {
+            header
+                .cell_iter()
+                .map(|cell| cell.content().trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for row in table.row_iter() {
+        let fields: Vec<String> = row
+            .cell_iter()
+            .enumerate()
+            .map(|(index, cell)| {
+                let content = cell
+                    .content()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (headers.get(index), content)
+            })
+            .filter(|(_, content)| !content.is_empty())
+            .map(|(header, content)| match header {
+                Some(header) => format!("{header}: {content}"),
+                None => content,
+            })
+            .collect();
+
+        println!("{}", fields.join(", "));
+    }
+}
+
+pub fn render_tasks_table(grouped_tasks: &Vec<(&String, &Vec<Task>)>, include_id: bool) {
+    render_tasks_table_with_notes(grouped_tasks, include_id, &TaskNotes::new());
+}
+
+// notes shown under their task by show --notes, (created_at, note) by task id
+pub type TaskNotes = HashMap<String, Vec<(String, String)>>;
+
+pub fn render_tasks_table_with_notes(
+    grouped_tasks: &Vec<(&String, &Vec<Task>)>,
+    include_id: bool,
+    notes: &TaskNotes,
+) {
+    render_tasks_chunk(grouped_tasks, include_id, notes);
+
+    if grouped_tasks.len() > 1 {
+        let mut completion = Completion::default();
+        for task in grouped_tasks.iter().flat_map(|(_, tasks)| tasks.iter()) {
+            completion.add(task);
+        }
+
+        print_overall_completion(&completion);
+    }
+}
+
+// one table of dates, a long listing printed piece by piece is several of these
+pub fn render_tasks_chunk(
+    grouped_tasks: &Vec<(&String, &Vec<Task>)>,
+    include_id: bool,
+    notes: &TaskNotes,
+) {
+    if display_options().porcelain {
+        print_porcelain_header();
+
+        for task in grouped_tasks.iter().flat_map(|(_, tasks)| tasks.iter()) {
+            println!(
+                "{}",
+                porcelain_task_line(task, &redact_description(&task.description))
+            );
+        }
+
+        return;
+    }
+
+    if display_options().compact {
+        render_compact_tasks(grouped_tasks, include_id, notes);
+        return;
+    }
+
+    let options = display_options();
+
+    let mut tasks_table = Table::new();
+
+    tasks_table
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_content_arrangement(match (options.wrap, options.wide) {
+            (false, _) => ContentArrangement::Disabled,
+            (true, true) => ContentArrangement::Dynamic,
+            (true, false) => ContentArrangement::DynamicFullWidth,
+        });
+
+    if !options.wide {
+        tasks_table.set_width(100);
+    }
+
+    let header_cell = |title: &str| {
+        Cell::new(title)
+            .fg(Color::Rgb {
+                r: 205,
+                g: 214,
+                b: 244,
+            })
+            .bg(Color::Rgb {
+                r: 49,
+                g: 50,
+                b: 68,
+            })
+            .add_attribute(Attribute::Bold)
+    };
+
+    let mut headers = vec![
+        header_cell(" Date "),
+        header_cell(" Description "),
+        header_cell(" Status "),
+    ];
+
+    // project, due, priority and points columns only show up once some task has one
+    let has_project = grouped_tasks
+        .iter()
+        .any(|(_, tasks)| tasks.iter().any(|task| task.project.is_some()));
+    let has_due = grouped_tasks
+        .iter()
+        .any(|(_, tasks)| tasks.iter().any(|task| task.due.is_some()));
+    let has_priority = grouped_tasks
+        .iter()
+        .any(|(_, tasks)| tasks.iter().any(|task| task.priority.is_some()));
+    let has_points = grouped_tasks
+        .iter()
+        .any(|(_, tasks)| tasks.iter().any(|task| task.points.is_some()));
+
+    if has_project {
+        headers.push(header_cell(" Project "));
+    }
+
+    if has_due {
+        headers.push(header_cell(" Due "));
+    }
+
+    if has_priority {
+        headers.push(header_cell(" Prio "));
+    }
+
+    if has_points {
+        headers.push(header_cell(" Pts "));
+    }
+
+    if include_id {
+        headers.push(header_cell(" ID "));
+    } else {
+        headers.push(header_cell(" Idx "));
+    }
+
+    let column_count = headers.len();
+    tasks_table.set_header(headers);
+
+    let widths = options.column_widths;
+    let mut column_widths = vec![widths.date, widths.description, widths.status];
+    if has_project {
+        column_widths.push(widths.project);
+    }
+    if has_due {
+        column_widths.push(widths.due);
+    }
+    if has_priority {
+        column_widths.push(widths.priority);
+    }
+    if has_points {
+        column_widths.push(widths.points);
+    }
+    column_widths.push(widths.index);
+
+    for (index, width) in column_widths.into_iter().enumerate() {
+        if let Some(width) = width
+            && let Some(column) = tasks_table.column_mut(index)
+        {
+            column.set_constraint(ColumnConstraint::UpperBoundary(Width::Fixed(width)));
+        }
+    }
+
+    let mut last_used_date = "";
+    for (date, tasks) in grouped_tasks.iter() {
+        for (task, branches) in nest_subtasks(tasks) {
+            // repeated dates are blanked for the eye only, a screen reader needs them on every line
+            let display_date = if date.as_str() == last_used_date && !display_options().accessible {
+                ""
+            } else {
+                date
+            };
+
+            let mut cells = vec![
+                Cell::new(display_date),
+                Cell::new(format!(
+                    "{branches}{}{}{}",
+                    truncate_description(&task.description),
+                    tags_suffix(task),
+                    subtasks_done(task, tasks)
+                ))
+                .fg(Color::Red),
+                match (overdue_days(task), &task.deferred_until) {
+                    (Some(days), _) => {
+                        Cell::new(format!("{} ({days}d old)", format_status(task.status)))
+                            .fg(Color::Red)
+                    }
+                    (None, Some(_)) => {
+                        Cell::new(format!("{} (snoozed)", format_status(task.status)))
+                    }
+                    (None, None) => match &task.block_reason {
+                        Some(reason) => {
+                            Cell::new(format!("{}: {reason}", format_status(task.status)))
+                        }
+                        None => Cell::new(format_status(task.status)),
+                    },
+                },
+            ];
+
+            if has_project {
+                cells.push(Cell::new(task.project.as_deref().unwrap_or_default()));
+            }
+
+            if has_due {
+                let due = task.due.as_deref().unwrap_or_default();
+                cells.push(match days_past_due(task) {
+                    Some(days) => Cell::new(format!("{due} ({days}d late)"))
+                        .fg(Color::Red)
+                        .add_attribute(Attribute::Bold),
+                    None => Cell::new(due),
+                });
+            }
+
+            if has_priority {
+                cells.push(match task.priority {
+                    Some(Priority::High) => Cell::new("high").add_attribute(Attribute::Bold),
+                    Some(priority) => Cell::new(priority),
+                    None => Cell::new(""),
+                });
+            }
+
+            if has_points {
+                cells.push(Cell::new(
+                    task.points
+                        .map(|points| points.to_string())
+                        .unwrap_or_default(),
+                ));
+            }
+
+            if include_id {
+                cells.push(Cell::new(task.short_or_id()));
+            } else {
+                cells.push(Cell::new(task.position));
+            }
+
+            if let Some(rule) = color_rule(task) {
+                cells[1] = rule.style.apply(cells[1].clone());
+            }
+
+            if let Some(rule) = highlight_rule(&task.description) {
+                cells = cells
+                    .into_iter()
+                    .map(|cell| rule.style.apply(cell))
+                    .collect();
+            }
+
+            tasks_table.add_row(cells);
+
+            // a row of their own under the task, in the description column
+            if let Some(task_notes) = notes.get(&task.id).filter(|notes| !notes.is_empty()) {
+                let mut notes_row = vec![
+                    Cell::new(""),
+                    Cell::new(
+                        task_notes
+                            .iter()
+                            .map(|(created_at, note)| note_line(created_at, note))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    )
+                    .add_attribute(Attribute::Italic),
+                ];
+                notes_row.resize_with(column_count, || Cell::new(""));
+                tasks_table.add_row(notes_row);
+            }
+
+            last_used_date = date;
+        }
+
+        // an empty group is a day shown on purpose (list --fill-gaps), it stands in for the tasks
+        let mut summary_row = match tasks.is_empty() {
+            true => vec![
+                Cell::new(date),
+                Cell::new("no tasks").add_attribute(Attribute::Italic),
+                Cell::new(""),
+            ],
+            false => vec![
+                Cell::new(""),
+                Cell::new(completion_summary(tasks.iter())).add_attribute(Attribute::Italic),
+                Cell::new(""),
+            ],
+        };
+        if has_project {
+            summary_row.push(Cell::new(""));
+        }
+        if has_due {
+            summary_row.push(Cell::new(""));
+        }
+        if has_priority {
+            summary_row.push(Cell::new(""));
+        }
+        if has_points {
+            summary_row.push(Cell::new(""));
+        }
+        summary_row.push(Cell::new(""));
+        tasks_table.add_row(summary_row);
+
+        if tasks.iter().any(|task| task.points.is_some()) {
+            let total_points: u32 = tasks.iter().filter_map(|task| task.points).sum();
+            let done_points: u32 = tasks
+                .iter()
+                .filter(|task| task.status == Status::Done)
+                .filter_map(|task| task.points)
+                .sum();
+
+            let mut points_row = vec![
+                Cell::new(""),
+                Cell::new("Points done / total").add_attribute(Attribute::Italic),
+                Cell::new(""),
+            ];
+            if has_project {
+                points_row.push(Cell::new(""));
+            }
+            if has_due {
+                points_row.push(Cell::new(""));
+            }
+            if has_priority {
+                points_row.push(Cell::new(""));
+            }
+            points_row.push(Cell::new(format!("{done_points}/{total_points}")));
+            points_row.push(Cell::new(""));
+            tasks_table.add_row(points_row);
+        }
+    }
+
+    print_table(&tasks_table);
+}
+
+/*
+ * the tasks of a date with each one's subtasks right under it, and the tree branches to draw
+ * in front of them. subtasks whose parent isn't among them stay where they are, as if they had none
+ *
+ * release
+ * ├ write notes
+ * │ └ proofread
+ * └ tag
+ * */
+fn nest_subtasks(tasks: &[Task]) -> Vec<(&Task, String)> {
+    let has_parent_here = |task: &Task| {
+        task.parent_id
+            .as_ref()
+            .is_some_and(|parent_id| tasks.iter().any(|other| other.id == *parent_id))
+    };
+
+    let mut nested = vec![];
+    for task in tasks.iter().filter(|task| !has_parent_here(task)) {
+        push_with_subtasks(task, tasks, String::new(), "", &mut nested);
+    }
+
+    nested
+}
+
+// indent is what goes in front of the subtasks' own branches, a line down from each open level
+fn push_with_subtasks<'a>(
+    task: &'a Task,
+    tasks: &'a [Task],
+    branches: String,
+    indent: &str,
+    nested: &mut Vec<(&'a Task, String)>,
+) {
+    nested.push((task, branches));
+
+    let subtasks: Vec<&Task> = tasks
+        .iter()
+        .filter(|subtask| subtask.parent_id.as_ref() == Some(&task.id))
+        .collect();
+
+    for (index, subtask) in subtasks.iter().enumerate() {
+        let (branch, below) = match index + 1 == subtasks.len() {
+            true => ("└ ", "  "),
+            false => ("├ ", "│ "),
+        };
+
+        push_with_subtasks(
+            subtask,
+            tasks,
+            format!("{indent}{branch}"),
+            &format!("{indent}{below}"),
+            nested,
+        );
+    }
+}
+
+// " (2/3)" of a parent with 3 subtasks among the tasks, 2 of them done
+fn subtasks_done(task: &Task, tasks: &[Task]) -> String {
+    let subtasks: Vec<&Task> = tasks
+        .iter()
+        .filter(|subtask| subtask.parent_id.as_ref() == Some(&task.id))
+        .collect();
+
+    match subtasks.is_empty() {
+        true => String::new(),
+        false => format!(
+            " ({}/{})",
+            subtasks
+                .iter()
+                .filter(|subtask| subtask.status == Status::Done)
+                .count(),
+            subtasks.len()
+        ),
+    }
+}
+
+fn note_line(created_at: &str, note: &str) -> String {
+    format!(
+        "{}  {}",
+        format_local_timestamp(created_at),
+        plain_markdown(&redact_description(note))
+    )
+}
+
+// below the tables only, porcelain and compact output have none
+pub fn print_overall_completion(completion: &Completion) {
+    if display_options().porcelain || display_options().compact {
+        return;
+    }
+
+    println!("Overall: {completion}");
+}
+
+// Done 4/7 · 1 blocked
+#[derive(Default)]
+pub struct Completion {
+    done: u32,
+    blocked: u32,
+    total: u32,
+}
+
+impl Completion {
+    pub fn add(&mut self, task: &Task) {
+        self.total += 1;
+        match task.status {
+            Status::Done => self.done += 1,
+            Status::Blocked => self.blocked += 1,
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for Completion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Completion {
+            done,
+            blocked,
+            total,
+        } = self;
+
+        match blocked {
+            0 => write!(f, "Done {done}/{total}"),
+            blocked => write!(f, "Done {done}/{total} · {blocked} blocked"),
+        }
+    }
+}
+
+fn completion_summary<'a>(tasks: impl Iterator<Item = &'a Task>) -> String {
+    let mut completion = Completion::default();
+    for task in tasks {
+        completion.add(task);
+    }
+
+    completion.to_string()
+}
+
+/*
+ * 2024-06-12  [x] fix login bug  (3)
+ * the marker falls back to symbols as the bare status name would be too noisy,
+ * the trailing field is the index (or id) just like the table's last column, followed by
+ * the due date of tasks that have one. subtasks hang off their parent, see nest_subtasks
+ * */
+fn render_compact_tasks(
+    grouped_tasks: &Vec<(&String, &Vec<Task>)>,
+    include_id: bool,
+    notes: &TaskNotes,
+) {
+    for (date, tasks) in grouped_tasks.iter() {
+        for (task, branches) in nest_subtasks(tasks) {
+            let marker = status_marker(task.status).unwrap_or_else(|| status_symbol(task.status));
+
+            let mut reference = if include_id {
+                task.short_or_id().to_string()
+            } else {
+                task.position.to_string()
+            };
+            if let Some(due) = &task.due {
+                reference.push_str(&format!(", due {due}"));
+            }
+
+            let priority = task
+                .priority
+                .map(|priority| format!("[{priority}] "))
+                .unwrap_or_default();
+
+            println!(
+                "{date}  {branches}{marker} {priority}{}{}{}  ({reference})",
+                render_markdown(
+                    &redact_description(&task.description),
+                    display_options().description_limit
+                ),
+                tags_suffix(task),
+                subtasks_done(task, tasks)
+            );
+
+            for (created_at, note) in notes.get(&task.id).into_iter().flatten() {
+                println!("{date}      {}", note_line(created_at, note));
+            }
+        }
+
+        match tasks.is_empty() {
+            true => println!("{date}  no tasks"),
+            false => println!("{date}  {}", completion_summary(tasks.iter())),
+        }
+    }
+}
+
+pub fn render_focus_banner(task: &Task) {
+    let mut banner = Table::new();
+
+    banner
+        .load_preset(comfy_table::presets::ASCII_FULL)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_width(100)
+        .add_row(vec![Cell::new(format!(
+            "\nFOCUS\n\n{}\n",
+            redact_description(&task.description).to_uppercase()
+        ))
+        .set_alignment(CellAlignment::Center)
+        .fg(Color::Rgb {
+            r: 205,
+            g: 214,
+            b: 244,
+        })
+        .add_attribute(Attribute::Bold)]);
+
+    print_table(&banner);
+}
+
+/*
+ * +--------------------------------------------------+
+ * | DONE   fix login redirect                        |
+ * |        2024-06-03 · added 09:12 · updated 11:40  |
+ * |        #auth #backend · 3 pts                    |
+ * |        01J0B3...                                 |
+ * +--------------------------------------------------+
+ * the status badge sits left of the task, sections (links, history, ...) follow with their
+ * name in its place. used by show --id and add/mark --card
+ * */
+pub fn render_task_card(task: &Task, sections: &[(&str, Vec<String>)]) {
+    let mut card = Table::new();
+
+    card.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_width(80);
+
+    card.add_row(vec![
+        status_badge(task.status),
+        Cell::new(plain_markdown(&redact_description(&task.description)))
+            .add_attribute(Attribute::Bold),
+    ]);
+
+    if let Some(reason) = &task.block_reason {
+        card.add_row(vec![
+            Cell::new(""),
+            Cell::new(format!("blocked on {reason}")).fg(Color::Red),
+        ]);
+    }
+
+    let mut dates = vec![task.date.clone()];
+    if let Some(deferred_until) = &task.deferred_until {
+        dates.push(format!("snoozed until {deferred_until}"));
+    }
+    if let Some(created_at) = task_created_at(task) {
+        dates.push(format!(
+            "added {}",
+            format_local_timestamp(&created_at.to_rfc3339_opts(SecondsFormat::Micros, true))
+        ));
+    }
+    if let Some(updated_at) = &task.updated_at {
+        dates.push(format!("updated {}", format_local_timestamp(updated_at)));
+    }
+    card.add_row(vec![Cell::new(""), Cell::new(dates.join(" · "))]);
+
+    // #tags of the description and attached ones, redacting leaves them out
+    let tags: Vec<String> = match display_options().redact {
+        Some(_) => vec![],
+        None => task
+            .all_tags()
+            .iter()
+            .map(|tag| format!("#{tag}"))
+            .collect(),
+    };
+
+    let mut facts = vec![];
+    if !tags.is_empty() {
+        facts.push(tags.join(" "));
+    }
+    if let Some(project) = &task.project {
+        facts.push(format!("project {project}"));
+    }
+    if let Some(due) = &task.due {
+        facts.push(match days_past_due(task) {
+            Some(days) => format!("due {due}, {days}d late"),
+            None => format!("due {due}"),
+        });
+    }
+    if let Some(priority) = task.priority {
+        facts.push(format!("{priority} priority"));
+    }
+    if let Some(points) = task.points {
+        facts.push(format!("{points} pts"));
+    }
+    if !facts.is_empty() {
+        card.add_row(vec![Cell::new(""), Cell::new(facts.join(" · "))]);
+    }
+
+    let ids = match &task.short_id {
+        Some(short_id) => format!("{short_id} · {}", task.id),
+        None => task.id.clone(),
+    };
+    card.add_row(vec![
+        Cell::new(""),
+        Cell::new(ids).add_attribute(Attribute::Dim),
+    ]);
+
+    for (name, entries) in sections.iter().filter(|(_, entries)| !entries.is_empty()) {
+        card.add_row(vec![
+            Cell::new(name).add_attribute(Attribute::Bold),
+            Cell::new(entries.join("\n")),
+        ]);
+    }
+
+    print_table(&card);
+}
+
+// status in capitals on its color, the configured marker kept in front
+fn status_badge(status: Status) -> Cell {
+    let color = match status {
+        Status::Todo => Color::Grey,
+        Status::InProgress => Color::Blue,
+        Status::Done => Color::Green,
+        Status::Blocked => Color::Red,
+    };
+
+    Cell::new(format!(
+        " {} ",
+        format_status(status).replace('_', " ").to_uppercase()
+    ))
+    .fg(Color::Black)
+    .bg(color)
+    .add_attribute(Attribute::Bold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn porcelain_task_round_trips_every_field() {
+        let task = Task {
+            id: "01J0TASK".to_string(),
+            description: "ship the release".to_string(),
+            status: Status::Blocked,
+            date: "2026-10-16".to_string(),
+            updated_at: None,
+            deferred_until: Some("2026-10-19".to_string()),
+            points: Some(3),
+            block_reason: Some("waiting on review".to_string()),
+            position: 0,
+            short_id: None,
+            priority: Some(Priority::High),
+            project: Some("web".to_string()),
+            due: Some("2026-12-01".to_string()),
+            parent_id: Some("01J0PARENT".to_string()),
+            tags: vec!["alpha".to_string(), "q4".to_string()],
+        };
+
+        let line = porcelain_task_line(&task, &task.description);
+        let (kind, fields) = parse_porcelain_line(&line);
+        let parsed = parse_porcelain_task(&fields).expect("task line parses");
+
+        assert_eq!(kind, "task");
+        assert_eq!(porcelain_task_line(&parsed, &parsed.description), line);
+        assert_eq!(parsed.priority, Some(Priority::High));
+        assert_eq!(parsed.deferred_until.as_deref(), Some("2026-10-19"));
+        assert_eq!(parsed.tags, task.tags);
+        assert_eq!(parsed.project.as_deref(), Some("web"));
+        assert_eq!(parsed.due.as_deref(), Some("2026-12-01"));
+        assert_eq!(parsed.parent_id.as_deref(), Some("01J0PARENT"));
+    }
+
+    #[test]
+    fn porcelain_v1_task_lines_still_parse() {
+        let (_, fields) =
+            parse_porcelain_line("task\t01J0TASK\t2026-10-16\ttodo\t\t\t\tship the release");
+        let task = parse_porcelain_task(&fields).expect("v1 task line parses");
+
+        assert_eq!(task.description, "ship the release");
+        assert_eq!(task.priority, None);
+        assert!(task.tags.is_empty());
+    }
+
+    #[test]
+    fn porcelain_task_dates_are_checked() {
+        let (_, fields) = parse_porcelain_line(
+            "task\t01J0TASK\t2026-10-16\ttodo\t\t\t\t\t\t\t2026-13-01\t\tship the release",
+        );
+
+        assert_eq!(
+            parse_porcelain_task(&fields).map(|task| task.due),
+            Err("Invalid date '2026-13-01', expected YYYY-MM-DD".to_string())
+        );
+    }
+
+    #[test]
+    fn later_dates_skip_today() {
+        let today = today();
+        let weekday = today.weekday().to_string();
+
+        assert_eq!(parse_date_arg(&weekday), Ok(today));
+        assert_eq!(parse_later_date_arg(&weekday), Ok(today + Days::new(7)));
+        assert_eq!(parse_later_date_arg("tomorrow"), Ok(today + Days::new(1)));
+        assert_eq!(
+            parse_later_date_arg(&today.succ_opt().expect("tomorrow").weekday().to_string()),
+            Ok(today + Days::new(1))
+        );
+    }
+
+    // an export of two tasks, as export writes it
+    fn export_lines() -> Vec<String> {
+        let task_lines = [
+            "task\t01J0A\t2026-10-16\ttodo\t\t\t\t\t\t\t\t\tfirst",
+            "task\t01J0B\t2026-10-16\tdone\t2\t\t\t\t\t\t\t\tsecond",
+        ];
+
+        [porcelain_header(), export_checksum(&task_lines)]
+            .into_iter()
+            .chain(task_lines.map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn export_checksum_matches_its_tasks() {
+        let lines = export_lines();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+
+        assert_eq!(verify_export_checksum(&lines), Ok(Some(2)));
+    }
+
+    #[test]
+    fn export_checksum_catches_cut_and_edited_files() {
+        let lines = export_lines();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+
+        let truncated = &lines[..lines.len() - 1];
+        assert!(verify_export_checksum(truncated).is_err());
+
+        let mut edited = lines.clone();
+        let edited_line = lines[3].replace("second", "changed");
+        edited[3] = &edited_line;
+        assert!(verify_export_checksum(&edited).is_err());
+    }
+
+    #[test]
+    fn export_without_checksum_line_is_unchecked() {
+        let lines = export_lines();
+        let lines: Vec<&str> = lines
+            .iter()
+            .map(String::as_str)
+            .filter(|line| !line.starts_with("checksum\t"))
+            .collect();
+
+        assert_eq!(verify_export_checksum(&lines), Ok(None));
+    }
+
+    #[test]
+    fn porcelain_escapes_round_trip() {
+        let description = "tab\there\nnew line \\t not a tab, trailing \\";
+        let line = porcelain_line("task", &["01J0A".to_string(), description.to_string()]);
+
+        assert_eq!(line.lines().count(), 1);
+        assert_eq!(line.matches('\t').count(), 2);
+        assert_eq!(
+            parse_porcelain_line(&line),
+            ("task", vec!["01J0A".to_string(), description.to_string()])
+        );
+    }
 }
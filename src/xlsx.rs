@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use rust_xlsxwriter::{Color, Format, Workbook, Worksheet, XlsxError};
+
+use crate::{utils::redact_description, Status, Task};
+
+/*
+ * excel workbook of tasks, one sheet per month (eg. 2024-06) by the date a task is on
+ *
+ * Date, Description, Status, Points, Blocked on, with the header row frozen and status cells
+ * filled by status. dates are real excel dates, so they sort and filter as such
+ * */
+pub fn render_xlsx(tasks: &[Task]) -> Result<Vec<u8>, XlsxError> {
+    let mut months: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+    for task in tasks {
+        let date = task.effective_date();
+        months
+            .entry(&date[..7.min(date.len())])
+            .or_default()
+            .push(task);
+    }
+
+    // a workbook needs at least one sheet
+    let sheets: Vec<(&str, Vec<&Task>)> = match months.is_empty() {
+        true => vec![("Tasks", vec![])],
+        false => months.into_iter().collect(),
+    };
+
+    let mut workbook = Workbook::new();
+
+    for (name, mut tasks) in sheets {
+        tasks.sort_by(|a, b| a.effective_date().cmp(b.effective_date()));
+
+        let worksheet = workbook.add_worksheet().set_name(name)?;
+        write_worksheet(worksheet, &tasks)?;
+    }
+
+    workbook.save_to_buffer()
+}
+
+// column titles and widths in characters
+const COLUMNS: [(&str, u32); 5] = [
+    ("Date", 12),
+    ("Description", 60),
+    ("Status", 14),
+    ("Points", 8),
+    ("Blocked on", 40),
+];
+
+fn status_format(status: Status) -> Format {
+    let color = match status {
+        Status::Todo => 0xE7E9EC,
+        Status::InProgress => 0xDDF4FF,
+        Status::Done => 0xDAFBE1,
+        Status::Blocked => 0xFFEBE9,
+    };
+
+    Format::new().set_background_color(Color::RGB(color))
+}
+
+fn write_worksheet(worksheet: &mut Worksheet, tasks: &[&Task]) -> Result<(), XlsxError> {
+    let header_format = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xF6F8FA));
+    // 14 is excel's built in short date, shown in the reader's locale
+    let date_format = Format::new().set_num_format_index(14);
+
+    for (column, (title, width)) in (0..).zip(COLUMNS) {
+        worksheet.set_column_width(column, width)?;
+        worksheet.write_string_with_format(0, column, title, &header_format)?;
+    }
+    worksheet.set_freeze_panes(1, 0)?;
+
+    for (task, row) in tasks.iter().zip(1..) {
+        let date = task.effective_date();
+        match excel_date(date) {
+            Some(serial) => worksheet.write_number_with_format(row, 0, serial, &date_format)?,
+            None => worksheet.write_string(row, 0, date)?,
+        };
+
+        worksheet.write_string(row, 1, redact_description(&task.description))?;
+        worksheet.write_string_with_format(
+            row,
+            2,
+            task.status.to_string(),
+            &status_format(task.status),
+        )?;
+
+        if let Some(points) = task.points {
+            worksheet.write_number(row, 3, points)?;
+        }
+
+        if let Some(reason) = &task.block_reason {
+            worksheet.write_string(row, 4, reason)?;
+        }
+    }
+
+    Ok(())
+}
+
+// days since 1899-12-30, what excel counts dates in
+fn excel_date(date: &str) -> Option<f64> {
+    let date = NaiveDate::parse_from_str(date, "%F").ok()?;
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?;
+
+    Some((date - epoch).num_days() as f64)
+}